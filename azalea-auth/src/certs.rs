@@ -0,0 +1,99 @@
+//! Get the keys that chat messages are signed with.
+
+use azalea_crypto::{PrivateKey, SigningError};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FetchCertificatesError {
+    #[error("Http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("Invalid private key: {0}")]
+    PrivateKey(#[from] SigningError),
+    #[error("Invalid expiry date: {0}")]
+    InvalidExpiryDate(String),
+}
+
+/// The keys that Mojang gave the player for signing chat messages.
+#[derive(Clone, Debug)]
+pub struct Certificates {
+    pub private_key: PrivateKey,
+    /// The DER encoded public key, which is sent to the server when joining.
+    pub public_key: Vec<u8>,
+    /// Mojang's signature of the public key, so servers can check that the
+    /// key is really the player's.
+    pub public_key_signature: Vec<u8>,
+    /// When the keys expire, in milliseconds since the epoch.
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CertificatesResponse {
+    key_pair: KeyPairResponse,
+    /// The signature that's used since 1.19.1.
+    public_key_signature_v2: String,
+    /// Looks like 2022-10-19T12:28:05.123456Z
+    expires_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyPairResponse {
+    private_key: String,
+    public_key: String,
+}
+
+/// Get the player's chat signing keys from Mojang. They're different every
+/// time this is called, and they expire after a couple days.
+pub async fn fetch_certificates(
+    minecraft_access_token: &str,
+) -> Result<Certificates, FetchCertificatesError> {
+    let res = reqwest::Client::new()
+        .post("https://api.minecraftservices.com/player/certificates")
+        .header("Authorization", format!("Bearer {minecraft_access_token}"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<CertificatesResponse>()
+        .await?;
+
+    let expires_at = DateTime::parse_from_rfc3339(&res.expires_at)
+        .map_err(|e| FetchCertificatesError::InvalidExpiryDate(format!("{}: {e}", res.expires_at)))?
+        .with_timezone(&Utc)
+        .timestamp_millis() as u64;
+
+    Ok(Certificates {
+        private_key: PrivateKey::from_pkcs8_der(&decode_pem(&res.key_pair.private_key)?)?,
+        public_key: decode_pem(&res.key_pair.public_key)?,
+        public_key_signature: base64::decode(&res.public_key_signature_v2)?,
+        expires_at,
+    })
+}
+
+/// Get the DER out of a PEM. We can't use a normal PEM parser since Mojang
+/// labels the keys as PKCS#1 even though they're actually PKCS#8 and SPKI.
+fn decode_pem(pem: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let base64: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+    base64::decode(base64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_pem() {
+        let pem =
+            "-----BEGIN RSA PUBLIC KEY-----\naGVsbG8g\nd29ybGQ=\n-----END RSA PUBLIC KEY-----\n";
+        assert_eq!(decode_pem(pem).unwrap(), b"hello world");
+        assert!(decode_pem("-----BEGIN RSA PUBLIC KEY-----\n!!!\n").is_err());
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use uuid::Uuid;
 
 #[derive(McBuf, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GameProfile {
     pub uuid: Uuid,
     pub name: String,
@@ -20,6 +21,7 @@ impl GameProfile {
 }
 
 #[derive(McBuf, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ProfilePropertyValue {
     pub value: String,
     pub signature: Option<String>,
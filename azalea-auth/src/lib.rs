@@ -1,5 +1,6 @@
 mod auth;
 mod cache;
+pub mod certs;
 pub mod game_profile;
 pub mod sessionserver;
 
@@ -1,7 +1,10 @@
-//! Tell Mojang you're joining a multiplayer server.
+//! Tell Mojang you're joining a multiplayer server, or check that a player
+//! joining your server told Mojang about it.
 //!
+use crate::game_profile::{GameProfile, ProfilePropertyValue};
 use serde::Deserialize;
 use serde_json::json;
+use std::net::IpAddr;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -17,6 +20,10 @@ pub enum SessionServerError {
     Unknown(String),
     #[error("Unexpected response from sessionserver (status code {status_code}): {body}")]
     UnexpectedResponse { status_code: u16, body: String },
+    #[error("The player hasn't joined the server with Mojang")]
+    NotAuthenticated,
+    #[error("Invalid UUID in sessionserver response: {0}")]
+    InvalidUuid(#[from] uuid::Error),
 }
 
 #[derive(Deserialize)]
@@ -37,11 +44,7 @@ pub async fn join(
 ) -> Result<(), SessionServerError> {
     let client = reqwest::Client::new();
 
-    let server_hash = azalea_crypto::hex_digest(&azalea_crypto::digest_data(
-        server_id.as_bytes(),
-        public_key,
-        private_key,
-    ));
+    let server_hash = server_hash(server_id, public_key, private_key);
 
     let mut encode_buffer = Uuid::encode_buffer();
     let undashed_uuid = uuid.simple().encode_lower(&mut encode_buffer);
@@ -76,3 +79,96 @@ pub async fn join(
         }
     }
 }
+
+/// Compute the hash that the client and server send to Mojang to prove
+/// they're talking to each other. This uses Minecraft's unusual hex encoding
+/// of SHA1, which can be negative.
+pub fn server_hash(server_id: &str, public_key: &[u8], private_key: &[u8]) -> String {
+    azalea_crypto::hex_digest(&azalea_crypto::digest_data(
+        server_id.as_bytes(),
+        public_key,
+        private_key,
+    ))
+}
+
+#[derive(Deserialize)]
+struct HasJoinedResponse {
+    id: String,
+    name: String,
+    #[serde(default)]
+    properties: Vec<HasJoinedProperty>,
+}
+
+#[derive(Deserialize)]
+struct HasJoinedProperty {
+    name: String,
+    value: String,
+    signature: Option<String>,
+}
+
+/// Ask Mojang whether a player joining our server actually authenticated
+/// with them, which is what online-mode servers do. This should be called
+/// after the client sends its encryption response, with the hash from
+/// [`server_hash`].
+///
+/// If `client_ip` is given, Mojang will also check that the player
+/// authenticated from that IP address (this is what `prevent-proxy-connections`
+/// does in vanilla).
+pub async fn has_joined(
+    username: &str,
+    server_hash: &str,
+    client_ip: Option<IpAddr>,
+) -> Result<GameProfile, SessionServerError> {
+    let client = reqwest::Client::new();
+
+    let mut query = vec![
+        ("username", username.to_string()),
+        ("serverId", server_hash.to_string()),
+    ];
+    if let Some(client_ip) = client_ip {
+        query.push(("ip", client_ip.to_string()));
+    }
+    let res = client
+        .get("https://sessionserver.mojang.com/session/minecraft/hasJoined")
+        .query(&query)
+        .send()
+        .await?;
+
+    match res.status() {
+        reqwest::StatusCode::OK => {
+            let response = res.json::<HasJoinedResponse>().await?;
+            let mut profile = GameProfile::new(Uuid::parse_str(&response.id)?, response.name);
+            for property in response.properties {
+                profile.properties.insert(
+                    property.name,
+                    ProfilePropertyValue {
+                        value: property.value,
+                        signature: property.signature,
+                    },
+                );
+            }
+            Ok(profile)
+        }
+        reqwest::StatusCode::NO_CONTENT => Err(SessionServerError::NotAuthenticated),
+        status_code => {
+            let body = res.text().await?;
+            Err(SessionServerError::UnexpectedResponse {
+                status_code: status_code.as_u16(),
+                body,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_hash_can_be_negative() {
+        assert_eq!(
+            server_hash("jeb_", &[], &[]),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+    }
+}
@@ -304,6 +304,7 @@ pub fn make_block_states(input: TokenStream) -> TokenStream {
     let mut block_state_enum_variants = quote! {};
     let mut block_structs = quote! {};
     let mut from_state_to_block_match = quote! {};
+    let mut from_id_and_properties_match = quote! {};
     for block in &input.block_definitions.blocks {
         let block_property_names = &block
             .properties_and_defaults
@@ -487,6 +488,71 @@ pub fn make_block_states(input: TokenStream) -> TokenStream {
             division *= property_variants_count;
         }
 
+        // "oak_slab" => {
+        //     let mut state = 9045;
+        //     state += match property_value("type").as_deref() {
+        //         Some("top") => 0,
+        //         Some("bottom") => 2,
+        //         Some("double") => 4,
+        //         _ => 2,
+        //     };
+        //     ...
+        //     state
+        // }
+        let mut from_id_and_properties_inner = quote! {};
+        let mut division = 1u32;
+        for i in (0..properties_with_name.len()).rev() {
+            let property = &properties_with_name[i];
+            let property_name = property.name.to_string();
+            // `type` is a keyword so it's called `kind` in the block structs
+            let property_name = if property_name == "kind" {
+                "type".to_string()
+            } else {
+                property_name
+            };
+            let property_variants = &block_properties_vec[i];
+
+            let default_variant = property.default.to_string();
+            let default_variant = default_variant
+                .rsplit("::")
+                .next()
+                .unwrap_or_default()
+                .trim();
+            let default_index = property_variants
+                .iter()
+                .position(|v| v == default_variant)
+                .unwrap_or_else(|| panic!("Invalid default for property '{property_name}'"))
+                as u32;
+            let default_offset = default_index * division;
+
+            let mut value_match_inner = quote! {};
+            for (index, variant) in property_variants.iter().enumerate() {
+                let value = variant.to_lowercase().replace('_', "");
+                let offset = index as u32 * division;
+                value_match_inner.extend(quote! {
+                    Some(#value) => #offset,
+                });
+            }
+            from_id_and_properties_inner.extend(quote! {
+                state += match property_value(#property_name).as_deref() {
+                    #value_match_inner
+                    _ => #default_offset,
+                };
+            });
+
+            division *= property_variants.len() as u32;
+        }
+        let first_state_id_u32 = first_state_id as u32;
+        let block_id = block.name.to_string();
+        from_id_and_properties_match.extend(quote! {
+            #block_id => {
+                #[allow(unused_mut)]
+                let mut state = #first_state_id_u32;
+                #from_id_and_properties_inner
+                state
+            },
+        });
+
         let last_state_id = state_id - 1;
         from_state_to_block_match.extend(quote! {
             #first_state_id..=#last_state_id => {
@@ -508,7 +574,6 @@ pub fn make_block_states(input: TokenStream) -> TokenStream {
         }
 
         let block_behavior = &block.behavior;
-        let block_id = block.name.to_string();
 
         let from_block_to_state_match = if !block.properties_and_defaults.is_empty() {
             quote! {
@@ -569,6 +634,27 @@ pub fn make_block_states(input: TokenStream) -> TokenStream {
             pub fn max_state() -> u32 {
                 #last_state_id
             }
+
+            /// Get the block state for a block id (like `"oak_log"`, without
+            /// the `minecraft:` namespace) and the values of its properties,
+            /// written like they are in NBT and block state strings. Properties
+            /// that are missing or have an invalid value are set to their
+            /// default. Returns `None` if there's no block with the given id.
+            pub fn from_id_and_properties(
+                id: &str,
+                properties: &std::collections::HashMap<String, String>,
+            ) -> Option<BlockState> {
+                let property_value = |name: &str| {
+                    properties
+                        .get(name)
+                        .map(|value| value.to_lowercase().replace('_', ""))
+                };
+                let state_id: u32 = match id {
+                    #from_id_and_properties_match
+                    _ => return None,
+                };
+                BlockState::try_from(state_id).ok()
+            }
         }
 
         impl std::fmt::Debug for BlockState {
@@ -52,6 +52,14 @@ impl McBufWritable for BlockState {
     }
 }
 
+/// Block states are serialized as their state id.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BlockState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(*self as u32)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,4 +80,47 @@ mod tests {
         let block: Box<dyn Block> = Box::<dyn Block>::from(BlockState::FloweringAzalea);
         assert_eq!(block.id(), "flowering_azalea");
     }
+
+    #[test]
+    fn test_from_id_and_properties() {
+        use std::collections::HashMap;
+
+        assert!(
+            BlockState::from_id_and_properties("air", &HashMap::new()) == Some(BlockState::Air)
+        );
+        assert!(BlockState::from_id_and_properties("not_a_block", &HashMap::new()).is_none());
+
+        // missing properties use the default
+        assert!(
+            BlockState::from_id_and_properties("oak_log", &HashMap::new())
+                == Some(BlockState::from(OakLogBlock { axis: Axis::Y }))
+        );
+        let properties = HashMap::from([("axis".to_string(), "x".to_string())]);
+        assert!(
+            BlockState::from_id_and_properties("oak_log", &properties)
+                == Some(BlockState::from(OakLogBlock { axis: Axis::X }))
+        );
+
+        let properties = HashMap::from([
+            ("type".to_string(), "top".to_string()),
+            ("waterlogged".to_string(), "true".to_string()),
+        ]);
+        assert!(
+            BlockState::from_id_and_properties("oak_slab", &properties)
+                == Some(BlockState::from(OakSlabBlock {
+                    kind: Type::Top,
+                    waterlogged: true,
+                }))
+        );
+
+        let properties = HashMap::from([("has_bottle_1".to_string(), "true".to_string())]);
+        assert!(
+            BlockState::from_id_and_properties("brewing_stand", &properties)
+                == Some(BlockState::from(BrewingStandBlock {
+                    has_bottle_0: false,
+                    has_bottle_1: true,
+                    has_bottle_2: false,
+                }))
+        );
+    }
 }
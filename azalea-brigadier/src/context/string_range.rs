@@ -1,6 +1,7 @@
 use std::cmp;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StringRange {
     start: usize,
     end: usize,
@@ -4,6 +4,7 @@ use crate::{context::StringRange, message::Message};
 pub use suggestions::*;
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Suggestion {
     pub range: StringRange,
     pub text: String,
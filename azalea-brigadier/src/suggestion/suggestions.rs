@@ -3,6 +3,7 @@ use crate::context::StringRange;
 use std::collections::HashSet;
 
 #[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Suggestions {
     pub range: StringRange,
     pub suggestions: Vec<Suggestion>,
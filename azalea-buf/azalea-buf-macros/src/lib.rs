@@ -1,8 +1,152 @@
 use proc_macro::TokenStream;
 use quote::{quote, ToTokens};
-use syn::{self, parse_macro_input, Data, DeriveInput, FieldsNamed, Ident};
+use syn::{self, ext::IdentExt, parse_macro_input, Data, DeriveInput, FieldsNamed, Ident};
 
-fn create_impl_mcbufreadable(ident: &Ident, data: &Data) -> proc_macro2::TokenStream {
+/// The options from a field's `#[mcbuf(...)]` attributes.
+#[derive(Default)]
+struct FieldOptions {
+    /// A function that's called with the buffer to read the field, instead
+    /// of using its McBufReadable implementation.
+    read_with: Option<syn::Path>,
+    /// A function that's called with the field and the buffer to write it,
+    /// instead of using its McBufWritable implementation.
+    write_with: Option<syn::Path>,
+    /// The field isn't sent at all, so it's read as `Default::default()`.
+    skip: bool,
+}
+
+fn field_options(field: &syn::Field) -> FieldOptions {
+    let mut options = FieldOptions::default();
+    for attr in field.attrs.iter().filter(|a| a.path.is_ident("mcbuf")) {
+        let nested = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested,
+            _ => {
+                panic!("#[mcbuf] should look like #[mcbuf(skip)] or #[mcbuf(read_with = \"...\")]")
+            }
+        };
+        for meta in nested {
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("skip") => {
+                    options.skip = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(lit),
+                    ..
+                })) if path.is_ident("read_with") || path.is_ident("write_with") => {
+                    let function = lit.parse::<syn::Path>().unwrap_or_else(|_| {
+                        panic!("{} should be a path to a function", lit.value())
+                    });
+                    if path.is_ident("read_with") {
+                        options.read_with = Some(function);
+                    } else {
+                        options.write_with = Some(function);
+                    }
+                }
+                meta => panic!("Unknown #[mcbuf] option {}", meta.to_token_stream()),
+            }
+        }
+    }
+    if options.skip && (options.read_with.is_some() || options.write_with.is_some()) {
+        panic!("Skipped fields can't have read_with or write_with");
+    }
+    options
+}
+
+/// How an enum's discriminant is sent, set with `#[mcbuf(tag = "...")]` on
+/// the enum.
+#[derive(Clone, Copy, Default)]
+enum TagEncoding {
+    U8,
+    #[default]
+    VarInt,
+    VarLong,
+}
+
+impl TagEncoding {
+    fn from_attrs(attrs: &[syn::Attribute]) -> Self {
+        let mut encoding = TagEncoding::default();
+        for attr in attrs.iter().filter(|a| a.path.is_ident("mcbuf")) {
+            let nested = match attr.parse_meta() {
+                Ok(syn::Meta::List(list)) => list.nested,
+                _ => panic!("#[mcbuf] on enums should look like #[mcbuf(tag = \"u8\")]"),
+            };
+            for meta in nested {
+                match meta {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    })) if path.is_ident("tag") => {
+                        encoding = match lit.value().as_str() {
+                            "u8" => TagEncoding::U8,
+                            "varint" => TagEncoding::VarInt,
+                            "varlong" => TagEncoding::VarLong,
+                            tag => panic!(
+                                "Unknown tag encoding {tag}, it should be u8, varint or varlong"
+                            ),
+                        };
+                    }
+                    meta => panic!("Unknown #[mcbuf] option {}", meta.to_token_stream()),
+                }
+            }
+        }
+        encoding
+    }
+
+    /// The discriminant as a literal of the type that it's sent as.
+    fn literal(self, discriminant: u32) -> proc_macro2::Literal {
+        match self {
+            TagEncoding::U8 => proc_macro2::Literal::u8_suffixed(
+                discriminant
+                    .try_into()
+                    .unwrap_or_else(|_| panic!("Discriminant {discriminant} doesn't fit in a u8")),
+            ),
+            TagEncoding::VarInt => proc_macro2::Literal::u32_suffixed(discriminant),
+            TagEncoding::VarLong => proc_macro2::Literal::u64_suffixed(discriminant.into()),
+        }
+    }
+
+    fn read(self) -> proc_macro2::TokenStream {
+        match self {
+            TagEncoding::U8 => quote! { <u8 as azalea_buf::McBufReadable>::read_from(buf)? },
+            TagEncoding::VarInt => {
+                quote! { <u32 as azalea_buf::McBufVarReadable>::var_read_from(buf)? }
+            }
+            TagEncoding::VarLong => {
+                quote! { <u64 as azalea_buf::McBufVarReadable>::var_read_from(buf)? }
+            }
+        }
+    }
+
+    /// The type that the discriminant is sent as.
+    fn ty(self) -> proc_macro2::TokenStream {
+        match self {
+            TagEncoding::U8 => quote! { u8 },
+            TagEncoding::VarInt => quote! { u32 },
+            TagEncoding::VarLong => quote! { u64 },
+        }
+    }
+
+    /// Write the discriminant, which has to already be the type from
+    /// [`Self::ty`].
+    fn write(self, discriminant: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self {
+            TagEncoding::U8 => {
+                quote! { azalea_buf::McBufWritable::write_into(&#discriminant, buf) }
+            }
+            TagEncoding::VarInt | TagEncoding::VarLong => {
+                quote! { azalea_buf::McBufVarWritable::var_write_into(&#discriminant, buf) }
+            }
+        }
+    }
+}
+
+fn create_impl_mcbufreadable(
+    ident: &Ident,
+    attrs: &[syn::Attribute],
+    data: &Data,
+) -> proc_macro2::TokenStream {
     match data {
         syn::Data::Struct(syn::DataStruct { fields, .. }) => {
             let FieldsNamed { named, .. } = match fields {
@@ -15,17 +159,35 @@ fn create_impl_mcbufreadable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
                 .map(|f| {
                     let field_name = &f.ident;
                     let field_type = &f.ty;
+                    let options = field_options(f);
+                    // errors say which field they happened in
+                    let field_ident = field_name.as_ref().unwrap();
+                    let field_name_litstr =
+                        syn::LitStr::new(&field_ident.unraw().to_string(), field_ident.span());
+                    let in_field = quote! {
+                        .map_err(|e| e.in_field(#field_name_litstr))?
+                    };
+                    if options.skip {
+                        return quote! {
+                            let #field_name = Default::default();
+                        };
+                    }
+                    if let Some(read_with) = options.read_with {
+                        return quote! {
+                            let #field_name = #read_with(buf)#in_field;
+                        };
+                    }
                     // do a different buf.write_* for each field depending on the type
                     // if it's a string, use buf.write_string
                     match field_type {
                         syn::Type::Path(_) | syn::Type::Array(_) => {
                             if f.attrs.iter().any(|a| a.path.is_ident("var")) {
                                 quote! {
-                                    let #field_name = azalea_buf::McBufVarReadable::var_read_from(buf)?;
+                                    let #field_name = azalea_buf::McBufVarReadable::var_read_from(buf)#in_field;
                                 }
                             } else {
                                 quote! {
-                                    let #field_name = azalea_buf::McBufReadable::read_from(buf)?;
+                                    let #field_name = azalea_buf::McBufReadable::read_from(buf)#in_field;
                                 }
                             }
                         }
@@ -51,6 +213,7 @@ fn create_impl_mcbufreadable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
             }
         }
         syn::Data::Enum(syn::DataEnum { variants, .. }) => {
+            let tag_encoding = TagEncoding::from_attrs(attrs);
             let mut match_contents = quote!();
             let mut variant_discrim: u32 = 0;
             let mut first = true;
@@ -82,9 +245,16 @@ fn create_impl_mcbufreadable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
                     syn::Fields::Named(_) => {
                         panic!("writing named fields in enums is not supported")
                     }
-                    syn::Fields::Unnamed(_) => quote! {
-                        Ok(Self::#variant_name(azalea_buf::McBufReadable::read_from(buf)?))
-                    },
+                    syn::Fields::Unnamed(_) => {
+                        let variant_name_litstr =
+                            syn::LitStr::new(&variant_name.to_string(), variant_name.span());
+                        quote! {
+                            Ok(Self::#variant_name(
+                                azalea_buf::McBufReadable::read_from(buf)
+                                    .map_err(|e| e.in_field(#variant_name_litstr))?
+                            ))
+                        }
+                    }
                     syn::Fields::Unit => quote! {
                         Ok(Self::#variant_name)
                     },
@@ -94,6 +264,7 @@ fn create_impl_mcbufreadable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
                     first = false;
                 };
 
+                let variant_discrim = tag_encoding.literal(variant_discrim);
                 match_contents.extend(quote! {
                     #variant_discrim => {
                         #reader
@@ -102,12 +273,13 @@ fn create_impl_mcbufreadable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
             }
 
             let first_reader = first_reader.expect("There should be at least one variant");
+            let read_id = tag_encoding.read();
 
             quote! {
             impl azalea_buf::McBufReadable for #ident {
                 fn read_from(buf: &mut std::io::Cursor<&[u8]>) -> Result<Self, azalea_buf::BufReadError>
                 {
-                    let id = azalea_buf::McBufVarReadable::var_read_from(buf)?;
+                    let id = #read_id;
                     match id {
                         #match_contents
                         // you'd THINK this throws an error, but mojang decided to make it default for some reason
@@ -121,7 +293,11 @@ fn create_impl_mcbufreadable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
     }
 }
 
-fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenStream {
+fn create_impl_mcbufwritable(
+    ident: &Ident,
+    attrs: &[syn::Attribute],
+    data: &Data,
+) -> proc_macro2::TokenStream {
     match data {
         syn::Data::Struct(syn::DataStruct { fields, .. }) => {
             let FieldsNamed { named, .. } = match fields {
@@ -134,6 +310,15 @@ fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
             .map(|f| {
                 let field_name = &f.ident;
                 let field_type = &f.ty;
+                let options = field_options(f);
+                if options.skip {
+                    return quote! {};
+                }
+                if let Some(write_with) = options.write_with {
+                    return quote! {
+                        #write_with(&self.#field_name, buf)?;
+                    };
+                }
                 // do a different buf.write_* for each field depending on the type
                 // if it's a string, use buf.write_string
                 match field_type {
@@ -167,6 +352,7 @@ fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
             }
         }
         syn::Data::Enum(syn::DataEnum { variants, .. }) => {
+            let tag_encoding = TagEncoding::from_attrs(attrs);
             // remember whether it's a data variant so we can do an optimization later
             let mut is_data_enum = false;
             let mut match_arms = quote!();
@@ -189,13 +375,14 @@ fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
                         }
                     }
                     None => {
-                        if first {
-                            first = false;
-                        } else {
+                        if !first {
                             variant_discrim += 1;
                         }
                     }
                 }
+                first = false;
+                let write_discrim =
+                    tag_encoding.write(tag_encoding.literal(variant_discrim).into_token_stream());
 
                 match &variant.fields {
                     syn::Fields::Named(_) => {
@@ -205,7 +392,7 @@ fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
                         let variant_name = &variant.ident;
                         match_arms.extend(quote! {
                             Self::#variant_name => {
-                                azalea_buf::McBufVarWritable::var_write_into(&#variant_discrim, buf)?;
+                                #write_discrim?;
                             }
                         });
                     }
@@ -214,7 +401,7 @@ fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
                         let variant_name = &variant.ident;
                         match_arms.extend(quote! {
                             Self::#variant_name(data) => {
-                                azalea_buf::McBufVarWritable::var_write_into(&#variant_discrim, buf)?;
+                                #write_discrim?;
                                 azalea_buf::McBufWritable::write_into(data, buf)?;
                             }
                         });
@@ -234,10 +421,12 @@ fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
                 }
             } else {
                 // optimization: if it doesn't have data we can just do `as u32`
+                let ty = tag_encoding.ty();
+                let write_discrim = tag_encoding.write(quote! { (*self as #ty) });
                 quote! {
                     impl azalea_buf::McBufWritable for #ident {
                         fn write_into(&self, buf: &mut impl std::io::Write) -> Result<(), std::io::Error> {
-                            azalea_buf::McBufVarWritable::var_write_into(&(*self as u32), buf)
+                            #write_discrim
                         }
                     }
                 }
@@ -247,26 +436,51 @@ fn create_impl_mcbufwritable(ident: &Ident, data: &Data) -> proc_macro2::TokenSt
     }
 }
 
-#[proc_macro_derive(McBufReadable, attributes(var))]
+#[proc_macro_derive(McBufReadable, attributes(var, mcbuf))]
 pub fn derive_mcbufreadable(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let DeriveInput {
+        ident, attrs, data, ..
+    } = parse_macro_input!(input);
 
-    create_impl_mcbufreadable(&ident, &data).into()
+    create_impl_mcbufreadable(&ident, &attrs, &data).into()
 }
 
-#[proc_macro_derive(McBufWritable, attributes(var))]
+#[proc_macro_derive(McBufWritable, attributes(var, mcbuf))]
 pub fn derive_mcbufwritable(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let DeriveInput {
+        ident, attrs, data, ..
+    } = parse_macro_input!(input);
 
-    create_impl_mcbufwritable(&ident, &data).into()
+    create_impl_mcbufwritable(&ident, &attrs, &data).into()
 }
 
-#[proc_macro_derive(McBuf, attributes(var))]
+/// Implement McBufReadable and McBufWritable by reading and writing every
+/// field in order.
+///
+/// Fields can have these attributes:
+/// - `#[var]` reads and writes the field as a varint (or varlong).
+/// - `#[mcbuf(read_with = "path::to::function")]` reads the field with
+///   `function(buf)` instead, and `#[mcbuf(write_with = "...")]` writes it
+///   with `function(&self.field, buf)`. These are for fields with weird
+///   encodings, like bitfields.
+/// - `#[mcbuf(skip)]` doesn't send the field, it's read as
+///   `Default::default()`.
+///
+/// Fixed-size arrays like `[u8; 16]` or `[Uuid; 2]` are sent as their
+/// elements without a length first. `#[var]` on an array makes every element
+/// a varint.
+///
+/// Enums are sent as their discriminant followed by the variant's data (if
+/// it has any). The discriminant is a varint, unless the enum has
+/// `#[mcbuf(tag = "u8")]` or `#[mcbuf(tag = "varlong")]`.
+#[proc_macro_derive(McBuf, attributes(var, mcbuf))]
 pub fn derive_mcbuf(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let DeriveInput {
+        ident, attrs, data, ..
+    } = parse_macro_input!(input);
 
-    let writable = create_impl_mcbufwritable(&ident, &data);
-    let readable = create_impl_mcbufreadable(&ident, &data);
+    let writable = create_impl_mcbufwritable(&ident, &attrs, &data);
+    let readable = create_impl_mcbufreadable(&ident, &attrs, &data);
     quote! {
         #writable
         #readable
@@ -0,0 +1,28 @@
+//! Read varints and varlongs from random bytes, and make sure they never
+//! read too many bytes and that writing them again gives the same value.
+//!
+//! Run with `cargo +nightly fuzz run varint` in azalea-buf.
+
+#![no_main]
+
+use azalea_buf::{McBufVarReadable, McBufVarWritable};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let mut cursor = Cursor::new(data);
+    if let Ok(value) = i32::var_read_from(&mut cursor) {
+        assert!(cursor.position() <= 5);
+        let mut buf = Vec::new();
+        value.var_write_into(&mut buf).unwrap();
+        assert_eq!(i32::var_read_from(&mut Cursor::new(&buf[..])).unwrap(), value);
+    }
+
+    let mut cursor = Cursor::new(data);
+    if let Ok(value) = i64::var_read_from(&mut cursor) {
+        assert!(cursor.position() <= 10);
+        let mut buf = Vec::new();
+        value.var_write_into(&mut buf).unwrap();
+        assert_eq!(i64::var_read_from(&mut Cursor::new(&buf[..])).unwrap(), value);
+    }
+});
@@ -0,0 +1,156 @@
+//! Serialize types with serde as the bytes that they're sent as in the
+//! protocol. This makes it possible to save things that only implement
+//! [`McBufReadable`] and [`McBufWritable`], like most packets, to JSON or
+//! CBOR and read them back.
+//!
+//! Use [`AsMcBuf`] to wrap a value, or `#[serde(with = "azalea_buf::as_mcbuf")]`
+//! on a field.
+//!
+//! ```
+//! # use azalea_buf::{McBuf, AsMcBuf};
+//! #[derive(McBuf, Debug, PartialEq)]
+//! struct Position {
+//!     x: i32,
+//!     #[var]
+//!     y: i32,
+//! }
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Fixture {
+//!     #[serde(with = "azalea_buf::as_mcbuf")]
+//!     position: Position,
+//! }
+//! ```
+
+use crate::{McBufReadable, McBufWritable};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, io::Cursor, marker::PhantomData};
+
+/// A value that's serialized with serde as the bytes it's written as with
+/// [`McBufWritable`].
+///
+/// Formats that don't have bytes, like JSON, serialize it as a list of
+/// numbers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AsMcBuf<T>(pub T);
+
+impl<T: McBufWritable> Serialize for AsMcBuf<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: McBufReadable> Deserialize<'de> for AsMcBuf<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(AsMcBuf)
+    }
+}
+
+/// Serialize the value as the bytes it's written as. This is meant to be
+/// used with `#[serde(with = "azalea_buf::as_mcbuf")]`.
+pub fn serialize<T: McBufWritable, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut buf = Vec::new();
+    value
+        .write_into(&mut buf)
+        .map_err(serde::ser::Error::custom)?;
+    serializer.serialize_bytes(&buf)
+}
+
+/// Read the value from bytes that were serialized with [`serialize`]. This
+/// fails if there are bytes left over after reading it.
+pub fn deserialize<'de, T: McBufReadable, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    deserializer.deserialize_bytes(BytesVisitor(PhantomData))
+}
+
+struct BytesVisitor<T>(PhantomData<T>);
+
+impl<'de, T: McBufReadable> de::Visitor<'de> for BytesVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<T, E> {
+        let mut cursor = Cursor::new(bytes);
+        let value = T::read_from(&mut cursor).map_err(E::custom)?;
+        let leftover = bytes.len() - cursor.position() as usize;
+        if leftover > 0 {
+            return Err(E::custom(format!("{leftover} bytes left over")));
+        }
+        Ok(value)
+    }
+
+    // formats like json don't have bytes, so they're a list of numbers
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        self.visit_bytes(&bytes)
+    }
+}
+
+/// A value that's sent as a JSON string, like the server status. This lets
+/// types that only implement serde be read and written with
+/// [`McBufReadable`] and [`McBufWritable`].
+#[cfg(feature = "serde_json")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "serde_json")]
+impl<T: de::DeserializeOwned> McBufReadable for Json<T> {
+    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, crate::BufReadError> {
+        let string = String::read_from(buf)?;
+        Ok(Json(serde_json::from_str(&string)?))
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<T: Serialize> McBufWritable for Json<T> {
+    fn write_into(&self, buf: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        serde_json::to_string(&self.0)?.write_into(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Fixture {
+        #[serde(with = "crate::as_mcbuf")]
+        names: Vec<String>,
+        id: u32,
+    }
+
+    #[test]
+    fn test_as_mcbuf_json() {
+        let fixture = Fixture {
+            names: vec!["a".to_string(), "bc".to_string()],
+            id: 300,
+        };
+        let json = serde_json::to_string(&fixture).unwrap();
+        assert_eq!(json, r#"{"names":[2,1,97,2,98,99],"id":300}"#);
+        assert_eq!(serde_json::from_str::<Fixture>(&json).unwrap(), fixture);
+
+        // there's an extra byte at the end
+        assert!(serde_json::from_str::<AsMcBuf<Vec<String>>>("[2,1,97,2,98,99,0]").is_err());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json() {
+        let mut buf = Vec::new();
+        Json(vec![1, 2]).write_into(&mut buf).unwrap();
+        // a string with the length first
+        assert_eq!(buf, b"\x05[1,2]");
+        let read = Json::<Vec<i32>>::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(read, Json(vec![1, 2]));
+    }
+}
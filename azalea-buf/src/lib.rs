@@ -5,20 +5,32 @@
 #![feature(error_generic_member_access)]
 #![feature(provide_any)]
 
+#[cfg(feature = "serde")]
+pub mod as_mcbuf;
 mod definitions;
 mod read;
 mod serializable_uuid;
 mod write;
 
+#[cfg(feature = "serde")]
+pub use as_mcbuf::AsMcBuf;
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+pub use as_mcbuf::Json;
 pub use azalea_buf_macros::*;
 pub use definitions::*;
-pub use read::{BufReadError, McBufReadable, McBufVarReadable};
+pub use read::{
+    read_collection_with_max, read_collection_with_max_by, read_prefixed_array,
+    read_string_with_max, BufReadError, FieldPathSegment, McBufReadable, McBufVarReadable,
+};
 pub use serializable_uuid::*;
 pub use write::{McBufVarWritable, McBufWritable};
 
 // const DEFAULT_NBT_QUOTA: u32 = 2097152;
-const MAX_STRING_LENGTH: u16 = 32767;
-// const MAX_COMPONENT_STRING_LENGTH: u32 = 262144;
+/// The longest a string can be (in characters), unless it has its own
+/// maximum.
+pub const MAX_STRING_LENGTH: u16 = 32767;
+/// The longest the JSON of a chat component can be (in characters).
+pub const MAX_COMPONENT_STRING_LENGTH: u32 = 262144;
 
 #[cfg(test)]
 mod tests {
@@ -142,6 +154,46 @@ mod tests {
         assert_eq!(i32::var_read_from(&mut Cursor::new(&buf)).unwrap(), 7178);
     }
 
+    #[test]
+    fn test_read_varint_invalid() {
+        // the continuation bit is set on all 5 bytes
+        let buf = vec![255, 255, 255, 255, 255, 1];
+        assert!(matches!(
+            i32::var_read_from(&mut Cursor::new(&buf)),
+            Err(BufReadError::InvalidVarInt)
+        ));
+        let buf = vec![128; 11];
+        assert!(matches!(
+            i64::var_read_from(&mut Cursor::new(&buf)),
+            Err(BufReadError::InvalidVarLong)
+        ));
+
+        // a varint that's cut off is an io error, since more data might come
+        let buf = [255, 255];
+        let mut cursor = Cursor::new(&buf[..]);
+        assert!(matches!(
+            i32::var_read_from(&mut cursor),
+            Err(BufReadError::Io(_))
+        ));
+        assert_eq!(cursor.position(), 0);
+        assert!(i32::var_read_from(&mut Cursor::new(&[][..])).is_err());
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        let values = (0..32).flat_map(|shift| {
+            let power = 1i32 << shift;
+            [power, power.wrapping_sub(1), power.wrapping_neg()]
+        });
+        for value in values {
+            let mut buf = Vec::new();
+            value.var_write_into(&mut buf).unwrap();
+            let mut cursor = Cursor::new(&buf[..]);
+            assert_eq!(i32::var_read_from(&mut cursor).unwrap(), value);
+            assert_eq!(cursor.position() as usize, buf.len());
+        }
+    }
+
     #[test]
     fn test_list() {
         let original_vec = vec!["a".to_string(), "bc".to_string(), "def".to_string()];
@@ -186,4 +238,68 @@ mod tests {
 
         assert_eq!(u64::read_from(&mut Cursor::new(&buf)).unwrap(), 123456);
     }
+
+    #[test]
+    fn test_varlong() {
+        for value in [0, 1, 300, i64::MAX, -1, i64::MIN] {
+            let mut buf = Vec::new();
+            value.var_write_into(&mut buf).unwrap();
+            let mut cursor = Cursor::new(&buf[..]);
+            assert_eq!(i64::var_read_from(&mut cursor).unwrap(), value);
+            assert_eq!(cursor.position() as usize, buf.len());
+        }
+        let mut buf = Vec::new();
+        0i64.var_write_into(&mut buf).unwrap();
+        assert_eq!(buf, [0]);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let bytes = bytes::Bytes::from_static(&[1, 2, 3]);
+        let mut buf = Vec::new();
+        bytes.write_into(&mut buf).unwrap();
+        // the same as a Vec<u8>
+        assert_eq!(buf, [3, 1, 2, 3]);
+        let mut cursor = Cursor::new(&buf[..]);
+        assert_eq!(bytes::Bytes::read_from(&mut cursor).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_read_collection_with_max() {
+        let mut buf = Vec::new();
+        vec![1u8, 2, 3].write_into(&mut buf).unwrap();
+
+        let result = read_collection_with_max::<u8>(&mut Cursor::new(&buf), 3).unwrap();
+        assert_eq!(result, vec![1, 2, 3]);
+        assert!(matches!(
+            read_collection_with_max::<u8>(&mut Cursor::new(&buf), 2),
+            Err(BufReadError::CollectionTooLong {
+                length: 3,
+                max_length: 2
+            })
+        ));
+
+        // a huge length fails before anything is allocated
+        let mut buf = Vec::new();
+        u32::MAX.var_write_into(&mut buf).unwrap();
+        assert!(read_collection_with_max::<u64>(&mut Cursor::new(&buf), 16).is_err());
+    }
+
+    #[test]
+    fn test_read_string_with_max() {
+        let mut buf = Vec::new();
+        "héllo".write_into(&mut buf).unwrap();
+        // é is two bytes but only one character
+        assert_eq!(
+            read_string_with_max(&mut Cursor::new(&buf), 5).unwrap(),
+            "héllo"
+        );
+        assert!(matches!(
+            read_string_with_max(&mut Cursor::new(&buf), 4),
+            Err(BufReadError::StringLengthTooLong {
+                length: 5,
+                max_length: 4
+            })
+        ));
+    }
 }
@@ -1,10 +1,7 @@
 use super::{UnsizedByteArray, MAX_STRING_LENGTH};
 use byteorder::{ReadBytesExt, BE};
-use std::{
-    collections::HashMap,
-    hash::Hash,
-    io::{Cursor, Read},
-};
+use bytes::Bytes;
+use std::{collections::HashMap, fmt, hash::Hash, io::Cursor};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -25,6 +22,10 @@ pub enum BufReadError {
     ),
     #[error("Invalid UTF-8")]
     InvalidUtf8,
+    #[error("Array has {length} elements, but it should have exactly {expected}")]
+    UnexpectedArrayLength { length: usize, expected: usize },
+    #[error("Collection has {length} elements, which is more than the maximum of {max_length}")]
+    CollectionTooLong { length: usize, max_length: usize },
     #[error("Unexpected enum variant {id}")]
     UnexpectedEnumVariant { id: i32 },
     #[error("Unexpected enum variant {id}")]
@@ -36,9 +37,88 @@ pub enum BufReadError {
     },
     #[error("{0}")]
     Custom(String),
+    /// An error from a type that has its own error type, like NBT or chunk
+    /// sections.
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error + Send + Sync>),
     #[cfg(feature = "serde_json")]
     #[error("{0}")]
     Deserialization(#[from] serde_json::Error),
+    /// Reading a field failed. This wraps the error so it says which field it
+    /// happened in, like `last_seen_messages.last_seen[2].profile_id`.
+    #[error("Error reading {}: {source}", FieldPath(path))]
+    InField {
+        path: Vec<FieldPathSegment>,
+        source: Box<BufReadError>,
+    },
+}
+
+/// A part of the path to the field where reading failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldPathSegment {
+    /// A field of a struct, or a variant of an enum.
+    Field(&'static str),
+    /// An element of a list.
+    Index(usize),
+}
+
+struct FieldPath<'a>(&'a [FieldPathSegment]);
+
+impl fmt::Display for FieldPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                FieldPathSegment::Field(name) if i == 0 => write!(f, "{name}")?,
+                FieldPathSegment::Field(name) => write!(f, ".{name}")?,
+                FieldPathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BufReadError {
+    /// Say that the error happened while reading the field with this name.
+    /// The McBuf derive does this for every field.
+    pub fn in_field(self, name: &'static str) -> Self {
+        self.in_segment(FieldPathSegment::Field(name))
+    }
+
+    /// Say that the error happened while reading the element at this index
+    /// of a list.
+    pub fn at_index(self, index: usize) -> Self {
+        self.in_segment(FieldPathSegment::Index(index))
+    }
+
+    fn in_segment(self, segment: FieldPathSegment) -> Self {
+        match self {
+            BufReadError::InField { mut path, source } => {
+                path.insert(0, segment);
+                BufReadError::InField { path, source }
+            }
+            source => BufReadError::InField {
+                path: vec![segment],
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The path to the field where reading failed, or an empty slice if we
+    /// don't know.
+    pub fn field_path(&self) -> &[FieldPathSegment] {
+        match self {
+            BufReadError::InField { path, .. } => path,
+            _ => &[],
+        }
+    }
+
+    /// The error without the field it happened in.
+    pub fn root_cause(&self) -> &BufReadError {
+        match self {
+            BufReadError::InField { source, .. } => source,
+            _ => self,
+        }
+    }
 }
 
 fn read_bytes<'a>(buf: &'a mut Cursor<&[u8]>, length: usize) -> Result<&'a [u8], BufReadError> {
@@ -54,7 +134,14 @@ fn read_bytes<'a>(buf: &'a mut Cursor<&[u8]>, length: usize) -> Result<&'a [u8],
     Ok(data)
 }
 
-fn read_utf_with_len(buf: &mut Cursor<&[u8]>, max_length: u32) -> Result<String, BufReadError> {
+/// Read a string that's at most `max_length` characters long, like a chat
+/// message. Strings are usually read with a maximum of [`MAX_STRING_LENGTH`].
+///
+/// The length is counted in UTF-16 code units, like Java does.
+pub fn read_string_with_max(
+    buf: &mut Cursor<&[u8]>,
+    max_length: u32,
+) -> Result<String, BufReadError> {
     let length = u32::var_read_from(buf)?;
     // i don't know why it's multiplied by 4 but it's like that in mojang's code so
     if length > max_length * 4 {
@@ -68,13 +155,62 @@ fn read_utf_with_len(buf: &mut Cursor<&[u8]>, max_length: u32) -> Result<String,
     let string = std::str::from_utf8(buffer)
         .map_err(|_| BufReadError::InvalidUtf8)?
         .to_string();
-    if string.len() > length as usize {
-        return Err(BufReadError::StringLengthTooLong { length, max_length });
+    // every UTF-16 code unit takes at least one byte in UTF-8, so short
+    // strings don't have to be counted
+    if string.len() > max_length as usize {
+        let length = string.encode_utf16().count() as u32;
+        if length > max_length {
+            return Err(BufReadError::StringLengthTooLong { length, max_length });
+        }
     }
 
     Ok(string)
 }
 
+/// Read a list that has at most `max_length` elements. Lists are usually
+/// only limited by how big the packet is, but elements that take up more
+/// memory than they do in the packet could still use a lot of it.
+pub fn read_collection_with_max<T: McBufReadable>(
+    buf: &mut Cursor<&[u8]>,
+    max_length: usize,
+) -> Result<Vec<T>, BufReadError> {
+    read_collection_with_max_by(buf, max_length, T::read_from)
+}
+
+/// Like [`read_collection_with_max`], but every element is read with the
+/// given function. This is useful for lists of strings that have their own
+/// maximum length.
+pub fn read_collection_with_max_by<T>(
+    buf: &mut Cursor<&[u8]>,
+    max_length: usize,
+    mut read_element: impl FnMut(&mut Cursor<&[u8]>) -> Result<T, BufReadError>,
+) -> Result<Vec<T>, BufReadError> {
+    let length = u32::var_read_from(buf)? as usize;
+    if length > max_length {
+        return Err(BufReadError::CollectionTooLong { length, max_length });
+    }
+    let mut contents = Vec::with_capacity(length);
+    for i in 0..length {
+        contents.push(read_element(buf).map_err(|e| e.at_index(i))?);
+    }
+    Ok(contents)
+}
+
+/// Read a byte array that has its length first, but that always has to be
+/// `N` bytes long, like light sections.
+pub fn read_prefixed_array<const N: usize>(
+    buf: &mut Cursor<&[u8]>,
+) -> Result<[u8; N], BufReadError> {
+    let length = u32::var_read_from(buf)? as usize;
+    if length != N {
+        return Err(BufReadError::UnexpectedArrayLength {
+            length,
+            expected: N,
+        });
+    }
+    <[u8; N]>::read_from(buf)
+}
+
 // fast varints modified from https://github.com/luojia65/mc-varint/blob/master/src/lib.rs#L67
 /// Read a single varint from the reader and return the value, along with the number of bytes read
 // pub async fn read_varint_async(
@@ -92,6 +228,52 @@ fn read_utf_with_len(buf: &mut Cursor<&[u8]>, max_length: u32) -> Result<String,
 //     Ok(ans)
 // }
 
+/// The most bytes that a varint can take up.
+const MAX_VARINT_LENGTH: usize = 5;
+/// The most bytes that a varlong can take up.
+const MAX_VARLONG_LENGTH: usize = 10;
+
+enum VarintError {
+    UnexpectedEof,
+    /// Every byte had the continuation bit set, so the varint never ended.
+    TooLong,
+}
+
+/// Decode a varint from the start of the slice, and return it along with
+/// how many bytes it took up. Bits that don't fit in a u64 are ignored, like
+/// vanilla does.
+#[inline]
+fn decode_varint(bytes: &[u8], max_length: usize) -> Result<(u64, usize), VarintError> {
+    // most varints are one byte, like packet ids and short lengths
+    if let Some(&byte) = bytes.first() {
+        if byte & 0b1000_0000 == 0 {
+            return Ok((byte as u64, 1));
+        }
+    }
+    let mut value = 0;
+    for (i, &byte) in bytes.iter().take(max_length).enumerate() {
+        value |= ((byte & 0b0111_1111) as u64) << (7 * i);
+        if byte & 0b1000_0000 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    if bytes.len() >= max_length {
+        Err(VarintError::TooLong)
+    } else {
+        Err(VarintError::UnexpectedEof)
+    }
+}
+
+/// Read a varint of at most `max_length` bytes straight from the cursor's
+/// slice. The cursor isn't moved if it fails.
+fn read_varint(buf: &mut Cursor<&[u8]>, max_length: usize) -> Result<u64, VarintError> {
+    let position = buf.position() as usize;
+    let bytes = buf.get_ref().get(position..).unwrap_or_default();
+    let (value, length) = decode_varint(bytes, max_length)?;
+    buf.set_position((position + length) as u64);
+    Ok(value)
+}
+
 pub trait McBufReadable
 where
     Self: Sized,
@@ -113,36 +295,26 @@ impl McBufReadable for i32 {
 }
 
 impl McBufVarReadable for i32 {
-    // fast varints modified from https://github.com/luojia65/mc-varint/blob/master/src/lib.rs#L67
-    /// Read a single varint from the reader and return the value
+    /// Read a varint, which is at most 5 bytes long.
     fn var_read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        let mut buffer = [0];
-        let mut ans = 0;
-        for i in 0..5 {
-            buf.read_exact(&mut buffer)?;
-            ans |= ((buffer[0] & 0b0111_1111) as i32) << (7 * i);
-            if buffer[0] & 0b1000_0000 == 0 {
-                break;
+        match read_varint(buf, MAX_VARINT_LENGTH) {
+            Ok(value) => Ok(value as i32),
+            // the frame splitter relies on this being an io error so it
+            // knows to wait for more data
+            Err(VarintError::UnexpectedEof) => {
+                Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
             }
+            Err(VarintError::TooLong) => Err(BufReadError::InvalidVarInt),
         }
-        Ok(ans)
     }
 }
 
 impl McBufVarReadable for i64 {
-    // fast varints modified from https://github.com/luojia65/mc-varint/blob/master/src/lib.rs#L54
+    /// Read a varlong, which is at most 10 bytes long.
     fn var_read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        let mut buffer = [0];
-        let mut ans = 0;
-        for i in 0..8 {
-            buf.read_exact(&mut buffer)
-                .map_err(|_| BufReadError::InvalidVarLong)?;
-            ans |= ((buffer[0] & 0b0111_1111) as i64) << (7 * i);
-            if buffer[0] & 0b1000_0000 == 0 {
-                break;
-            }
-        }
-        Ok(ans)
+        read_varint(buf, MAX_VARLONG_LENGTH)
+            .map(|value| value as i64)
+            .map_err(|_| BufReadError::InvalidVarLong)
     }
 }
 impl McBufVarReadable for u64 {
@@ -166,8 +338,8 @@ impl<T: McBufReadable + Send> McBufReadable for Vec<T> {
         // we don't set the capacity here so we can't get exploited into
         // allocating a bunch
         let mut contents = vec![];
-        for _ in 0..length {
-            contents.push(T::read_from(buf)?);
+        for i in 0..length {
+            contents.push(T::read_from(buf).map_err(|e| e.at_index(i))?);
         }
         Ok(contents)
     }
@@ -204,9 +376,16 @@ impl McBufReadable for Vec<u8> {
     }
 }
 
+impl McBufReadable for Bytes {
+    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        let length = i32::var_read_from(buf)? as usize;
+        read_bytes(buf, length).map(Bytes::copy_from_slice)
+    }
+}
+
 impl McBufReadable for String {
     fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        read_utf_with_len(buf, MAX_STRING_LENGTH.into())
+        read_string_with_max(buf, MAX_STRING_LENGTH.into())
     }
 }
 
@@ -244,8 +423,8 @@ impl<T: McBufVarReadable> McBufVarReadable for Vec<T> {
     fn var_read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
         let length = i32::var_read_from(buf)? as usize;
         let mut contents = Vec::new();
-        for _ in 0..length {
-            contents.push(T::var_read_from(buf)?);
+        for i in 0..length {
+            contents.push(T::var_read_from(buf).map_err(|e| e.at_index(i))?);
         }
         Ok(contents)
     }
@@ -319,8 +498,29 @@ impl<T: McBufVarReadable> McBufVarReadable for Option<T> {
 impl<T: McBufReadable, const N: usize> McBufReadable for [T; N] {
     default fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
         let mut contents = Vec::with_capacity(N);
-        for _ in 0..N {
-            contents.push(T::read_from(buf)?);
+        for i in 0..N {
+            contents.push(T::read_from(buf).map_err(|e| e.at_index(i))?);
+        }
+        contents.try_into().map_err(|_| {
+            unreachable!("Panic is not possible since the Vec is the same size as the array")
+        })
+    }
+}
+
+// [u8; 16], read all at once instead of a byte at a time
+impl<const N: usize> McBufReadable for [u8; N] {
+    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        let bytes = read_bytes(buf, N)?;
+        Ok(bytes.try_into().expect("read_bytes returns N bytes"))
+    }
+}
+
+// #[var] [i32; 3]
+impl<T: McBufVarReadable, const N: usize> McBufVarReadable for [T; N] {
+    fn var_read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        let mut contents = Vec::with_capacity(N);
+        for i in 0..N {
+            contents.push(T::var_read_from(buf).map_err(|e| e.at_index(i))?);
         }
         contents.try_into().map_err(|_| {
             unreachable!("Panic is not possible since the Vec is the same size as the array")
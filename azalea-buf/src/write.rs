@@ -1,5 +1,6 @@
 use super::{UnsizedByteArray, MAX_STRING_LENGTH};
 use byteorder::{BigEndian, WriteBytesExt};
+use bytes::Bytes;
 use std::{collections::HashMap, io::Write};
 
 fn write_utf_with_len(
@@ -104,6 +105,13 @@ impl McBufWritable for Vec<u8> {
     }
 }
 
+impl McBufWritable for Bytes {
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        (self.len() as u32).var_write_into(buf)?;
+        buf.write_all(self)
+    }
+}
+
 impl McBufWritable for String {
     fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
         write_utf_with_len(buf, self, MAX_STRING_LENGTH.into())
@@ -132,6 +140,9 @@ impl McBufVarWritable for i64 {
     fn var_write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
         let mut buffer = [0];
         let mut value = *self;
+        if value == 0 {
+            buf.write_all(&buffer)?;
+        }
         while value != 0 {
             buffer[0] = (value & 0b0111_1111) as u8;
             value = (value >> 7) & (i64::max_value() >> 6);
@@ -256,3 +267,18 @@ impl<T: McBufWritable, const N: usize> McBufWritable for [T; N] {
         Ok(())
     }
 }
+
+impl<const N: usize> McBufWritable for [u8; N] {
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        buf.write_all(self)
+    }
+}
+
+impl<T: McBufVarWritable, const N: usize> McBufVarWritable for [T; N] {
+    fn var_write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        for i in self {
+            i.var_write_into(buf)?;
+        }
+        Ok(())
+    }
+}
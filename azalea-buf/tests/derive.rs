@@ -0,0 +1,174 @@
+use azalea_buf::{
+    read_prefixed_array, BufReadError, FieldPathSegment, McBuf, McBufReadable, McBufWritable,
+};
+use std::io::{Cursor, Write};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, McBuf)]
+struct Flags {
+    #[var]
+    id: u32,
+    #[mcbuf(read_with = "read_flag", write_with = "write_flag")]
+    flag: bool,
+    #[mcbuf(skip)]
+    cached: Option<String>,
+    name: String,
+}
+
+fn read_flag(buf: &mut Cursor<&[u8]>) -> Result<bool, BufReadError> {
+    Ok(u8::read_from(buf)? == 0x42)
+}
+
+fn write_flag(flag: &bool, buf: &mut impl Write) -> Result<(), std::io::Error> {
+    u8::write_into(&if *flag { 0x42 } else { 0 }, buf)
+}
+
+#[test]
+fn test_field_attributes() {
+    let flags = Flags {
+        id: 300,
+        flag: true,
+        cached: Some("not sent".to_string()),
+        name: "a".to_string(),
+    };
+    let mut buf = Vec::new();
+    flags.write_into(&mut buf).unwrap();
+    assert_eq!(buf, [0xac, 0x02, 0x42, 1, b'a']);
+
+    let read = Flags::read_from(&mut Cursor::new(&buf[..])).unwrap();
+    assert_eq!(
+        read,
+        Flags {
+            cached: None,
+            ..flags
+        }
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, McBuf)]
+#[mcbuf(tag = "u8")]
+enum Small {
+    A,
+    B = 200,
+    C,
+}
+
+#[derive(Debug, PartialEq, McBuf)]
+#[mcbuf(tag = "varlong")]
+enum WithData {
+    Nothing,
+    Number(u16),
+}
+
+#[test]
+fn test_enum_tags() {
+    let mut buf = Vec::new();
+    for small in [Small::A, Small::B, Small::C] {
+        small.write_into(&mut buf).unwrap();
+    }
+    // a varint would take two bytes for 200 and 201
+    assert_eq!(buf, [0, 200, 201]);
+    let mut cursor = Cursor::new(&buf[..]);
+    for small in [Small::A, Small::B, Small::C] {
+        assert_eq!(Small::read_from(&mut cursor).unwrap(), small);
+    }
+
+    let mut buf = Vec::new();
+    WithData::Nothing.write_into(&mut buf).unwrap();
+    WithData::Number(1).write_into(&mut buf).unwrap();
+    assert_eq!(buf, [0, 1, 0, 1]);
+    let mut cursor = Cursor::new(&buf[..]);
+    assert_eq!(WithData::read_from(&mut cursor).unwrap(), WithData::Nothing);
+    assert_eq!(
+        WithData::read_from(&mut cursor).unwrap(),
+        WithData::Number(1)
+    );
+}
+
+#[derive(Debug, McBuf)]
+struct Outer {
+    list: Vec<Inner>,
+}
+
+#[derive(Debug, McBuf)]
+struct Inner {
+    #[var]
+    r#type: u32,
+    data: WithData,
+}
+
+#[test]
+fn test_error_field_path() {
+    // the second element's data is cut off
+    let buf = [2, 1, 0, 2, 1];
+    let err = Outer::read_from(&mut Cursor::new(&buf[..])).unwrap_err();
+    assert_eq!(
+        err.field_path(),
+        [
+            FieldPathSegment::Field("list"),
+            FieldPathSegment::Index(1),
+            FieldPathSegment::Field("data"),
+            FieldPathSegment::Field("Number"),
+        ]
+    );
+    assert!(matches!(
+        err.root_cause(),
+        BufReadError::UnexpectedEof { .. } | BufReadError::Io(_)
+    ));
+    assert!(err
+        .to_string()
+        .starts_with("Error reading list[1].data.Number: "));
+}
+
+#[derive(Debug, PartialEq, McBuf)]
+struct Arrays {
+    bytes: [u8; 3],
+    #[var]
+    numbers: [i32; 2],
+    longs: [u64; 2],
+    uuids: [Uuid; 2],
+}
+
+#[test]
+fn test_array_fields() {
+    let arrays = Arrays {
+        bytes: [1, 2, 3],
+        numbers: [300, -1],
+        longs: [4, 5],
+        uuids: [Uuid::from_u128(6), Uuid::from_u128(7)],
+    };
+    let mut buf = Vec::new();
+    arrays.write_into(&mut buf).unwrap();
+    // arrays don't have their length first
+    assert_eq!(&buf[..3], [1, 2, 3]);
+    assert_eq!(&buf[3..5], [0xac, 0x02]);
+    assert_eq!(buf.len(), 3 + 2 + 5 + 8 * 2 + 16 * 2);
+    assert_eq!(
+        Arrays::read_from(&mut Cursor::new(&buf[..])).unwrap(),
+        arrays
+    );
+
+    // the last uuid is cut off
+    let err = Arrays::read_from(&mut Cursor::new(&buf[..buf.len() - 1])).unwrap_err();
+    assert_eq!(
+        err.field_path(),
+        [FieldPathSegment::Field("uuids"), FieldPathSegment::Index(1)]
+    );
+}
+
+#[test]
+fn test_prefixed_array() {
+    let mut buf = [3, 1, 2, 3];
+    assert_eq!(
+        read_prefixed_array::<3>(&mut Cursor::new(&buf[..])).unwrap(),
+        [1, 2, 3]
+    );
+    buf[0] = 4;
+    assert!(matches!(
+        read_prefixed_array::<3>(&mut Cursor::new(&buf[..])),
+        Err(BufReadError::UnexpectedArrayLength {
+            length: 4,
+            expected: 3
+        })
+    ));
+}
@@ -3,8 +3,12 @@ use std::{
     io::{Cursor, Write},
 };
 
-use azalea_buf::{BufReadError, McBufReadable, McBufWritable};
-use serde::{de, Deserialize, Deserializer};
+use azalea_buf::{
+    read_string_with_max, BufReadError, McBufReadable, McBufWritable, MAX_COMPONENT_STRING_LENGTH,
+};
+use azalea_nbt::Tag;
+use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
 
 use crate::{
     base_component::BaseComponent,
@@ -238,21 +242,100 @@ impl<'de> Deserialize<'de> for Component {
     }
 }
 
+/// Components are serialized to the same JSON as vanilla, so they can be
+/// sent to the server.
+impl Serialize for Component {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            Component::Text(c) => map.serialize_entry("text", &c.text)?,
+            Component::Translatable(c) => {
+                map.serialize_entry("translate", &c.key)?;
+                if !c.args.is_empty() {
+                    map.serialize_entry("with", &c.args)?;
+                }
+            }
+        }
+        let base = self.get_base();
+        base.style.serialize_entries(&mut map)?;
+        if !base.siblings.is_empty() {
+            map.serialize_entry("extra", &base.siblings)?;
+        }
+        map.end()
+    }
+}
+
 impl McBufReadable for Component {
     fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        let string = String::read_from(buf)?;
-        let json: serde_json::Value = serde_json::from_str(string.as_str())?;
-        let component = Component::deserialize(json)?;
-        Ok(component)
+        let string = read_string_with_max(buf, MAX_COMPONENT_STRING_LENGTH)?;
+        Ok(Component::from_json(&string)?)
+    }
+}
+
+impl Component {
+    /// Parse a component from its JSON, which is how components are sent
+    /// over the network and stored in item NBT.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let json: serde_json::Value = serde_json::from_str(json)?;
+        Component::deserialize(json)
+    }
+
+    /// Convert an NBT tag into a component. Since Minecraft 1.20.3, this is
+    /// how components are sent over the network instead of as JSON strings.
+    pub fn from_nbt(tag: &Tag) -> Result<Self, serde_json::Error> {
+        Component::deserialize(nbt_to_json(tag))
+    }
+
+    /// Read a component that was sent as unnamed NBT, like in 1.20.3+.
+    pub fn read_nbt_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        let tag = Tag::read_unnamed(buf)?;
+        Ok(Component::from_nbt(&tag)?)
+    }
+}
+
+/// Convert NBT to the JSON that the component would've been sent as before
+/// 1.20.3, so we only need one deserializer.
+fn nbt_to_json(tag: &Tag) -> Value {
+    match tag {
+        Tag::End => Value::Null,
+        // booleans are sent as bytes, and components don't have any other
+        // byte fields
+        Tag::Byte(b) => match b {
+            0 => Value::Bool(false),
+            1 => Value::Bool(true),
+            _ => Value::from(*b),
+        },
+        Tag::Short(n) => Value::from(*n),
+        Tag::Int(n) => Value::from(*n),
+        Tag::Long(n) => Value::from(*n),
+        Tag::Float(n) => Value::from(*n),
+        Tag::Double(n) => Value::from(*n),
+        Tag::ByteArray(bytes) => Value::from(bytes.clone()),
+        Tag::String(s) => Value::from(s.clone()),
+        Tag::List(list) => Value::Array(list.iter().map(nbt_to_json).collect()),
+        Tag::Compound(map) => {
+            // NBT lists can only have one type, so lists that mix strings and
+            // components (like translation args) have their strings wrapped
+            // in a compound with an empty key
+            if map.len() == 1 {
+                if let Some(value) = map.get("") {
+                    return nbt_to_json(value);
+                }
+            }
+            Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), nbt_to_json(value)))
+                    .collect(),
+            )
+        }
+        Tag::IntArray(ints) => Value::from(ints.clone()),
+        Tag::LongArray(longs) => Value::from(longs.clone()),
     }
 }
 
 impl McBufWritable for Component {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        // let json = serde_json::to_string(self).unwrap();
-        // json.write_into(_buf);
-        // Ok(())
-        todo!()
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        serde_json::to_string(self)?.write_into(buf)
     }
 }
 
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, fmt};
 
 use azalea_buf::McBuf;
+use serde::{ser::SerializeMap, Serialize, Serializer};
 use serde_json::Value;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -26,6 +27,15 @@ impl TextColor {
     fn from_rgb(value: u32) -> TextColor {
         TextColor { value, name: None }
     }
+
+    /// The color like it's written in json, which is the name for named
+    /// colors and the hex code for the rest.
+    pub fn serialize(&self) -> String {
+        match &self.name {
+            Some(name) => name.to_ascii_lowercase(),
+            None => format!("#{:06X}", self.value),
+        }
+    }
 }
 
 lazy_static! {
@@ -98,6 +108,13 @@ pub enum ChatFormatting {
     Reset,
 }
 
+/// Formatting is serialized as its lowercase name, like in json.
+impl Serialize for ChatFormatting {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name().to_ascii_lowercase())
+    }
+}
+
 impl ChatFormatting {
     pub const FORMATTERS: [ChatFormatting; 22] = [
         ChatFormatting::Black,
@@ -292,6 +309,26 @@ impl Style {
         Self::default()
     }
 
+    /// Add the style to the json object of a component. Unset options
+    /// aren't added.
+    pub(crate) fn serialize_entries<M: SerializeMap>(&self, map: &mut M) -> Result<(), M::Error> {
+        if let Some(color) = &self.color {
+            map.serialize_entry("color", &color.serialize())?;
+        }
+        for (key, value) in [
+            ("bold", self.bold),
+            ("italic", self.italic),
+            ("underlined", self.underlined),
+            ("strikethrough", self.strikethrough),
+            ("obfuscated", self.obfuscated),
+        ] {
+            if let Some(value) = value {
+                map.serialize_entry(key, &value)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn deserialize(json: &Value) -> Style {
         return if json.is_object() {
             let json_object = json.as_object().unwrap();
@@ -1,5 +1,7 @@
 use std::fmt::{self, Display, Formatter};
 
+use serde::{Serialize, Serializer};
+
 use crate::{
     base_component::BaseComponent, component::Component, style::Style,
     text_component::TextComponent,
@@ -11,6 +13,15 @@ pub enum StringOrComponent {
     Component(Component),
 }
 
+impl Serialize for StringOrComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            StringOrComponent::String(s) => serializer.serialize_str(s),
+            StringOrComponent::Component(c) => c.serialize(serializer),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TranslatableComponent {
     pub base: BaseComponent,
@@ -1,9 +1,11 @@
+use azalea_buf::McBufWritable;
 use azalea_chat::{
     component::Component,
     style::{Ansi, ChatFormatting, TextColor},
 };
 use serde::Deserialize;
 use serde_json::Value;
+use std::io::Cursor;
 
 #[test]
 fn basic_ansi_test() {
@@ -73,3 +75,62 @@ fn component_from_string() {
     let component = Component::deserialize(&j).unwrap();
     assert_eq!(component.to_ansi(None), "foo");
 }
+
+#[test]
+fn nbt_component_matches_json() {
+    fn string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u16).to_be_bytes().to_vec();
+        bytes.extend(value.as_bytes());
+        bytes
+    }
+    fn named(id: u8, name: &str, payload: Vec<u8>) -> Vec<u8> {
+        let mut bytes = vec![id];
+        bytes.extend(string(name));
+        bytes.extend(payload);
+        bytes
+    }
+
+    // {translate: "chat.type.text", with: [{"": "bot"}, {text: "hi", bold: 1b}]}
+    let mut nbt = vec![10];
+    nbt.extend(named(8, "translate", string("chat.type.text")));
+    nbt.extend(named(9, "with", {
+        let mut list = vec![10, 0, 0, 0, 2];
+        list.extend(named(8, "", string("bot")));
+        list.push(0);
+        list.extend(named(8, "text", string("hi")));
+        list.extend(named(1, "bold", vec![1]));
+        list.push(0);
+        list
+    }));
+    nbt.push(0);
+    let nbt_component = Component::read_nbt_from(&mut Cursor::new(&nbt)).unwrap();
+
+    let j: Value = serde_json::from_str(
+        r#"{"translate": "chat.type.text", "with": ["bot", {"text": "hi", "bold": true}]}"#,
+    )
+    .unwrap();
+    let json_component = Component::deserialize(&j).unwrap();
+
+    assert_eq!(format!("{nbt_component:?}"), format!("{json_component:?}"));
+    assert_eq!(nbt_component.to_ansi(None), json_component.to_ansi(None));
+}
+
+#[test]
+fn component_serializes_to_vanilla_json() {
+    let j: Value = serde_json::from_str(
+        r##"{
+    "translate": "chat.type.text",
+    "with": ["bot", {"text": "hi", "color": "#FF00AA", "bold": true}],
+    "color": "gold",
+    "extra": [{"text": "!", "italic": false}]
+}"##,
+    )
+    .unwrap();
+    let component = Component::deserialize(&j).unwrap();
+    assert_eq!(serde_json::to_value(&component).unwrap(), j);
+
+    let component = Component::from("hello".to_string());
+    let mut buf = Vec::new();
+    component.write_into(&mut buf).unwrap();
+    assert_eq!(buf, b"\x10{\"text\":\"hello\"}");
+}
@@ -1,6 +1,8 @@
 //! Connect to Minecraft servers.
 
 use crate::get_mc_dir;
+use azalea_auth::certs::Certificates;
+use log::warn;
 use uuid::Uuid;
 
 /// Something that can join Minecraft servers.
@@ -15,6 +17,9 @@ pub struct Account {
     pub access_token: Option<String>,
     /// Only required for online-mode accounts.
     pub uuid: Option<uuid::Uuid>,
+    /// The keys that chat messages are signed with. Servers with
+    /// `enforce-secure-profile` kick players that don't have these.
+    pub certs: Option<Certificates>,
 }
 
 impl Account {
@@ -26,6 +31,7 @@ impl Account {
             username: username.to_string(),
             access_token: None,
             uuid: None,
+            certs: None,
         }
     }
 
@@ -43,10 +49,20 @@ impl Account {
             },
         )
         .await?;
+        // chat still works on most servers without these, so it's not worth
+        // failing over
+        let certs = match azalea_auth::certs::fetch_certificates(&auth_result.access_token).await {
+            Ok(certs) => Some(certs),
+            Err(e) => {
+                warn!("Couldn't get chat signing keys: {e}");
+                None
+            }
+        };
         Ok(Self {
             username: auth_result.profile.name,
             access_token: Some(auth_result.access_token),
             uuid: Some(Uuid::parse_str(&auth_result.profile.id).expect("Invalid UUID")),
+            certs,
         })
     }
 }
@@ -1,55 +1,153 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use azalea_crypto::MessageSignature;
+use azalea_crypto::{MessageChain, MessageSignature, PrivateKey};
 use azalea_protocol::packets::game::{
-    clientbound_player_chat_packet::LastSeenMessagesUpdate,
+    clientbound_player_chat_packet::{LastSeenMessagesEntry, LastSeenMessagesUpdate},
     serverbound_chat_command_packet::ServerboundChatCommandPacket,
     serverbound_chat_packet::ServerboundChatPacket,
 };
+use log::warn;
+use uuid::Uuid;
 
 use crate::Client;
 
+/// How many senders we tell the server we've seen messages from.
+const LAST_SEEN_SIZE: usize = 5;
+/// How many messages we can receive before we have to acknowledge them with a
+/// chat ack packet, if we haven't sent any chat messages in the meantime.
+const MAX_UNACKNOWLEDGED: u32 = 64;
+
+/// The state that's needed to sign our chat messages, so the server can
+/// check that they were really sent by us.
+pub(crate) struct ChatSigner {
+    /// The player's private key, or `None` for offline accounts. Messages
+    /// are sent unsigned if this isn't present.
+    private_key: Option<PrivateKey>,
+    chain: MessageChain,
+    /// The last message from each player that we've seen, newest first.
+    last_seen: Vec<LastSeenMessagesEntry>,
+    /// How many messages we've seen since we last told the server about
+    /// them.
+    unacknowledged: u32,
+}
+
+impl ChatSigner {
+    pub(crate) fn new(private_key: Option<PrivateKey>, uuid: Uuid) -> Self {
+        Self {
+            private_key,
+            chain: MessageChain::new(uuid),
+            last_seen: Vec::new(),
+            unacknowledged: 0,
+        }
+    }
+
+    /// Remember that we saw a signed message. If we've seen too many without
+    /// acknowledging them, this returns the update that should be sent in a
+    /// chat ack packet.
+    pub(crate) fn add_seen(
+        &mut self,
+        entry: LastSeenMessagesEntry,
+    ) -> Option<LastSeenMessagesUpdate> {
+        self.last_seen
+            .retain(|seen| seen.profile_id != entry.profile_id);
+        self.last_seen.insert(0, entry);
+        self.last_seen.truncate(LAST_SEEN_SIZE);
+
+        self.unacknowledged += 1;
+        if self.unacknowledged > MAX_UNACKNOWLEDGED {
+            Some(self.acknowledge())
+        } else {
+            None
+        }
+    }
+
+    /// The messages that we tell the server we've seen. Every message we
+    /// send acknowledges them.
+    fn acknowledge(&mut self) -> LastSeenMessagesUpdate {
+        self.unacknowledged = 0;
+        LastSeenMessagesUpdate {
+            last_seen: self.last_seen.clone(),
+            last_received: None,
+        }
+    }
+
+    /// Sign a chat message, which adds it to our message chain. The
+    /// signature is empty if we don't have a key or signing failed.
+    fn sign(
+        &mut self,
+        message: &str,
+        timestamp: u64,
+        salt: u64,
+        last_seen: &LastSeenMessagesUpdate,
+    ) -> MessageSignature {
+        let private_key = match &self.private_key {
+            Some(private_key) => private_key,
+            None => return MessageSignature::default(),
+        };
+        let last_seen = last_seen
+            .last_seen
+            .iter()
+            .map(|entry| (entry.profile_id, &entry.last_signature))
+            .collect::<Vec<_>>();
+        let body_hash =
+            azalea_crypto::hash_message_body(salt, timestamp, message, None, &last_seen);
+        match self.chain.sign(&body_hash, |data| private_key.sign(data)) {
+            Ok((_, signature)) => signature,
+            Err(e) => {
+                warn!("Couldn't sign chat message, sending it unsigned: {e}");
+                MessageSignature::default()
+            }
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time shouldn't be before epoch")
+        .as_millis()
+        .try_into()
+        .expect("Instant should fit into a u64")
+}
+
 impl Client {
     /// Sends chat message to the server. This only sends the chat packet and
     /// not the command packet. The [`Client::chat`] function handles checking whether
     /// the message is a command and using the proper packet for you, so you
     /// should use that instead.
     pub async fn send_chat_packet(&self, message: &str) -> Result<(), std::io::Error> {
-        // TODO: chat signing
-        let signature = sign_message();
-        let packet = ServerboundChatPacket {
-            message: message.to_string(),
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time shouldn't be before epoch")
-                .as_millis()
-                .try_into()
-                .expect("Instant should fit into a u64"),
-            salt: azalea_crypto::make_salt(),
-            signature,
-            signed_preview: false,
-            last_seen_messages: LastSeenMessagesUpdate::default(),
-        }
-        .get();
+        let timestamp = now_millis();
+        let salt = azalea_crypto::make_salt();
+        let packet = {
+            let mut chat_signer = self.chat_signer.lock();
+            let last_seen_messages = chat_signer.acknowledge();
+            ServerboundChatPacket {
+                message: message.to_string(),
+                timestamp,
+                salt,
+                signature: chat_signer.sign(message, timestamp, salt, &last_seen_messages),
+                signed_preview: false,
+                last_seen_messages,
+            }
+            .get()
+        };
         self.write_packet(packet).await
     }
 
     /// Send a command packet to the server. The `command` argument should not
     /// include the slash at the front.
+    ///
+    /// The command's arguments aren't signed, so servers that enforce secure
+    /// chat won't accept signed arguments (like the message in `/msg`) from
+    /// it.
     pub async fn send_command_packet(&self, command: &str) -> Result<(), std::io::Error> {
-        // TODO: chat signing
         let packet = ServerboundChatCommandPacket {
             command: command.to_string(),
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time shouldn't be before epoch")
-                .as_millis()
-                .try_into()
-                .expect("Instant should fit into a u64"),
+            timestamp: now_millis(),
             salt: azalea_crypto::make_salt(),
             argument_signatures: vec![],
             signed_preview: false,
-            last_seen_messages: LastSeenMessagesUpdate::default(),
+            last_seen_messages: self.chat_signer.lock().acknowledge(),
         }
         .get();
         self.write_packet(packet).await
@@ -96,8 +194,50 @@ impl Client {
     // pub fn acknowledge_preview(&self, message: &str) {}
 }
 
-// TODO
-// MessageSigner, ChatMessageContent, LastSeenMessages
-fn sign_message() -> MessageSignature {
-    MessageSignature::default()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sender: u128, signature: u8) -> LastSeenMessagesEntry {
+        LastSeenMessagesEntry {
+            profile_id: Uuid::from_u128(sender),
+            last_signature: MessageSignature {
+                bytes: vec![signature],
+            },
+        }
+    }
+
+    #[test]
+    fn test_last_seen() {
+        let mut chat_signer = ChatSigner::new(None, Uuid::nil());
+        for sender in 0..7 {
+            assert!(chat_signer.add_seen(entry(sender, 0)).is_none());
+        }
+        // a newer message from the same sender replaces the old one
+        chat_signer.add_seen(entry(4, 1));
+        let seen = chat_signer
+            .acknowledge()
+            .last_seen
+            .iter()
+            .map(|entry| (entry.profile_id.as_u128(), entry.last_signature.bytes[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(seen, [(4, 1), (6, 0), (5, 0), (3, 0), (2, 0)]);
+
+        for _ in 0..MAX_UNACKNOWLEDGED {
+            assert!(chat_signer.add_seen(entry(1, 0)).is_none());
+        }
+        assert!(chat_signer.add_seen(entry(1, 0)).is_some());
+        assert_eq!(chat_signer.unacknowledged, 0);
+    }
+
+    #[test]
+    fn test_unsigned_without_key() {
+        let mut chat_signer = ChatSigner::new(None, Uuid::nil());
+        let last_seen = chat_signer.acknowledge();
+        assert_eq!(
+            chat_signer.sign("hello", 0, 0, &last_seen),
+            MessageSignature::default()
+        );
+        assert!(chat_signer.chain.previous_signature().is_none());
+    }
 }
@@ -1,41 +1,55 @@
-use crate::{movement::MoveDirection, Account, Player};
+use crate::{
+    chat::ChatSigner, movement::MoveDirection, tab_complete::PendingSuggestions,
+    tag_query::PendingTagQueries, Account, Listeners, Player, PlayerInventory, PlayerList,
+    PluginChannels, ResourcePackPolicy, WorldBorder, HOTBAR_SIZE,
+};
 use azalea_auth::game_profile::GameProfile;
+use azalea_block::BlockState;
 use azalea_chat::component::Component;
-use azalea_core::{ChunkPos, ResourceLocation, Vec3};
+use azalea_core::{BlockPos, ChunkPos, ResourceLocation, Vec3};
+use azalea_crypto::SaltSignaturePair;
 use azalea_protocol::{
     connect::{Connection, ConnectionError, ReadConnection, WriteConnection},
+    forwarding::{BungeecordForwarding, VelocityForwarding},
     packets::{
         game::{
-            clientbound_player_chat_packet::ClientboundPlayerChatPacket,
+            clientbound_light_update_packet::ClientboundLightUpdatePacketData,
+            clientbound_player_chat_packet::{ClientboundPlayerChatPacket, LastSeenMessagesEntry},
             clientbound_system_chat_packet::ClientboundSystemChatPacket,
             serverbound_accept_teleportation_packet::ServerboundAcceptTeleportationPacket,
+            serverbound_chat_ack_packet::ServerboundChatAckPacket,
             serverbound_client_information_packet::ServerboundClientInformationPacket,
             serverbound_custom_payload_packet::ServerboundCustomPayloadPacket,
             serverbound_keep_alive_packet::ServerboundKeepAlivePacket,
             serverbound_move_player_pos_rot_packet::ServerboundMovePlayerPosRotPacket,
+            serverbound_pong_packet::ServerboundPongPacket,
             ClientboundGamePacket, ServerboundGamePacket,
         },
         handshake::client_intention_packet::ClientIntentionPacket,
         login::{
-            serverbound_hello_packet::ServerboundHelloPacket,
+            clientbound_custom_query_packet::ClientboundCustomQueryPacket,
+            serverbound_hello_packet::{ProfilePublicKeyData, ServerboundHelloPacket},
             serverbound_key_packet::{NonceOrSaltSignature, ServerboundKeyPacket},
             ClientboundLoginPacket,
         },
-        ConnectionProtocol, PROTOCOL_VERSION,
+        ConnectionProtocol,
     },
     read::ReadPacketError,
     resolver, ServerAddress,
 };
+use azalea_registry::BlockEntityType;
 use azalea_world::{
     entity::{EntityData, EntityMut, EntityRef},
-    Dimension,
+    BiomeRegistry, BlockEntity, Dimension, LightLayer,
 };
 use log::{debug, error, warn};
 use parking_lot::{Mutex, RwLock};
 use std::{
     fmt::Debug,
     io::{self, Cursor},
+    net::IpAddr,
     sync::Arc,
+    time::Duration,
 };
 use thiserror::Error;
 use tokio::{
@@ -43,6 +57,7 @@ use tokio::{
     task::JoinHandle,
     time::{self},
 };
+use uuid::Uuid;
 
 pub type ClientInformation = ServerboundClientInformationPacket;
 
@@ -56,10 +71,21 @@ pub enum Event {
     /// doesn't have to be sent twice.
     Initialize,
     Login,
+    /// A chat message, either from a player or from the server (which
+    /// includes most plugin messages and action bar text).
     Chat(ChatPacket),
     /// Happens 20 times per second, but only when the world is loaded.
     Tick,
     Packet(Box<ClientboundGamePacket>),
+    /// The server told us our new round-trip time, see [`Client::latency`].
+    Latency(Duration),
+    /// The client was disconnected from the server. This is the last event
+    /// that will be sent.
+    ///
+    /// The reason is `Some` if the server kicked us with a message, and
+    /// `None` if the connection was closed some other way (for example the
+    /// socket died).
+    Disconnect(Option<Component>),
 }
 
 #[derive(Debug, Clone)]
@@ -69,12 +95,48 @@ pub enum ChatPacket {
 }
 
 impl ChatPacket {
+    /// The full message, including the sender part for player chat.
     pub fn message(&self) -> Component {
         match self {
             ChatPacket::System(p) => p.content.clone(),
             ChatPacket::Player(p) => p.message(false),
         }
     }
+
+    /// The message without the sender part.
+    pub fn content(&self) -> Component {
+        match self {
+            ChatPacket::System(p) => p.content.clone(),
+            ChatPacket::Player(p) => p.message.content(false),
+        }
+    }
+
+    /// The display name of the player that sent the message, or `None` if it
+    /// was sent by the server.
+    pub fn sender(&self) -> Option<String> {
+        match self {
+            ChatPacket::System(_) => None,
+            ChatPacket::Player(p) => Some(p.chat_type.name.to_string()),
+        }
+    }
+
+    /// The UUID of the player that sent the message, or `None` if it was sent
+    /// by the server.
+    pub fn sender_uuid(&self) -> Option<Uuid> {
+        match self {
+            ChatPacket::System(_) => None,
+            ChatPacket::Player(p) => Some(p.message.signed_header.sender),
+        }
+    }
+
+    /// Whether the message should be shown above the hotbar (the "action
+    /// bar") instead of in the chat box.
+    pub fn is_overlay(&self) -> bool {
+        match self {
+            ChatPacket::System(p) => p.overlay,
+            ChatPacket::Player(_) => false,
+        }
+    }
 }
 
 /// A player that you control that is currently in a Minecraft server.
@@ -84,9 +146,29 @@ pub struct Client {
     pub read_conn: Arc<tokio::sync::Mutex<ReadConnection<ClientboundGamePacket>>>,
     pub write_conn: Arc<tokio::sync::Mutex<WriteConnection<ServerboundGamePacket>>>,
     pub player: Arc<Mutex<Player>>,
+    pub inventory: Arc<Mutex<PlayerInventory>>,
+    /// The players in the tab list.
+    pub player_list: Arc<Mutex<PlayerList>>,
+    /// The world border of the dimension that we're in.
+    pub world_border: Arc<Mutex<WorldBorder>>,
+    /// Handlers for specific packets, registered with [`Client::on`].
+    pub listeners: Arc<Mutex<Listeners>>,
+    /// Handlers for plugin messages, registered with
+    /// [`Client::on_plugin_message`].
+    pub plugin_channels: Arc<Mutex<PluginChannels>>,
     pub dimension: Arc<Mutex<Dimension>>,
     pub physics_state: Arc<Mutex<PhysicsState>>,
     pub client_information: Arc<RwLock<ClientInformation>>,
+    /// How resource packs from the server are answered, see
+    /// [`Client::set_resource_pack_policy`].
+    pub(crate) resource_pack_policy: Arc<RwLock<ResourcePackPolicy>>,
+    /// The [`Client::tab_complete`] requests that are waiting for a response.
+    pub(crate) pending_suggestions: Arc<Mutex<PendingSuggestions>>,
+    /// The [`Client::query_block_entity_tag`] and
+    /// [`Client::query_entity_tag`] requests that are waiting for a response.
+    pub(crate) pending_tag_queries: Arc<Mutex<PendingTagQueries>>,
+    /// Our message chain and the messages we've seen, for signing chat.
+    pub(crate) chat_signer: Arc<Mutex<ChatSigner>>,
     tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
 }
 
@@ -98,6 +180,9 @@ pub struct PhysicsState {
     pub move_direction: MoveDirection,
     pub forward_impulse: f32,
     pub left_impulse: f32,
+    /// Whether we're trying to get off our vehicle, see
+    /// [`Client::dismount`].
+    pub dismounting: bool,
 }
 
 /// Whether we should ignore errors when decoding packets.
@@ -117,6 +202,12 @@ pub enum JoinError {
     SessionServer(#[from] azalea_auth::sessionserver::SessionServerError),
     #[error("The given address could not be parsed into a ServerAddress")]
     InvalidAddress,
+    #[error("Couldn't join the server because we were disconnected: {reason}")]
+    Disconnect { reason: Component },
+    #[error("Couldn't encrypt the connection: {0}")]
+    Encryption(String),
+    #[error("Couldn't sign the encryption response: {0}")]
+    Signing(#[from] azalea_crypto::SigningError),
 }
 
 #[derive(Error, Debug)]
@@ -150,9 +241,151 @@ impl Client {
     pub async fn join(
         account: &Account,
         address: impl TryInto<ServerAddress>,
+    ) -> Result<(Self, UnboundedReceiver<Event>), JoinError> {
+        Self::join_with_login_plugin_handler(account, address, |_| None).await
+    }
+
+    /// Connect to an offline-mode Minecraft server with the given username.
+    /// This is the same as calling [`Client::join`] with
+    /// [`Account::offline`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use azalea_client::Client;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (client, rx) = Client::join_offline("localhost", "bot").await?;
+    /// client.chat("Hello, world!").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn join_offline(
+        address: impl TryInto<ServerAddress>,
+        username: &str,
+    ) -> Result<(Self, UnboundedReceiver<Event>), JoinError> {
+        Self::join(&Account::offline(username), address).await
+    }
+
+    /// Connect to a Minecraft server, answering the login plugin requests
+    /// (custom queries) that it sends while we're logging in. This is needed
+    /// for some proxies and modded servers.
+    ///
+    /// The handler should return `None` for requests it doesn't understand,
+    /// which is what [`Client::join`] does for every request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use azalea_client::{Account, Client};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let account = Account::offline("bot");
+    /// let (client, rx) = Client::join_with_login_plugin_handler(&account, "localhost", |p| {
+    ///     if p.identifier.to_string() == "example:hello" {
+    ///         Some(b"hi".to_vec())
+    ///     } else {
+    ///         None
+    ///     }
+    /// })
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn join_with_login_plugin_handler(
+        account: &Account,
+        address: impl TryInto<ServerAddress>,
+        login_plugin_handler: impl FnMut(&ClientboundCustomQueryPacket) -> Option<Vec<u8>> + Send,
     ) -> Result<(Self, UnboundedReceiver<Event>), JoinError> {
         let address: ServerAddress = address.try_into().map_err(|_| JoinError::InvalidAddress)?;
+        let hostname = address.host.clone();
+        Self::join_with_hostname(account, address, hostname, None, login_plugin_handler).await
+    }
 
+    /// Connect directly to a backend server that's behind a BungeeCord proxy
+    /// with `ip_forward` enabled, skipping the proxy. The server trusts the
+    /// forwarded data, so it'll think we're connecting from `ip`.
+    ///
+    /// The account's UUID is forwarded, or the offline-mode UUID for its
+    /// username if it doesn't have one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use azalea_client::{Account, Client};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let account = Account::offline("bot");
+    /// let (client, rx) = Client::join_with_bungeecord_forwarding(
+    ///     &account,
+    ///     "localhost:25566",
+    ///     "127.0.0.1".parse()?,
+    /// )
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn join_with_bungeecord_forwarding(
+        account: &Account,
+        address: impl TryInto<ServerAddress>,
+        ip: IpAddr,
+    ) -> Result<(Self, UnboundedReceiver<Event>), JoinError> {
+        let address: ServerAddress = address.try_into().map_err(|_| JoinError::InvalidAddress)?;
+        let hostname = BungeecordForwarding {
+            host: address.host.clone(),
+            ip: ip.to_string(),
+            uuid: account
+                .uuid
+                .unwrap_or_else(|| azalea_crypto::offline_uuid(&account.username)),
+            properties: vec![],
+        }
+        .to_hostname();
+        Self::join_with_hostname(account, address, hostname, None, |_| None).await
+    }
+
+    /// Connect directly to a backend server that's behind a Velocity proxy
+    /// with modern forwarding, skipping the proxy. `secret` is the forwarding
+    /// secret from Velocity's config, and the server will think we're
+    /// connecting from `ip`.
+    ///
+    /// The account's UUID is forwarded, or the offline-mode UUID for its
+    /// username if it doesn't have one.
+    pub async fn join_with_velocity_forwarding(
+        account: &Account,
+        address: impl TryInto<ServerAddress>,
+        ip: IpAddr,
+        secret: &[u8],
+    ) -> Result<(Self, UnboundedReceiver<Event>), JoinError> {
+        let forwarding = VelocityForwarding::new(
+            ip.to_string(),
+            account
+                .uuid
+                .unwrap_or_else(|| azalea_crypto::offline_uuid(&account.username)),
+            account.username.clone(),
+        );
+        let address: ServerAddress = address.try_into().map_err(|_| JoinError::InvalidAddress)?;
+        let hostname = address.host.clone();
+        Self::join_with_hostname(
+            account,
+            address,
+            hostname,
+            Some((&forwarding, secret)),
+            |_| None,
+        )
+        .await
+    }
+
+    /// Connect to a server, sending the given hostname in the handshake.
+    /// If `velocity` is given, Velocity's forwarding request is answered
+    /// with it and every other login plugin request goes to
+    /// `login_plugin_handler`.
+    async fn join_with_hostname(
+        account: &Account,
+        address: ServerAddress,
+        hostname: String,
+        velocity: Option<(&VelocityForwarding, &[u8])>,
+        mut login_plugin_handler: impl FnMut(&ClientboundCustomQueryPacket) -> Option<Vec<u8>> + Send,
+    ) -> Result<(Self, UnboundedReceiver<Event>), JoinError> {
         let resolved_address = resolver::resolve_address(&address).await?;
 
         let mut conn = Connection::new(&resolved_address).await?;
@@ -160,8 +393,8 @@ impl Client {
         // handshake
         conn.write(
             ClientIntentionPacket {
-                protocol_version: PROTOCOL_VERSION,
-                hostname: address.host.clone(),
+                protocol_version: conn.protocol_version().id(),
+                hostname,
                 port: address.port,
                 intention: ConnectionProtocol::Login,
             }
@@ -174,8 +407,12 @@ impl Client {
         conn.write(
             ServerboundHelloPacket {
                 username: account.username.clone(),
-                public_key: None,
-                profile_id: None,
+                public_key: account.certs.as_ref().map(|certs| ProfilePublicKeyData {
+                    expires_at: certs.expires_at,
+                    key: certs.public_key.clone(),
+                    key_signature: certs.public_key_signature.clone(),
+                }),
+                profile_id: account.certs.as_ref().and(account.uuid),
             }
             .get(),
         )
@@ -187,7 +424,24 @@ impl Client {
                 Ok(packet) => match packet {
                     ClientboundLoginPacket::Hello(p) => {
                         debug!("Got encryption request");
-                        let e = azalea_crypto::encrypt(&p.public_key, &p.nonce).unwrap();
+                        let e = azalea_crypto::encrypt(&p.public_key, &p.nonce)
+                            .map_err(JoinError::Encryption)?;
+
+                        // if we sent a public key, the server wants the nonce
+                        // signed instead of encrypted
+                        let nonce_or_salt_signature = match &account.certs {
+                            Some(certs) => {
+                                let salt = azalea_crypto::make_salt();
+                                let signature = certs
+                                    .private_key
+                                    .sign(&[p.nonce.as_slice(), &salt.to_be_bytes()].concat())?;
+                                NonceOrSaltSignature::SaltSignature(SaltSignaturePair {
+                                    salt,
+                                    signature,
+                                })
+                            }
+                            None => NonceOrSaltSignature::Nonce(e.encrypted_nonce),
+                        };
 
                         if let Some(access_token) = &account.access_token {
                             conn.authenticate(
@@ -203,9 +457,7 @@ impl Client {
 
                         conn.write(
                             ServerboundKeyPacket {
-                                nonce_or_salt_signature: NonceOrSaltSignature::Nonce(
-                                    e.encrypted_nonce,
-                                ),
+                                nonce_or_salt_signature,
                                 key_bytes: e.encrypted_public_key,
                             }
                             .get(),
@@ -224,13 +476,24 @@ impl Client {
                     }
                     ClientboundLoginPacket::LoginDisconnect(p) => {
                         debug!("Got disconnect {:?}", p);
+                        return Err(JoinError::Disconnect { reason: p.reason });
                     }
                     ClientboundLoginPacket::CustomQuery(p) => {
                         debug!("Got custom query {:?}", p);
+                        if let Some((forwarding, secret)) = velocity {
+                            if conn
+                                .respond_velocity_forwarding(&p, forwarding, secret)
+                                .await?
+                            {
+                                continue;
+                            }
+                        }
+                        let data = login_plugin_handler(&p);
+                        conn.respond_login_plugin(p.transaction_id, data).await?;
                     }
                 },
                 Err(e) => {
-                    panic!("Error: {e:?}");
+                    return Err(e.into());
                 }
             }
         };
@@ -242,14 +505,31 @@ impl Client {
 
         let (tx, rx) = mpsc::unbounded_channel();
 
+        let chat_signer = ChatSigner::new(
+            account
+                .certs
+                .as_ref()
+                .map(|certs| certs.private_key.clone()),
+            game_profile.uuid,
+        );
+
         // we got the GameConnection, so the server is now connected :)
         let client = Client {
             game_profile,
             read_conn,
             write_conn,
             player: Arc::new(Mutex::new(Player::default())),
+            inventory: Arc::new(Mutex::new(PlayerInventory::default())),
+            player_list: Arc::new(Mutex::new(PlayerList::default())),
+            world_border: Arc::new(Mutex::new(WorldBorder::default())),
+            listeners: Arc::new(Mutex::new(Listeners::default())),
+            plugin_channels: Arc::new(Mutex::new(PluginChannels::default())),
             dimension: Arc::new(Mutex::new(Dimension::default())),
             physics_state: Arc::new(Mutex::new(PhysicsState::default())),
+            resource_pack_policy: Arc::new(RwLock::new(ResourcePackPolicy::default())),
+            pending_suggestions: Arc::new(Mutex::new(PendingSuggestions::default())),
+            pending_tag_queries: Arc::new(Mutex::new(PendingTagQueries::default())),
+            chat_signer: Arc::new(Mutex::new(chat_signer)),
             tasks: Arc::new(Mutex::new(Vec::new())),
             client_information: Arc::new(RwLock::new(ClientInformation::default())),
         };
@@ -281,18 +561,52 @@ impl Client {
 
     /// Disconnect from the server, ending all tasks.
     pub async fn shutdown(self) -> Result<(), std::io::Error> {
-        self.write_conn.lock().await.shutdown().await?;
-        let tasks = self.tasks.lock();
-        for task in tasks.iter() {
+        self.disconnect().await
+    }
+
+    /// Leave the server cleanly. There's no serverbound disconnect packet, so
+    /// this sends any packets that are still buffered, closes our side of the
+    /// connection, and then ends all tasks.
+    pub async fn disconnect(&self) -> Result<(), std::io::Error> {
+        self.disconnect_with_final_packet(None).await
+    }
+
+    /// Like [`Client::disconnect`], but sends the given packet right before
+    /// the connection is closed.
+    pub async fn disconnect_with_final_packet(
+        &self,
+        packet: Option<ServerboundGamePacket>,
+    ) -> Result<(), std::io::Error> {
+        let result = async {
+            let mut write_conn = self.write_conn.lock().await;
+            if let Some(packet) = packet {
+                write_conn.write(packet).await?;
+            }
+            write_conn.shutdown().await
+        }
+        .await;
+        // the tasks are ended even if the connection couldn't be closed
+        // cleanly, since it's probably gone anyways
+        for task in self.tasks.lock().iter() {
             task.abort();
         }
-        Ok(())
+        result
     }
 
     async fn protocol_loop(client: Client, tx: UnboundedSender<Event>) {
-        loop {
+        let reason = loop {
             let r = client.read_conn.lock().await.read().await;
+            if let Ok(packet) = &r {
+                client.dispatch_to_listeners(packet);
+            }
             match r {
+                Ok(ClientboundGamePacket::Disconnect(p)) => {
+                    debug!("Got disconnect packet {:?}", p);
+                    // the receiver might have been dropped already, but we
+                    // still have to stop the other tasks below
+                    let _ = tx.send(Event::Packet(Box::new(p.clone().get())));
+                    break Some(p.reason);
+                }
                 Ok(packet) => match Self::handle(&packet, &client, &tx).await {
                     Ok(_) => {}
                     Err(e) => {
@@ -304,6 +618,23 @@ impl Client {
                         }
                     }
                 },
+                Err(
+                    ReadPacketError::ConnectionClosed
+                    | ReadPacketError::IoError { .. }
+                    | ReadPacketError::Timeout,
+                ) => {
+                    debug!("Connection closed");
+                    break None;
+                }
+                Err(
+                    e @ (ReadPacketError::UnknownPacketId { .. }
+                    | ReadPacketError::UnexpectedPacket { .. }),
+                ) => {
+                    // servers with plugins or mods can send packets that
+                    // don't exist in vanilla, we just skip them
+                    warn!("{}", e);
+                    continue;
+                }
                 Err(e) => {
                     if IGNORE_ERRORS {
                         warn!("{}", e);
@@ -316,6 +647,13 @@ impl Client {
                     }
                 }
             };
+        };
+
+        let _ = tx.send(Event::Disconnect(reason));
+        // stop the other tasks (like the tick loop) so the event receiver
+        // gets closed after the disconnect event
+        for task in client.tasks.lock().iter() {
+            task.abort();
         }
     }
 
@@ -394,6 +732,10 @@ impl Client {
                     // the 16 here is our render distance
                     // i'll make this an actual setting later
                     *dimension_lock = Dimension::new(16, height, min_y);
+                    match BiomeRegistry::from_registry_holder(&p.registry_holder) {
+                        Some(biome_registry) => dimension_lock.set_biomes(biome_registry, p.seed),
+                        None => warn!("No biome registry in login packet"),
+                    }
 
                     let entity = EntityData::new(client.game_profile.uuid, Vec3::default());
                     dimension_lock.add_entity(p.player_id, entity);
@@ -427,6 +769,7 @@ impl Client {
             }
             ClientboundGamePacket::CustomPayload(p) => {
                 debug!("Got custom payload packet {:?}", p);
+                client.dispatch_plugin_message(p);
             }
             ClientboundGamePacket::ChangeDifficulty(p) => {
                 debug!("Got difficulty packet {:?}", p);
@@ -439,12 +782,17 @@ impl Client {
             }
             ClientboundGamePacket::SetCarriedItem(p) => {
                 debug!("Got set carried item packet {:?}", p);
+                if p.slot < HOTBAR_SIZE {
+                    client.inventory.lock().selected_hotbar_slot = p.slot;
+                } else {
+                    warn!("Server told us to hold invalid hotbar slot {}", p.slot);
+                }
             }
             ClientboundGamePacket::UpdateTags(_p) => {
                 debug!("Got update tags packet");
             }
-            ClientboundGamePacket::Disconnect(p) => {
-                debug!("Got disconnect packet {:?}", p);
+            ClientboundGamePacket::Disconnect(_) => {
+                // handled in the protocol loop
             }
             ClientboundGamePacket::UpdateRecipes(_p) => {
                 debug!("Got update recipes packet");
@@ -548,6 +896,21 @@ impl Client {
             }
             ClientboundGamePacket::PlayerInfo(p) => {
                 debug!("Got player info packet {:?}", p);
+                let latency = {
+                    let mut player_list = client.player_list.lock();
+                    let old_latency = player_list
+                        .get(&client.game_profile.uuid)
+                        .map(|p| p.latency);
+                    player_list.update(p);
+                    player_list
+                        .get(&client.game_profile.uuid)
+                        .map(|p| p.latency)
+                        .filter(|&latency| Some(latency) != old_latency)
+                };
+                if let Some(latency) = latency {
+                    tx.send(Event::Latency(Duration::from_millis(latency.max(0) as u64)))
+                        .unwrap();
+                }
             }
             ClientboundGamePacket::SetChunkCacheCenter(p) => {
                 debug!("Got chunk cache center packet {:?}", p);
@@ -561,14 +924,41 @@ impl Client {
                 let pos = ChunkPos::new(p.x, p.z);
                 // let chunk = Chunk::read_with_world_height(&mut p.chunk_data);
                 // debug("chunk {:?}")
-                client
-                    .dimension
-                    .lock()
-                    .replace_with_packet_data(&pos, &mut Cursor::new(&p.chunk_data.data))
+                let mut dimension = client.dimension.lock();
+                dimension
+                    .replace_with_packet_data(
+                        &pos,
+                        &mut Cursor::new(&p.chunk_data.data),
+                        &p.chunk_data.heightmaps,
+                    )
                     .unwrap();
+                for block_entity in &p.chunk_data.block_entities {
+                    let kind = match BlockEntityType::try_from(block_entity.type_ as u32) {
+                        Ok(kind) => kind,
+                        Err(_) => {
+                            warn!("Unknown block entity type {}", block_entity.type_);
+                            continue;
+                        }
+                    };
+                    let pos = BlockPos::new(
+                        p.x * 16 + (block_entity.packed_xz >> 4) as i32,
+                        block_entity.y as i16 as i32,
+                        p.z * 16 + (block_entity.packed_xz & 15) as i32,
+                    );
+                    dimension.set_block_entity(
+                        &pos,
+                        Some(BlockEntity::new(kind, block_entity.data.clone())),
+                    );
+                }
+                update_light(&mut dimension, &pos, &p.light_data);
             }
             ClientboundGamePacket::LightUpdate(p) => {
-                debug!("Got light update packet {:?}", p);
+                debug!("Got light update packet {} {}", p.x, p.z);
+                update_light(
+                    &mut client.dimension.lock(),
+                    &ChunkPos::new(p.x, p.z),
+                    &p.light_data,
+                );
             }
             ClientboundGamePacket::AddEntity(p) => {
                 debug!("Got add entity packet {:?}", p);
@@ -581,6 +971,23 @@ impl Client {
             ClientboundGamePacket::UpdateAttributes(_p) => {
                 // debug!("Got update attributes packet {:?}", p);
             }
+            ClientboundGamePacket::Explode(p) => {
+                debug!("Got explode packet {:?}", p);
+                let player_entity_id = client.player.lock().entity_id;
+                let mut dimension = client.dimension.lock();
+                // the server doesn't send block updates for the blocks that
+                // the explosion destroyed
+                for pos in &p.to_blow {
+                    dimension.set_block_state(pos, BlockState::Air);
+                }
+                if let Some(mut player_entity) = dimension.entity_mut(player_entity_id) {
+                    player_entity.delta += Vec3 {
+                        x: p.knockback_x as f64,
+                        y: p.knockback_y as f64,
+                        z: p.knockback_z as f64,
+                    };
+                }
+            }
             ClientboundGamePacket::SetEntityMotion(_p) => {
                 // debug!("Got entity velocity packet {:?}", p);
             }
@@ -594,6 +1001,7 @@ impl Client {
             }
             ClientboundGamePacket::InitializeBorder(p) => {
                 debug!("Got initialize border packet {:?}", p);
+                client.world_border.lock().initialize(p);
             }
             ClientboundGamePacket::SetTime(p) => {
                 debug!("Got set time packet {:?}", p);
@@ -603,6 +1011,10 @@ impl Client {
             }
             ClientboundGamePacket::ContainerSetContent(p) => {
                 debug!("Got container set content packet {:?}", p);
+                // container 0 is always the player's inventory
+                if p.container_id == 0 {
+                    client.inventory.lock().set_content(p.items.clone());
+                }
             }
             ClientboundGamePacket::SetHealth(p) => {
                 debug!("Got set health packet {:?}", p);
@@ -655,9 +1067,25 @@ impl Client {
             }
             ClientboundGamePacket::RemoveEntities(p) => {
                 debug!("Got remove entities packet {:?}", p);
+                if let Some(vehicle_id) = client.vehicle_id() {
+                    if p.entity_ids.contains(&vehicle_id) {
+                        client.stop_riding();
+                    }
+                }
             }
             ClientboundGamePacket::PlayerChat(p) => {
                 // debug!("Got player chat packet {:?}", p);
+                if !p.message.header_signature.bytes.is_empty() {
+                    let update = client.chat_signer.lock().add_seen(LastSeenMessagesEntry {
+                        profile_id: p.message.signed_header.sender,
+                        last_signature: p.message.header_signature.clone(),
+                    });
+                    if let Some(last_seen_messages) = update {
+                        client
+                            .write_packet(ServerboundChatAckPacket { last_seen_messages }.get())
+                            .await?;
+                    }
+                }
                 tx.send(Event::Chat(ChatPacket::Player(Box::new(p.clone()))))
                     .unwrap();
             }
@@ -705,27 +1133,54 @@ impl Client {
             ClientboundGamePacket::AwardStats(_) => {}
             ClientboundGamePacket::BlockChangedAck(_) => {}
             ClientboundGamePacket::BlockDestruction(_) => {}
-            ClientboundGamePacket::BlockEntityData(_) => {}
+            ClientboundGamePacket::BlockEntityData(p) => {
+                debug!("Got block entity data packet {:?}", p);
+                client.dimension.lock().set_block_entity(
+                    &p.pos,
+                    Some(BlockEntity::new(p.block_entity_type, p.tag.clone())),
+                );
+            }
             ClientboundGamePacket::BlockEvent(_) => {}
             ClientboundGamePacket::BossEvent(_) => {}
             ClientboundGamePacket::ChatPreview(_) => {}
-            ClientboundGamePacket::CommandSuggestions(_) => {}
+            ClientboundGamePacket::CommandSuggestions(p) => {
+                client
+                    .pending_suggestions
+                    .lock()
+                    .resolve(p.id, p.suggestions.clone());
+            }
             ClientboundGamePacket::ContainerSetData(_) => {}
-            ClientboundGamePacket::ContainerSetSlot(_) => {}
+            ClientboundGamePacket::ContainerSetSlot(p) => {
+                debug!("Got container set slot packet {:?}", p);
+                if p.container_id == 0 {
+                    client
+                        .inventory
+                        .lock()
+                        .set_slot(p.slot as usize, p.item_stack.clone());
+                }
+            }
             ClientboundGamePacket::Cooldown(_) => {}
             ClientboundGamePacket::CustomChatCompletions(_) => {}
             ClientboundGamePacket::CustomSound(_) => {}
             ClientboundGamePacket::DeleteChat(_) => {}
-            ClientboundGamePacket::Explode(_) => {}
             ClientboundGamePacket::ForgetLevelChunk(_) => {}
             ClientboundGamePacket::HorseScreenOpen(_) => {}
             ClientboundGamePacket::MapItemData(_) => {}
             ClientboundGamePacket::MerchantOffers(_) => {}
-            ClientboundGamePacket::MoveVehicle(_) => {}
+            ClientboundGamePacket::MoveVehicle(p) => {
+                debug!("Got move vehicle packet {:?}", p);
+                client.handle_move_vehicle(p).await?;
+            }
             ClientboundGamePacket::OpenBook(_) => {}
             ClientboundGamePacket::OpenScreen(_) => {}
             ClientboundGamePacket::OpenSignEditor(_) => {}
-            ClientboundGamePacket::Ping(_) => {}
+            ClientboundGamePacket::Ping(p) => {
+                // anticheats use these to check how far behind we are, and
+                // kick us if we don't answer
+                client
+                    .write_packet(ServerboundPongPacket { id: p.id }.get())
+                    .await?;
+            }
             ClientboundGamePacket::PlaceGhostRecipe(_) => {}
             ClientboundGamePacket::PlayerChatHeader(_) => {}
             ClientboundGamePacket::PlayerCombatEnd(_) => {}
@@ -733,20 +1188,45 @@ impl Client {
             ClientboundGamePacket::PlayerCombatKill(_) => {}
             ClientboundGamePacket::PlayerLookAt(_) => {}
             ClientboundGamePacket::RemoveMobEffect(_) => {}
-            ClientboundGamePacket::ResourcePack(_) => {}
-            ClientboundGamePacket::Respawn(_) => {}
+            ClientboundGamePacket::ResourcePack(p) => {
+                debug!("Got resource pack packet {:?}", p);
+                client.answer_resource_pack(p).await?;
+            }
+            ClientboundGamePacket::Respawn(_) => {
+                // vehicles don't come with us to the new dimension
+                client.stop_riding();
+            }
             ClientboundGamePacket::SelectAdvancementsTab(_) => {}
             ClientboundGamePacket::SetActionBarText(_) => {}
-            ClientboundGamePacket::SetBorderCenter(_) => {}
-            ClientboundGamePacket::SetBorderLerpSize(_) => {}
-            ClientboundGamePacket::SetBorderSize(_) => {}
-            ClientboundGamePacket::SetBorderWarningDelay(_) => {}
-            ClientboundGamePacket::SetBorderWarningDistance(_) => {}
+            ClientboundGamePacket::SetBorderCenter(p) => {
+                let mut world_border = client.world_border.lock();
+                world_border.center_x = p.new_center_x;
+                world_border.center_z = p.new_center_z;
+            }
+            ClientboundGamePacket::SetBorderLerpSize(p) => {
+                client.world_border.lock().lerp_size_between(
+                    p.old_size,
+                    p.new_size,
+                    Duration::from_millis(p.lerp_time),
+                );
+            }
+            ClientboundGamePacket::SetBorderSize(p) => {
+                client.world_border.lock().set_size(p.size);
+            }
+            ClientboundGamePacket::SetBorderWarningDelay(p) => {
+                client.world_border.lock().warning_time = p.warning_delay;
+            }
+            ClientboundGamePacket::SetBorderWarningDistance(p) => {
+                client.world_border.lock().warning_blocks = p.warning_blocks;
+            }
             ClientboundGamePacket::SetCamera(_) => {}
             ClientboundGamePacket::SetDisplayChatPreview(_) => {}
             ClientboundGamePacket::SetDisplayObjective(_) => {}
             ClientboundGamePacket::SetObjective(_) => {}
-            ClientboundGamePacket::SetPassengers(_) => {}
+            ClientboundGamePacket::SetPassengers(p) => {
+                debug!("Got set passengers packet {:?}", p);
+                client.update_passengers(p);
+            }
             ClientboundGamePacket::SetPlayerTeam(_) => {}
             ClientboundGamePacket::SetScore(_) => {}
             ClientboundGamePacket::SetSimulationDistance(_) => {}
@@ -756,7 +1236,12 @@ impl Client {
             ClientboundGamePacket::SoundEntity(_) => {}
             ClientboundGamePacket::StopSound(_) => {}
             ClientboundGamePacket::TabList(_) => {}
-            ClientboundGamePacket::TagQuery(_) => {}
+            ClientboundGamePacket::TagQuery(p) => {
+                client
+                    .pending_tag_queries
+                    .lock()
+                    .resolve(p.transaction_id, p.tag.clone());
+            }
             ClientboundGamePacket::TakeItemEntity(_) => {}
         }
 
@@ -765,7 +1250,7 @@ impl Client {
 
     /// Runs game_tick every 50 milliseconds.
     async fn game_tick_loop(mut client: Client, tx: UnboundedSender<Event>) {
-        let mut game_tick_interval = time::interval(time::Duration::from_millis(50));
+        let mut game_tick_interval = time::interval(Duration::from_millis(50));
         // TODO: Minecraft bursts up to 10 ticks and then skips, we should too
         game_tick_interval.set_missed_tick_behavior(time::MissedTickBehavior::Burst);
         loop {
@@ -794,12 +1279,18 @@ impl Client {
 
         tx.send(Event::Tick).unwrap();
 
-        // TODO: if we're a passenger, send the required packets
-
-        if let Err(e) = client.send_position().await {
-            warn!("Error sending position: {:?}", e);
+        // passengers move with their vehicle, so they only send what they
+        // want to do
+        if client.vehicle_id().is_some() {
+            if let Err(e) = client.send_passenger_input().await {
+                warn!("Error sending passenger input: {:?}", e);
+            }
+        } else {
+            if let Err(e) = client.send_position().await {
+                warn!("Error sending position: {:?}", e);
+            }
+            client.ai_step();
         }
-        client.ai_step();
 
         // TODO: minecraft does ambient sounds here
     }
@@ -832,6 +1323,16 @@ impl Client {
         player.entity(&dimension).is_some()
     }
 
+    /// Our round-trip time to the server, or `None` if we aren't in the tab
+    /// list yet. The server measures it from how long we take to answer keep
+    /// alive packets and sends it to everyone in the tab list, so it only
+    /// changes every few seconds.
+    pub fn latency(&self) -> Option<Duration> {
+        let player_list = self.player_list.lock();
+        let latency = player_list.get(&self.game_profile.uuid)?.latency;
+        Some(Duration::from_millis(latency.max(0) as u64))
+    }
+
     /// Tell the server we changed our game options (i.e. render distance, main hand).
     /// If this is not set before the login packet, the default will be sent.
     pub async fn set_client_information(
@@ -855,8 +1356,70 @@ impl Client {
     }
 }
 
+/// Apply the sky and block light from a packet to a chunk in the dimension.
+fn update_light(
+    dimension: &mut Dimension,
+    pos: &ChunkPos,
+    data: &ClientboundLightUpdatePacketData,
+) {
+    dimension.update_light(
+        pos,
+        LightLayer::Sky,
+        &data.sky_y_mask,
+        &data.empty_sky_y_mask,
+        &data.sky_updates,
+    );
+    dimension.update_light(
+        pos,
+        LightLayer::Block,
+        &data.block_y_mask,
+        &data.empty_block_y_mask,
+        &data.block_updates,
+    );
+}
+
 impl<T> From<std::sync::PoisonError<T>> for HandleError {
     fn from(e: std::sync::PoisonError<T>) -> Self {
         HandleError::Poison(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_protocol::packets::handshake::{
+        ClientboundHandshakePacket, ServerboundHandshakePacket,
+    };
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_join_with_bungeecord_forwarding() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut conn =
+                Connection::<ServerboundHandshakePacket, ClientboundHandshakePacket>::wrap(stream)
+                    .unwrap();
+            // the connection is closed after this, so joining fails
+            match conn.read().await.unwrap() {
+                ServerboundHandshakePacket::ClientIntention(p) => p.hostname,
+            }
+        });
+
+        let account = Account::offline("bot");
+        let result = Client::join_with_bungeecord_forwarding(
+            &account,
+            address.as_str(),
+            "203.0.113.5".parse().unwrap(),
+        )
+        .await;
+        assert!(result.is_err());
+
+        let forwarding = BungeecordForwarding::from_hostname(&server.await.unwrap()).unwrap();
+        assert_eq!(forwarding.host, "127.0.0.1");
+        assert_eq!(forwarding.ip, "203.0.113.5");
+        assert_eq!(forwarding.uuid, azalea_crypto::offline_uuid("bot"));
+    }
+}
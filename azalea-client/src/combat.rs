@@ -0,0 +1,104 @@
+use crate::Client;
+use azalea_core::{BlockPos, Direction, Vec3};
+use azalea_protocol::packets::game::{
+    serverbound_interact_packet::{ActionType, InteractionHand, ServerboundInteractPacket},
+    serverbound_player_action_packet::{Action, ServerboundPlayerActionPacket},
+    serverbound_swing_packet::ServerboundSwingPacket,
+};
+
+impl Client {
+    /// Attack the entity, like left clicking it in vanilla. This also swings
+    /// our main hand, since the server expects that.
+    ///
+    /// The server ignores attacks on entities that are too far away, and
+    /// attacks do less damage if the attack cooldown isn't over yet.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # async fn example(bot: &azalea_client::Client, zombie_id: u32) -> Result<(), std::io::Error> {
+    /// bot.attack(zombie_id).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn attack(&self, entity_id: u32) -> Result<(), std::io::Error> {
+        self.write_packet(
+            ServerboundInteractPacket {
+                entity_id,
+                action: ActionType::Attack,
+                using_secondary_action: false,
+            }
+            .get(),
+        )
+        .await?;
+        self.swing(InteractionHand::MainHand).await
+    }
+
+    /// Interact with the entity, like right clicking it in vanilla. This is
+    /// used for things like trading with villagers or getting in a boat.
+    ///
+    /// Some interactions are different if we're sneaking, like opening a
+    /// horse's inventory instead of riding it.
+    pub async fn interact(
+        &self,
+        entity_id: u32,
+        hand: InteractionHand,
+        sneaking: bool,
+    ) -> Result<(), std::io::Error> {
+        self.write_packet(
+            ServerboundInteractPacket {
+                entity_id,
+                action: ActionType::Interact { hand },
+                using_secondary_action: sneaking,
+            }
+            .get(),
+        )
+        .await
+    }
+
+    /// Interact with a specific part of the entity. The location is relative
+    /// to the entity's position, and is only used by armor stands to decide
+    /// which slot we clicked.
+    ///
+    /// Vanilla sends this right before [`Client::interact`], and the server
+    /// only uses the regular interaction if this one didn't do anything.
+    pub async fn interact_at(
+        &self,
+        entity_id: u32,
+        location: Vec3,
+        hand: InteractionHand,
+        sneaking: bool,
+    ) -> Result<(), std::io::Error> {
+        self.write_packet(
+            ServerboundInteractPacket {
+                entity_id,
+                action: ActionType::InteractAt { location, hand },
+                using_secondary_action: sneaking,
+            }
+            .get(),
+        )
+        .await
+    }
+
+    /// Swing our arm. Other players see the animation, and the server resets
+    /// our attack cooldown.
+    pub async fn swing(&self, hand: InteractionHand) -> Result<(), std::io::Error> {
+        self.write_packet(ServerboundSwingPacket { hand }.get())
+            .await
+    }
+
+    /// Stop using the item that we're holding, like letting go of right
+    /// click. This is what shoots a bow or lowers a shield.
+    pub async fn release_use_item(&self) -> Result<(), std::io::Error> {
+        self.write_packet(
+            ServerboundPlayerActionPacket {
+                action: Action::ReleaseUseItem,
+                pos: BlockPos::default(),
+                direction: Direction::Down,
+                sequence: 0,
+            }
+            .get(),
+        )
+        .await
+    }
+}
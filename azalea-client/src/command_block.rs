@@ -0,0 +1,56 @@
+use crate::Client;
+use azalea_core::BlockPos;
+use azalea_protocol::packets::game::serverbound_set_command_block_packet::{
+    Mode, ServerboundSetCommandBlockPacket,
+};
+
+impl Client {
+    /// Set the command in the command block at the position, like editing it
+    /// in the command block screen. The bot has to be an operator in
+    /// creative mode for the server to accept this.
+    ///
+    /// Conditional command blocks only run if the command block behind them
+    /// succeeded, and automatic ones don't need redstone to run. The output
+    /// of the command is always tracked.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use azalea_client::Client;
+    /// use azalea_core::BlockPos;
+    /// use azalea_protocol::packets::game::serverbound_set_command_block_packet::Mode;
+    ///
+    /// # async fn example(bot: &Client) -> Result<(), std::io::Error> {
+    /// bot.set_command_block(
+    ///     BlockPos::new(0, 64, 0),
+    ///     "time set day",
+    ///     Mode::Redstone,
+    ///     false,
+    ///     false,
+    /// )
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_command_block(
+        &self,
+        pos: BlockPos,
+        command: &str,
+        mode: Mode,
+        conditional: bool,
+        automatic: bool,
+    ) -> Result<(), std::io::Error> {
+        self.write_packet(
+            ServerboundSetCommandBlockPacket {
+                pos,
+                command: command.to_string(),
+                mode,
+                track_output: true,
+                conditional,
+                automatic,
+            }
+            .get(),
+        )
+        .await
+    }
+}
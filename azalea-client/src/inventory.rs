@@ -0,0 +1,456 @@
+use crate::Client;
+use azalea_chat::component::Component;
+use azalea_core::{Slot, SlotData};
+use azalea_nbt::Tag;
+use azalea_protocol::packets::game::{
+    serverbound_set_carried_item_packet::ServerboundSetCarriedItemPacket,
+    serverbound_set_creative_mode_slot_packet::ServerboundSetCreativeModeSlotPacket,
+};
+use azalea_registry::{Enchantment, Item};
+use thiserror::Error;
+
+/// The number of slots in the player's inventory menu, including the
+/// crafting grid, armor, and offhand.
+const INVENTORY_SIZE: usize = 46;
+/// The index of the first hotbar slot in the inventory menu.
+const HOTBAR_START: usize = 36;
+/// The number of slots in the hotbar.
+pub const HOTBAR_SIZE: u8 = 9;
+
+#[derive(Error, Debug)]
+pub enum HoldSlotError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("Hotbar index must be less than {HOTBAR_SIZE}, but it was {0}")]
+    InvalidSlot(u8),
+}
+
+/// A stack of items in a slot.
+#[derive(Debug, Clone)]
+pub struct ItemStack {
+    pub kind: Item,
+    pub count: u8,
+    /// Extra data about the item, like its enchantments, damage, and custom
+    /// name. This is [`Tag::End`] if the item doesn't have any.
+    pub nbt: Tag,
+}
+
+impl ItemStack {
+    /// A stack of items without any NBT.
+    pub fn new(kind: Item, count: u8) -> Self {
+        ItemStack {
+            kind,
+            count,
+            nbt: Tag::End,
+        }
+    }
+
+    /// Get the item in a slot. Returns `None` if the slot is empty or has an
+    /// item id that we don't know about.
+    pub fn from_slot(slot: &Slot) -> Option<Self> {
+        match slot {
+            Slot::Present(data) => Some(ItemStack {
+                kind: Item::try_from(u32::try_from(data.id).ok()?).ok()?,
+                count: data.count,
+                nbt: data.nbt.clone(),
+            }),
+            Slot::Empty => None,
+        }
+    }
+
+    /// The slot with this stack in it, which is how items are sent to the
+    /// server.
+    pub fn to_slot(&self) -> Slot {
+        Slot::Present(SlotData {
+            id: self.kind as i32,
+            count: self.count,
+            nbt: self.nbt.clone(),
+        })
+    }
+
+    /// Get a tag from the root compound of the item's NBT.
+    pub fn tag(&self, name: &str) -> Option<&Tag> {
+        // the root compound is wrapped in another compound with its name
+        self.nbt
+            .as_compound()
+            .and_then(|root| root.values().next())
+            .and_then(|root| root.as_compound())
+            .and_then(|root| root.get(name))
+    }
+
+    /// Set a tag in the root compound of the item's NBT, creating the root
+    /// compound if the item doesn't have NBT yet.
+    pub fn set_tag(&mut self, name: &str, tag: Tag) {
+        let has_root = self
+            .nbt
+            .as_compound()
+            .and_then(|root| root.values().next())
+            .and_then(|root| root.as_compound())
+            .is_some();
+        if !has_root {
+            self.nbt = Tag::Compound(
+                [(String::new(), Tag::Compound(Default::default()))]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        if let Tag::Compound(root) = &mut self.nbt {
+            if let Some(Tag::Compound(root)) = root.values_mut().next() {
+                root.insert(name.to_string(), tag);
+            }
+        }
+    }
+
+    /// How much durability the item has lost, or 0 if it can't be damaged.
+    pub fn damage(&self) -> i32 {
+        self.tag("Damage")
+            .and_then(|damage| damage.as_int())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The name that the item was given in an anvil, if it has one.
+    pub fn custom_name(&self) -> Option<Component> {
+        let name = self
+            .tag("display")?
+            .as_compound()?
+            .get("Name")?
+            .as_string()?;
+        Component::from_json(name).ok()
+    }
+
+    /// Give the item a name, like renaming it in an anvil.
+    pub fn set_custom_name(&mut self, name: &str) {
+        let mut display = self
+            .tag("display")
+            .and_then(|display| display.as_compound())
+            .cloned()
+            .unwrap_or_default();
+        display.insert(
+            "Name".to_string(),
+            Tag::String(serde_json::json!({ "text": name }).to_string()),
+        );
+        self.set_tag("display", Tag::Compound(display));
+    }
+
+    /// The level of an enchantment on the item, or 0 if it doesn't have it.
+    /// Enchantments stored in enchanted books aren't counted.
+    pub fn enchantment_level(&self, enchantment: Enchantment) -> u16 {
+        let id = enchantment.to_string();
+        self.tag("Enchantments")
+            .and_then(|enchantments| enchantments.as_list())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|enchantment| enchantment.as_compound())
+            .find(|enchantment| {
+                enchantment.get("id").and_then(|id| id.as_string()) == Some(id.as_str())
+            })
+            .and_then(|enchantment| enchantment.get("lvl"))
+            .and_then(|level| level.as_short())
+            .map(|&level| level.max(0) as u16)
+            .unwrap_or(0)
+    }
+
+    /// Enchant the item, replacing the enchantment's level if the item
+    /// already has it. Levels above the enchantment's maximum work, since
+    /// they're only checked when enchanting in survival.
+    pub fn add_enchantment(&mut self, enchantment: Enchantment, level: u16) {
+        let id = enchantment.to_string();
+        let mut enchantments: Vec<Tag> = self
+            .tag("Enchantments")
+            .and_then(|enchantments| enchantments.as_list())
+            .unwrap_or_default()
+            .iter()
+            .filter(|enchantment| {
+                enchantment
+                    .as_compound()
+                    .and_then(|enchantment| enchantment.get("id"))
+                    .and_then(|id| id.as_string())
+                    != Some(id.as_str())
+            })
+            .cloned()
+            .collect();
+        enchantments.push(Tag::Compound(
+            [
+                ("id".to_string(), Tag::String(id)),
+                (
+                    "lvl".to_string(),
+                    Tag::Short(level.min(i16::MAX as u16) as i16),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        self.set_tag("Enchantments", Tag::List(enchantments));
+    }
+}
+
+/// The player's own inventory, laid out like the inventory menu (container
+/// id 0).
+#[derive(Debug, Clone)]
+pub struct PlayerInventory {
+    pub slots: Vec<Slot>,
+    /// The hotbar slot that we're holding, from 0 to 8.
+    pub selected_hotbar_slot: u8,
+}
+
+impl Default for PlayerInventory {
+    fn default() -> Self {
+        Self {
+            slots: vec![Slot::Empty; INVENTORY_SIZE],
+            selected_hotbar_slot: 0,
+        }
+    }
+}
+
+impl PlayerInventory {
+    /// The slots in the hotbar, from left to right.
+    pub fn hotbar(&self) -> &[Slot] {
+        &self.slots[HOTBAR_START..HOTBAR_START + HOTBAR_SIZE as usize]
+    }
+
+    /// The item that we're holding in our main hand. This is only `None` if
+    /// the selected hotbar slot was set to something that isn't in the
+    /// hotbar.
+    pub fn held_item(&self) -> Option<&Slot> {
+        self.hotbar().get(self.selected_hotbar_slot as usize)
+    }
+
+    /// The items in every slot, or `None` for empty slots.
+    pub fn items(&self) -> Vec<Option<ItemStack>> {
+        self.slots.iter().map(ItemStack::from_slot).collect()
+    }
+
+    /// Get the index of the first hotbar slot that has the given item.
+    pub fn find_in_hotbar(&self, item: Item) -> Option<u8> {
+        self.hotbar()
+            .iter()
+            .position(|slot| match slot {
+                Slot::Present(data) => data.id as u32 == item as u32,
+                Slot::Empty => false,
+            })
+            .map(|index| index as u8)
+    }
+
+    pub(crate) fn set_content(&mut self, items: Vec<Slot>) {
+        self.slots = items;
+        self.slots.resize(INVENTORY_SIZE, Slot::Empty);
+    }
+
+    pub(crate) fn set_slot(&mut self, index: usize, slot: Slot) {
+        if let Some(existing) = self.slots.get_mut(index) {
+            *existing = slot;
+        }
+    }
+}
+
+impl Client {
+    /// The items in every slot of our inventory, laid out like the inventory
+    /// menu. Empty slots are `None`.
+    pub fn inventory(&self) -> Vec<Option<ItemStack>> {
+        self.inventory.lock().items()
+    }
+
+    /// Switch to the given hotbar slot (from 0 to 8). Returns
+    /// [`HoldSlotError::InvalidSlot`] if `hotbar_index` isn't a valid hotbar
+    /// slot.
+    pub async fn hold_slot(&self, hotbar_index: u8) -> Result<(), HoldSlotError> {
+        if hotbar_index >= HOTBAR_SIZE {
+            return Err(HoldSlotError::InvalidSlot(hotbar_index));
+        }
+        self.select_hotbar_slot(hotbar_index).await?;
+        Ok(())
+    }
+
+    /// Tell the server that we switched to the hotbar slot, which must be
+    /// valid.
+    async fn select_hotbar_slot(&self, hotbar_index: u8) -> Result<(), std::io::Error> {
+        self.inventory.lock().selected_hotbar_slot = hotbar_index;
+        self.write_packet(
+            ServerboundSetCarriedItemPacket {
+                slot: hotbar_index as u16,
+            }
+            .get(),
+        )
+        .await
+    }
+
+    /// Put an item in a slot of our inventory, or empty the slot if the item
+    /// is `None`. The slot index is laid out like the inventory menu. This
+    /// only works in creative mode, and the server doesn't tell us if it
+    /// didn't work.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use azalea_client::{Client, ItemStack};
+    /// use azalea_registry::{Enchantment, Item};
+    ///
+    /// # async fn example(bot: &Client) -> Result<(), std::io::Error> {
+    /// let mut sword = ItemStack::new(Item::DiamondSword, 1);
+    /// sword.add_enchantment(Enchantment::Sharpness, 5);
+    /// sword.set_custom_name("Excalibur");
+    /// // the first hotbar slot
+    /// bot.set_creative_slot(36, Some(&sword)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_creative_slot(
+        &self,
+        index: u16,
+        item: Option<&ItemStack>,
+    ) -> Result<(), std::io::Error> {
+        let slot = item.map(ItemStack::to_slot).unwrap_or(Slot::Empty);
+        self.inventory.lock().set_slot(index as usize, slot.clone());
+        self.write_packet(
+            ServerboundSetCreativeModeSlotPacket {
+                slot_num: index,
+                item_stack: slot,
+            }
+            .get(),
+        )
+        .await
+    }
+
+    /// Hold the first stack of the given item in our hotbar. Returns `false`
+    /// (and doesn't change the held item) if the item isn't in our hotbar.
+    pub async fn hold_item(&self, item: Item) -> Result<bool, std::io::Error> {
+        let hotbar_index = self.inventory.lock().find_in_hotbar(item);
+        match hotbar_index {
+            Some(hotbar_index) => {
+                self.select_hotbar_slot(hotbar_index).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_buf::{McBufReadable, McBufWritable};
+    use azalea_nbt::Tag;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_find_in_hotbar() {
+        let mut inventory = PlayerInventory::default();
+        inventory.set_slot(
+            HOTBAR_START + 3,
+            Slot::Present(SlotData {
+                id: Item::GoldenApple as i32,
+                count: 1,
+                nbt: Tag::End,
+            }),
+        );
+        assert_eq!(inventory.find_in_hotbar(Item::GoldenApple), Some(3));
+        assert_eq!(inventory.find_in_hotbar(Item::Apple), None);
+    }
+
+    #[test]
+    fn test_held_item() {
+        let mut inventory = PlayerInventory::default();
+        inventory.set_slot(
+            HOTBAR_START + 8,
+            Slot::Present(SlotData {
+                id: Item::Apple as i32,
+                count: 1,
+                nbt: Tag::End,
+            }),
+        );
+        inventory.selected_hotbar_slot = 8;
+        assert!(matches!(
+            inventory.held_item(),
+            Some(Slot::Present(data)) if data.id == Item::Apple as i32
+        ));
+        inventory.selected_hotbar_slot = HOTBAR_SIZE;
+        assert!(inventory.held_item().is_none());
+    }
+
+    #[test]
+    fn test_item_stack_nbt() {
+        let nbt = Tag::from([(
+            "",
+            Tag::from([
+                ("Damage", Tag::Int(12)),
+                (
+                    "Enchantments",
+                    Tag::List(vec![
+                        Tag::from([
+                            ("id", Tag::String("minecraft:sharpness".to_string())),
+                            ("lvl", Tag::Short(5)),
+                        ]),
+                        Tag::from([
+                            ("id", Tag::String("minecraft:unbreaking".to_string())),
+                            ("lvl", Tag::Short(3)),
+                        ]),
+                    ]),
+                ),
+                (
+                    "display",
+                    Tag::from([("Name", Tag::String(r#"{"text":"Excalibur"}"#.to_string()))]),
+                ),
+            ]),
+        )]);
+
+        let mut inventory = PlayerInventory::default();
+        inventory.set_slot(
+            HOTBAR_START,
+            Slot::Present(SlotData {
+                id: Item::DiamondSword as i32,
+                count: 1,
+                nbt,
+            }),
+        );
+        let items = inventory.items();
+        assert_eq!(items.len(), INVENTORY_SIZE);
+        assert!(items[0].is_none());
+
+        let sword = items[HOTBAR_START].as_ref().unwrap();
+        assert_eq!(sword.kind, Item::DiamondSword);
+        assert_eq!(sword.damage(), 12);
+        assert_eq!(sword.enchantment_level(Enchantment::Sharpness), 5);
+        assert_eq!(sword.enchantment_level(Enchantment::Unbreaking), 3);
+        assert_eq!(sword.enchantment_level(Enchantment::Mending), 0);
+        assert_eq!(sword.custom_name().unwrap().to_string(), "Excalibur");
+    }
+
+    #[test]
+    fn test_write_item_stack() {
+        let mut item = ItemStack::new(Item::Stone, 2);
+        item.set_custom_name("🐝");
+        let mut buf = Vec::new();
+        item.to_slot().write_into(&mut buf).unwrap();
+
+        let name = b"{\"text\":\"\xed\xa0\xbd\xed\xb0\x9d\"}";
+        let mut expected = vec![1, Item::Stone as u8, 2];
+        // the root compound, then the display compound and its name
+        expected.extend_from_slice(&[10, 0, 0, 10, 0, 7]);
+        expected.extend_from_slice(b"display");
+        expected.extend_from_slice(&[8, 0, 4]);
+        expected.extend_from_slice(b"Name");
+        expected.extend_from_slice(&[0, name.len() as u8]);
+        expected.extend_from_slice(name);
+        expected.extend_from_slice(&[0, 0]);
+        assert_eq!(buf, expected);
+
+        let mut sword = ItemStack::new(Item::DiamondSword, 1);
+        sword.add_enchantment(Enchantment::Sharpness, 3);
+        sword.add_enchantment(Enchantment::Unbreaking, 3);
+        sword.add_enchantment(Enchantment::Sharpness, 10);
+        sword.set_custom_name("Excalibur");
+        let mut buf = Vec::new();
+        sword.to_slot().write_into(&mut buf).unwrap();
+        let read =
+            ItemStack::from_slot(&Slot::read_from(&mut Cursor::new(&buf[..])).unwrap()).unwrap();
+        assert_eq!(read.nbt, sword.nbt);
+        assert_eq!(read.enchantment_level(Enchantment::Sharpness), 10);
+        assert_eq!(read.enchantment_level(Enchantment::Unbreaking), 3);
+        assert_eq!(
+            read.tag("Enchantments").unwrap().as_list().unwrap().len(),
+            2
+        );
+        assert_eq!(read.custom_name().unwrap().to_string(), "Excalibur");
+    }
+}
@@ -8,15 +8,36 @@
 mod account;
 mod chat;
 mod client;
+mod combat;
+mod command_block;
 mod get_mc_dir;
+mod inventory;
+mod listeners;
 mod movement;
 pub mod ping;
 mod player;
+mod player_list;
+mod plugin_channels;
+mod resource_pack;
+mod sign;
+mod tab_complete;
+mod tag_query;
+mod vehicle;
+mod world_border;
 
 pub use account::Account;
-pub use client::{Client, ClientInformation, Event};
+pub use client::{ChatPacket, Client, ClientInformation, Event};
+pub use inventory::{HoldSlotError, ItemStack, PlayerInventory, HOTBAR_SIZE};
+pub use listeners::Listeners;
 pub use movement::MoveDirection;
 pub use player::Player;
+pub use player_list::{PlayerInfo, PlayerList};
+pub use plugin_channels::PluginChannels;
+pub use resource_pack::ResourcePackPolicy;
+pub use sign::{WriteSignError, MAX_SIGN_LINE_LENGTH};
+pub use tab_complete::TabCompleteError;
+pub use tag_query::TagQueryError;
+pub use world_border::WorldBorder;
 
 #[cfg(test)]
 mod tests {
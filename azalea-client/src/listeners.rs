@@ -0,0 +1,103 @@
+use crate::Client;
+use azalea_protocol::packets::game::ClientboundGamePacket;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+type Handler = Arc<dyn Fn(&dyn Any, &Client) + Send + Sync>;
+
+/// Handlers for specific game packets, for when [`Event`] isn't granular
+/// enough or doesn't model a packet yet.
+///
+/// [`Event`]: crate::Event
+#[derive(Default)]
+pub struct Listeners {
+    handlers: HashMap<TypeId, Vec<Handler>>,
+}
+
+impl Listeners {
+    /// Register a handler that's called every time we receive a packet of
+    /// type `P`. `P` should be a clientbound game packet struct like
+    /// `ClientboundPlayerChatPacket`, otherwise the handler will never be
+    /// called.
+    pub fn on<P: Any>(&mut self, handler: impl Fn(&P, &Client) + Send + Sync + 'static) {
+        self.handlers
+            .entry(TypeId::of::<P>())
+            .or_default()
+            .push(Arc::new(move |packet, client| {
+                if let Some(packet) = packet.downcast_ref::<P>() {
+                    handler(packet, client);
+                }
+            }));
+    }
+
+    /// Get the handlers for the given packet. This is empty (and doesn't
+    /// allocate) if nothing is listening for the packet.
+    fn handlers_for(&self, packet: &ClientboundGamePacket) -> Vec<Handler> {
+        if self.handlers.is_empty() {
+            return Vec::new();
+        }
+        self.handlers
+            .get(&Any::type_id(packet.as_any()))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Client {
+    /// Register a handler that's called every time the client receives a
+    /// packet of type `P`. See [`Listeners::on`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use azalea_client::Client;
+    /// use azalea_protocol::packets::game::clientbound_set_time_packet::ClientboundSetTimePacket;
+    ///
+    /// # fn example(bot: &Client) {
+    /// bot.on::<ClientboundSetTimePacket>(|packet, _client| {
+    ///     println!("the day time is now {}", packet.day_time);
+    /// });
+    /// # }
+    /// ```
+    pub fn on<P: Any>(&self, handler: impl Fn(&P, &Client) + Send + Sync + 'static) {
+        self.listeners.lock().on(handler);
+    }
+
+    /// Call the handlers that are listening for this packet.
+    pub(crate) fn dispatch_to_listeners(&self, packet: &ClientboundGamePacket) {
+        // the handlers are cloned out so they can register more listeners
+        // without deadlocking
+        let handlers = self.listeners.lock().handlers_for(packet);
+        let packet = packet.as_any();
+        for handler in handlers {
+            handler(packet, self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_protocol::packets::game::{
+        clientbound_keep_alive_packet::ClientboundKeepAlivePacket,
+        clientbound_set_time_packet::ClientboundSetTimePacket,
+    };
+
+    #[test]
+    fn test_handlers_for_packet_type() {
+        let mut listeners = Listeners::default();
+        listeners.on::<ClientboundSetTimePacket>(|_, _| {});
+
+        let set_time = ClientboundSetTimePacket {
+            game_time: 0,
+            day_time: 0,
+        }
+        .get();
+        let keep_alive = ClientboundKeepAlivePacket { id: 0 }.get();
+        assert_eq!(listeners.handlers_for(&set_time).len(), 1);
+        assert!(listeners.handlers_for(&keep_alive).is_empty());
+    }
+}
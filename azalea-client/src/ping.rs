@@ -1,20 +1,32 @@
 //! Ping Minecraft servers.
 
+use azalea_chat::{component::Component, text_component::TextComponent};
 use azalea_protocol::{
     connect::{Connection, ConnectionError},
     packets::{
         handshake::client_intention_packet::ClientIntentionPacket,
         status::{
-            clientbound_status_response_packet::ClientboundStatusResponsePacket,
+            clientbound_status_response_packet::{
+                ClientboundStatusResponsePacket, Players, Version,
+            },
+            serverbound_ping_request_packet::ServerboundPingRequestPacket,
             serverbound_status_request_packet::ServerboundStatusRequestPacket,
-            ClientboundStatusPacket,
+            ClientboundStatusPacket, ServerboundStatusPacket,
         },
         ConnectionProtocol, PROTOCOL_VERSION,
     },
     resolver, ServerAddress,
 };
-use std::io;
+use std::{
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use thiserror::Error;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
 
 #[derive(Error, Debug)]
 pub enum PingError {
@@ -28,6 +40,10 @@ pub enum PingError {
     WritePacket(#[from] io::Error),
     #[error("The given address could not be parsed into a ServerAddress")]
     InvalidAddress,
+    #[error("The server's pong didn't match our ping")]
+    WrongPong,
+    #[error("Invalid legacy ping response")]
+    InvalidLegacyResponse,
 }
 
 /// Ping a Minecraft server.
@@ -46,16 +62,153 @@ pub enum PingError {
 pub async fn ping_server(
     address: impl TryInto<ServerAddress>,
 ) -> Result<ClientboundStatusResponsePacket, PingError> {
-    let address: ServerAddress = address.try_into().map_err(|_| PingError::InvalidAddress)?;
+    ping_server_with_protocol_version(address, PROTOCOL_VERSION).await
+}
+
+/// Ping a Minecraft server while sending a custom protocol version in the
+/// handshake. Some pingers send -1 here, since the server will respond with
+/// its own version regardless of what we send.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use azalea_client::ping;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let response = ping::ping_server_with_protocol_version("play.hypixel.net", -1)
+///         .await
+///         .unwrap();
+///     println!("{}", response.version.protocol);
+/// }
+/// ```
+pub async fn ping_server_with_protocol_version(
+    address: impl TryInto<ServerAddress>,
+    protocol_version: i32,
+) -> Result<ClientboundStatusResponsePacket, PingError> {
+    let (address, resolved_address) = resolve(address).await?;
+    let mut conn = connect_status(&address, &resolved_address, protocol_version).await?;
+    read_status(&mut conn).await
+}
+
+/// Ping a Minecraft server, and if it doesn't understand the status
+/// protocol, try again with the legacy ping that servers before 1.7 use.
+///
+/// See [`ping_server_legacy`] for how legacy responses are converted.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use azalea_client::ping;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let response = ping::ping_server_with_legacy_fallback("localhost")
+///         .await
+///         .unwrap();
+///     println!("{}", response.version.name);
+/// }
+/// ```
+pub async fn ping_server_with_legacy_fallback(
+    address: impl TryInto<ServerAddress>,
+) -> Result<ClientboundStatusResponsePacket, PingError> {
+    let (address, resolved_address) = resolve(address).await?;
+    let result = async {
+        let mut conn = connect_status(&address, &resolved_address, PROTOCOL_VERSION).await?;
+        read_status(&mut conn).await
+    }
+    .await;
+    match result {
+        // old servers kick us or just close the connection when they get a
+        // packet they don't understand
+        Err(PingError::ReadPacket(_) | PingError::WritePacket(_)) => {
+            legacy_ping(&address, &resolved_address).await
+        }
+        result => result,
+    }
+}
+
+/// Ping a server with the legacy server list ping that was used before 1.7.
+/// Newer servers still respond to it too.
+///
+/// The response is converted to look like a modern one. There's no favicon
+/// or player sample, and servers before 1.4 don't send their version, so
+/// the version name is empty and the protocol is -1 for them.
+pub async fn ping_server_legacy(
+    address: impl TryInto<ServerAddress>,
+) -> Result<ClientboundStatusResponsePacket, PingError> {
+    let (address, resolved_address) = resolve(address).await?;
+    legacy_ping(&address, &resolved_address).await
+}
+
+/// Ping a Minecraft server and measure how long it takes to respond. The
+/// latency is the time between sending a ping and getting the pong back,
+/// like in the vanilla server list.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use azalea_client::ping;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let (response, latency) = ping::ping("play.hypixel.net").await.unwrap();
+///     println!("{} ({}ms)", response.description.to_ansi(None), latency.as_millis());
+/// }
+/// ```
+pub async fn ping(
+    address: impl TryInto<ServerAddress>,
+) -> Result<(ClientboundStatusResponsePacket, Duration), PingError> {
+    let (address, resolved_address) = resolve(address).await?;
+    let mut conn = connect_status(&address, &resolved_address, PROTOCOL_VERSION).await?;
+    let status = read_status(&mut conn).await?;
+
+    // vanilla sends the current time, but the server just echoes whatever we
+    // send back
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let start = Instant::now();
+    conn.write(ServerboundPingRequestPacket { time }.get())
+        .await?;
+    let latency = loop {
+        match conn.read().await? {
+            ClientboundStatusPacket::PongResponse(p) => {
+                if p.time != time {
+                    return Err(PingError::WrongPong);
+                }
+                break start.elapsed();
+            }
+            // some servers send the status again, we can ignore it
+            ClientboundStatusPacket::StatusResponse(_) => {}
+        }
+    };
 
+    Ok((status, latency))
+}
+
+/// Parse an address and look up its IP address.
+async fn resolve(
+    address: impl TryInto<ServerAddress>,
+) -> Result<(ServerAddress, SocketAddr), PingError> {
+    let address: ServerAddress = address.try_into().map_err(|_| PingError::InvalidAddress)?;
     let resolved_address = resolver::resolve_address(&address).await?;
+    Ok((address, resolved_address))
+}
 
-    let mut conn = Connection::new(&resolved_address).await?;
+/// Connect to a server and switch to the status state.
+async fn connect_status(
+    address: &ServerAddress,
+    resolved_address: &SocketAddr,
+    protocol_version: i32,
+) -> Result<Connection<ClientboundStatusPacket, ServerboundStatusPacket>, PingError> {
+    let mut conn = Connection::new(resolved_address).await?;
 
     // send the client intention packet and switch to the status state
     conn.write(
         ClientIntentionPacket {
-            protocol_version: PROTOCOL_VERSION,
+            protocol_version,
             hostname: address.host.clone(),
             port: address.port,
             intention: ConnectionProtocol::Status,
@@ -63,15 +216,18 @@ pub async fn ping_server(
         .get(),
     )
     .await?;
-    let mut conn = conn.status();
+    Ok(conn.status())
+}
 
+/// Ask the server for its status and wait for the response.
+async fn read_status(
+    conn: &mut Connection<ClientboundStatusPacket, ServerboundStatusPacket>,
+) -> Result<ClientboundStatusResponsePacket, PingError> {
     // send the empty status request packet
     conn.write(ServerboundStatusRequestPacket {}.get()).await?;
 
-    let packet = conn.read().await?;
-
     loop {
-        match packet {
+        match conn.read().await? {
             ClientboundStatusPacket::StatusResponse(p) => return Ok(p),
             ClientboundStatusPacket::PongResponse(_) => {
                 // we should never get this packet since we didn't send a ping
@@ -79,3 +235,203 @@ pub async fn ping_server(
         }
     }
 }
+
+/// The protocol version that's sent in legacy pings, which is 1.6.4's.
+const LEGACY_PROTOCOL_VERSION: u8 = 78;
+
+/// Encode a string as UTF-16BE, which is what the legacy ping uses.
+fn encode_utf16(string: &str) -> Vec<u8> {
+    string.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+/// Do a legacy ping like the 1.6 client does. Servers older than 1.6 ignore
+/// everything after the first two bytes.
+async fn legacy_ping(
+    address: &ServerAddress,
+    resolved_address: &SocketAddr,
+) -> Result<ClientboundStatusResponsePacket, PingError> {
+    let mut stream = TcpStream::connect(resolved_address)
+        .await
+        .map_err(ConnectionError::from)?;
+
+    let hostname = encode_utf16(&address.host);
+    let channel = "MC|PingHost";
+    let mut request = vec![0xfe, 0x01, 0xfa];
+    request.extend((channel.len() as u16).to_be_bytes());
+    request.extend(encode_utf16(channel));
+    request.extend((7 + hostname.len() as u16).to_be_bytes());
+    request.push(LEGACY_PROTOCOL_VERSION);
+    request.extend((address.host.encode_utf16().count() as u16).to_be_bytes());
+    request.extend(hostname);
+    request.extend((address.port as i32).to_be_bytes());
+    stream.write_all(&request).await?;
+
+    // the response is a kick packet with the status in the reason
+    if stream.read_u8().await? != 0xff {
+        return Err(PingError::InvalidLegacyResponse);
+    }
+    let length = stream.read_u16().await?;
+    let mut response = vec![0; length as usize * 2];
+    stream.read_exact(&mut response).await?;
+    let response = response
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect::<Vec<_>>();
+    let response = String::from_utf16(&response).map_err(|_| PingError::InvalidLegacyResponse)?;
+    parse_legacy_response(&response).ok_or(PingError::InvalidLegacyResponse)
+}
+
+/// Convert a legacy ping response to a modern one. Servers since 1.4 send
+/// `§1\0protocol\0version\0motd\0online\0max`, and older ones send
+/// `motd§online§max`.
+fn parse_legacy_response(response: &str) -> Option<ClientboundStatusResponsePacket> {
+    let (protocol, version_name, motd, online, max) = match response.strip_prefix("§1\0") {
+        Some(response) => {
+            let mut fields = response.split('\0');
+            let protocol = fields.next()?.parse().ok()?;
+            let version_name = fields.next()?;
+            let motd = fields.next()?;
+            (protocol, version_name, motd, fields.next()?, fields.next()?)
+        }
+        None => {
+            let mut fields = response.rsplitn(3, '§');
+            let max = fields.next()?;
+            let online = fields.next()?;
+            (-1, "", fields.next()?, online, max)
+        }
+    };
+    Some(ClientboundStatusResponsePacket {
+        description: Component::Text(TextComponent::new(motd.to_string())),
+        favicon: None,
+        players: Players {
+            max: max.parse().ok()?,
+            online: online.parse().ok()?,
+            sample: Vec::new(),
+        },
+        version: Version {
+            name: Component::from(version_name.to_string()),
+            protocol,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_buf::{McBufVarWritable, McBufWritable};
+    use tokio::{io::AsyncWrite, net::TcpListener};
+
+    /// Write a raw packet to the stream, since we can't serialize status
+    /// responses.
+    async fn write_raw_packet(stream: &mut (impl AsyncWrite + Unpin), id: u32, body: &[u8]) {
+        let mut packet = Vec::new();
+        id.var_write_into(&mut packet).unwrap();
+        packet.extend_from_slice(body);
+        let mut frame = Vec::new();
+        (packet.len() as u32).var_write_into(&mut frame).unwrap();
+        frame.extend(packet);
+        stream.write_all(&frame).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read_stream, mut write_stream) = stream.into_split();
+            // we only read with the connection, the responses are written to
+            // the stream directly
+            let mut conn = Connection::<
+                azalea_protocol::packets::handshake::ServerboundHandshakePacket,
+                azalea_protocol::packets::handshake::ClientboundHandshakePacket,
+                _,
+                _,
+            >::from_split(read_stream, tokio::io::sink());
+            conn.read().await.unwrap();
+            let mut conn = conn.status();
+
+            assert!(matches!(
+                conn.read().await.unwrap(),
+                ServerboundStatusPacket::StatusRequest(_)
+            ));
+            let mut body = Vec::new();
+            r#"{"description":{"text":"hello"},"players":{"max":20,"online":1},"version":{"name":"1.19.2","protocol":760}}"#
+                .to_string()
+                .write_into(&mut body)
+                .unwrap();
+            write_raw_packet(&mut write_stream, 0x00, &body).await;
+
+            let time = match conn.read().await.unwrap() {
+                ServerboundStatusPacket::PingRequest(p) => p.time,
+                p => panic!("Expected a ping request, got {p:?}"),
+            };
+            let mut body = Vec::new();
+            time.write_into(&mut body).unwrap();
+            write_raw_packet(&mut write_stream, 0x01, &body).await;
+        });
+
+        let (response, latency) = ping(address.as_str()).await.unwrap();
+        assert_eq!(response.description.to_ansi(None), "hello");
+        assert_eq!(response.players.online, 1);
+        assert_eq!(response.version.protocol, 760);
+        assert!(response.favicon.is_none());
+        assert!(latency < Duration::from_secs(5));
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_parse_legacy_response() {
+        let response =
+            parse_legacy_response("§1\x0078\x001.6.4\x00A §aMinecraft§r Server\x003\x0020")
+                .unwrap();
+        assert_eq!(response.version.protocol, 78);
+        assert_eq!(response.version.name.to_string(), "1.6.4");
+        assert_eq!(response.players.online, 3);
+        assert_eq!(response.players.max, 20);
+        assert_eq!(response.description.to_string(), "A Minecraft Server");
+
+        // before 1.4 the motd can't have § in it, so the last two are the
+        // player counts
+        let response = parse_legacy_response("A Minecraft Server§0§10").unwrap();
+        assert_eq!(response.version.protocol, -1);
+        assert_eq!(response.players.online, 0);
+        assert_eq!(response.players.max, 10);
+        assert_eq!(response.description.to_string(), "A Minecraft Server");
+
+        assert!(parse_legacy_response("§1\x0078\x001.6.4").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_fallback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            // a server that doesn't know the modern protocol and kicks us
+            // for sending an unknown packet
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1];
+            stream.read_exact(&mut buf).await.unwrap();
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut header = [0; 3];
+            stream.read_exact(&mut header).await.unwrap();
+            assert_eq!(header, [0xfe, 0x01, 0xfa]);
+            let response = encode_utf16("§1\x0078\x001.6.4\x00hi\x001\x0020");
+            let mut packet = vec![0xff];
+            packet.extend(((response.len() / 2) as u16).to_be_bytes());
+            packet.extend(response);
+            stream.write_all(&packet).await.unwrap();
+        });
+
+        let response = ping_server_with_legacy_fallback(address.as_str())
+            .await
+            .unwrap();
+        assert_eq!(response.version.protocol, 78);
+        assert_eq!(response.description.to_string(), "hi");
+        server.await.unwrap();
+    }
+}
@@ -14,6 +14,8 @@ pub struct Player {
     pub uuid: Uuid,
     /// The player's entity id.
     pub entity_id: u32,
+    /// The entity id of the vehicle that the player is riding.
+    pub vehicle_id: Option<u32>,
 }
 
 impl Player {
@@ -0,0 +1,154 @@
+use azalea_chat::component::Component;
+use azalea_protocol::packets::{
+    game::clientbound_player_info_packet::{Action, ClientboundPlayerInfoPacket, PlayerProperty},
+    login::serverbound_hello_packet::ProfilePublicKeyData,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A player in the tab list.
+#[derive(Clone, Debug)]
+pub struct PlayerInfo {
+    pub uuid: Uuid,
+    pub name: String,
+    /// The properties of the player's game profile, like their skin.
+    pub properties: Vec<PlayerProperty>,
+    pub gamemode: u32,
+    /// The player's ping in milliseconds, as reported by the server.
+    pub latency: i32,
+    /// The name that's shown in the tab list, if it's different from the
+    /// player's username.
+    pub display_name: Option<Component>,
+    /// The key that the player signs their chat messages with.
+    pub profile_public_key: Option<ProfilePublicKeyData>,
+}
+
+/// The players in the tab list, updated from the server's player info
+/// packets.
+#[derive(Clone, Debug, Default)]
+pub struct PlayerList {
+    players: HashMap<Uuid, PlayerInfo>,
+}
+
+impl PlayerList {
+    /// Get a player in the tab list by their UUID.
+    pub fn get(&self, uuid: &Uuid) -> Option<&PlayerInfo> {
+        self.players.get(uuid)
+    }
+
+    /// Get a player in the tab list by their username. Minecraft usernames
+    /// are case-insensitive, so this is too.
+    pub fn get_by_name(&self, name: &str) -> Option<&PlayerInfo> {
+        self.players
+            .values()
+            .find(|player| player.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get the key that a player signs their chat messages with, if they
+    /// have one.
+    pub fn public_key(&self, uuid: &Uuid) -> Option<&ProfilePublicKeyData> {
+        self.get(uuid)?.profile_public_key.as_ref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PlayerInfo> {
+        self.players.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    /// Apply a player info packet from the server. Updates for players that
+    /// aren't in the list are ignored, like the vanilla client does.
+    pub fn update(&mut self, packet: &ClientboundPlayerInfoPacket) {
+        match &packet.action {
+            Action::AddPlayer(players) => {
+                for player in players {
+                    self.players.insert(
+                        player.uuid,
+                        PlayerInfo {
+                            uuid: player.uuid,
+                            name: player.name.clone(),
+                            properties: player.properties.clone(),
+                            gamemode: player.gamemode,
+                            latency: player.ping,
+                            display_name: player.display_name.clone(),
+                            profile_public_key: player.profile_public_key.clone(),
+                        },
+                    );
+                }
+            }
+            Action::UpdateGameMode(players) => {
+                for player in players {
+                    if let Some(info) = self.players.get_mut(&player.uuid) {
+                        info.gamemode = player.gamemode;
+                    }
+                }
+            }
+            Action::UpdateLatency(players) => {
+                for player in players {
+                    if let Some(info) = self.players.get_mut(&player.uuid) {
+                        info.latency = player.ping;
+                    }
+                }
+            }
+            Action::UpdateDisplayName(players) => {
+                for player in players {
+                    if let Some(info) = self.players.get_mut(&player.uuid) {
+                        info.display_name = player.display_name.clone();
+                    }
+                }
+            }
+            Action::RemovePlayer(players) => {
+                for player in players {
+                    self.players.remove(&player.uuid);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_protocol::packets::game::clientbound_player_info_packet::{
+        AddPlayer, RemovePlayer, UpdateLatency,
+    };
+
+    #[test]
+    fn test_add_update_remove() {
+        let uuid = Uuid::from_u128(1);
+        let mut player_list = PlayerList::default();
+        player_list.update(&ClientboundPlayerInfoPacket {
+            action: Action::AddPlayer(vec![AddPlayer {
+                uuid,
+                name: "Bot".to_string(),
+                properties: vec![],
+                gamemode: 0,
+                ping: 0,
+                display_name: None,
+                profile_public_key: Some(ProfilePublicKeyData {
+                    expires_at: 0,
+                    key: vec![1, 2, 3],
+                    key_signature: vec![],
+                }),
+            }]),
+        });
+        player_list.update(&ClientboundPlayerInfoPacket {
+            action: Action::UpdateLatency(vec![UpdateLatency { uuid, ping: 50 }]),
+        });
+
+        assert_eq!(player_list.get(&uuid).unwrap().latency, 50);
+        assert_eq!(player_list.get_by_name("bot").unwrap().uuid, uuid);
+        assert_eq!(player_list.public_key(&uuid).unwrap().key, vec![1, 2, 3]);
+
+        player_list.update(&ClientboundPlayerInfoPacket {
+            action: Action::RemovePlayer(vec![RemovePlayer { uuid }]),
+        });
+        assert!(player_list.is_empty());
+    }
+}
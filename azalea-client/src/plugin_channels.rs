@@ -0,0 +1,196 @@
+use crate::Client;
+use azalea_core::ResourceLocation;
+use azalea_protocol::packets::game::{
+    clientbound_custom_payload_packet::ClientboundCustomPayloadPacket,
+    serverbound_custom_payload_packet::ServerboundCustomPayloadPacket,
+};
+use log::debug;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+type Handler = Arc<dyn Fn(&[u8], &Client) + Send + Sync>;
+
+/// The channel that's used to tell the other side which plugin channels we
+/// listen on.
+const REGISTER_CHANNEL: &str = "minecraft:register";
+const UNREGISTER_CHANNEL: &str = "minecraft:unregister";
+
+/// Handlers for plugin messages (custom payloads), by the channel they're
+/// sent on. Plugins and mods use these to talk to clients, like
+/// `worldedit:cui` or `bungeecord:main`.
+#[derive(Default)]
+pub struct PluginChannels {
+    handlers: HashMap<ResourceLocation, Vec<Handler>>,
+    /// The channels that the server registered.
+    server_channels: HashSet<ResourceLocation>,
+}
+
+impl PluginChannels {
+    /// Register a handler that's called with the data of every plugin
+    /// message that the server sends on the channel.
+    pub fn on(
+        &mut self,
+        channel: ResourceLocation,
+        handler: impl Fn(&[u8], &Client) + Send + Sync + 'static,
+    ) {
+        self.handlers
+            .entry(channel)
+            .or_default()
+            .push(Arc::new(handler));
+    }
+
+    /// The channels that the server said it listens on. Servers usually
+    /// ignore messages on channels that aren't in here.
+    pub fn server_channels(&self) -> &HashSet<ResourceLocation> {
+        &self.server_channels
+    }
+
+    fn handlers_for(&self, channel: &ResourceLocation) -> Vec<Handler> {
+        self.handlers.get(channel).cloned().unwrap_or_default()
+    }
+
+    /// Keep track of the channels if this is a register or unregister
+    /// message from the server.
+    fn update_server_channels(&mut self, channel: &ResourceLocation, data: &[u8]) {
+        let channel = channel.to_string();
+        if channel == REGISTER_CHANNEL {
+            self.server_channels.extend(decode_channels(data));
+        } else if channel == UNREGISTER_CHANNEL {
+            for channel in decode_channels(data) {
+                self.server_channels.remove(&channel);
+            }
+        }
+    }
+}
+
+/// The data of a register message, which is the names of the channels
+/// separated by null bytes.
+fn encode_channels(channels: &[ResourceLocation]) -> Vec<u8> {
+    channels
+        .iter()
+        .map(|channel| channel.to_string())
+        .collect::<Vec<_>>()
+        .join("\0")
+        .into_bytes()
+}
+
+fn decode_channels(data: &[u8]) -> impl Iterator<Item = ResourceLocation> + '_ {
+    data.split(|&b| b == 0)
+        .filter(|channel| !channel.is_empty())
+        .filter_map(|channel| ResourceLocation::new(&String::from_utf8_lossy(channel)).ok())
+}
+
+impl Client {
+    /// Register a handler that's called every time the server sends a plugin
+    /// message on the channel. See [`PluginChannels::on`].
+    ///
+    /// Servers often only send messages on channels that the client
+    /// registered, so you'll probably want to call
+    /// [`Client::register_plugin_channels`] too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use azalea_client::Client;
+    /// use azalea_core::ResourceLocation;
+    ///
+    /// # async fn example(bot: &Client) -> Result<(), std::io::Error> {
+    /// let channel = ResourceLocation::new("worldedit:cui").unwrap();
+    /// bot.on_plugin_message(channel.clone(), |data, _client| {
+    ///     println!("worldedit says {}", String::from_utf8_lossy(data));
+    /// });
+    /// bot.register_plugin_channels(&[channel]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_plugin_message(
+        &self,
+        channel: ResourceLocation,
+        handler: impl Fn(&[u8], &Client) + Send + Sync + 'static,
+    ) {
+        self.plugin_channels.lock().on(channel, handler);
+    }
+
+    /// Tell the server that we listen on these channels, with a
+    /// `minecraft:register` message.
+    pub async fn register_plugin_channels(
+        &self,
+        channels: &[ResourceLocation],
+    ) -> Result<(), std::io::Error> {
+        self.send_plugin_message(
+            ResourceLocation::new(REGISTER_CHANNEL).unwrap(),
+            encode_channels(channels),
+        )
+        .await
+    }
+
+    /// Send a plugin message (custom payload) to the server on the channel.
+    pub async fn send_plugin_message(
+        &self,
+        channel: ResourceLocation,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<(), std::io::Error> {
+        self.write_packet(
+            ServerboundCustomPayloadPacket {
+                identifier: channel,
+                data: data.into().into(),
+            }
+            .get(),
+        )
+        .await
+    }
+
+    /// Call the handlers that are listening on the message's channel.
+    pub(crate) fn dispatch_plugin_message(&self, packet: &ClientboundCustomPayloadPacket) {
+        // the handlers are cloned out so they can register more handlers
+        // without deadlocking
+        let handlers = {
+            let mut plugin_channels = self.plugin_channels.lock();
+            plugin_channels.update_server_channels(&packet.identifier, &packet.data);
+            plugin_channels.handlers_for(&packet.identifier)
+        };
+        if handlers.is_empty() {
+            debug!(
+                "Nothing is listening for plugin channel {}",
+                packet.identifier
+            );
+        }
+        for handler in handlers {
+            handler(&packet.data, self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_channels() {
+        let channels = [
+            ResourceLocation::new("worldedit:cui").unwrap(),
+            ResourceLocation::new("bungeecord:main").unwrap(),
+        ];
+        let data = encode_channels(&channels);
+        assert_eq!(data, b"worldedit:cui\0bungeecord:main");
+        assert_eq!(decode_channels(&data).collect::<Vec<_>>(), channels);
+    }
+
+    #[test]
+    fn test_server_channels() {
+        let mut plugin_channels = PluginChannels::default();
+        let register = ResourceLocation::new(REGISTER_CHANNEL).unwrap();
+        let unregister = ResourceLocation::new(UNREGISTER_CHANNEL).unwrap();
+        plugin_channels.update_server_channels(&register, b"worldedit:cui\0bungeecord:main\0");
+        plugin_channels.update_server_channels(&unregister, b"worldedit:cui");
+        // other channels aren't registrations
+        plugin_channels
+            .update_server_channels(&ResourceLocation::new("worldedit:cui").unwrap(), b"foo:bar");
+        assert_eq!(
+            plugin_channels.server_channels(),
+            &HashSet::from([ResourceLocation::new("bungeecord:main").unwrap()])
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::Client;
+use azalea_protocol::packets::game::{
+    clientbound_resource_pack_packet::ClientboundResourcePackPacket,
+    serverbound_resource_pack_packet::{Action, ServerboundResourcePackPacket},
+};
+use log::debug;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+type ResourcePackCallback = Arc<dyn Fn(&ClientboundResourcePackPacket) -> bool + Send + Sync>;
+
+/// How the client answers when a server asks it to use a resource pack. Set
+/// it with [`Client::set_resource_pack_policy`].
+///
+/// Resource packs are never actually downloaded, accepting one just tells
+/// the server that it loaded.
+#[derive(Clone, Default)]
+pub enum ResourcePackPolicy {
+    /// Accept every resource pack, so servers that require one don't kick
+    /// us.
+    #[default]
+    AcceptAll,
+    /// Decline every resource pack. Servers that require one will kick us.
+    DeclineAll,
+    /// Accept the resource pack if the function returns true.
+    Callback(ResourcePackCallback),
+}
+
+impl ResourcePackPolicy {
+    /// Create a policy that calls the function to decide whether to accept
+    /// each resource pack.
+    pub fn callback(
+        callback: impl Fn(&ClientboundResourcePackPacket) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        ResourcePackPolicy::Callback(Arc::new(callback))
+    }
+
+    /// The responses that we send to the server for the resource pack, in
+    /// order.
+    fn responses(&self, packet: &ClientboundResourcePackPacket) -> &'static [Action] {
+        let accept = match self {
+            ResourcePackPolicy::AcceptAll => true,
+            ResourcePackPolicy::DeclineAll => false,
+            ResourcePackPolicy::Callback(callback) => callback(packet),
+        };
+        if accept {
+            &[Action::Accepted, Action::SuccessfullyLoaded]
+        } else {
+            &[Action::Declined]
+        }
+    }
+}
+
+impl Debug for ResourcePackPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourcePackPolicy::AcceptAll => write!(f, "AcceptAll"),
+            ResourcePackPolicy::DeclineAll => write!(f, "DeclineAll"),
+            ResourcePackPolicy::Callback(_) => write!(f, "Callback"),
+        }
+    }
+}
+
+impl Client {
+    /// Change how resource packs that the server sends are answered. By
+    /// default they're all accepted.
+    pub fn set_resource_pack_policy(&self, policy: ResourcePackPolicy) {
+        *self.resource_pack_policy.write() = policy;
+    }
+
+    /// Answer a resource pack that the server sent with the current policy.
+    pub(crate) async fn answer_resource_pack(
+        &self,
+        packet: &ClientboundResourcePackPacket,
+    ) -> Result<(), std::io::Error> {
+        let responses = self.resource_pack_policy.read().responses(packet);
+        debug!("Answering resource pack {} with {responses:?}", packet.url);
+        for &action in responses {
+            self.write_packet(ServerboundResourcePackPacket { action }.get())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource_pack(required: bool) -> ClientboundResourcePackPacket {
+        ClientboundResourcePackPacket {
+            url: "https://example.com/pack.zip".to_string(),
+            hash: String::new(),
+            required,
+            prompt: None,
+        }
+    }
+
+    #[test]
+    fn test_resource_pack_policy() {
+        assert!(matches!(
+            ResourcePackPolicy::default().responses(&resource_pack(true)),
+            [Action::Accepted, Action::SuccessfullyLoaded]
+        ));
+        assert!(matches!(
+            ResourcePackPolicy::DeclineAll.responses(&resource_pack(true)),
+            [Action::Declined]
+        ));
+
+        let policy = ResourcePackPolicy::callback(|packet| packet.required);
+        assert!(matches!(
+            policy.responses(&resource_pack(true)),
+            [Action::Accepted, Action::SuccessfullyLoaded]
+        ));
+        assert!(matches!(
+            policy.responses(&resource_pack(false)),
+            [Action::Declined]
+        ));
+    }
+}
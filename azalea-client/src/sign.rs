@@ -0,0 +1,86 @@
+use crate::Client;
+use azalea_core::BlockPos;
+use azalea_protocol::packets::game::serverbound_sign_update_packet::ServerboundSignUpdatePacket;
+use thiserror::Error;
+
+/// The most characters that the server reads for each line of a sign.
+/// Servers disconnect clients that send longer lines.
+pub const MAX_SIGN_LINE_LENGTH: usize = 384;
+
+#[derive(Error, Debug)]
+pub enum WriteSignError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("Signs only have 4 lines, but {0} were given")]
+    TooManyLines(usize),
+    #[error("Line {line} is longer than {MAX_SIGN_LINE_LENGTH} characters")]
+    LineTooLong { line: usize },
+}
+
+/// Turn the lines into the 4 lines that are sent, with missing lines left
+/// empty.
+fn sign_lines(lines: &[&str]) -> Result<[String; 4], WriteSignError> {
+    if lines.len() > 4 {
+        return Err(WriteSignError::TooManyLines(lines.len()));
+    }
+    let mut sign_lines: [String; 4] = Default::default();
+    for (i, line) in lines.iter().enumerate() {
+        if line.chars().count() > MAX_SIGN_LINE_LENGTH {
+            return Err(WriteSignError::LineTooLong { line: i });
+        }
+        sign_lines[i] = line.to_string();
+    }
+    Ok(sign_lines)
+}
+
+impl Client {
+    /// Set the text on the sign at the position. Missing lines are left
+    /// empty.
+    ///
+    /// The server only lets you edit a sign right after you place it, when
+    /// it sends a `ClientboundOpenSignEditorPacket`, so you should call this
+    /// after receiving that.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use azalea_client::{Client, Event};
+    /// # use azalea_protocol::packets::game::ClientboundGamePacket;
+    /// # async fn handle(bot: Client, event: Event) -> anyhow::Result<()> {
+    /// if let Event::Packet(packet) = event {
+    ///     if let ClientboundGamePacket::OpenSignEditor(p) = *packet {
+    ///         bot.write_sign(p.pos, &["Diamonds", "5 for 1 emerald"]).await?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn write_sign(&self, pos: BlockPos, lines: &[&str]) -> Result<(), WriteSignError> {
+        let lines = sign_lines(lines)?;
+        self.write_packet(ServerboundSignUpdatePacket { pos, lines }.get())
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_lines() {
+        assert_eq!(
+            sign_lines(&["Diamonds", "5 for 1"]).unwrap(),
+            ["Diamonds", "5 for 1", "", ""]
+        );
+        assert!(matches!(
+            sign_lines(&["a", "b", "c", "d", "e"]),
+            Err(WriteSignError::TooManyLines(5))
+        ));
+        let long_line = "a".repeat(MAX_SIGN_LINE_LENGTH + 1);
+        assert!(matches!(
+            sign_lines(&["", &long_line]),
+            Err(WriteSignError::LineTooLong { line: 1 })
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::Client;
+use azalea_brigadier::suggestion::Suggestions;
+use azalea_protocol::packets::game::serverbound_command_suggestion_packet::ServerboundCommandSuggestionPacket;
+use log::warn;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+/// How long [`Client::tab_complete`] waits for the server to respond. Some
+/// servers ignore requests that are sent too quickly instead of responding.
+const TAB_COMPLETE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum TabCompleteError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("The server didn't respond with any suggestions")]
+    NoResponse,
+}
+
+/// The tab complete requests that are waiting for a response, by the id
+/// that was sent with them.
+#[derive(Default)]
+pub(crate) struct PendingSuggestions {
+    next_id: u32,
+    requests: HashMap<u32, oneshot::Sender<Suggestions>>,
+}
+
+impl PendingSuggestions {
+    fn add(&mut self) -> (u32, oneshot::Receiver<Suggestions>) {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let (tx, rx) = oneshot::channel();
+        self.requests.insert(id, tx);
+        (id, rx)
+    }
+
+    /// Send the suggestions to whoever requested them.
+    pub(crate) fn resolve(&mut self, id: u32, suggestions: Suggestions) {
+        match self.requests.remove(&id) {
+            // the receiver is gone if the request timed out, which is fine
+            Some(tx) => {
+                let _ = tx.send(suggestions);
+            }
+            None => warn!("Got command suggestions for unknown request {id}"),
+        }
+    }
+}
+
+impl Client {
+    /// Ask the server how the given command could be completed, like
+    /// pressing tab in the chat box. The command should start with a slash,
+    /// and suggestions are for the end of it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # async fn example(client: &azalea_client::Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// // find every command that the server has
+    /// let suggestions = client.tab_complete("/").await?;
+    /// for suggestion in suggestions.suggestions {
+    ///     println!("{}", suggestion.text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn tab_complete(&self, command: &str) -> Result<Suggestions, TabCompleteError> {
+        let (id, rx) = self.pending_suggestions.lock().add();
+        let result = self
+            .write_packet(
+                ServerboundCommandSuggestionPacket {
+                    id,
+                    command: command.to_string(),
+                }
+                .get(),
+            )
+            .await;
+        if let Err(e) = result {
+            self.pending_suggestions.lock().requests.remove(&id);
+            return Err(e.into());
+        }
+
+        match tokio::time::timeout(TAB_COMPLETE_TIMEOUT, rx).await {
+            Ok(Ok(suggestions)) => Ok(suggestions),
+            // the request was replaced, which only happens if the ids
+            // wrapped around
+            Ok(Err(_)) => Err(TabCompleteError::NoResponse),
+            Err(_) => {
+                self.pending_suggestions.lock().requests.remove(&id);
+                Err(TabCompleteError::NoResponse)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_brigadier::context::StringRange;
+
+    #[test]
+    fn test_pending_suggestions() {
+        let mut pending = PendingSuggestions::default();
+        let (first_id, mut first) = pending.add();
+        let (second_id, mut second) = pending.add();
+        assert_ne!(first_id, second_id);
+
+        let suggestions = Suggestions {
+            range: StringRange::at(1),
+            suggestions: Vec::new(),
+        };
+        pending.resolve(second_id, suggestions.clone());
+        assert_eq!(second.try_recv().unwrap(), suggestions);
+        assert!(first.try_recv().is_err());
+        // responses for requests we don't know about are ignored
+        pending.resolve(second_id, suggestions);
+        assert_eq!(pending.requests.len(), 1);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::Client;
+use azalea_core::BlockPos;
+use azalea_nbt::Tag;
+use azalea_protocol::packets::game::{
+    serverbound_block_entity_tag_query::ServerboundBlockEntityTagQuery,
+    serverbound_entity_tag_query::ServerboundEntityTagQuery, ServerboundGamePacket,
+};
+use log::warn;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+/// How long tag queries wait for the server to respond. The server ignores
+/// queries from players that aren't operators, so this is how long it takes
+/// to find out we don't have permission.
+const TAG_QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum TagQueryError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("The server didn't respond to the query, we might not be an operator")]
+    NoResponse,
+}
+
+/// The tag queries that are waiting for a response, by their transaction
+/// id.
+#[derive(Default)]
+pub(crate) struct PendingTagQueries {
+    next_id: u32,
+    requests: HashMap<u32, oneshot::Sender<Tag>>,
+}
+
+impl PendingTagQueries {
+    fn add(&mut self) -> (u32, oneshot::Receiver<Tag>) {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let (tx, rx) = oneshot::channel();
+        self.requests.insert(id, tx);
+        (id, rx)
+    }
+
+    /// Send the tag to whoever queried it.
+    pub(crate) fn resolve(&mut self, id: u32, tag: Tag) {
+        match self.requests.remove(&id) {
+            // the receiver is gone if the query timed out, which is fine
+            Some(tx) => {
+                let _ = tx.send(tag);
+            }
+            None => warn!("Got tag for unknown query {id}"),
+        }
+    }
+}
+
+impl Client {
+    /// Get the full NBT of the block entity at the position, like a chest's
+    /// items or a sign's text. This only works if we're an operator.
+    ///
+    /// Returns `None` if there's no block entity there.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use azalea_client::Client;
+    /// use azalea_core::BlockPos;
+    ///
+    /// # async fn example(bot: &Client) -> Result<(), Box<dyn std::error::Error>> {
+    /// if let Some(tag) = bot.query_block_entity_tag(BlockPos::new(0, 64, 0)).await? {
+    ///     println!("{tag:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_block_entity_tag(
+        &self,
+        pos: BlockPos,
+    ) -> Result<Option<Tag>, TagQueryError> {
+        self.query_tag(|transaction_id| {
+            ServerboundBlockEntityTagQuery {
+                transaction_id,
+                pos,
+            }
+            .get()
+        })
+        .await
+    }
+
+    /// Get the full NBT of the entity with the id, like its health or the
+    /// items in its inventory. This only works if we're an operator.
+    ///
+    /// Returns `None` if there's no entity with the id.
+    pub async fn query_entity_tag(&self, entity_id: u32) -> Result<Option<Tag>, TagQueryError> {
+        self.query_tag(|transaction_id| {
+            ServerboundEntityTagQuery {
+                transaction_id,
+                entity_id,
+            }
+            .get()
+        })
+        .await
+    }
+
+    async fn query_tag(
+        &self,
+        packet: impl FnOnce(u32) -> ServerboundGamePacket,
+    ) -> Result<Option<Tag>, TagQueryError> {
+        let (id, rx) = self.pending_tag_queries.lock().add();
+        if let Err(e) = self.write_packet(packet(id)).await {
+            self.pending_tag_queries.lock().requests.remove(&id);
+            return Err(e.into());
+        }
+
+        match tokio::time::timeout(TAG_QUERY_TIMEOUT, rx).await {
+            // the server sends an empty tag if there was nothing there
+            Ok(Ok(Tag::End)) => Ok(None),
+            Ok(Ok(tag)) => Ok(Some(tag)),
+            // the query was replaced, which only happens if the ids wrapped
+            // around
+            Ok(Err(_)) => Err(TagQueryError::NoResponse),
+            Err(_) => {
+                self.pending_tag_queries.lock().requests.remove(&id);
+                Err(TagQueryError::NoResponse)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_tag_queries() {
+        let mut pending = PendingTagQueries::default();
+        let (first_id, mut first) = pending.add();
+        let (second_id, mut second) = pending.add();
+        assert_ne!(first_id, second_id);
+
+        pending.resolve(first_id, Tag::Int(5));
+        assert_eq!(first.try_recv().unwrap(), Tag::Int(5));
+        assert!(second.try_recv().is_err());
+        // tags for queries we don't know about are ignored
+        pending.resolve(first_id, Tag::End);
+        assert_eq!(pending.requests.len(), 1);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::Client;
+use azalea_core::Vec3;
+use azalea_protocol::packets::game::{
+    clientbound_move_vehicle_packet::ClientboundMoveVehiclePacket,
+    clientbound_set_passengers_packet::ClientboundSetPassengersPacket,
+    serverbound_move_player_rot_packet::ServerboundMovePlayerRotPacket,
+    serverbound_move_vehicle_packet::ServerboundMoveVehiclePacket,
+    serverbound_paddle_boat_packet::ServerboundPaddleBoatPacket,
+    serverbound_player_input_packet::ServerboundPlayerInputPacket,
+};
+use log::debug;
+
+impl Client {
+    /// The entity id of the vehicle (like a boat, horse, or minecart) that
+    /// we're riding, if we're riding one.
+    pub fn vehicle_id(&self) -> Option<u32> {
+        self.player.lock().vehicle_id
+    }
+
+    /// Move the vehicle that we're controlling, like a boat or a saddled
+    /// horse. Vanilla clients simulate their vehicle's physics and send where
+    /// it ended up every tick, but azalea doesn't simulate vehicles yet, so
+    /// you have to decide where it goes.
+    ///
+    /// The server might move the vehicle back if it went somewhere it
+    /// couldn't have.
+    pub async fn move_vehicle(&self, pos: Vec3, y_rot: f32, x_rot: f32) -> std::io::Result<()> {
+        if let Some(vehicle_id) = self.vehicle_id() {
+            let mut dimension = self.dimension.lock();
+            // the vehicle might not be loaded, but we can still tell the server
+            let _ = dimension.set_entity_pos(vehicle_id, pos);
+            if let Some(mut vehicle) = dimension.entity_mut(vehicle_id) {
+                vehicle.set_rotation(y_rot, x_rot);
+            }
+        }
+        self.write_packet(
+            ServerboundMoveVehiclePacket {
+                x: pos.x,
+                y: pos.y,
+                z: pos.z,
+                y_rot,
+                x_rot,
+            }
+            .get(),
+        )
+        .await
+    }
+
+    /// Set which paddles of the boat we're in are moving. This is only for
+    /// the animation and sounds, the boat itself is moved with
+    /// [`Client::move_vehicle`].
+    pub async fn paddle_boat(&self, left: bool, right: bool) -> std::io::Result<()> {
+        self.write_packet(ServerboundPaddleBoatPacket { left, right }.get())
+            .await
+    }
+
+    /// Get off the vehicle that we're riding, like pressing shift in
+    /// vanilla. The server will teleport us next to it.
+    pub fn dismount(&self) {
+        self.physics_state.lock().dismounting = true;
+    }
+
+    /// Keep track of whether we got on or off a vehicle.
+    pub(crate) fn update_passengers(&self, packet: &ClientboundSetPassengersPacket) {
+        let mut player = self.player.lock();
+        if packet.passengers.contains(&player.entity_id) {
+            debug!("Started riding {}", packet.vehicle);
+            player.vehicle_id = Some(packet.vehicle);
+        } else if player.vehicle_id == Some(packet.vehicle) {
+            drop(player);
+            self.stop_riding();
+        }
+    }
+
+    /// Forget about our vehicle, because we got off it or it's gone.
+    pub(crate) fn stop_riding(&self) {
+        if let Some(vehicle_id) = self.player.lock().vehicle_id.take() {
+            debug!("Stopped riding {vehicle_id}");
+        }
+        self.physics_state.lock().dismounting = false;
+    }
+
+    /// The server moved the vehicle we're controlling, so move it and tell
+    /// the server where it is now like vanilla does.
+    pub(crate) async fn handle_move_vehicle(
+        &self,
+        packet: &ClientboundMoveVehiclePacket,
+    ) -> std::io::Result<()> {
+        if self.vehicle_id().is_none() {
+            return Ok(());
+        }
+        let pos = Vec3 {
+            x: packet.x,
+            y: packet.y,
+            z: packet.z,
+        };
+        self.move_vehicle(pos, packet.y_rot, packet.x_rot).await
+    }
+
+    /// Send our rotation and the keys we're pressing, which is what's sent
+    /// every tick instead of our position while we're riding something.
+    pub(crate) async fn send_passenger_input(&self) -> std::io::Result<()> {
+        let (rot_packet, input_packet) = {
+            let dimension = self.dimension.lock();
+            let player_entity = self.entity(&dimension);
+            let physics_state = self.physics_state.lock();
+            (
+                ServerboundMovePlayerRotPacket {
+                    y_rot: player_entity.y_rot,
+                    x_rot: player_entity.x_rot,
+                    on_ground: player_entity.on_ground,
+                },
+                ServerboundPlayerInputPacket {
+                    xxa: physics_state.left_impulse,
+                    zza: physics_state.forward_impulse,
+                    is_jumping: player_entity.jumping,
+                    is_shift_key_down: physics_state.dismounting,
+                },
+            )
+        };
+        self.write_packet(rot_packet.get()).await?;
+        self.write_packet(input_packet.get()).await
+    }
+}
@@ -0,0 +1,148 @@
+use azalea_core::BlockPos;
+use azalea_protocol::packets::game::clientbound_initialize_border_packet::ClientboundInitializeBorderPacket;
+use std::time::{Duration, Instant};
+
+/// The world border, updated from the server's border packets. The border is
+/// a square around its center, and players can't go outside of it.
+#[derive(Clone, Debug)]
+pub struct WorldBorder {
+    pub center_x: f64,
+    pub center_z: f64,
+    /// The size that the border is moving from.
+    old_size: f64,
+    /// The size that the border is moving to.
+    new_size: f64,
+    /// When the border started moving.
+    lerp_start: Instant,
+    /// How long the border takes to get from the old size to the new size.
+    lerp_duration: Duration,
+    /// The border can't go further than this many blocks from 0, 0 no matter
+    /// what its center and size are.
+    pub absolute_max_size: u32,
+    /// How far away from the border players see the warning.
+    pub warning_blocks: u32,
+    /// How many seconds before a moving border reaches players they see the
+    /// warning.
+    pub warning_time: u32,
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        // the same as vanilla
+        Self {
+            center_x: 0.,
+            center_z: 0.,
+            old_size: 59999968.,
+            new_size: 59999968.,
+            lerp_start: Instant::now(),
+            lerp_duration: Duration::ZERO,
+            absolute_max_size: 29999984,
+            warning_blocks: 5,
+            warning_time: 15,
+        }
+    }
+}
+
+impl WorldBorder {
+    /// The length of the border's sides right now.
+    pub fn size(&self) -> f64 {
+        self.size_at(Instant::now())
+    }
+
+    /// The length of the border's sides at the given time, which is
+    /// different from [`WorldBorder::size`] if the border is moving.
+    pub fn size_at(&self, time: Instant) -> f64 {
+        let elapsed = time.saturating_duration_since(self.lerp_start);
+        if elapsed >= self.lerp_duration {
+            return self.new_size;
+        }
+        let progress = elapsed.as_secs_f64() / self.lerp_duration.as_secs_f64();
+        self.old_size + (self.new_size - self.old_size) * progress
+    }
+
+    /// The size that the border will end up at, which is the same as
+    /// [`WorldBorder::size`] unless the border is moving.
+    pub fn target_size(&self) -> f64 {
+        self.new_size
+    }
+
+    pub fn min_x(&self) -> f64 {
+        (self.center_x - self.size() / 2.).max(-(self.absolute_max_size as f64))
+    }
+
+    pub fn max_x(&self) -> f64 {
+        (self.center_x + self.size() / 2.).min(self.absolute_max_size as f64)
+    }
+
+    pub fn min_z(&self) -> f64 {
+        (self.center_z - self.size() / 2.).max(-(self.absolute_max_size as f64))
+    }
+
+    pub fn max_z(&self) -> f64 {
+        (self.center_z + self.size() / 2.).min(self.absolute_max_size as f64)
+    }
+
+    /// Whether any part of the block is inside the border right now.
+    pub fn is_within_border(&self, pos: &BlockPos) -> bool {
+        let (x, z) = (pos.x as f64, pos.z as f64);
+        x + 1. > self.min_x() && x < self.max_x() && z + 1. > self.min_z() && z < self.max_z()
+    }
+
+    /// Start moving the border to the new size, like the `/worldborder set`
+    /// command does when it's given a time.
+    pub fn lerp_size_between(&mut self, old_size: f64, new_size: f64, duration: Duration) {
+        self.old_size = old_size;
+        self.new_size = new_size;
+        self.lerp_start = Instant::now();
+        self.lerp_duration = duration;
+    }
+
+    /// Set the size of the border immediately, stopping it if it was moving.
+    pub fn set_size(&mut self, size: f64) {
+        self.lerp_size_between(size, size, Duration::ZERO);
+    }
+
+    /// Apply the border packet that the server sends when we join or change
+    /// dimensions.
+    pub fn initialize(&mut self, packet: &ClientboundInitializeBorderPacket) {
+        self.center_x = packet.new_center_x;
+        self.center_z = packet.new_center_z;
+        self.lerp_size_between(
+            packet.old_size,
+            packet.new_size,
+            Duration::from_millis(packet.lerp_time),
+        );
+        self.absolute_max_size = packet.new_absolute_max_size;
+        self.warning_blocks = packet.warning_blocks;
+        self.warning_time = packet.warning_time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_within_border() {
+        let mut border = WorldBorder {
+            center_x: 10.,
+            ..Default::default()
+        };
+        border.set_size(10.);
+        assert!(border.is_within_border(&BlockPos::new(5, 64, -5)));
+        assert!(border.is_within_border(&BlockPos::new(14, 0, 4)));
+        assert!(!border.is_within_border(&BlockPos::new(15, 64, 0)));
+        assert!(!border.is_within_border(&BlockPos::new(10, 64, -6)));
+    }
+
+    #[test]
+    fn test_lerp_size() {
+        let mut border = WorldBorder::default();
+        border.lerp_size_between(100., 50., Duration::from_secs(10));
+        let start = border.lerp_start;
+        assert_eq!(border.size_at(start), 100.);
+        assert_eq!(border.size_at(start + Duration::from_secs(5)), 75.);
+        assert_eq!(border.size_at(start + Duration::from_secs(20)), 50.);
+        assert_eq!(border.target_size(), 50.);
+    }
+}
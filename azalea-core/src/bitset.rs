@@ -2,6 +2,7 @@ use azalea_buf::McBuf;
 
 /// Represents Java's BitSet, a list of bits.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BitSet {
     data: Vec<u64>,
 }
@@ -16,8 +17,13 @@ impl BitSet {
         }
     }
 
+    /// Whether the bit at the given index is set. Bits past the end of the
+    /// BitSet are always `false`, like in Java.
     pub fn index(&self, index: usize) -> bool {
-        (self.data[index / 64] & (1u64 << (index % 64))) != 0
+        match self.data.get(index / 64) {
+            Some(word) => (word & (1u64 << (index % 64))) != 0,
+            None => false,
+        }
     }
 
     fn check_range(&self, from_index: usize, to_index: usize) {
@@ -117,6 +123,7 @@ mod tests {
         assert_eq!(bitset.index(0), false);
         assert_eq!(bitset.index(1), true);
         assert_eq!(bitset.index(2), false);
+        assert!(!bitset.index(1000));
     }
 
     #[test]
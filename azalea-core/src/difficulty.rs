@@ -6,6 +6,7 @@ use std::{
 use azalea_buf::{BufReadError, McBufReadable, McBufWritable};
 
 #[derive(Hash, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Difficulty {
     PEACEFUL = 0,
     EASY = 1,
@@ -3,6 +3,7 @@ use azalea_buf::McBuf;
 use crate::floor_mod;
 
 #[derive(Clone, Copy, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Direction {
     Down = 0,
     Up = 1,
@@ -2,6 +2,7 @@ use azalea_buf::{BufReadError, McBufReadable, McBufWritable};
 use std::io::{Cursor, Write};
 
 #[derive(Hash, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum GameType {
     SURVIVAL,
     CREATIVE,
@@ -93,6 +94,7 @@ impl McBufWritable for GameType {
 
 /// Rust doesn't let us `impl McBufReadable for Option<GameType>` so we have to make a new type :(
 #[derive(Hash, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct OptionalGameType(Option<GameType>);
 
 impl From<Option<GameType>> for OptionalGameType {
@@ -1,112 +1,176 @@
-use crate::{BlockPos, Slot};
-use azalea_buf::{BufReadError, McBuf, McBufReadable, McBufVarReadable, McBufWritable};
+use crate::{BlockPos, ResourceLocation, Slot};
+use azalea_buf::{
+    BufReadError, McBuf, McBufReadable, McBufVarReadable, McBufVarWritable, McBufWritable,
+};
 use std::io::{Cursor, Write};
 
-#[derive(Debug, Clone, McBuf)]
-pub struct Particle {
-    #[var]
-    pub id: i32,
-    pub data: ParticleData,
+/// Define the [`Particle`] enum, with the options that each particle type
+/// has. The ids are the ones in the particle type registry.
+macro_rules! particles {
+    ($($id:literal => $variant:ident $(($data:ty))?,)*) => {
+        /// A particle type and its options, like the color of dust. This is
+        /// what's in the level particles packet and entity metadata.
+        #[derive(Debug, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+        pub enum Particle {
+            $($variant $(($data))?,)*
+        }
+
+        impl Particle {
+            /// The id of the particle type in the registry.
+            pub fn id(&self) -> u32 {
+                match self {
+                    $(particles!(@pattern $variant _, $(($data))?) => $id,)*
+                }
+            }
+
+            /// Read the particle's options, when we already read the id of
+            /// the particle type.
+            pub fn read_from_particle_id(
+                buf: &mut Cursor<&[u8]>,
+                id: u32,
+            ) -> Result<Self, BufReadError> {
+                Ok(match id {
+                    $($id => Particle::$variant $((<$data>::read_from(buf)?))?,)*
+                    _ => return Err(BufReadError::UnexpectedEnumVariant { id: id as i32 }),
+                })
+            }
+
+            /// Write the particle's options without the id of the particle
+            /// type, which is how they're sent in the level particles packet.
+            pub fn write_without_id(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+                match self {
+                    $(particles!(@pattern $variant data, $(($data))?) => {
+                        particles!(@write data buf $(($data))?)
+                    })*
+                }
+            }
+        }
+    };
+    (@pattern $variant:ident $binding:pat, ($data:ty)) => { Particle::$variant($binding) };
+    (@pattern $variant:ident $binding:pat,) => { Particle::$variant };
+    (@write $binding:ident $buf:ident ($data:ty)) => { $binding.write_into($buf) };
+    (@write $binding:ident $buf:ident) => { Ok(()) };
 }
 
-#[derive(Clone, Debug)]
-pub enum ParticleData {
-    AmbientEntityEffect,
-    AngryVillager,
-    Block(BlockParticle),
-    BlockMarker(BlockParticle),
-    Bubble,
-    Cloud,
-    Crit,
-    DamageIndicator,
-    DragonBreath,
-    DrippingLava,
-    FallingLava,
-    LandingLava,
-    DrippingWater,
-    FallingWater,
-    Dust(DustParticle),
-    DustColorTransition(DustColorTransitionParticle),
-    Effect,
-    ElderGuardian,
-    EnchantedHit,
-    Enchant,
-    EndRod,
-    EntityEffect,
-    ExplosionEmitter,
-    Explosion,
-    FallingDust(BlockParticle),
-    Firework,
-    Fishing,
-    Flame,
-    SoulFireFlame,
-    Soul,
-    Flash,
-    HappyVillager,
-    Composter,
-    Heart,
-    InstantEffect,
-    Item(ItemParticle),
-    Vibration(VibrationParticle),
-    ItemSlime,
-    ItemSnowball,
-    LargeSmoke,
-    Lava,
-    Mycelium,
-    Note,
-    Poof,
-    Portal,
-    Rain,
-    Smoke,
-    Sneeze,
-    Spit,
-    SquidInk,
-    SweepAttack,
-    TotemOfUndying,
-    Underwater,
-    Splash,
-    Witch,
-    BubblePop,
-    CurrentDown,
-    BubbleColumnUp,
-    Nautilus,
-    Dolphin,
-    CampfireCozySmoke,
-    CampfireSignalSmoke,
-    DrippingHoney,
-    FallingHoney,
-    LandingHoney,
-    FallingNectar,
-    FallingSporeBlossom,
-    Ash,
-    CrimsonSpore,
-    WarpedSpore,
-    SporeBlossomAir,
-    DrippingObsidianTear,
-    FallingObsidianTear,
-    LandingObsidianTear,
-    ReversePortal,
-    WhiteAsh,
-    SmallFlame,
-    Snowflake,
-    DrippingDripstoneLava,
-    FallingDripstoneLava,
-    DrippingDripstoneWater,
-    FallingDripstoneWater,
-    GlowSquidInk,
-    Glow,
-    WaxOn,
-    WaxOff,
-    ElectricSpark,
-    Scrape,
+particles! {
+    0 => AmbientEntityEffect,
+    1 => AngryVillager,
+    2 => Block(BlockParticle),
+    3 => BlockMarker(BlockParticle),
+    4 => Bubble,
+    5 => Cloud,
+    6 => Crit,
+    7 => DamageIndicator,
+    8 => DragonBreath,
+    9 => DrippingLava,
+    10 => FallingLava,
+    11 => LandingLava,
+    12 => DrippingWater,
+    13 => FallingWater,
+    14 => Dust(DustParticle),
+    15 => DustColorTransition(DustColorTransitionParticle),
+    16 => Effect,
+    17 => ElderGuardian,
+    18 => EnchantedHit,
+    19 => Enchant,
+    20 => EndRod,
+    21 => EntityEffect,
+    22 => ExplosionEmitter,
+    23 => Explosion,
+    24 => SonicBoom,
+    25 => FallingDust(BlockParticle),
+    26 => Firework,
+    27 => Fishing,
+    28 => Flame,
+    29 => SculkSoul,
+    30 => SculkCharge(SculkChargeParticle),
+    31 => SculkChargePop,
+    32 => SoulFireFlame,
+    33 => Soul,
+    34 => Flash,
+    35 => HappyVillager,
+    36 => Composter,
+    37 => Heart,
+    38 => InstantEffect,
+    39 => Item(ItemParticle),
+    40 => Vibration(VibrationParticle),
+    41 => ItemSlime,
+    42 => ItemSnowball,
+    43 => LargeSmoke,
+    44 => Lava,
+    45 => Mycelium,
+    46 => Note,
+    47 => Poof,
+    48 => Portal,
+    49 => Rain,
+    50 => Smoke,
+    51 => Sneeze,
+    52 => Spit,
+    53 => SquidInk,
+    54 => SweepAttack,
+    55 => TotemOfUndying,
+    56 => Underwater,
+    57 => Splash,
+    58 => Witch,
+    59 => BubblePop,
+    60 => CurrentDown,
+    61 => BubbleColumnUp,
+    62 => Nautilus,
+    63 => Dolphin,
+    64 => CampfireCosySmoke,
+    65 => CampfireSignalSmoke,
+    66 => DrippingHoney,
+    67 => FallingHoney,
+    68 => LandingHoney,
+    69 => FallingNectar,
+    70 => FallingSporeBlossom,
+    71 => Ash,
+    72 => CrimsonSpore,
+    73 => WarpedSpore,
+    74 => SporeBlossomAir,
+    75 => DrippingObsidianTear,
+    76 => FallingObsidianTear,
+    77 => LandingObsidianTear,
+    78 => ReversePortal,
+    79 => WhiteAsh,
+    80 => SmallFlame,
+    81 => Snowflake,
+    82 => DrippingDripstoneLava,
+    83 => FallingDripstoneLava,
+    84 => DrippingDripstoneWater,
+    85 => FallingDripstoneWater,
+    86 => GlowSquidInk,
+    87 => Glow,
+    88 => WaxOn,
+    89 => WaxOff,
+    90 => ElectricSpark,
+    91 => Scrape,
+    92 => Shriek(ShriekParticle),
+}
+
+impl McBufReadable for Particle {
+    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        let id = u32::var_read_from(buf)?;
+        Particle::read_from_particle_id(buf, id)
+    }
+}
+
+impl McBufWritable for Particle {
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        self.id().var_write_into(buf)?;
+        self.write_without_id(buf)
+    }
 }
 
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BlockParticle {
     #[var]
     pub block_state: i32,
 }
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DustParticle {
     /// Red value, 0-1
     pub red: f32,
@@ -119,6 +183,7 @@ pub struct DustParticle {
 }
 
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DustColorTransitionParticle {
     /// Red value, 0-1
     pub from_red: f32,
@@ -137,126 +202,130 @@ pub struct DustColorTransitionParticle {
 }
 
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ItemParticle {
     pub item: Slot,
 }
 
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct VibrationParticle {
-    pub origin: BlockPos,
-    pub position_type: String,
-    pub block_position: BlockPos,
+    /// Where the vibration is going to.
+    pub destination: PositionSource,
+    /// How many ticks it takes to get there.
     #[var]
-    pub entity_id: u32,
+    pub arrival_in_ticks: u32,
+}
+
+#[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SculkChargeParticle {
+    /// The rotation of the particle, in radians.
+    pub roll: f32,
+}
+
+#[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShriekParticle {
+    /// How many ticks to wait before showing the particle.
     #[var]
-    pub ticks: u32,
+    pub delay: u32,
 }
 
-impl ParticleData {
-    pub fn read_from_particle_id(buf: &mut Cursor<&[u8]>, id: u32) -> Result<Self, BufReadError> {
-        Ok(match id {
-            0 => ParticleData::AmbientEntityEffect,
-            1 => ParticleData::AngryVillager,
-            2 => ParticleData::Block(BlockParticle::read_from(buf)?),
-            3 => ParticleData::BlockMarker(BlockParticle::read_from(buf)?),
-            4 => ParticleData::Bubble,
-            5 => ParticleData::Cloud,
-            6 => ParticleData::Crit,
-            7 => ParticleData::DamageIndicator,
-            8 => ParticleData::DragonBreath,
-            9 => ParticleData::DrippingLava,
-            10 => ParticleData::FallingLava,
-            11 => ParticleData::LandingLava,
-            12 => ParticleData::DrippingWater,
-            13 => ParticleData::FallingWater,
-            14 => ParticleData::Dust(DustParticle::read_from(buf)?),
-            15 => ParticleData::DustColorTransition(DustColorTransitionParticle::read_from(buf)?),
-            16 => ParticleData::Effect,
-            17 => ParticleData::ElderGuardian,
-            18 => ParticleData::EnchantedHit,
-            19 => ParticleData::Enchant,
-            20 => ParticleData::EndRod,
-            21 => ParticleData::EntityEffect,
-            22 => ParticleData::ExplosionEmitter,
-            23 => ParticleData::Explosion,
-            24 => ParticleData::FallingDust(BlockParticle::read_from(buf)?),
-            25 => ParticleData::Firework,
-            26 => ParticleData::Fishing,
-            27 => ParticleData::Flame,
-            28 => ParticleData::SoulFireFlame,
-            29 => ParticleData::Soul,
-            30 => ParticleData::Flash,
-            31 => ParticleData::HappyVillager,
-            32 => ParticleData::Composter,
-            33 => ParticleData::Heart,
-            34 => ParticleData::InstantEffect,
-            35 => ParticleData::Item(ItemParticle::read_from(buf)?),
-            36 => ParticleData::Vibration(VibrationParticle::read_from(buf)?),
-            37 => ParticleData::ItemSlime,
-            38 => ParticleData::ItemSnowball,
-            39 => ParticleData::LargeSmoke,
-            40 => ParticleData::Lava,
-            41 => ParticleData::Mycelium,
-            42 => ParticleData::Note,
-            43 => ParticleData::Poof,
-            44 => ParticleData::Portal,
-            45 => ParticleData::Rain,
-            46 => ParticleData::Smoke,
-            47 => ParticleData::Sneeze,
-            48 => ParticleData::Spit,
-            49 => ParticleData::SquidInk,
-            50 => ParticleData::SweepAttack,
-            51 => ParticleData::TotemOfUndying,
-            52 => ParticleData::Underwater,
-            53 => ParticleData::Splash,
-            54 => ParticleData::Witch,
-            55 => ParticleData::BubblePop,
-            56 => ParticleData::CurrentDown,
-            57 => ParticleData::BubbleColumnUp,
-            58 => ParticleData::Nautilus,
-            59 => ParticleData::Dolphin,
-            60 => ParticleData::CampfireCozySmoke,
-            61 => ParticleData::CampfireSignalSmoke,
-            62 => ParticleData::DrippingHoney,
-            63 => ParticleData::FallingHoney,
-            64 => ParticleData::LandingHoney,
-            65 => ParticleData::FallingNectar,
-            66 => ParticleData::FallingSporeBlossom,
-            67 => ParticleData::Ash,
-            68 => ParticleData::CrimsonSpore,
-            69 => ParticleData::WarpedSpore,
-            70 => ParticleData::SporeBlossomAir,
-            71 => ParticleData::DrippingObsidianTear,
-            72 => ParticleData::FallingObsidianTear,
-            73 => ParticleData::LandingObsidianTear,
-            74 => ParticleData::ReversePortal,
-            75 => ParticleData::WhiteAsh,
-            76 => ParticleData::SmallFlame,
-            77 => ParticleData::Snowflake,
-            78 => ParticleData::DrippingDripstoneLava,
-            79 => ParticleData::FallingDripstoneLava,
-            80 => ParticleData::DrippingDripstoneWater,
-            81 => ParticleData::FallingDripstoneWater,
-            82 => ParticleData::GlowSquidInk,
-            83 => ParticleData::Glow,
-            84 => ParticleData::WaxOn,
-            85 => ParticleData::WaxOff,
-            86 => ParticleData::ElectricSpark,
-            87 => ParticleData::Scrape,
-            _ => return Err(BufReadError::UnexpectedEnumVariant { id: id as i32 }),
-        })
-    }
+/// Something that a vibration can go to.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PositionSource {
+    Block(BlockPos),
+    Entity {
+        id: u32,
+        /// How far above the entity's feet the vibration goes.
+        y_offset: f32,
+    },
 }
 
-impl McBufReadable for ParticleData {
+impl McBufReadable for PositionSource {
     fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        let id = u32::var_read_from(buf)?;
-        ParticleData::read_from_particle_id(buf, id)
+        let kind = ResourceLocation::read_from(buf)?.to_string();
+        match kind.as_str() {
+            "minecraft:block" => Ok(PositionSource::Block(BlockPos::read_from(buf)?)),
+            "minecraft:entity" => Ok(PositionSource::Entity {
+                id: u32::var_read_from(buf)?,
+                y_offset: f32::read_from(buf)?,
+            }),
+            _ => Err(BufReadError::UnexpectedStringEnumVariant { id: kind }),
+        }
+    }
+}
+
+impl McBufWritable for PositionSource {
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        match self {
+            PositionSource::Block(pos) => {
+                ResourceLocation::new("minecraft:block")
+                    .unwrap()
+                    .write_into(buf)?;
+                pos.write_into(buf)
+            }
+            PositionSource::Entity { id, y_offset } => {
+                ResourceLocation::new("minecraft:entity")
+                    .unwrap()
+                    .write_into(buf)?;
+                id.var_write_into(buf)?;
+                y_offset.write_into(buf)
+            }
+        }
     }
 }
 
-impl McBufWritable for ParticleData {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        todo!()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(particle: &Particle) -> Particle {
+        let mut buf = Vec::new();
+        particle.write_into(&mut buf).unwrap();
+        let mut cursor = Cursor::new(&buf[..]);
+        let read = Particle::read_from(&mut cursor).unwrap();
+        assert_eq!(cursor.position() as usize, buf.len());
+        read
+    }
+
+    #[test]
+    fn test_read_write_particle() {
+        assert!(matches!(round_trip(&Particle::Scrape), Particle::Scrape));
+        assert!(matches!(
+            round_trip(&Particle::Shriek(ShriekParticle { delay: 5 })),
+            Particle::Shriek(ShriekParticle { delay: 5 })
+        ));
+        assert!(matches!(
+            round_trip(&Particle::Vibration(VibrationParticle {
+                destination: PositionSource::Entity {
+                    id: 12,
+                    y_offset: 1.5,
+                },
+                arrival_in_ticks: 20,
+            })),
+            Particle::Vibration(VibrationParticle {
+                destination: PositionSource::Entity { id: 12, y_offset },
+                arrival_in_ticks: 20,
+            }) if y_offset == 1.5
+        ));
+        match round_trip(&Particle::Dust(DustParticle {
+            red: 1.,
+            green: 0.5,
+            blue: 0.,
+            scale: 2.,
+        })) {
+            Particle::Dust(dust) => assert_eq!((dust.red, dust.green, dust.scale), (1., 0.5, 2.)),
+            particle => panic!("expected dust, got {particle:?}"),
+        }
+    }
+
+    #[test]
+    fn test_particle_ids() {
+        assert_eq!(Particle::SonicBoom.id(), 24);
+        assert_eq!(Particle::Shriek(ShriekParticle { delay: 0 }).id(), 92);
+        assert!(Particle::read_from_particle_id(&mut Cursor::new(&[][..]), 93).is_err());
     }
 }
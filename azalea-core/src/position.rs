@@ -53,6 +53,7 @@ where
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BlockPos {
     pub x: i32,
     pub y: i32,
@@ -115,6 +116,7 @@ impl PositionXYZ<i32> for BlockPos {
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChunkPos {
     pub x: i32,
     pub z: i32,
@@ -128,6 +130,7 @@ impl ChunkPos {
 
 /// The coordinates of a chunk section in the world.
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChunkSectionPos {
     pub x: i32,
     pub y: i32,
@@ -143,7 +146,8 @@ impl ChunkSectionPos {
     }
 }
 /// The coordinates of a block inside a chunk.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChunkBlockPos {
     pub x: u8,
     pub y: i32,
@@ -156,7 +160,8 @@ impl ChunkBlockPos {
     }
 }
 /// The coordinates of a block inside a chunk section.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChunkSectionBlockPos {
     /// A number between 0 and 16.
     pub x: u8,
@@ -186,6 +191,7 @@ impl Add<ChunkSectionBlockPos> for ChunkSectionPos {
 
 /// A block pos with an attached dimension
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GlobalPos {
     pub pos: BlockPos,
     // this is actually a ResourceKey in Minecraft, but i don't think it matters?
@@ -194,6 +200,7 @@ pub struct GlobalPos {
 
 /// An exact point in the world.
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -271,9 +278,9 @@ impl From<&BlockPos> for ChunkBlockPos {
 impl From<&BlockPos> for ChunkSectionBlockPos {
     fn from(pos: &BlockPos) -> Self {
         ChunkSectionBlockPos {
-            x: pos.x.rem(16).unsigned_abs() as u8,
-            y: pos.y.rem(16).unsigned_abs() as u8,
-            z: pos.z.rem(16).unsigned_abs() as u8,
+            x: pos.x.rem_euclid(16).unsigned_abs() as u8,
+            y: pos.y.rem_euclid(16).unsigned_abs() as u8,
+            z: pos.z.rem_euclid(16).unsigned_abs() as u8,
         }
     }
 }
@@ -282,7 +289,7 @@ impl From<&ChunkBlockPos> for ChunkSectionBlockPos {
     fn from(pos: &ChunkBlockPos) -> Self {
         ChunkSectionBlockPos {
             x: pos.x,
-            y: pos.y.rem(16).unsigned_abs() as u8,
+            y: pos.y.rem_euclid(16).unsigned_abs() as u8,
             z: pos.z,
         }
     }
@@ -389,6 +396,21 @@ mod tests {
         assert_eq!(chunk_block_pos, ChunkBlockPos::new(5, 78, 14));
     }
 
+    #[test]
+    fn test_from_negative_block_pos_to_chunk_section_block_pos() {
+        let block_pos = BlockPos::new(-5, -60, 40);
+        let chunk_section_block_pos = ChunkSectionBlockPos::from(&block_pos);
+        assert_eq!(
+            chunk_section_block_pos,
+            ChunkSectionBlockPos { x: 11, y: 4, z: 8 }
+        );
+        let chunk_block_pos = ChunkBlockPos::from(&block_pos);
+        assert_eq!(
+            ChunkSectionBlockPos::from(&chunk_block_pos),
+            chunk_section_block_pos
+        );
+    }
+
     #[test]
     fn test_from_entity_pos_to_block_pos() {
         let entity_pos = Vec3 {
@@ -45,6 +45,13 @@ impl std::fmt::Debug for ResourceLocation {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for ResourceLocation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl McBufReadable for ResourceLocation {
     fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
         let location_string = String::read_from(buf)?;
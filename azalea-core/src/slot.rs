@@ -4,12 +4,14 @@ use azalea_buf::{BufReadError, McBuf, McBufReadable, McBufWritable};
 use std::io::{Cursor, Write};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Slot {
     Empty,
     Present(SlotData),
 }
 
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SlotData {
     #[var]
     pub id: i32,
@@ -31,8 +33,11 @@ impl McBufReadable for Slot {
 impl McBufWritable for Slot {
     fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
         match self {
-            Slot::Empty => 0u8.write_into(buf)?,
-            Slot::Present(i) => i.write_into(buf)?,
+            Slot::Empty => false.write_into(buf)?,
+            Slot::Present(i) => {
+                true.write_into(buf)?;
+                i.write_into(buf)?;
+            }
         }
 
         Ok(())
@@ -2,12 +2,15 @@ mod signing;
 
 use aes::cipher::inout::InOutBuf;
 use aes::{
-    cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit},
+    cipher::{BlockDecryptMut, BlockEncryptMut, IvState, KeyIvInit},
     Aes128,
 };
+use md5::Md5;
 use rand::{rngs::OsRng, RngCore};
 use sha1::{Digest, Sha1};
+use sha2::Sha256;
 pub use signing::*;
+use uuid::Uuid;
 
 fn generate_secret_key() -> [u8; 16] {
     let mut key = [0u8; 16];
@@ -32,6 +35,37 @@ pub fn hex_digest(digest: &[u8]) -> String {
     num_bigint::BigInt::from_signed_bytes_be(digest).to_str_radix(16)
 }
 
+/// Compute the HMAC-SHA256 of some data. This is used by Velocity's modern
+/// forwarding to sign the player info it sends to backend servers.
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+
+    // keys longer than the block size are hashed first
+    let mut block_key = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block_key[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block_key.map(|b| b ^ 0x36));
+    inner.update(data);
+    let inner_hash = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block_key.map(|b| b ^ 0x5c));
+    outer.update(inner_hash);
+    outer.finalize().into()
+}
+
+/// The UUID that offline-mode servers (and BungeeCord in offline mode) give
+/// a player, which is based on their username.
+pub fn offline_uuid(username: &str) -> Uuid {
+    let hash = Md5::digest(format!("OfflinePlayer:{username}"));
+    uuid::Builder::from_md5_bytes(hash.into()).into_uuid()
+}
+
 #[derive(Debug)]
 pub struct EncryptResult {
     pub secret_key: [u8; 16],
@@ -68,6 +102,29 @@ pub fn create_cipher(key: &[u8]) -> (Aes128CfbEnc, Aes128CfbDec) {
     )
 }
 
+/// Create ciphers that continue from where ciphers with the given key and
+/// IVs left off. The IVs are from [`encrypt_iv`] and [`decrypt_iv`].
+pub fn create_cipher_with_iv(
+    key: &[u8],
+    encrypt_iv: &[u8],
+    decrypt_iv: &[u8],
+) -> (Aes128CfbEnc, Aes128CfbDec) {
+    (
+        Aes128CfbEnc::new_from_slices(key, encrypt_iv).unwrap(),
+        Aes128CfbDec::new_from_slices(key, decrypt_iv).unwrap(),
+    )
+}
+
+/// The current IV of the cipher, which changes as it encrypts data.
+pub fn encrypt_iv(cipher: &Aes128CfbEnc) -> [u8; 16] {
+    cipher.iv_state().into()
+}
+
+/// The current IV of the cipher, which changes as it decrypts data.
+pub fn decrypt_iv(cipher: &Aes128CfbDec) -> [u8; 16] {
+    cipher.iv_state().into()
+}
+
 pub fn encrypt_packet(cipher: &mut Aes128CfbEnc, packet: &mut [u8]) {
     let (chunks, rest) = InOutBuf::from(packet).into_chunks();
     assert!(rest.is_empty());
@@ -89,6 +146,26 @@ mod tests {
         assert_eq!(key.len(), 16);
     }
 
+    #[test]
+    fn test_create_cipher_with_iv() {
+        let key = generate_secret_key();
+        let (mut enc, mut dec) = create_cipher(&key);
+        let mut data = *b"hello";
+        encrypt_packet(&mut enc, &mut data);
+        decrypt_packet(&mut dec, &mut data);
+
+        // the new ciphers pick up where the old ones were
+        let (mut new_enc, mut new_dec) =
+            create_cipher_with_iv(&key, &encrypt_iv(&enc), &decrypt_iv(&dec));
+        let mut data = *b"world";
+        let mut expected = data;
+        encrypt_packet(&mut new_enc, &mut data);
+        encrypt_packet(&mut enc, &mut expected);
+        assert_eq!(data, expected);
+        decrypt_packet(&mut new_dec, &mut data);
+        assert_eq!(&data, b"world");
+    }
+
     #[test]
     fn test_hex_digest() {
         let digest = hex_digest(&digest_data(b"Notch", &[], &[]));
@@ -101,6 +178,38 @@ mod tests {
         assert_eq!(digest, "88e16a1019277b15d58faf0541e11910eb756f6");
     }
 
+    #[test]
+    fn test_hmac_sha256() {
+        // test cases 2 and 6 from rfc 4231
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?"),
+            [
+                0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95,
+                0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9,
+                0x64, 0xec, 0x38, 0x43
+            ]
+        );
+        assert_eq!(
+            hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            [
+                0x60, 0xe4, 0x31, 0x59, 0x1e, 0xe0, 0xb6, 0x7f, 0x0d, 0x8a, 0x26, 0xaa, 0xcb, 0xf5,
+                0xb7, 0x7f, 0x8e, 0x0b, 0xc6, 0x21, 0x37, 0x28, 0xc5, 0x14, 0x05, 0x46, 0x04, 0x0f,
+                0x0e, 0xe3, 0x7f, 0x54
+            ]
+        );
+    }
+
+    #[test]
+    fn test_offline_uuid() {
+        assert_eq!(
+            offline_uuid("Notch").to_string(),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+    }
+
     #[test]
     fn encode_packet_twice() {
         let mut packet = vec![0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
@@ -1,24 +1,310 @@
 use azalea_buf::McBuf;
+use rsa::{pkcs8::DecodePrivateKey, PaddingScheme, RsaPrivateKey};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SaltSignaturePair {
     pub salt: u64,
     pub signature: Vec<u8>,
 }
 
-#[derive(Clone, Debug, Default, McBuf)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MessageSignature {
     pub bytes: Vec<u8>,
 }
 
-#[derive(Clone, Debug, McBuf)]
+#[derive(Clone, Debug, PartialEq, Eq, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SignedMessageHeader {
     pub previous_signature: Option<MessageSignature>,
     pub sender: Uuid,
 }
 
+impl SignedMessageHeader {
+    /// The data that the header signature is created from, given the hash
+    /// of the message body (see [`hash_message_body`]).
+    pub fn signature_data(&self, body_hash: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        if let Some(previous_signature) = &self.previous_signature {
+            data.extend_from_slice(&previous_signature.bytes);
+        }
+        data.extend_from_slice(self.sender.as_bytes());
+        data.extend_from_slice(body_hash);
+        data
+    }
+}
+
 /// Generates a random u64 to use as a salt
 pub fn make_salt() -> u64 {
     rand::random()
 }
+
+/// Hash the body of a chat message, like vanilla's `SignedMessageBody.hash`.
+///
+/// `timestamp` is in milliseconds since the epoch, like it is in chat
+/// packets. `decorated_json` is the stable JSON of the decorated content,
+/// and should only be given if the message was decorated. `last_seen` is the
+/// sender and last signature of the messages the sender acknowledged.
+pub fn hash_message_body(
+    salt: u64,
+    timestamp: u64,
+    plain: &str,
+    decorated_json: Option<&str>,
+    last_seen: &[(Uuid, &MessageSignature)],
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.to_be_bytes());
+    hasher.update((timestamp / 1000).to_be_bytes());
+    hasher.update(plain.as_bytes());
+    hasher.update([70]);
+    if let Some(decorated_json) = decorated_json {
+        hasher.update(decorated_json.as_bytes());
+    }
+    for (profile_id, last_signature) in last_seen {
+        hasher.update([70]);
+        hasher.update(profile_id.as_bytes());
+        hasher.update(&last_signature.bytes);
+    }
+    hasher.finalize().into()
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MessageChainError {
+    #[error("Message was sent by {actual}, but the chain is for {expected}")]
+    WrongSender { expected: Uuid, actual: Uuid },
+    #[error("Message doesn't follow the previous message in the chain")]
+    BrokenChain,
+}
+
+/// The chain of signed chat messages from one player. Since 1.19.1 every
+/// message header includes the signature of the previous message from the
+/// same sender, and servers kick players whose chain is broken.
+///
+/// Use [`MessageChain::sign`] for our own messages, and
+/// [`MessageChain::validate`] to check the messages we receive from another
+/// player.
+#[derive(Clone, Debug)]
+pub struct MessageChain {
+    sender: Uuid,
+    previous_signature: Option<MessageSignature>,
+}
+
+impl MessageChain {
+    pub fn new(sender: Uuid) -> Self {
+        Self {
+            sender,
+            previous_signature: None,
+        }
+    }
+
+    /// The signature of the last message in the chain, if there is one.
+    pub fn previous_signature(&self) -> Option<&MessageSignature> {
+        self.previous_signature.as_ref()
+    }
+
+    /// The header that the next message in the chain should have.
+    pub fn next_header(&self) -> SignedMessageHeader {
+        SignedMessageHeader {
+            previous_signature: self.previous_signature.clone(),
+            sender: self.sender,
+        }
+    }
+
+    /// Sign the next message in the chain. `sign` is given the data to sign
+    /// (with the player's private key, using SHA256withRSA) and should
+    /// return the signature.
+    ///
+    /// The signature is only added to the chain after `sign` returns, so if
+    /// it fails the chain is unchanged.
+    pub fn sign<E>(
+        &mut self,
+        body_hash: &[u8],
+        sign: impl FnOnce(&[u8]) -> Result<Vec<u8>, E>,
+    ) -> Result<(SignedMessageHeader, MessageSignature), E> {
+        let header = self.next_header();
+        let signature = MessageSignature {
+            bytes: sign(&header.signature_data(body_hash))?,
+        };
+        self.previous_signature = Some(signature.clone());
+        Ok((header, signature))
+    }
+
+    /// Check that a message we received from the sender follows the
+    /// previous one, and add it to the chain if it does. The first message we
+    /// see is always accepted, since the sender might've sent messages before
+    /// we joined.
+    ///
+    /// This doesn't check the signature itself.
+    pub fn validate(
+        &mut self,
+        header: &SignedMessageHeader,
+        header_signature: &MessageSignature,
+    ) -> Result<(), MessageChainError> {
+        if header.sender != self.sender {
+            return Err(MessageChainError::WrongSender {
+                expected: self.sender,
+                actual: header.sender,
+            });
+        }
+        if self.previous_signature.is_some() && header.previous_signature != self.previous_signature
+        {
+            return Err(MessageChainError::BrokenChain);
+        }
+        self.previous_signature = Some(header_signature.clone());
+        Ok(())
+    }
+}
+
+/// The DER encoded `DigestInfo` that goes before a SHA-256 hash in a
+/// PKCS#1 v1.5 signature.
+const SHA256_DIGEST_INFO_PREFIX: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+#[derive(Error, Debug)]
+pub enum SigningError {
+    #[error("Invalid private key: {0}")]
+    InvalidKey(#[from] rsa::pkcs8::Error),
+    #[error("Couldn't sign: {0}")]
+    Rsa(#[from] rsa::errors::Error),
+}
+
+/// A player's private key from Mojang, which their chat messages are signed
+/// with.
+#[derive(Clone)]
+pub struct PrivateKey(RsaPrivateKey);
+
+impl PrivateKey {
+    /// Parse a PKCS#8 DER encoded private key, which is what Mojang's
+    /// `player/certificates` endpoint returns (despite the PEM label saying
+    /// it's an RSA private key).
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<Self, SigningError> {
+        Ok(Self(RsaPrivateKey::from_pkcs8_der(der)?))
+    }
+
+    /// Sign the data with SHA256withRSA, like vanilla does for chat.
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>, SigningError> {
+        let digest_info = [SHA256_DIGEST_INFO_PREFIX.as_slice(), &Sha256::digest(data)].concat();
+        Ok(self
+            .0
+            .sign(PaddingScheme::new_pkcs1v15_sign_raw(), &digest_info)?)
+    }
+}
+
+impl std::fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // don't leak the key into logs
+        f.write_str("PrivateKey")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_sign(data: &[u8]) -> Result<Vec<u8>, ()> {
+        Ok(Sha256::digest(data).to_vec())
+    }
+
+    #[test]
+    fn test_sign_and_validate_chain() {
+        let sender = Uuid::from_u128(1);
+        let mut outgoing = MessageChain::new(sender);
+        let mut incoming = MessageChain::new(sender);
+
+        let body_hash = hash_message_body(1, 1_000, "hello", None, &[]);
+        let (first_header, first_signature) = outgoing.sign(&body_hash, fake_sign).unwrap();
+        assert_eq!(first_header.previous_signature, None);
+        assert_eq!(
+            first_header.signature_data(&body_hash),
+            [sender.as_bytes().as_slice(), &body_hash].concat()
+        );
+
+        let body_hash = hash_message_body(2, 2_000, "world", None, &[]);
+        let (second_header, second_signature) = outgoing.sign(&body_hash, fake_sign).unwrap();
+        assert_eq!(
+            second_header.previous_signature.as_ref(),
+            Some(&first_signature)
+        );
+        assert_eq!(outgoing.previous_signature(), Some(&second_signature));
+
+        incoming.validate(&first_header, &first_signature).unwrap();
+        incoming
+            .validate(&second_header, &second_signature)
+            .unwrap();
+        // the first message can't come after the second one
+        assert_eq!(
+            incoming.validate(&first_header, &first_signature),
+            Err(MessageChainError::BrokenChain)
+        );
+        assert!(matches!(
+            MessageChain::new(Uuid::from_u128(2)).validate(&first_header, &first_signature),
+            Err(MessageChainError::WrongSender { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_joins_chain_midway() {
+        let sender = Uuid::from_u128(1);
+        let mut incoming = MessageChain::new(sender);
+        let header = SignedMessageHeader {
+            previous_signature: Some(MessageSignature { bytes: vec![1] }),
+            sender,
+        };
+        incoming
+            .validate(&header, &MessageSignature { bytes: vec![2] })
+            .unwrap();
+        assert_eq!(
+            incoming.previous_signature(),
+            Some(&MessageSignature { bytes: vec![2] })
+        );
+    }
+
+    #[test]
+    fn test_failed_sign_keeps_chain() {
+        let mut chain = MessageChain::new(Uuid::from_u128(1));
+        assert!(chain.sign(&[0; 32], |_| Err("no key")).is_err());
+        assert_eq!(chain.previous_signature(), None);
+    }
+
+    #[test]
+    fn test_hash_message_body_uses_seconds() {
+        assert_eq!(
+            hash_message_body(1, 1_000, "hi", None, &[]),
+            hash_message_body(1, 1_999, "hi", None, &[])
+        );
+        assert_ne!(
+            hash_message_body(1, 1_000, "hi", None, &[]),
+            hash_message_body(1, 1_000, "hi", Some("{\"text\":\"hi\"}"), &[])
+        );
+    }
+
+    #[test]
+    fn test_private_key_sign() {
+        use rsa::{pkcs8::EncodePrivateKey, PublicKey, RsaPublicKey};
+
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 512).unwrap();
+        let der = key.to_pkcs8_der().unwrap();
+        let private_key = PrivateKey::from_pkcs8_der(der.as_bytes()).unwrap();
+        let signature = private_key.sign(b"hello").unwrap();
+
+        let digest_info = [
+            SHA256_DIGEST_INFO_PREFIX.as_slice(),
+            &Sha256::digest(b"hello"),
+        ]
+        .concat();
+        RsaPublicKey::from(&key)
+            .verify(
+                PaddingScheme::new_pkcs1v15_sign_raw(),
+                &digest_info,
+                &signature,
+            )
+            .unwrap();
+        assert!(PrivateKey::from_pkcs8_der(b"not a key").is_err());
+    }
+}
@@ -23,7 +23,30 @@ fn read_string(stream: &mut Cursor<&[u8]>) -> Result<String, Error> {
     let length = stream.read_u16::<BE>()? as usize;
 
     let buf = read_bytes(stream, length)?;
-    Ok(std::str::from_utf8(buf)?.to_string())
+    match std::str::from_utf8(buf) {
+        Ok(string) => Ok(string.to_string()),
+        // modified utf-8 from java isn't valid utf-8 if it has null
+        // characters or surrogate pairs
+        Err(e) => decode_modified_utf8(buf).ok_or(Error::Utf8Error(e)),
+    }
+}
+
+/// Decode java's modified utf-8, where null is 2 bytes and characters
+/// outside the bmp are utf-16 surrogate pairs with each half encoded
+/// separately.
+fn decode_modified_utf8(buf: &[u8]) -> Option<String> {
+    let mut units = Vec::with_capacity(buf.len());
+    let mut bytes = buf.iter().map(|&b| b as u16);
+    while let Some(first) = bytes.next() {
+        let mut continuation = || bytes.next().filter(|b| b & 0xc0 == 0x80).map(|b| b & 0x3f);
+        units.push(match first {
+            0x00..=0x7f => first,
+            0xc0..=0xdf => ((first & 0x1f) << 6) | continuation()?,
+            0xe0..=0xef => ((first & 0x0f) << 12) | (continuation()? << 6) | continuation()?,
+            _ => return None,
+        });
+    }
+    String::from_utf16(&units).ok()
 }
 
 impl Tag {
@@ -137,6 +160,14 @@ impl Tag {
         Ok(Tag::Compound(map))
     }
 
+    /// Read a tag that doesn't have a name, which is how NBT is sent over the
+    /// network since Minecraft 1.20.2. Unlike [`Tag::read`], the root tag
+    /// doesn't have to be a compound.
+    pub fn read_unnamed(stream: &mut Cursor<&[u8]>) -> Result<Tag, Error> {
+        let tag_id = stream.read_u8()?;
+        Tag::read_known(stream, tag_id)
+    }
+
     pub fn read_zlib(stream: &mut impl BufRead) -> Result<Tag, Error> {
         let mut gz = ZlibDecoder::new(stream);
         let mut buf = Vec::new();
@@ -159,6 +190,6 @@ impl McBufReadable for Tag {
 }
 impl From<Error> for BufReadError {
     fn from(e: Error) -> Self {
-        BufReadError::Custom(e.to_string())
+        BufReadError::Other(Box::new(e))
     }
 }
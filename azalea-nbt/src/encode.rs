@@ -10,8 +10,33 @@ use std::io::Write;
 
 #[inline]
 fn write_string(writer: &mut dyn Write, string: &str) -> Result<(), Error> {
-    writer.write_u16::<BE>(string.len() as u16)?;
-    writer.write_all(string.as_bytes())?;
+    // java uses modified utf-8, which is only different from utf-8 for null
+    // characters and characters outside the bmp (which take 4 bytes)
+    if !string.bytes().any(|b| b == 0 || b >= 0xf0) {
+        writer.write_u16::<BE>(string.len() as u16)?;
+        writer.write_all(string.as_bytes())?;
+        return Ok(());
+    }
+
+    let mut bytes = Vec::with_capacity(string.len() + 2);
+    for c in string.chars() {
+        match c as u32 {
+            0 => bytes.extend_from_slice(&[0xc0, 0x80]),
+            0x10000.. => {
+                // each half of the utf-16 surrogate pair is encoded on its own
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    bytes.extend_from_slice(&[
+                        0xe0 | (*unit >> 12) as u8,
+                        0x80 | ((*unit >> 6) & 0x3f) as u8,
+                        0x80 | (*unit & 0x3f) as u8,
+                    ]);
+                }
+            }
+            _ => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    writer.write_u16::<BE>(bytes.len() as u16)?;
+    writer.write_all(&bytes)?;
 
     Ok(())
 }
@@ -193,6 +218,12 @@ impl Tag {
                 write_compound(writer, value, false)?;
                 Ok(())
             }
+            // the opposite of what Tag::read does for an empty root, which
+            // is how items without nbt are sent
+            Tag::End => {
+                writer.write_u8(0)?;
+                Ok(())
+            }
             _ => Err(Error::InvalidTag),
         }
     }
@@ -17,12 +17,48 @@ pub enum Tag {
     LongArray(Vec<i64>),             // 12
 }
 
+/// Tags are serialized as their values, so compounds become maps and lists
+/// become sequences.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Tag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Tag::End => serializer.serialize_unit(),
+            Tag::Byte(value) => serializer.serialize_i8(*value),
+            Tag::Short(value) => serializer.serialize_i16(*value),
+            Tag::Int(value) => serializer.serialize_i32(*value),
+            Tag::Long(value) => serializer.serialize_i64(*value),
+            Tag::Float(value) => serializer.serialize_f32(*value),
+            Tag::Double(value) => serializer.serialize_f64(*value),
+            Tag::ByteArray(value) => serializer.collect_seq(value),
+            Tag::String(value) => serializer.serialize_str(value),
+            Tag::List(value) => serializer.collect_seq(value),
+            Tag::Compound(value) => serializer.collect_map(value),
+            Tag::IntArray(value) => serializer.collect_seq(value),
+            Tag::LongArray(value) => serializer.collect_seq(value),
+        }
+    }
+}
+
 impl Default for Tag {
     fn default() -> Self {
         Tag::End
     }
 }
 
+/// Make a compound from its names and tags, like
+/// `Tag::from([("Name", Tag::String("minecraft:stone".to_string()))])`.
+impl<const N: usize> From<[(&str, Tag); N]> for Tag {
+    fn from(entries: [(&str, Tag); N]) -> Self {
+        Tag::Compound(
+            entries
+                .into_iter()
+                .map(|(name, tag)| (name.to_string(), tag))
+                .collect(),
+        )
+    }
+}
+
 impl Tag {
     #[inline]
     pub fn id(&self) -> u8 {
@@ -40,6 +40,34 @@ fn test_roundtrip_hello_world() {
     assert_eq!(result, original);
 }
 
+#[test]
+fn test_roundtrip_empty() {
+    let mut result = Vec::new();
+    Tag::End.write(&mut result).unwrap();
+    assert_eq!(result, [0]);
+    assert_eq!(Tag::read(&mut Cursor::new(&result[..])).unwrap(), Tag::End);
+}
+
+#[test]
+fn test_modified_utf8() {
+    let tag = Tag::Compound(AHashMap::from_iter(vec![(
+        String::new(),
+        Tag::Compound(AHashMap::from_iter(vec![(
+            "Name".to_string(),
+            Tag::String("a\0🐝".to_string()),
+        )])),
+    )]));
+    let mut result = Vec::new();
+    tag.write(&mut result).unwrap();
+    // the null is 2 bytes and the bee is a surrogate pair of 3 bytes each
+    let mut expected = vec![
+        10, 0, 0, 8, 0, 4, b'N', b'a', b'm', b'e', 0, 9, b'a', 0xc0, 0x80,
+    ];
+    expected.extend_from_slice(&[0xed, 0xa0, 0xbd, 0xed, 0xb0, 0x9d, 0]);
+    assert_eq!(result, expected);
+    assert_eq!(Tag::read(&mut Cursor::new(&result[..])).unwrap(), tag);
+}
+
 #[test]
 fn test_bigtest() {
     // read bigtest.nbt
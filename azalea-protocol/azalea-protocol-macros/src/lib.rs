@@ -25,15 +25,20 @@ fn as_packet_derive(input: TokenStream, state: proc_macro2::TokenStream) -> Toke
                 #state::#variant_name(self)
             }
 
-            pub fn write(&self, buf: &mut impl std::io::Write) -> Result<(), std::io::Error> {
-                azalea_buf::McBufWritable::write_into(self, buf)
+            pub fn write(
+                &self,
+                buf: &mut impl std::io::Write,
+                version: crate::packets::ProtocolVersion,
+            ) -> Result<(), std::io::Error> {
+                crate::packets::McBufVersioned::write_versioned(self, buf, version)
             }
 
             pub fn read(
                 buf: &mut std::io::Cursor<&[u8]>,
+                version: crate::packets::ProtocolVersion,
             ) -> Result<#state, azalea_buf::BufReadError> {
-                use azalea_buf::McBufReadable;
-                Ok(Self::read_from(buf)?.get())
+                use crate::packets::McBufVersioned;
+                Ok(Self::read_versioned(buf, version)?.get())
             }
         }
     };
@@ -197,6 +202,21 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
     let has_serverbound_packets = !input.serverbound.packets.is_empty();
     let has_clientbound_packets = !input.clientbound.packets.is_empty();
 
+    // the ConnectionProtocol variant, like `Game` for `GamePacket`
+    let state_name = input.name.to_string();
+    let protocol_variant = Ident::new(
+        state_name.strip_suffix("Packet").unwrap_or(&state_name),
+        input.name.span(),
+    );
+    // ids past the end of the table usually mean we're reading packets from
+    // the wrong state, rather than that the server sent a weird packet
+    let out_of_range = |packets: &[PacketIdPair]| match packets.iter().map(|p| p.id).max() {
+        Some(max_id) => quote! { id > #max_id },
+        None => quote! { true },
+    };
+    let serverbound_out_of_range = out_of_range(&input.serverbound.packets);
+    let clientbound_out_of_range = out_of_range(&input.clientbound.packets);
+
     let mut serverbound_enum_contents = quote!();
     let mut clientbound_enum_contents = quote!();
     let mut serverbound_id_match_contents = quote!();
@@ -205,6 +225,8 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
     let mut clientbound_write_match_contents = quote!();
     let mut serverbound_read_match_contents = quote!();
     let mut clientbound_read_match_contents = quote!();
+    let mut serverbound_as_any_match_contents = quote!();
+    let mut clientbound_as_any_match_contents = quote!();
 
     for PacketIdPair { id, module, name } in input.serverbound.packets {
         let variant_name = variant_name_from(&name);
@@ -217,11 +239,14 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
             #serverbound_state_name::#variant_name(_packet) => #id,
         });
         serverbound_write_match_contents.extend(quote! {
-            #serverbound_state_name::#variant_name(packet) => packet.write(buf),
+            #serverbound_state_name::#variant_name(packet) => packet.write(buf, version),
+        });
+        serverbound_as_any_match_contents.extend(quote! {
+            #serverbound_state_name::#variant_name(packet) => packet,
         });
         serverbound_read_match_contents.extend(quote! {
             #id => {
-                let data = #module::#name::read(buf).map_err(|e| crate::read::ReadPacketError::Parse { source: e, packet_id: #id, packet_name: #name_litstr.to_string() })?;
+                let data = #module::#name::read(buf, version).map_err(|e| crate::read::ReadPacketError::Parse { source: e, packet_id: #id, packet_name: #name_litstr.to_string(), data: buf.get_ref()[start..].to_vec() })?;
                 let mut leftover = Vec::new();
                 let _ = std::io::Read::read_to_end(buf, &mut leftover);
                 if !leftover.is_empty() {
@@ -242,11 +267,14 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
             #clientbound_state_name::#variant_name(_packet) => #id,
         });
         clientbound_write_match_contents.extend(quote! {
-            #clientbound_state_name::#variant_name(packet) => packet.write(buf),
+            #clientbound_state_name::#variant_name(packet) => packet.write(buf, version),
+        });
+        clientbound_as_any_match_contents.extend(quote! {
+            #clientbound_state_name::#variant_name(packet) => packet,
         });
         clientbound_read_match_contents.extend(quote! {
             #id => {
-                let data = #module::#name::read(buf).map_err(|e| crate::read::ReadPacketError::Parse { source: e, packet_id: #id, packet_name: #name_litstr.to_string() })?;
+                let data = #module::#name::read(buf, version).map_err(|e| crate::read::ReadPacketError::Parse { source: e, packet_id: #id, packet_name: #name_litstr.to_string(), data: buf.get_ref()[start..].to_vec() })?;
                 #[cfg(debug_assertions)]
                 {
                     let mut leftover = Vec::new();
@@ -268,6 +296,9 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
         serverbound_write_match_contents.extend(quote! {
             _ => unreachable!("This enum is empty and can't exist.")
         });
+        serverbound_as_any_match_contents.extend(quote! {
+            _ => unreachable!("This enum is empty and can't exist.")
+        });
     }
     if !has_clientbound_packets {
         clientbound_id_match_contents.extend(quote! {
@@ -276,10 +307,14 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
         clientbound_write_match_contents.extend(quote! {
             _ => unreachable!("This enum is empty and can't exist.")
         });
+        clientbound_as_any_match_contents.extend(quote! {
+            _ => unreachable!("This enum is empty and can't exist.")
+        });
     }
 
     let mut contents = quote! {
         #[derive(Clone, Debug)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
         pub enum #serverbound_state_name
         where
         Self: Sized,
@@ -287,16 +322,39 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
             #serverbound_enum_contents
         }
         #[derive(Clone, Debug)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
         pub enum #clientbound_state_name
         where
             Self: Sized,
         {
             #clientbound_enum_contents
         }
+
+        #[allow(unreachable_code)]
+        impl #serverbound_state_name {
+            /// Get the packet inside of this enum, so it can be downcast to
+            /// its concrete type.
+            pub fn as_any(&self) -> &dyn std::any::Any {
+                match self {
+                    #serverbound_as_any_match_contents
+                }
+            }
+        }
+        #[allow(unreachable_code)]
+        impl #clientbound_state_name {
+            /// Get the packet inside of this enum, so it can be downcast to
+            /// its concrete type.
+            pub fn as_any(&self) -> &dyn std::any::Any {
+                match self {
+                    #clientbound_as_any_match_contents
+                }
+            }
+        }
     };
 
     contents.extend(quote! {
-        #[allow(unreachable_code)]
+        // the version isn't used if there aren't any packets
+        #[allow(unreachable_code, unused_variables)]
         impl crate::packets::ProtocolPacket for #serverbound_state_name {
             fn id(&self) -> u32 {
                 match self {
@@ -304,7 +362,11 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
                 }
             }
 
-            fn write(&self, buf: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+            fn write(
+                &self,
+                buf: &mut impl std::io::Write,
+                version: crate::packets::ProtocolVersion,
+            ) -> Result<(), std::io::Error> {
                 match self {
                     #serverbound_write_match_contents
                 }
@@ -314,20 +376,30 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
             fn read(
                 id: u32,
                 buf: &mut std::io::Cursor<&[u8]>,
+                version: crate::packets::ProtocolVersion,
             ) -> Result<#serverbound_state_name, crate::read::ReadPacketError>
             where
                 Self: Sized,
             {
+                let start = buf.position() as usize;
                 Ok(match id {
                     #serverbound_read_match_contents
-                    _ => return Err(crate::read::ReadPacketError::UnknownPacketId { state_name: #state_name_litstr.to_string(), id }),
+                    _ => {
+                        let data = buf.get_ref()[start..].to_vec();
+                        return Err(if #serverbound_out_of_range {
+                            crate::read::ReadPacketError::UnexpectedPacket { state: crate::packets::ConnectionProtocol::#protocol_variant, id, data }
+                        } else {
+                            crate::read::ReadPacketError::UnknownPacketId { state_name: #state_name_litstr.to_string(), id, data }
+                        });
+                    }
                 })
             }
         }
     });
 
     contents.extend(quote! {
-        #[allow(unreachable_code)]
+        // the version isn't used if there aren't any packets
+        #[allow(unreachable_code, unused_variables)]
         impl crate::packets::ProtocolPacket for #clientbound_state_name {
             fn id(&self) -> u32 {
                 match self {
@@ -335,7 +407,11 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
                 }
             }
 
-            fn write(&self, buf: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+            fn write(
+                &self,
+                buf: &mut impl std::io::Write,
+                version: crate::packets::ProtocolVersion,
+            ) -> Result<(), std::io::Error> {
                 match self {
                     #clientbound_write_match_contents
                 }
@@ -345,13 +421,22 @@ pub fn declare_state_packets(input: TokenStream) -> TokenStream {
             fn read(
                 id: u32,
                 buf: &mut std::io::Cursor<&[u8]>,
+                version: crate::packets::ProtocolVersion,
             ) -> Result<#clientbound_state_name, crate::read::ReadPacketError>
             where
                 Self: Sized,
             {
+                let start = buf.position() as usize;
                 Ok(match id {
                     #clientbound_read_match_contents
-                    _ => return Err(crate::read::ReadPacketError::UnknownPacketId { state_name: #state_name_litstr.to_string(), id }),
+                    _ => {
+                        let data = buf.get_ref()[start..].to_vec();
+                        return Err(if #clientbound_out_of_range {
+                            crate::read::ReadPacketError::UnexpectedPacket { state: crate::packets::ConnectionProtocol::#protocol_variant, id, data }
+                        } else {
+                            crate::read::ReadPacketError::UnknownPacketId { state_name: #state_name_litstr.to_string(), id, data }
+                        });
+                    }
                 })
             }
         }
@@ -0,0 +1,103 @@
+use azalea_buf::{McBufVarWritable, McBufWritable};
+use azalea_protocol::{
+    packets::{game::ClientboundGamePacket, ProtocolPacket},
+    read::{read_packet, Decompressor},
+    write::{write_packet, Compressor},
+};
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::io::Cursor;
+
+const PACKET_COUNT: usize = 100;
+const COMPRESSION_THRESHOLD: u32 = 256;
+
+/// Make a chunk packet with some section data that compresses about as well
+/// as a real chunk would.
+fn chunk_packet() -> ClientboundGamePacket {
+    let mut body = Vec::new();
+    // x and z
+    0i32.write_into(&mut body).unwrap();
+    0i32.write_into(&mut body).unwrap();
+    // heightmaps, an empty compound
+    body.extend([10, 0, 0, 0]);
+    // section data
+    let data = (0..16384).map(|i| (i % 7) as u8).collect::<Vec<u8>>();
+    data.write_into(&mut body).unwrap();
+    // block entities
+    0u32.var_write_into(&mut body).unwrap();
+    // light data: trust edges, four empty bitsets, and no light updates
+    body.extend([0, 0, 0, 0, 0, 0, 0]);
+
+    ClientboundGamePacket::read(0x21, &mut Cursor::new(&body[..])).unwrap()
+}
+
+fn bench(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let packet = chunk_packet();
+    let mut stream = Vec::new();
+    let mut compressor = Compressor::default();
+    runtime.block_on(async {
+        for _ in 0..PACKET_COUNT {
+            write_packet(
+                &packet,
+                &mut stream,
+                Some(COMPRESSION_THRESHOLD),
+                &mut None,
+                &mut compressor,
+            )
+            .await
+            .unwrap();
+        }
+    });
+
+    let mut group = c.benchmark_group("Compressed chunk packets");
+    group.throughput(Throughput::Bytes(stream.len() as u64));
+
+    group.bench_function("Read", |b| {
+        let mut decompressor = Decompressor::default();
+        b.iter(|| {
+            runtime.block_on(async {
+                let mut cursor = Cursor::new(&stream[..]);
+                let mut buffer = BytesMut::new();
+                for _ in 0..PACKET_COUNT {
+                    read_packet::<ClientboundGamePacket, _>(
+                        &mut cursor,
+                        &mut buffer,
+                        Some(COMPRESSION_THRESHOLD),
+                        &mut None,
+                        &mut decompressor,
+                    )
+                    .await
+                    .unwrap();
+                }
+            })
+        })
+    });
+
+    group.bench_function("Write", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let mut stream = Vec::new();
+                for _ in 0..PACKET_COUNT {
+                    write_packet(
+                        &packet,
+                        &mut stream,
+                        Some(COMPRESSION_THRESHOLD),
+                        &mut None,
+                        &mut compressor,
+                    )
+                    .await
+                    .unwrap();
+                }
+            })
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
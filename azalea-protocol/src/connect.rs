@@ -1,44 +1,159 @@
 //! Create connections that communicate with a remote server or client.
 
+use crate::forwarding::{VelocityForwarding, VELOCITY_CHANNEL};
+use crate::packets::game::serverbound_keep_alive_packet::ServerboundKeepAlivePacket;
 use crate::packets::game::{ClientboundGamePacket, ServerboundGamePacket};
 use crate::packets::handshake::{ClientboundHandshakePacket, ServerboundHandshakePacket};
+use crate::packets::login::clientbound_custom_query_packet::ClientboundCustomQueryPacket;
 use crate::packets::login::clientbound_hello_packet::ClientboundHelloPacket;
+use crate::packets::login::serverbound_custom_query_packet::ServerboundCustomQueryPacket;
 use crate::packets::login::{ClientboundLoginPacket, ServerboundLoginPacket};
 use crate::packets::status::{ClientboundStatusPacket, ServerboundStatusPacket};
-use crate::packets::ProtocolPacket;
-use crate::read::{read_packet, ReadPacketError};
-use crate::write::write_packet;
-use azalea_auth::sessionserver::SessionServerError;
+use crate::packets::{ConnectionProtocol, ProtocolPacket, ProtocolVersion};
+use crate::read::{
+    decompress_frame, packet_decoder, read_raw_frame, Decompressor, PacketLimits, ReadPacketError,
+};
+use crate::recording::{Direction, Recorder};
+use crate::write::{encode_packet, encode_raw_packet, write_encoded_packet, Compressor};
+use azalea_auth::game_profile::GameProfile;
+use azalea_auth::sessionserver::{self, SessionServerError};
+use azalea_buf::{McBufVarReadable, UnsizedByteArray};
 use azalea_crypto::{Aes128CfbDec, Aes128CfbEnc};
 use bytes::BytesMut;
 use std::fmt::Debug;
+use std::io::Cursor;
 use std::marker::PhantomData;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::{unix, UnixStream};
+use tokio::sync::mpsc;
+use tokio_socks::tcp::Socks5Stream;
+use tracing::{debug, debug_span, trace, warn, Instrument, Span};
 use uuid::Uuid;
 
-/// The read half of a connection.
-pub struct ReadConnection<R: ProtocolPacket> {
-    read_stream: OwnedReadHalf,
+/// The read half of a connection. `S` is the stream that packets are read
+/// from, which is the read half of a TCP stream by default.
+pub struct ReadConnection<R: ProtocolPacket, S = OwnedReadHalf> {
+    id: u64,
+    read_stream: S,
     buffer: BytesMut,
     compression_threshold: Option<u32>,
     dec_cipher: Option<Aes128CfbDec>,
+    /// The key that the cipher was made with, since it can't be gotten from
+    /// the cipher.
+    encryption_key: Option<[u8; 16]>,
+    decompressor: Decompressor,
+    last_read_at: Instant,
+    /// The capacity that the buffer is shrunk back to after reading a big
+    /// packet, or `None` if it should never be shrunk.
+    buffer_baseline: Option<usize>,
+    read_timeout: Option<Duration>,
+    limits: PacketLimits,
+    protocol_version: ProtocolVersion,
+    interceptor: Option<Interceptor<R>>,
+    recorder: Option<Recorder>,
     _reading: PhantomData<R>,
 }
 
-/// The write half of a connection.
-pub struct WriteConnection<W: ProtocolPacket> {
-    write_stream: OwnedWriteHalf,
+/// The error from [`ReadConnection::reunite`] when the read and write halves
+/// are from different connections.
+pub struct ReuniteError<R, W, RS = OwnedReadHalf, WS = OwnedWriteHalf>(
+    pub ReadConnection<R, RS>,
+    pub WriteConnection<W, WS>,
+)
+where
+    R: ProtocolPacket,
+    W: ProtocolPacket;
+
+impl<R: ProtocolPacket, W: ProtocolPacket, RS, WS> Debug for ReuniteError<R, W, RS, WS> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReuniteError")
+            .field("read_id", &self.0.id)
+            .field("write_id", &self.1.id)
+            .finish()
+    }
+}
+
+impl<R: ProtocolPacket, W: ProtocolPacket, RS, WS> std::fmt::Display
+    for ReuniteError<R, W, RS, WS>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Tried to reunite halves of different connections ({} and {})",
+            self.0.id, self.1.id
+        )
+    }
+}
+
+impl<R: ProtocolPacket, W: ProtocolPacket, RS, WS> std::error::Error
+    for ReuniteError<R, W, RS, WS>
+{
+}
+
+/// A function that's called with every packet that goes through a
+/// connection, along with the packet's raw bytes. See
+/// [`ReadConnection::on_read`] and [`WriteConnection::on_write`].
+pub type Interceptor<P> = Box<dyn Fn(&P, &[u8]) + Send + Sync>;
+
+/// A packet that hasn't been decoded. See [`ReadConnection::read_raw`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawPacket {
+    pub id: u32,
+    /// The packet's fields, after decryption and decompression.
+    pub data: Vec<u8>,
+}
+
+/// How much bigger than the baseline the read buffer can get before it's
+/// shrunk.
+const BUFFER_SHRINK_FACTOR: usize = 4;
+
+/// The id that the next connection will get.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The span that the logs for a connection are in, so they can be told apart
+/// when there's more than one connection. It's at the debug level so it's
+/// still there for the debug logs about the connection opening and changing
+/// state.
+pub(crate) fn connection_span(id: u64) -> Span {
+    debug_span!("connection", id)
+}
+
+/// The write half of a connection. `S` is the stream that packets are written
+/// to, which is the write half of a TCP stream by default.
+pub struct WriteConnection<W: ProtocolPacket, S = OwnedWriteHalf> {
+    id: u64,
+    write_stream: S,
     compression_threshold: Option<u32>,
     enc_cipher: Option<Aes128CfbEnc>,
+    encryption_key: Option<[u8; 16]>,
+    compressor: Compressor,
+    /// Packets that have been encoded but not sent yet.
+    write_buffer: Vec<u8>,
+    auto_flush_threshold: Option<usize>,
+    last_written_at: Instant,
+    write_timeout: Option<Duration>,
+    rate_limiter: Option<RateLimiter>,
+    protocol_version: ProtocolVersion,
+    interceptor: Option<Interceptor<W>>,
+    recorder: Option<Recorder>,
     _writing: PhantomData<W>,
 }
 
 /// A connection that can read and write packets.
 ///
+/// Connections use TCP by default, but any pair of streams can be used with
+/// [`Connection::from_split`], like a TLS stream or an in-memory
+/// [`tokio::io::duplex`] for tests.
+///
 /// # Examples
 ///
 /// Join an offline-mode server and go through the handshake.
@@ -96,9 +211,12 @@ pub struct WriteConnection<W: ProtocolPacket> {
 ///             }
 ///             ClientboundLoginPacket::LoginDisconnect(p) => {
 ///                 println!("login disconnect: {}", p.reason);
-///                 bail!(JoinError::Disconnected(p.reason));
+///                 bail!(JoinError::Disconnect { reason: p.reason });
+///             }
+///             ClientboundLoginPacket::CustomQuery(p) => {
+///                 // we don't know any login plugin channels
+///                 conn.respond_login_plugin(p.transaction_id, None).await?;
 ///             }
-///             ClientboundLoginPacket::CustomQuery(p) => {}
 ///         },
 ///         Err(e) => {
 ///             eprintln!("Error: {:?}", e);
@@ -107,52 +225,524 @@ pub struct WriteConnection<W: ProtocolPacket> {
 ///     }
 /// };
 /// ```
-pub struct Connection<R: ProtocolPacket, W: ProtocolPacket> {
-    pub reader: ReadConnection<R>,
-    pub writer: WriteConnection<W>,
+pub struct Connection<R: ProtocolPacket, W: ProtocolPacket, RS = OwnedReadHalf, WS = OwnedWriteHalf>
+{
+    pub reader: ReadConnection<R, RS>,
+    pub writer: WriteConnection<W, WS>,
 }
 
-impl<R> ReadConnection<R>
+impl<R, S> ReadConnection<R, S>
 where
     R: ProtocolPacket + Debug,
+    S: AsyncRead + Unpin + Send + Sync,
 {
+    /// Read a packet from the stream.
+    ///
+    /// If the packet couldn't be decoded, you can keep reading as long as
+    /// the error is [recoverable](ReadPacketError::is_recoverable).
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe, so it can be used in `tokio::select!`.
+    /// Everything that was read before it was cancelled is kept in the
+    /// connection's buffer (already decrypted), and the next read continues
+    /// from there.
+    ///
+    /// ```rust,no_run
+    /// # use azalea_protocol::connect::ReadConnection;
+    /// # use azalea_protocol::packets::game::ClientboundGamePacket;
+    /// # async fn example(
+    /// #     mut conn: ReadConnection<ClientboundGamePacket>,
+    /// #     mut commands: tokio::sync::mpsc::Receiver<String>,
+    /// # ) -> Result<(), Box<dyn std::error::Error>> {
+    /// loop {
+    ///     tokio::select! {
+    ///         packet = conn.read() => println!("{:?}", packet?),
+    ///         Some(command) = commands.recv() => println!("{command}"),
+    ///     }
+    /// }
+    /// # }
+    /// ```
     pub async fn read(&mut self) -> Result<R, ReadPacketError> {
-        read_packet::<R, _>(
+        let span = connection_span(self.id);
+        let version = self.protocol_version;
+        let result = self
+            .read_with(|buf, interceptor| {
+                let packet = packet_decoder(&mut Cursor::new(buf), version)?;
+                if let Some(interceptor) = interceptor {
+                    interceptor(&packet, buf);
+                }
+                Ok(packet)
+            })
+            .instrument(span.clone())
+            .await;
+        let _enter = span.enter();
+        match &result {
+            Ok(packet) => trace!("read packet {packet:?}"),
+            Err(e) => trace!("couldn't read packet: {e}"),
+        }
+        result
+    }
+
+    /// Read a packet without decoding it. This works for packets that azalea
+    /// doesn't know about, so it's useful for proxies that have to forward
+    /// everything.
+    ///
+    /// The packet isn't passed to the function from [`Self::on_read`].
+    ///
+    /// This is cancel safe, like [`Self::read`].
+    pub async fn read_raw(&mut self) -> Result<RawPacket, ReadPacketError> {
+        let span = connection_span(self.id);
+        let result = self
+            .read_with(|buf, _| {
+                let mut stream = Cursor::new(buf);
+                let id = u32::var_read_from(&mut stream)
+                    .map_err(|e| ReadPacketError::ReadPacketId { source: e })?;
+                Ok(RawPacket {
+                    id,
+                    data: buf[stream.position() as usize..].to_vec(),
+                })
+            })
+            .instrument(span.clone())
+            .await;
+        let _enter = span.enter();
+        match &result {
+            Ok(packet) => trace!(
+                "read raw packet {} ({} bytes)",
+                packet.id,
+                packet.data.len()
+            ),
+            Err(e) => trace!("couldn't read packet: {e}"),
+        }
+        result
+    }
+
+    /// Read a frame and decompress it, then pass the bytes of the packet to
+    /// `decode`.
+    async fn read_with<T>(
+        &mut self,
+        decode: impl FnOnce(&[u8], &Option<Interceptor<R>>) -> Result<T, ReadPacketError>,
+    ) -> Result<T, ReadPacketError> {
+        let frame = read_raw_frame(
             &mut self.read_stream,
             &mut self.buffer,
-            self.compression_threshold,
             &mut self.dec_cipher,
-        )
-        .await
+            self.limits.max_frame_size,
+        );
+        // if the timeout is hit, the partial packet stays in the buffer so the
+        // next read can finish it
+        let frame = match self.read_timeout {
+            Some(read_timeout) => tokio::time::timeout(read_timeout, frame)
+                .await
+                .unwrap_or(Err(ReadPacketError::Timeout)),
+            None => frame.await,
+        };
+        let result = match frame {
+            Ok(frame) => {
+                // the frame still points into the buffer's allocation, so this
+                // is (at least) how much memory the buffer is using
+                let allocated = frame.len() + self.buffer.capacity();
+                let result = decompress_frame(
+                    &frame,
+                    self.compression_threshold,
+                    &mut self.decompressor,
+                    self.limits.max_decompressed_size,
+                )
+                .and_then(|buf| {
+                    if let Some(recorder) = &self.recorder {
+                        recorder.record(Direction::Inbound, buf);
+                    }
+                    decode(buf, &self.interceptor)
+                });
+                drop(frame);
+                self.shrink_buffer_if_needed(allocated);
+                result
+            }
+            Err(e) => Err(e),
+        };
+        // packets that we couldn't parse were still received
+        if !matches!(
+            result,
+            Err(ReadPacketError::ConnectionClosed
+                | ReadPacketError::IoError { .. }
+                | ReadPacketError::Timeout)
+        ) {
+            self.last_read_at = Instant::now();
+        }
+        result
+    }
+
+    /// The id of the connection that this is a part of, which is unique
+    /// for every connection made in this process. It's included in logs.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The version of the protocol that packets are read with.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Put the halves from [`Connection::into_split`] back together. This
+    /// fails if the write half is from a different connection, and the error
+    /// has both halves in it so they aren't lost.
+    // the error is as big as the connection since it gives the halves back
+    #[allow(clippy::result_large_err, clippy::type_complexity)]
+    pub fn reunite<W, WS>(
+        self,
+        writer: WriteConnection<W, WS>,
+    ) -> Result<Connection<R, W, S, WS>, ReuniteError<R, W, S, WS>>
+    where
+        W: ProtocolPacket,
+    {
+        if self.id != writer.id {
+            return Err(ReuniteError(self, writer));
+        }
+        Ok(Connection {
+            reader: self,
+            writer,
+        })
+    }
+
+    /// Call the given function with every packet that's read, right after
+    /// it's decoded. This replaces the previous function if there was one.
+    ///
+    /// The function also gets the bytes the packet was decoded from, which
+    /// are the packet id followed by its fields (after decryption and
+    /// decompression). Packets that couldn't be decoded aren't passed to it,
+    /// but their bytes are in the [`ReadPacketError`].
+    ///
+    /// The function is removed when the connection changes state, since the
+    /// packet type changes.
+    pub fn on_read(&mut self, interceptor: impl Fn(&R, &[u8]) + Send + Sync + 'static) {
+        self.interceptor = Some(Box::new(interceptor));
+    }
+
+    /// Make sure the read buffer can hold at least `capacity` bytes without
+    /// having to reallocate.
+    pub fn reserve_buffer(&mut self, capacity: usize) {
+        self.buffer
+            .reserve(capacity.saturating_sub(self.buffer.len()));
+    }
+
+    /// Set the capacity that the read buffer is shrunk back to after it grew
+    /// to fit a big packet (like a chunk). The buffer is shrunk after a
+    /// packet is read if it's using more than four times the baseline.
+    /// `None` (the default) never shrinks the buffer.
+    pub fn set_buffer_baseline(&mut self, baseline: Option<usize>) {
+        self.buffer_baseline = baseline;
+    }
+
+    fn shrink_buffer_if_needed(&mut self, allocated: usize) {
+        let baseline = match self.buffer_baseline {
+            Some(baseline) => baseline,
+            None => return,
+        };
+        if allocated <= baseline * BUFFER_SHRINK_FACTOR {
+            return;
+        }
+        // the buffer might already have part of the next packet in it, so
+        // that has to be kept
+        let mut buffer = BytesMut::with_capacity(baseline.max(self.buffer.len()));
+        buffer.extend_from_slice(&self.buffer);
+        self.buffer = buffer;
+    }
+
+    /// When we last received a packet, or when the connection was created if
+    /// we haven't received anything yet. This can be used to detect
+    /// connections that are still open but stopped sending packets.
+    pub fn last_read_at(&self) -> Instant {
+        self.last_read_at
+    }
+
+    /// Set how long [`Self::read`] waits for a packet before returning
+    /// [`ReadPacketError::Timeout`]. `None` (the default) waits forever.
+    pub fn set_read_timeout(&mut self, read_timeout: Option<Duration>) {
+        self.read_timeout = read_timeout;
+    }
+
+    /// Set how big the packets we read are allowed to be. Packets that are
+    /// too big fail with a [`ReadPacketError`] instead of being read.
+    pub fn set_packet_limits(&mut self, limits: PacketLimits) {
+        self.limits = limits;
     }
 }
-impl<W> WriteConnection<W>
+
+impl<W, S> WriteConnection<W, S>
 where
     W: ProtocolPacket + Debug,
+    S: AsyncWrite + Unpin + Send,
 {
     /// Write a packet to the server.
+    ///
+    /// If write buffering is enabled with [`Self::set_auto_flush_threshold`],
+    /// the packet might not be sent until [`Self::flush`] is called.
     pub async fn write(&mut self, packet: W) -> std::io::Result<()> {
-        write_packet(
-            &packet,
-            &mut self.write_stream,
+        let span = connection_span(self.id);
+        {
+            let _enter = span.enter();
+            trace!("writing packet {packet:?}");
+            encode_packet(&packet, &mut self.compressor, self.protocol_version);
+            if let Some(interceptor) = &self.interceptor {
+                interceptor(&packet, self.compressor.last_packet());
+            }
+        }
+        self.write_encoded().instrument(span).await
+    }
+
+    /// Write a lot of packets at once. They're all encoded into the write
+    /// buffer and then sent together when the connection is flushed at the
+    /// end, which is much faster than writing them one at a time.
+    ///
+    /// Anything that was already buffered is sent first.
+    pub async fn write_all(&mut self, packets: impl IntoIterator<Item = W>) -> std::io::Result<()> {
+        let span = connection_span(self.id);
+        async move {
+            for packet in packets {
+                trace!("writing packet {packet:?}");
+                encode_packet(&packet, &mut self.compressor, self.protocol_version);
+                if let Some(interceptor) = &self.interceptor {
+                    interceptor(&packet, self.compressor.last_packet());
+                }
+                self.wait_for_rate_limit().await?;
+                write_encoded_packet(
+                    &mut self.write_buffer,
+                    self.compression_threshold,
+                    &mut self.enc_cipher,
+                    &mut self.compressor,
+                )
+                .await?;
+                self.record_written();
+            }
+            self.last_written_at = Instant::now();
+            self.flush().await
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Write a packet that was already encoded, like one from
+    /// [`ReadConnection::read_raw`]. It's compressed and encrypted like normal
+    /// packets.
+    ///
+    /// The packet isn't passed to the function from [`Self::on_write`].
+    pub async fn write_raw(&mut self, packet: &RawPacket) -> std::io::Result<()> {
+        let span = connection_span(self.id);
+        span.in_scope(|| {
+            trace!(
+                "writing raw packet {} ({} bytes)",
+                packet.id,
+                packet.data.len()
+            )
+        });
+        encode_raw_packet(packet.id, &packet.data, &mut self.compressor);
+        self.write_encoded().instrument(span).await
+    }
+
+    /// Send or buffer the packet that was just encoded into the compressor.
+    async fn write_encoded(&mut self) -> std::io::Result<()> {
+        self.wait_for_rate_limit().await?;
+        if self.auto_flush_threshold == Some(0) && self.write_buffer.is_empty() {
+            // nothing is buffered, so we can skip the extra copy
+            with_write_timeout(self.write_timeout, async {
+                write_encoded_packet(
+                    &mut self.write_stream,
+                    self.compression_threshold,
+                    &mut self.enc_cipher,
+                    &mut self.compressor,
+                )
+                .await?;
+                // this does nothing for TCP, but streams like WebSockets
+                // might not send anything until they're flushed
+                self.write_stream.flush().await
+            })
+            .await?;
+            self.last_written_at = Instant::now();
+            self.record_written();
+            return Ok(());
+        }
+
+        // the packet is compressed and encrypted now so changing the
+        // compression threshold or encryption key later doesn't affect it
+        write_encoded_packet(
+            &mut self.write_buffer,
             self.compression_threshold,
             &mut self.enc_cipher,
+            &mut self.compressor,
         )
+        .await?;
+        self.last_written_at = Instant::now();
+        self.record_written();
+        if let Some(threshold) = self.auto_flush_threshold {
+            if self.write_buffer.len() >= threshold {
+                self.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait until the packet that was just encoded can be sent without
+    /// going over the rate limit. Anything that's buffered is sent first so
+    /// it isn't held back while we wait.
+    async fn wait_for_rate_limit(&mut self) -> std::io::Result<()> {
+        let wait = match &mut self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.take(self.compressor.last_packet().len()),
+            None => return Ok(()),
+        };
+        if !wait.is_zero() {
+            trace!("rate limited for {wait:?}");
+            if !self.write_buffer.is_empty() {
+                self.flush().await?;
+            }
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    /// Add the packet that was just written to the recording, if there is
+    /// one.
+    fn record_written(&self) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(Direction::Outbound, self.compressor.last_packet());
+        }
+    }
+
+    /// The id of the connection that this is a part of, which is unique
+    /// for every connection made in this process. It's included in logs.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The version of the protocol that packets are written with.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Call the given function with every packet that's written, right
+    /// after it's encoded. This replaces the previous function if there was
+    /// one.
+    ///
+    /// It's called before the packet is sent, so packets that are waiting
+    /// for the rate limit or that fail to be written are still passed to it.
+    ///
+    /// The function also gets the bytes the packet was encoded to, which are
+    /// the packet id followed by its fields (before compression and
+    /// encryption).
+    ///
+    /// The function is removed when the connection changes state, since the
+    /// packet type changes.
+    pub fn on_write(&mut self, interceptor: impl Fn(&W, &[u8]) + Send + Sync + 'static) {
+        self.interceptor = Some(Box::new(interceptor));
+    }
+
+    /// When we last wrote a packet, or when the connection was created if we
+    /// haven't written anything yet. Packets that are buffered count as
+    /// written.
+    pub fn last_written_at(&self) -> Instant {
+        self.last_written_at
+    }
+
+    /// Send all the buffered packets to the server.
+    pub async fn flush(&mut self) -> std::io::Result<()> {
+        with_write_timeout(self.write_timeout, async {
+            if !self.write_buffer.is_empty() {
+                self.write_stream.write_all(&self.write_buffer).await?;
+                self.write_buffer.clear();
+            }
+            self.write_stream.flush().await
+        })
         .await
     }
 
-    /// End the connection.
+    /// Set how long writing or flushing can take before it fails with an
+    /// [`std::io::ErrorKind::TimedOut`] error. `None` (the default) waits
+    /// forever.
+    ///
+    /// If a write times out, part of a packet might have been sent, so the
+    /// connection can't be used anymore.
+    pub fn set_write_timeout(&mut self, write_timeout: Option<Duration>) {
+        self.write_timeout = write_timeout;
+    }
+
+    /// Limit how fast packets are written, or remove the limit with `None`.
+    /// Writing waits when the limit is reached, so anti-bot plugins that
+    /// kick clients for sending bursts of packets don't notice us.
+    pub fn set_rate_limit(&mut self, rate_limit: Option<RateLimit>) {
+        self.rate_limiter = rate_limit.map(RateLimiter::new);
+    }
+
+    /// Set how many bytes of packets can be buffered before they're
+    /// automatically sent. `Some(0)` (the default) sends every packet as soon
+    /// as it's written, and `None` only sends packets when [`Self::flush`] is
+    /// called.
+    ///
+    /// Packets are always sent in the order they were written.
+    pub fn set_auto_flush_threshold(&mut self, threshold: Option<usize>) {
+        self.auto_flush_threshold = threshold;
+    }
+
+    /// Set the zlib level that packets are compressed with, from 0 (no
+    /// compression) to 9 (best compression). Lower levels use less CPU but
+    /// send more data. This only matters once compression is enabled, and is
+    /// the default level (6) unless it's changed.
+    pub fn set_compression_level(&mut self, level: u32) {
+        self.compressor.set_level(level);
+    }
+
+    /// Flush the buffered packets and end the connection.
     pub async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.flush().await?;
         self.write_stream.shutdown().await
     }
 }
 
-impl<R, W> Connection<R, W>
+/// Run a write, failing with [`std::io::ErrorKind::TimedOut`] if it takes
+/// longer than the timeout.
+async fn with_write_timeout(
+    write_timeout: Option<Duration>,
+    write: impl std::future::Future<Output = std::io::Result<()>>,
+) -> std::io::Result<()> {
+    match write_timeout {
+        Some(write_timeout) => tokio::time::timeout(write_timeout, write)
+            .await
+            .unwrap_or_else(|_| {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Timed out writing packet",
+                ))
+            }),
+        None => write.await,
+    }
+}
+
+impl<W> WriteConnection<W>
+where
+    W: ProtocolPacket + Debug,
+{
+    /// Set whether Nagle's algorithm is disabled on the socket (TCP_NODELAY).
+    /// This is `true` by default since it's better for interactive play, but
+    /// turning it off lets the OS combine lots of small packets into fewer
+    /// TCP segments, which can help on slow or metered connections.
+    pub fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        self.write_stream.as_ref().set_nodelay(nodelay)
+    }
+
+    /// Whether Nagle's algorithm is disabled on the socket (TCP_NODELAY).
+    pub fn nodelay(&self) -> std::io::Result<bool> {
+        self.write_stream.as_ref().nodelay()
+    }
+}
+
+impl<R, W, RS, WS> Connection<R, W, RS, WS>
 where
     R: ProtocolPacket + Debug,
     W: ProtocolPacket + Debug,
+    RS: AsyncRead + Unpin + Send + Sync,
+    WS: AsyncWrite + Unpin + Send,
 {
     /// Read a packet from the other side of the connection.
+    ///
+    /// This is cancel safe, see [`ReadConnection::read`].
     pub async fn read(&mut self) -> Result<R, ReadPacketError> {
         self.reader.read().await
     }
@@ -162,57 +752,61 @@ where
         self.writer.write(packet).await
     }
 
-    /// Split the reader and writer into two objects. This doesn't allocate.
-    pub fn into_split(self) -> (ReadConnection<R>, WriteConnection<W>) {
-        (self.reader, self.writer)
+    /// Write a lot of packets at once. See [`WriteConnection::write_all`].
+    pub async fn write_all(&mut self, packets: impl IntoIterator<Item = W>) -> std::io::Result<()> {
+        self.writer.write_all(packets).await
     }
-}
-
-#[derive(Error, Debug)]
-pub enum ConnectionError {
-    #[error("{0}")]
-    Io(#[from] std::io::Error),
-}
 
-impl Connection<ClientboundHandshakePacket, ServerboundHandshakePacket> {
-    /// Create a new connection to the given address.
-    pub async fn new(address: &SocketAddr) -> Result<Self, ConnectionError> {
-        let stream = TcpStream::connect(address).await?;
+    /// Send all the packets that were buffered by the writer.
+    pub async fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush().await
+    }
 
-        // enable tcp_nodelay
-        stream.set_nodelay(true)?;
+    /// Call the given function with every packet that's read. See
+    /// [`ReadConnection::on_read`].
+    pub fn on_read(&mut self, interceptor: impl Fn(&R, &[u8]) + Send + Sync + 'static) {
+        self.reader.on_read(interceptor);
+    }
 
-        let (read_stream, write_stream) = stream.into_split();
+    /// Call the given function with every packet that's written. See
+    /// [`WriteConnection::on_write`].
+    pub fn on_write(&mut self, interceptor: impl Fn(&W, &[u8]) + Send + Sync + 'static) {
+        self.writer.on_write(interceptor);
+    }
 
-        Ok(Connection {
-            reader: ReadConnection {
-                read_stream,
-                buffer: BytesMut::new(),
-                compression_threshold: None,
-                dec_cipher: None,
-                _reading: PhantomData,
-            },
-            writer: WriteConnection {
-                write_stream,
-                compression_threshold: None,
-                enc_cipher: None,
-                _writing: PhantomData,
-            },
-        })
+    /// Record every packet that's read or written from now on, including
+    /// packets that couldn't be decoded and raw packets. Unlike
+    /// interceptors, the recorder is kept when the connection changes state.
+    /// See the [`recording`](crate::recording) module for the format.
+    ///
+    /// ```rust,no_run
+    /// # use azalea_protocol::connect::Connection;
+    /// # use azalea_protocol::packets::ConnectionProtocol;
+    /// # use azalea_protocol::recording::Recorder;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut conn = Connection::new(&"127.0.0.1:25565".parse()?).await?;
+    /// conn.record(Recorder::create("session.azrp", ConnectionProtocol::Handshake)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record(&mut self, recorder: Recorder) {
+        self.reader.recorder = Some(recorder.clone());
+        self.writer.recorder = Some(recorder);
     }
 
-    /// Change our state from handshake to login. This is the state that is used for logging in.
-    pub fn login(self) -> Connection<ClientboundLoginPacket, ServerboundLoginPacket> {
-        Connection::from(self)
+    /// The id of this connection, which is unique for every connection made
+    /// in this process. It's included in logs, so it can be used to tell
+    /// apart the logs from different connections.
+    pub fn id(&self) -> u64 {
+        self.writer.id
     }
 
-    /// Change our state from handshake to status. This is the state that is used for pinging the server.
-    pub fn status(self) -> Connection<ClientboundStatusPacket, ServerboundStatusPacket> {
-        Connection::from(self)
+    /// Split the reader and writer into two objects. This doesn't allocate.
+    /// They can be put back together with [`ReadConnection::reunite`].
+    pub fn into_split(self) -> (ReadConnection<R, RS>, WriteConnection<W, WS>) {
+        (self.reader, self.writer)
     }
-}
 
-impl Connection<ClientboundLoginPacket, ServerboundLoginPacket> {
     /// Set our compression threshold, i.e. the maximum size that a packet is
     /// allowed to be without getting compressed. If you set it to less than 0
     /// then compression gets disabled.
@@ -227,88 +821,1857 @@ impl Connection<ClientboundLoginPacket, ServerboundLoginPacket> {
         }
     }
 
+    /// Apply the read and write timeouts and the packet limits from the
+    /// options. The connect timeout is ignored since we're already connected.
+    pub fn set_options(&mut self, options: &ConnectionOptions) {
+        self.reader.set_read_timeout(options.read_timeout);
+        self.reader.set_packet_limits(options.packet_limits);
+        self.writer.set_write_timeout(options.write_timeout);
+        self.writer.set_rate_limit(options.rate_limit);
+    }
+
+    /// The version of the protocol that the connection is using, which is
+    /// [`ProtocolVersion::LATEST`] by default.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.reader.protocol_version
+    }
+
+    /// Set the version of the protocol that the connection uses. This
+    /// should match the version in the handshake, and shouldn't be changed
+    /// after that.
+    pub fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+        self.reader.protocol_version = protocol_version;
+        self.writer.protocol_version = protocol_version;
+    }
+
+    /// Set the zlib level that packets we write are compressed with. See
+    /// [`WriteConnection::set_compression_level`].
+    pub fn set_compression_level(&mut self, level: u32) {
+        self.writer.set_compression_level(level);
+    }
+
     /// Set the encryption key that is used to encrypt and decrypt packets. It's the same for both reading and writing.
     pub fn set_encryption_key(&mut self, key: [u8; 16]) {
         let (enc_cipher, dec_cipher) = azalea_crypto::create_cipher(&key);
         self.reader.dec_cipher = Some(dec_cipher);
+        self.reader.encryption_key = Some(key);
         self.writer.enc_cipher = Some(enc_cipher);
+        self.writer.encryption_key = Some(key);
     }
 
-    /// Change our state from login to game. This is the state that's used when you're actually in the game.
-    pub fn game(self) -> Connection<ClientboundGamePacket, ServerboundGamePacket> {
-        Connection::from(self)
+    /// Flush the connection and take it apart into its streams and the
+    /// state that's needed to keep using them, like the compression
+    /// threshold and the state of the ciphers. The streams can be put back
+    /// into a connection with [`Connection::from_handover`], for example in
+    /// another process after sending it the socket.
+    pub async fn into_handover(mut self) -> std::io::Result<(RS, WS, HandoverState)> {
+        self.flush().await?;
+        let encryption = match (
+            &self.writer.enc_cipher,
+            &self.reader.dec_cipher,
+            self.writer.encryption_key,
+        ) {
+            (Some(enc_cipher), Some(dec_cipher), Some(key)) => Some(EncryptionState {
+                key,
+                encrypt_iv: azalea_crypto::encrypt_iv(enc_cipher),
+                decrypt_iv: azalea_crypto::decrypt_iv(dec_cipher),
+            }),
+            _ => None,
+        };
+        let state = HandoverState {
+            compression_threshold: self.reader.compression_threshold,
+            encryption,
+            unread: self.reader.buffer.to_vec(),
+        };
+        connection_span(self.id()).in_scope(|| debug!("handed over"));
+        Ok((self.reader.read_stream, self.writer.write_stream, state))
     }
 
-    /// Authenticate with Minecraft's servers, which is required to join
-    /// online-mode servers. This must happen when you get a
-    /// `ClientboundLoginPacket::Hello` packet.
-    ///
-    /// # Examples
-    ///
-    /// ```rust,no_run
-    /// let token = azalea_auth::auth(azalea_auth::AuthOpts {
-    ///    ..Default::default()
-    /// })
-    /// .await;
-    /// let player_data = azalea_auth::get_profile(token).await;
-    ///
-    /// let mut connection = azalea::Connection::new(&server_address).await?;
+    /// Make a connection from streams and the state from
+    /// [`Connection::into_handover`]. Like [`Connection::from_split`], the
+    /// connection starts in whatever state the other side is in.
+    pub fn from_handover(read_stream: RS, write_stream: WS, state: HandoverState) -> Self {
+        let mut connection = Connection::from_split(read_stream, write_stream);
+        connection.reader.compression_threshold = state.compression_threshold;
+        connection.writer.compression_threshold = state.compression_threshold;
+        if let Some(encryption) = state.encryption {
+            let (enc_cipher, dec_cipher) = azalea_crypto::create_cipher_with_iv(
+                &encryption.key,
+                &encryption.encrypt_iv,
+                &encryption.decrypt_iv,
+            );
+            connection.reader.dec_cipher = Some(dec_cipher);
+            connection.reader.encryption_key = Some(encryption.key);
+            connection.writer.enc_cipher = Some(enc_cipher);
+            connection.writer.encryption_key = Some(encryption.key);
+        }
+        connection.reader.buffer.extend_from_slice(&state.unread);
+        connection
+    }
+}
+
+impl<R, W, RS, WS> Connection<R, W, RS, WS>
+where
+    R: ProtocolPacket + Debug + Send + 'static,
+    W: ProtocolPacket + Debug + Send + 'static,
+    RS: AsyncRead + Unpin + Send + Sync + 'static,
+    WS: AsyncWrite + Unpin + Send + 'static,
+{
+    /// Spawn tasks that read and write packets, and return a channel that
+    /// gets the packets that are read and a channel for packets to write.
     ///
-    /// // transition to the login state, in a real program we would have done a handshake first
-    /// connection.login();
+    /// Packets that can't be decoded are logged and skipped, and the reading
+    /// task stops when the connection closes, a packet can't be read, or the
+    /// receiver is dropped. Packets sent at the same time are written
+    /// together and flushed right away. When every sender is dropped, the
+    /// writing task sends anything that's left and shuts down the write half
+    /// of the connection.
     ///
-    /// match connection.read().await? {
-    ///    ClientboundLoginPacket::Hello(p) => {
-    ///       // tell Mojang we're joining the server
-    ///       connection.authenticate(&token, player_data.uuid, p).await?;
-    ///   }
-    ///  _ => {}
-    /// }
-    /// ```
-    pub async fn authenticate(
-        &self,
-        access_token: &str,
-        uuid: &Uuid,
-        private_key: [u8; 16],
-        packet: ClientboundHelloPacket,
-    ) -> Result<(), SessionServerError> {
-        azalea_auth::sessionserver::join(
-            access_token,
-            &packet.public_key,
-            &private_key,
-            uuid,
-            &packet.server_id,
-        )
-        .await
+    /// This has to be called from inside a Tokio runtime.
+    pub fn into_channels(self) -> (mpsc::UnboundedReceiver<R>, mpsc::UnboundedSender<W>) {
+        let span = connection_span(self.id());
+        let (mut reader, mut writer) = self.into_split();
+        let (read_tx, read_rx) = mpsc::unbounded_channel();
+        let (write_tx, mut write_rx) = mpsc::unbounded_channel::<W>();
+
+        let read_span = span.clone();
+        tokio::spawn(async move {
+            loop {
+                let packet = tokio::select! {
+                    packet = reader.read() => packet,
+                    // stop reading if nobody's listening anymore
+                    _ = read_tx.closed() => break,
+                };
+                match packet {
+                    Ok(packet) => {
+                        if read_tx.send(packet).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) if e.is_recoverable() => {
+                        read_span.in_scope(|| warn!("skipping packet: {e}"));
+                    }
+                    Err(e) => {
+                        read_span.in_scope(|| debug!("stopped reading: {e}"));
+                        break;
+                    }
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some(packet) = write_rx.recv().await {
+                // write everything that's queued up at once so it only has to
+                // be flushed once
+                let mut packets = vec![packet];
+                while let Ok(packet) = write_rx.try_recv() {
+                    packets.push(packet);
+                }
+                if let Err(e) = writer.write_all(packets).await {
+                    span.in_scope(|| debug!("stopped writing: {e}"));
+                    return;
+                }
+            }
+            if let Err(e) = writer.shutdown().await {
+                span.in_scope(|| debug!("couldn't shut down: {e}"));
+            }
+        });
+
+        (read_rx, write_tx)
     }
 }
 
-// rust doesn't let us implement From because allegedly it conflicts with
-// `core`'s "impl<T> From<T> for T" so we do this instead
-impl<R1, W1> Connection<R1, W1>
+impl<R, W> Connection<R, W>
 where
-    R1: ProtocolPacket + Debug,
-    W1: ProtocolPacket + Debug,
+    R: ProtocolPacket + Debug,
+    W: ProtocolPacket + Debug,
 {
-    fn from<R2, W2>(connection: Connection<R1, W1>) -> Connection<R2, W2>
-    where
-        R2: ProtocolPacket + Debug,
-        W2: ProtocolPacket + Debug,
-    {
-        Connection {
-            reader: ReadConnection {
-                read_stream: connection.reader.read_stream,
-                buffer: connection.reader.buffer,
-                compression_threshold: connection.reader.compression_threshold,
-                dec_cipher: connection.reader.dec_cipher,
-                _reading: PhantomData,
-            },
-            writer: WriteConnection {
-                compression_threshold: connection.writer.compression_threshold,
-                write_stream: connection.writer.write_stream,
-                enc_cipher: connection.writer.enc_cipher,
-                _writing: PhantomData,
-            },
-        }
+    /// Set whether Nagle's algorithm is disabled on the socket (TCP_NODELAY).
+    /// See [`WriteConnection::set_nodelay`].
+    pub fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        self.writer.set_nodelay(nodelay)
+    }
+
+    /// Whether Nagle's algorithm is disabled on the socket (TCP_NODELAY).
+    pub fn nodelay(&self) -> std::io::Result<bool> {
+        self.writer.nodelay()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ConnectionError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Proxy(#[from] tokio_socks::Error),
+    #[error("Timed out connecting")]
+    Timeout,
+    /// The error is boxed since it's much bigger than the other variants.
+    #[cfg(feature = "websocket")]
+    #[error("{0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+}
+
+#[cfg(feature = "websocket")]
+impl From<tokio_tungstenite::tungstenite::Error> for ConnectionError {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        ConnectionError::WebSocket(Box::new(e))
+    }
+}
+
+/// What's needed to keep using a connection's streams after it's taken
+/// apart with [`Connection::into_handover`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandoverState {
+    pub compression_threshold: Option<u32>,
+    pub encryption: Option<EncryptionState>,
+    /// Data that was already read from the stream (and decrypted) but
+    /// hasn't been read as a packet yet.
+    pub unread: Vec<u8>,
+}
+
+/// The state of a connection's ciphers. The IVs change as data is
+/// encrypted and decrypted, so they're only valid until the stream is used
+/// again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncryptionState {
+    pub key: [u8; 16],
+    pub encrypt_iv: [u8; 16],
+    pub decrypt_iv: [u8; 16],
+}
+
+/// Timeouts and limits for a connection. The timeouts are all `None` by
+/// default, which means waiting forever.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionOptions {
+    /// How long to wait for the TCP connection to be made (including the
+    /// proxy handshake if there is one) before giving up with
+    /// [`ConnectionError::Timeout`].
+    pub connect_timeout: Option<Duration>,
+    /// How long [`Connection::read`] waits for a packet before returning
+    /// [`ReadPacketError::Timeout`]. Servers send keepalives every 15
+    /// seconds in the game state, so this should be longer than that.
+    pub read_timeout: Option<Duration>,
+    /// How long a write can take before it fails with
+    /// [`std::io::ErrorKind::TimedOut`].
+    pub write_timeout: Option<Duration>,
+    /// How big the packets we read are allowed to be.
+    pub packet_limits: PacketLimits,
+    /// If this is set, a PROXY protocol v2 header saying that the connection
+    /// comes from this address is sent right after connecting. Some hosts
+    /// need this when the server is behind a load balancer. See
+    /// [`proxy_protocol_header`].
+    pub proxy_protocol_source: Option<SocketAddr>,
+    /// How fast packets can be written. See
+    /// [`WriteConnection::set_rate_limit`].
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// A limit on how fast packets can be written. Up to a second's worth of
+/// packets can be sent in a burst before we start waiting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub packets_per_second: Option<u32>,
+    /// This counts the size of packets before they're compressed.
+    pub bytes_per_second: Option<u32>,
+}
+
+/// Keeps track of how much of a [`RateLimit`] has been used up.
+#[derive(Clone, Debug)]
+struct RateLimiter {
+    packets: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl RateLimiter {
+    fn new(rate_limit: RateLimit) -> Self {
+        RateLimiter {
+            packets: rate_limit.packets_per_second.map(TokenBucket::new),
+            bytes: rate_limit.bytes_per_second.map(TokenBucket::new),
+        }
+    }
+
+    /// Use up the budget for a packet and return how long to wait before
+    /// it can be sent.
+    fn take(&mut self, packet_size: usize) -> Duration {
+        let packets_wait = self.packets.as_mut().map(|bucket| bucket.take(1.));
+        let bytes_wait = self
+            .bytes
+            .as_mut()
+            .map(|bucket| bucket.take(packet_size as f64));
+        packets_wait
+            .into_iter()
+            .chain(bytes_wait)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    /// How many tokens are added every second, which is also how many the
+    /// bucket can hold.
+    rate: f64,
+    /// This goes negative when we take more than there is, and the debt has
+    /// to be paid off before anything else can be sent.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32) -> Self {
+        // a rate of 0 would mean waiting forever
+        let rate = rate.max(1) as f64;
+        TokenBucket {
+            rate,
+            tokens: rate,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, amount: f64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+        self.tokens -= amount;
+        if self.tokens < 0. {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// A SOCKS5 proxy that a connection can go through.
+#[derive(Clone, Debug)]
+pub struct Proxy {
+    pub address: SocketAddr,
+    /// The username and password for the proxy, if it needs them.
+    pub auth: Option<ProxyAuth>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProxyAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl Proxy {
+    pub fn new(address: SocketAddr, auth: Option<ProxyAuth>) -> Self {
+        Proxy { address, auth }
+    }
+}
+
+impl Connection<ClientboundHandshakePacket, ServerboundHandshakePacket> {
+    /// Create a new connection to the given address.
+    pub async fn new(address: &SocketAddr) -> Result<Self, ConnectionError> {
+        Connection::new_with_options(address, &ConnectionOptions::default()).await
+    }
+
+    /// Create a new connection to the given address with the given timeouts.
+    pub async fn new_with_options(
+        address: &SocketAddr,
+        options: &ConnectionOptions,
+    ) -> Result<Self, ConnectionError> {
+        let stream =
+            with_connect_timeout(options, async { Ok(TcpStream::connect(address).await?) }).await?;
+        Connection::from_connected_stream(stream, address, options).await
+    }
+
+    /// Create a new connection to the given address that goes through a
+    /// SOCKS5 proxy.
+    pub async fn new_with_proxy(
+        address: &SocketAddr,
+        proxy: &Proxy,
+    ) -> Result<Self, ConnectionError> {
+        Connection::new_with_proxy_and_options(address, proxy, &ConnectionOptions::default()).await
+    }
+
+    /// Create a new connection to the given address that goes through a
+    /// SOCKS5 proxy, with the given timeouts.
+    pub async fn new_with_proxy_and_options(
+        address: &SocketAddr,
+        proxy: &Proxy,
+        options: &ConnectionOptions,
+    ) -> Result<Self, ConnectionError> {
+        let stream = with_connect_timeout(options, async {
+            Ok(match &proxy.auth {
+                Some(auth) => {
+                    Socks5Stream::connect_with_password(
+                        proxy.address,
+                        *address,
+                        &auth.username,
+                        &auth.password,
+                    )
+                    .await?
+                }
+                None => Socks5Stream::connect(proxy.address, *address).await?,
+            })
+        })
+        .await?;
+        // after the handshake the proxy just forwards everything, so we can
+        // use the stream like a normal one
+        Connection::from_connected_stream(stream.into_inner(), address, options).await
+    }
+
+    /// Send the PROXY protocol header if the options want one, and make a
+    /// connection from the stream.
+    async fn from_connected_stream(
+        mut stream: TcpStream,
+        address: &SocketAddr,
+        options: &ConnectionOptions,
+    ) -> Result<Self, ConnectionError> {
+        if let Some(source) = options.proxy_protocol_source {
+            stream
+                .write_all(&proxy_protocol_header(source, *address))
+                .await?;
+        }
+        let mut connection = Connection::from_stream(stream)?;
+        connection.set_options(options);
+        Ok(connection)
+    }
+}
+
+/// The signature that every PROXY protocol v2 header starts with.
+const PROXY_PROTOCOL_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Create a PROXY protocol v2 header, which tells servers behind load
+/// balancers like HAProxy the real address that a connection came from.
+/// IPv4 addresses are mapped to IPv6 if only one of the addresses is IPv6.
+pub fn proxy_protocol_header(source: SocketAddr, destination: SocketAddr) -> Vec<u8> {
+    let mut header = PROXY_PROTOCOL_SIGNATURE.to_vec();
+    // version 2, PROXY command
+    header.push(0x21);
+    match (source.ip(), destination.ip()) {
+        (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) => {
+            // TCP over IPv4
+            header.push(0x11);
+            header.extend_from_slice(&12u16.to_be_bytes());
+            header.extend_from_slice(&source_ip.octets());
+            header.extend_from_slice(&destination_ip.octets());
+        }
+        (source_ip, destination_ip) => {
+            let to_ipv6 = |ip| match ip {
+                IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+                IpAddr::V6(ip) => ip,
+            };
+            // TCP over IPv6
+            header.push(0x21);
+            header.extend_from_slice(&36u16.to_be_bytes());
+            header.extend_from_slice(&to_ipv6(source_ip).octets());
+            header.extend_from_slice(&to_ipv6(destination_ip).octets());
+        }
+    }
+    header.extend_from_slice(&source.port().to_be_bytes());
+    header.extend_from_slice(&destination.port().to_be_bytes());
+    header
+}
+
+/// Run a connect, failing with [`ConnectionError::Timeout`] if it takes longer
+/// than the options' connect timeout.
+async fn with_connect_timeout<T>(
+    options: &ConnectionOptions,
+    connect: impl std::future::Future<Output = Result<T, ConnectionError>>,
+) -> Result<T, ConnectionError> {
+    match options.connect_timeout {
+        Some(connect_timeout) => tokio::time::timeout(connect_timeout, connect)
+            .await
+            .unwrap_or(Err(ConnectionError::Timeout)),
+        None => connect.await,
+    }
+}
+
+#[cfg(unix)]
+impl
+    Connection<
+        ClientboundHandshakePacket,
+        ServerboundHandshakePacket,
+        unix::OwnedReadHalf,
+        unix::OwnedWriteHalf,
+    >
+{
+    /// Create a new connection to a server that's listening on a Unix
+    /// socket at `path`, which avoids going through the TCP stack when the
+    /// server is on the same machine.
+    pub async fn new_unix(path: impl AsRef<Path>) -> Result<Self, ConnectionError> {
+        Self::new_unix_with_options(path, &ConnectionOptions::default()).await
+    }
+
+    /// Create a new connection to a Unix socket with the given options. The
+    /// PROXY protocol header is never sent, since there's no address to put
+    /// in it.
+    pub async fn new_unix_with_options(
+        path: impl AsRef<Path>,
+        options: &ConnectionOptions,
+    ) -> Result<Self, ConnectionError> {
+        let path = path.as_ref();
+        let stream =
+            with_connect_timeout(options, async { Ok(UnixStream::connect(path).await?) }).await?;
+        let (read_stream, write_stream) = stream.into_split();
+        let mut connection = Connection::from_split(read_stream, write_stream);
+        connection_span(connection.id()).in_scope(|| debug!("is with {}", path.display()));
+        connection.set_options(options);
+        Ok(connection)
+    }
+}
+
+impl<RS, WS> Connection<ClientboundHandshakePacket, ServerboundHandshakePacket, RS, WS>
+where
+    RS: AsyncRead + Unpin + Send + Sync,
+    WS: AsyncWrite + Unpin + Send,
+{
+    /// Change our state from handshake to login. This is the state that is used for logging in.
+    pub fn login(self) -> Connection<ClientboundLoginPacket, ServerboundLoginPacket, RS, WS> {
+        Connection::from(self, ConnectionProtocol::Login)
+    }
+
+    /// Change our state from handshake to status. This is the state that is used for pinging the server.
+    pub fn status(self) -> Connection<ClientboundStatusPacket, ServerboundStatusPacket, RS, WS> {
+        Connection::from(self, ConnectionProtocol::Status)
+    }
+}
+
+impl<RS, WS> Connection<ClientboundLoginPacket, ServerboundLoginPacket, RS, WS>
+where
+    RS: AsyncRead + Unpin + Send + Sync,
+    WS: AsyncWrite + Unpin + Send,
+{
+    /// Change our state from login to game. This is the state that's used when you're actually in the game.
+    pub fn game(self) -> Connection<ClientboundGamePacket, ServerboundGamePacket, RS, WS> {
+        Connection::from(self, ConnectionProtocol::Game)
+    }
+
+    /// Answer a login plugin request (a `ClientboundLoginPacket::CustomQuery`
+    /// packet) from the server. Proxies like Velocity and mod loaders like
+    /// Forge use these to talk to the client while it's logging in.
+    ///
+    /// Every request must get a response, or the server might wait forever
+    /// for one. If you don't understand the request, respond with `None`,
+    /// which tells the server that we don't know the channel.
+    pub async fn respond_login_plugin(
+        &mut self,
+        transaction_id: u32,
+        data: Option<Vec<u8>>,
+    ) -> std::io::Result<()> {
+        self.write(
+            ServerboundCustomQueryPacket {
+                transaction_id,
+                data: data.map(UnsizedByteArray::from),
+            }
+            .get(),
+        )
+        .await
+    }
+
+    /// Answer the request that a Velocity proxy's backend server sends to
+    /// get the player's info, so we can join the backend server directly
+    /// when it uses modern forwarding. `secret` is the forwarding secret
+    /// from Velocity's config.
+    ///
+    /// Returns whether the request was from Velocity. Other requests aren't
+    /// answered, so you should pass them to [`Self::respond_login_plugin`].
+    pub async fn respond_velocity_forwarding(
+        &mut self,
+        query: &ClientboundCustomQueryPacket,
+        forwarding: &VelocityForwarding,
+        secret: &[u8],
+    ) -> std::io::Result<bool> {
+        if query.identifier.to_string() != VELOCITY_CHANNEL {
+            return Ok(false);
+        }
+        connection_span(self.id())
+            .in_scope(|| debug!("responding to Velocity's forwarding request"));
+        self.write(
+            forwarding
+                .response_packet(query.transaction_id, secret)
+                .get(),
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Authenticate with Minecraft's servers, which is required to join
+    /// online-mode servers. This must happen when you get a
+    /// `ClientboundLoginPacket::Hello` packet.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let token = azalea_auth::auth(azalea_auth::AuthOpts {
+    ///    ..Default::default()
+    /// })
+    /// .await;
+    /// let player_data = azalea_auth::get_profile(token).await;
+    ///
+    /// let mut connection = azalea::Connection::new(&server_address).await?;
+    ///
+    /// // transition to the login state, in a real program we would have done a handshake first
+    /// connection.login();
+    ///
+    /// match connection.read().await? {
+    ///    ClientboundLoginPacket::Hello(p) => {
+    ///       // tell Mojang we're joining the server
+    ///       connection.authenticate(&token, player_data.uuid, p).await?;
+    ///   }
+    ///  _ => {}
+    /// }
+    /// ```
+    pub async fn authenticate(
+        &self,
+        access_token: &str,
+        uuid: &Uuid,
+        private_key: [u8; 16],
+        packet: ClientboundHelloPacket,
+    ) -> Result<(), SessionServerError> {
+        let span = connection_span(self.id());
+        span.in_scope(|| debug!("authenticating with Mojang"));
+        azalea_auth::sessionserver::join(
+            access_token,
+            &packet.public_key,
+            &private_key,
+            uuid,
+            &packet.server_id,
+        )
+        .instrument(span)
+        .await
+    }
+}
+
+impl Connection<ServerboundHandshakePacket, ClientboundHandshakePacket> {
+    /// Wrap a stream that a client connected to us with, for when you're the
+    /// server.
+    pub fn wrap(stream: TcpStream) -> Result<Self, ConnectionError> {
+        Connection::from_stream(stream)
+    }
+}
+
+impl<RS, WS> Connection<ClientboundGamePacket, ServerboundGamePacket, RS, WS>
+where
+    RS: AsyncRead + Unpin + Send + Sync,
+    WS: AsyncWrite + Unpin + Send,
+{
+    /// Read a packet like [`Self::read`], but answer keep alive packets
+    /// right away so the server doesn't time us out. The keep alive packet
+    /// is still returned.
+    ///
+    /// This is for simple tools like packet loggers that don't need a whole
+    /// client.
+    ///
+    /// Unlike [`Self::read`], this isn't cancel safe, since it might be in
+    /// the middle of writing the answer to a keep alive.
+    ///
+    /// ```rust,no_run
+    /// # use azalea_protocol::connect::Connection;
+    /// # use azalea_protocol::packets::game::{ClientboundGamePacket, ServerboundGamePacket};
+    /// # async fn example(
+    /// #     mut conn: Connection<ClientboundGamePacket, ServerboundGamePacket>,
+    /// # ) -> Result<(), Box<dyn std::error::Error>> {
+    /// loop {
+    ///     let packet = conn.read_and_keep_alive().await?;
+    ///     println!("{packet:?}");
+    /// }
+    /// # }
+    /// ```
+    pub async fn read_and_keep_alive(&mut self) -> Result<ClientboundGamePacket, ReadPacketError> {
+        let packet = self.reader.read().await?;
+        if let ClientboundGamePacket::KeepAlive(keep_alive) = &packet {
+            connection_span(self.id())
+                .in_scope(|| trace!("answering keep alive {}", keep_alive.id));
+            self.writer
+                .write(ServerboundKeepAlivePacket { id: keep_alive.id }.get())
+                .await?;
+            // the server only waits so long, so don't leave it in the buffer
+            self.writer.flush().await?;
+        }
+        Ok(packet)
+    }
+}
+
+impl<RS, WS> Connection<ServerboundHandshakePacket, ClientboundHandshakePacket, RS, WS>
+where
+    RS: AsyncRead + Unpin + Send + Sync,
+    WS: AsyncWrite + Unpin + Send,
+{
+    /// Change our state from handshake to login. This is the state that is used for logging in.
+    pub fn login(self) -> Connection<ServerboundLoginPacket, ClientboundLoginPacket, RS, WS> {
+        Connection::from(self, ConnectionProtocol::Login)
+    }
+
+    /// Change our state from handshake to status. This is the state that is used for pinging the server.
+    pub fn status(self) -> Connection<ServerboundStatusPacket, ClientboundStatusPacket, RS, WS> {
+        Connection::from(self, ConnectionProtocol::Status)
+    }
+}
+
+impl<RS, WS> Connection<ServerboundLoginPacket, ClientboundLoginPacket, RS, WS>
+where
+    RS: AsyncRead + Unpin + Send + Sync,
+    WS: AsyncWrite + Unpin + Send,
+{
+    /// Change our state from login to game. This is the state that's used when the client is actually in the game.
+    pub fn game(self) -> Connection<ServerboundGamePacket, ClientboundGamePacket, RS, WS> {
+        Connection::from(self, ConnectionProtocol::Game)
+    }
+
+    /// Check with Mojang that the player actually authenticated, which is
+    /// what online-mode servers do. This must happen after the client sends
+    /// its `ServerboundKeyPacket` and we've decrypted the shared secret.
+    ///
+    /// The server id should be the same as the one we sent in the
+    /// `ClientboundHelloPacket` (it's an empty string in vanilla), and
+    /// `client_ip` should be passed if you want to prevent proxy
+    /// connections.
+    pub async fn authenticate_player(
+        &self,
+        username: &str,
+        server_id: &str,
+        public_key: &[u8],
+        secret: &[u8; 16],
+        client_ip: Option<IpAddr>,
+    ) -> Result<GameProfile, SessionServerError> {
+        let span = connection_span(self.id());
+        span.in_scope(|| debug!("checking that {username} authenticated"));
+        let server_hash = sessionserver::server_hash(server_id, public_key, secret);
+        sessionserver::has_joined(username, &server_hash, client_ip)
+            .instrument(span)
+            .await
+    }
+}
+
+// rust doesn't let us implement From because allegedly it conflicts with
+// `core`'s "impl<T> From<T> for T" so we do this instead
+impl<R1, W1> Connection<R1, W1>
+where
+    R1: ProtocolPacket + Debug,
+    W1: ProtocolPacket + Debug,
+{
+    fn from_stream(stream: TcpStream) -> Result<Self, ConnectionError> {
+        // enable tcp_nodelay by default, it can be turned off with
+        // `set_nodelay`
+        stream.set_nodelay(true)?;
+
+        let peer_address = stream.peer_addr();
+        let (read_stream, write_stream) = stream.into_split();
+        let connection = Connection::from_split(read_stream, write_stream);
+        if let Ok(address) = peer_address {
+            connection_span(connection.id()).in_scope(|| debug!("is with {address}"));
+        }
+        Ok(connection)
+    }
+}
+
+impl<R1, W1, RS, WS> Connection<R1, W1, RS, WS>
+where
+    R1: ProtocolPacket + Debug,
+    W1: ProtocolPacket + Debug,
+    RS: AsyncRead + Unpin + Send + Sync,
+    WS: AsyncWrite + Unpin + Send,
+{
+    /// Create a connection from a stream to read packets from and a stream
+    /// to write packets to, which are usually the two halves of the same
+    /// stream. Nothing is sent, so the connection starts in whatever state
+    /// the other side is expecting.
+    pub fn from_split(read_stream: RS, write_stream: WS) -> Self {
+        let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+        connection_span(id).in_scope(|| debug!("opened"));
+        let now = Instant::now();
+
+        Connection {
+            reader: ReadConnection {
+                id,
+                read_stream,
+                buffer: BytesMut::new(),
+                compression_threshold: None,
+                dec_cipher: None,
+                encryption_key: None,
+                decompressor: Decompressor::default(),
+                last_read_at: now,
+                buffer_baseline: None,
+                read_timeout: None,
+                limits: PacketLimits::default(),
+                protocol_version: ProtocolVersion::default(),
+                interceptor: None,
+                recorder: None,
+                _reading: PhantomData,
+            },
+            writer: WriteConnection {
+                id,
+                write_stream,
+                compression_threshold: None,
+                enc_cipher: None,
+                encryption_key: None,
+                compressor: Compressor::default(),
+                write_buffer: Vec::new(),
+                auto_flush_threshold: Some(0),
+                last_written_at: now,
+                write_timeout: None,
+                rate_limiter: None,
+                protocol_version: ProtocolVersion::default(),
+                interceptor: None,
+                recorder: None,
+                _writing: PhantomData,
+            },
+        }
+    }
+
+    fn from<R2, W2>(
+        connection: Connection<R1, W1, RS, WS>,
+        state: ConnectionProtocol,
+    ) -> Connection<R2, W2, RS, WS>
+    where
+        R2: ProtocolPacket + Debug,
+        W2: ProtocolPacket + Debug,
+    {
+        connection_span(connection.id()).in_scope(|| debug!("switched to {state:?}"));
+        if let Some(recorder) = &connection.reader.recorder {
+            recorder.set_state(state);
+        }
+        Connection {
+            reader: ReadConnection {
+                id: connection.reader.id,
+                read_stream: connection.reader.read_stream,
+                buffer: connection.reader.buffer,
+                compression_threshold: connection.reader.compression_threshold,
+                dec_cipher: connection.reader.dec_cipher,
+                encryption_key: connection.reader.encryption_key,
+                decompressor: connection.reader.decompressor,
+                last_read_at: connection.reader.last_read_at,
+                buffer_baseline: connection.reader.buffer_baseline,
+                read_timeout: connection.reader.read_timeout,
+                limits: connection.reader.limits,
+                protocol_version: connection.reader.protocol_version,
+                interceptor: None,
+                recorder: connection.reader.recorder,
+                _reading: PhantomData,
+            },
+            writer: WriteConnection {
+                id: connection.writer.id,
+                compression_threshold: connection.writer.compression_threshold,
+                write_stream: connection.writer.write_stream,
+                enc_cipher: connection.writer.enc_cipher,
+                encryption_key: connection.writer.encryption_key,
+                compressor: connection.writer.compressor,
+                write_buffer: connection.writer.write_buffer,
+                auto_flush_threshold: connection.writer.auto_flush_threshold,
+                last_written_at: connection.writer.last_written_at,
+                write_timeout: connection.writer.write_timeout,
+                rate_limiter: connection.writer.rate_limiter,
+                protocol_version: connection.writer.protocol_version,
+                interceptor: None,
+                recorder: connection.writer.recorder,
+                _writing: PhantomData,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::handshake::client_intention_packet::ClientIntentionPacket;
+    use crate::packets::login::{
+        clientbound_custom_query_packet::ClientboundCustomQueryPacket,
+        clientbound_login_compression_packet::ClientboundLoginCompressionPacket,
+    };
+    use crate::write::write_packet;
+    use azalea_core::ResourceLocation;
+    use tokio::io::{AsyncReadExt, DuplexStream, ReadHalf, WriteHalf};
+
+    type DuplexConnection<R, W> = Connection<R, W, ReadHalf<DuplexStream>, WriteHalf<DuplexStream>>;
+
+    /// Make a client and a server connection that are connected to each other
+    /// in memory.
+    fn connection_pair<R, W>() -> (DuplexConnection<R, W>, DuplexConnection<W, R>)
+    where
+        R: ProtocolPacket + Debug,
+        W: ProtocolPacket + Debug,
+    {
+        let (client_stream, server_stream) = tokio::io::duplex(1024);
+        let (read_stream, write_stream) = tokio::io::split(client_stream);
+        let client = Connection::from_split(read_stream, write_stream);
+        let (read_stream, write_stream) = tokio::io::split(server_stream);
+        let server = Connection::from_split(read_stream, write_stream);
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn test_shrink_read_buffer() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut conn = Connection::new(&address).await.unwrap().login();
+        let (mut server_stream, _) = listener.accept().await.unwrap();
+        conn.reader.set_buffer_baseline(Some(1024));
+
+        // send a big packet and a small one right after it, so the buffer
+        // probably has part of the small one when the big one is read
+        let mut data = Vec::new();
+        let mut compressor = Compressor::default();
+        write_packet(
+            &ClientboundCustomQueryPacket {
+                transaction_id: 0,
+                identifier: ResourceLocation::new("azalea:big").unwrap(),
+                data: UnsizedByteArray::from(vec![0; 200_000]),
+            }
+            .get(),
+            &mut data,
+            None,
+            &mut None,
+            &mut compressor,
+        )
+        .await
+        .unwrap();
+        write_packet(
+            &ClientboundLoginCompressionPacket {
+                compression_threshold: 256,
+            }
+            .get(),
+            &mut data,
+            None,
+            &mut None,
+            &mut compressor,
+        )
+        .await
+        .unwrap();
+        server_stream.write_all(&data).await.unwrap();
+
+        match conn.read().await.unwrap() {
+            ClientboundLoginPacket::CustomQuery(p) => assert_eq!(p.data.len(), 200_000),
+            _ => panic!("Wrong packet type"),
+        }
+        assert!(conn.reader.buffer.capacity() <= 1024 * BUFFER_SHRINK_FACTOR);
+        match conn.read().await.unwrap() {
+            ClientboundLoginPacket::LoginCompression(p) => {
+                assert_eq!(p.compression_threshold, 256)
+            }
+            _ => panic!("Wrong packet type"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors() {
+        use crate::packets::login::serverbound_hello_packet::ServerboundHelloPacket;
+        use std::sync::{Arc, Mutex};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client_conn = Connection::new(&address).await.unwrap().login();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let mut server_conn =
+            Connection::<ServerboundHandshakePacket, ClientboundHandshakePacket>::wrap(
+                server_stream,
+            )
+            .unwrap()
+            .login();
+
+        // compress everything so we can check that the interceptors get the
+        // uncompressed bytes
+        client_conn.set_compression_threshold(0);
+        server_conn.set_compression_threshold(0);
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let read = Arc::new(Mutex::new(Vec::new()));
+        {
+            let written = written.clone();
+            client_conn.on_write(move |packet, bytes| {
+                if let ServerboundLoginPacket::Hello(p) = packet {
+                    written
+                        .lock()
+                        .unwrap()
+                        .push((p.username.clone(), bytes.to_vec()));
+                }
+            });
+            let read = read.clone();
+            server_conn.on_read(move |packet, bytes| {
+                if let ServerboundLoginPacket::Hello(p) = packet {
+                    read.lock()
+                        .unwrap()
+                        .push((p.username.clone(), bytes.to_vec()));
+                }
+            });
+        }
+
+        client_conn
+            .write(
+                ServerboundHelloPacket {
+                    username: "bot".to_string(),
+                    public_key: None,
+                    profile_id: None,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        server_conn.read().await.unwrap();
+
+        // the packet id, the username, and two empty options
+        let bytes = vec![0, 3, b'b', b'o', b't', 0, 0];
+        assert_eq!(
+            *written.lock().unwrap(),
+            vec![("bot".to_string(), bytes.clone())]
+        );
+        assert_eq!(*read.lock().unwrap(), vec![("bot".to_string(), bytes)]);
+    }
+
+    #[tokio::test]
+    async fn test_write_interceptor_before_sending() {
+        use crate::packets::login::serverbound_hello_packet::ServerboundHelloPacket;
+        use std::sync::{Arc, Mutex};
+
+        let (mut client_conn, server) =
+            connection_pair::<ClientboundLoginPacket, ServerboundLoginPacket>();
+        // nothing is reading on the other side, so writing fails
+        drop(server);
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        {
+            let written = written.clone();
+            client_conn.on_write(move |_, bytes| written.lock().unwrap().push(bytes.to_vec()));
+        }
+        let packet = ServerboundHelloPacket {
+            username: "bot".to_string(),
+            public_key: None,
+            profile_id: None,
+        };
+        assert!(client_conn.write(packet.clone().get()).await.is_err());
+        assert!(client_conn.write_all([packet.get()]).await.is_err());
+        // the packet id, the username, and two empty options
+        let bytes = vec![0, 3, b'b', b'o', b't', 0, 0];
+        assert_eq!(*written.lock().unwrap(), vec![bytes.clone(), bytes]);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version() {
+        use crate::packets::login::serverbound_hello_packet::ServerboundHelloPacket;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client_conn = Connection::new(&address).await.unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let mut server_conn =
+            Connection::<ServerboundHandshakePacket, ClientboundHandshakePacket>::wrap(
+                server_stream,
+            )
+            .unwrap();
+        client_conn.set_protocol_version(ProtocolVersion::V1_19);
+        server_conn.set_protocol_version(ProtocolVersion::V1_19);
+        // the version is kept when the state changes
+        let mut client_conn = client_conn.login();
+        let mut server_conn = server_conn.login();
+        assert_eq!(client_conn.protocol_version(), ProtocolVersion::V1_19);
+
+        client_conn
+            .write(
+                ServerboundHelloPacket {
+                    username: "bot".to_string(),
+                    public_key: None,
+                    profile_id: Some(uuid::Uuid::from_u128(1)),
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        // 1.19 doesn't have the profile id, so it isn't sent
+        match server_conn.read().await.unwrap() {
+            ServerboundLoginPacket::Hello(p) => {
+                assert_eq!(p.username, "bot");
+                assert_eq!(p.profile_id, None);
+            }
+            p => panic!("Expected a hello packet, got {p:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_nodelay() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let conn = Connection::new(&address).await.unwrap();
+        assert!(conn.nodelay().unwrap());
+
+        conn.set_nodelay(false).unwrap();
+        // the setting is kept when the state changes
+        let conn = conn.login();
+        assert!(!conn.nodelay().unwrap());
+
+        let (_, writer) = conn.into_split();
+        writer.set_nodelay(true).unwrap();
+        assert!(writer.nodelay().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_connection_id() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let first = Connection::new(&address).await.unwrap();
+        let second = Connection::new(&address).await.unwrap();
+        assert_ne!(first.id(), second.id());
+
+        let id = first.id();
+        let first = first.login().game();
+        assert_eq!(first.id(), id);
+        let (reader, writer) = first.into_split();
+        assert_eq!(reader.id(), id);
+        assert_eq!(writer.id(), id);
+    }
+
+    #[tokio::test]
+    async fn test_new_with_proxy() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Proxy::new(
+            listener.local_addr().unwrap(),
+            Some(ProxyAuth {
+                username: "user".to_string(),
+                password: "pass".to_string(),
+            }),
+        );
+        let target: SocketAddr = "10.1.2.3:25565".parse().unwrap();
+
+        // a minimal SOCKS5 server that only accepts username/password auth
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut header = [0; 2];
+            stream.read_exact(&mut header).await.unwrap();
+            assert_eq!(header[0], 5);
+            let mut methods = vec![0; header[1] as usize];
+            stream.read_exact(&mut methods).await.unwrap();
+            assert!(methods.contains(&2));
+            stream.write_all(&[5, 2]).await.unwrap();
+
+            let mut auth = [0; 11];
+            stream.read_exact(&mut auth).await.unwrap();
+            assert_eq!(&auth, b"\x01\x04user\x04pass");
+            stream.write_all(&[1, 0]).await.unwrap();
+
+            let mut request = [0; 10];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [5, 1, 0, 1, 10, 1, 2, 3, 0x63, 0xdd]);
+            stream
+                .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+
+            Connection::<ServerboundHandshakePacket, ClientboundHandshakePacket>::wrap(stream)
+                .unwrap()
+                .read()
+                .await
+                .unwrap()
+        });
+
+        let mut conn = Connection::new_with_proxy(&target, &proxy).await.unwrap();
+        conn.write(
+            ClientIntentionPacket {
+                protocol_version: 760,
+                hostname: "example.com".to_string(),
+                port: 25565,
+                intention: ConnectionProtocol::Login,
+            }
+            .get(),
+        )
+        .await
+        .unwrap();
+
+        match server.await.unwrap() {
+            ServerboundHandshakePacket::ClientIntention(p) => {
+                assert_eq!(p.hostname, "example.com")
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_connection() {
+        let (mut client, mut server) =
+            connection_pair::<ClientboundHandshakePacket, ServerboundHandshakePacket>();
+
+        client
+            .write(
+                ClientIntentionPacket {
+                    protocol_version: 760,
+                    hostname: "localhost".to_string(),
+                    port: 25565,
+                    intention: ConnectionProtocol::Login,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        let mut client = client.login();
+        assert!(matches!(
+            server.read().await.unwrap(),
+            ServerboundHandshakePacket::ClientIntention(_)
+        ));
+        let mut server = server.login();
+
+        server
+            .write(
+                ClientboundLoginCompressionPacket {
+                    compression_threshold: 256,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundLoginPacket::LoginCompression(p) if p.compression_threshold == 256
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_read() {
+        let (mut client, mut server) =
+            connection_pair::<ClientboundLoginPacket, ServerboundLoginPacket>();
+        client.set_encryption_key([1; 16]);
+
+        let mut data = Vec::new();
+        let mut enc_cipher = Some(azalea_crypto::create_cipher(&[1; 16]).0);
+        let mut compressor = Compressor::default();
+        for transaction_id in [1, 2] {
+            write_packet(
+                &ClientboundCustomQueryPacket {
+                    transaction_id,
+                    identifier: ResourceLocation::new("azalea:test").unwrap(),
+                    data: UnsizedByteArray::from(vec![0; 100]),
+                }
+                .get(),
+                &mut data,
+                None,
+                &mut enc_cipher,
+                &mut compressor,
+            )
+            .await
+            .unwrap();
+        }
+        // send the first packet and half of the second one
+        let (first, second) = data.split_at(data.len() * 3 / 4);
+        server.writer.write_stream.write_all(first).await.unwrap();
+
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundLoginPacket::CustomQuery(p) if p.transaction_id == 1
+        ));
+        tokio::select! {
+            packet = client.read() => panic!("Got {packet:?} before it was sent"),
+            _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+        }
+        server.writer.write_stream.write_all(second).await.unwrap();
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundLoginPacket::CustomQuery(p) if p.transaction_id == 2
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_timeouts() {
+        let (mut client, server) =
+            connection_pair::<ClientboundHandshakePacket, ServerboundHandshakePacket>();
+        client.set_options(&ConnectionOptions {
+            read_timeout: Some(Duration::from_millis(50)),
+            write_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let mut client = client.login();
+
+        // the server never sends anything
+        assert!(matches!(client.read().await, Err(ReadPacketError::Timeout)));
+
+        // and never reads anything, so the duplex fills up
+        let result = client
+            .write(
+                ServerboundCustomQueryPacket {
+                    transaction_id: 0,
+                    data: Some(UnsizedByteArray::from(vec![0; 2048])),
+                }
+                .get(),
+            )
+            .await;
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        drop(server);
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout() {
+        // a proxy that accepts the connection but never answers the handshake
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Proxy::new(listener.local_addr().unwrap(), None);
+        let target: SocketAddr = "10.1.2.3:25565".parse().unwrap();
+
+        let result = Connection::new_with_proxy_and_options(
+            &target,
+            &proxy,
+            &ConnectionOptions {
+                connect_timeout: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(ConnectionError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_raw_packets() {
+        let (mut client, mut server) =
+            connection_pair::<ClientboundLoginPacket, ServerboundLoginPacket>();
+        client.set_compression_threshold(0);
+        server.set_compression_threshold(0);
+
+        // a packet id that doesn't exist in the login state
+        let unknown = RawPacket {
+            id: 0x7f,
+            data: vec![1, 2, 3],
+        };
+        server.writer.write_raw(&unknown).await.unwrap();
+        // and the login compression packet with a threshold of 256
+        server
+            .writer
+            .write_raw(&RawPacket {
+                id: 0x03,
+                data: vec![0x80, 0x02],
+            })
+            .await
+            .unwrap();
+        server
+            .write(
+                ClientboundLoginCompressionPacket {
+                    compression_threshold: 512,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(client.reader.read_raw().await.unwrap(), unknown);
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundLoginPacket::LoginCompression(p) if p.compression_threshold == 256
+        ));
+        assert_eq!(
+            client.reader.read_raw().await.unwrap(),
+            RawPacket {
+                id: 0x03,
+                data: vec![0x80, 0x04],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_after_unknown_packet() {
+        let (mut client, mut server) =
+            connection_pair::<ClientboundLoginPacket, ServerboundLoginPacket>();
+
+        // a custom packet, and a login compression packet without its field
+        for packet in [
+            RawPacket {
+                id: 0x7f,
+                data: vec![1, 2, 3],
+            },
+            RawPacket {
+                id: 0x03,
+                data: vec![],
+            },
+        ] {
+            server.writer.write_raw(&packet).await.unwrap();
+        }
+        server
+            .write(
+                ClientboundLoginCompressionPacket {
+                    compression_threshold: 256,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+
+        let err = client.read().await.unwrap_err();
+        assert!(
+            matches!(&err, ReadPacketError::UnexpectedPacket { id: 0x7f, data, .. } if data == &[1, 2, 3])
+        );
+        assert!(err.is_recoverable());
+        let err = client.read().await.unwrap_err();
+        assert!(matches!(
+            err,
+            ReadPacketError::Parse {
+                packet_id: 0x03,
+                ..
+            }
+        ));
+        assert!(err.is_recoverable());
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundLoginPacket::LoginCompression(p) if p.compression_threshold == 256
+        ));
+
+        drop(server);
+        let err = client.read().await.unwrap_err();
+        assert!(matches!(err, ReadPacketError::ConnectionClosed));
+        assert!(!err.is_recoverable());
+    }
+
+    /// A stream that counts how many times it was written to.
+    #[derive(Default)]
+    struct CountingWriter {
+        data: Vec<u8>,
+        writes: usize,
+    }
+
+    impl AsyncWrite for CountingWriter {
+        fn poll_write(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            self.writes += 1;
+            self.data.extend_from_slice(buf);
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_all() {
+        let mut conn =
+            Connection::<ServerboundLoginPacket, ClientboundLoginPacket, _, _>::from_split(
+                tokio::io::empty(),
+                CountingWriter::default(),
+            );
+        conn.write_all((0..100).map(|transaction_id| {
+            ClientboundCustomQueryPacket {
+                transaction_id,
+                identifier: ResourceLocation::new("azalea:test").unwrap(),
+                data: UnsizedByteArray::from(vec![1, 2, 3]),
+            }
+            .get()
+        }))
+        .await
+        .unwrap();
+
+        let writer = conn.writer.write_stream;
+        assert_eq!(writer.writes, 1);
+        let mut client =
+            Connection::<ClientboundLoginPacket, ServerboundLoginPacket, _, _>::from_split(
+                &writer.data[..],
+                tokio::io::sink(),
+            );
+        for i in 0..100 {
+            match client.read().await.unwrap() {
+                ClientboundLoginPacket::CustomQuery(p) => assert_eq!(p.transaction_id, i),
+                _ => panic!("Wrong packet type"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_respond_velocity_forwarding() {
+        let (mut client, mut server) =
+            connection_pair::<ClientboundLoginPacket, ServerboundLoginPacket>();
+        let forwarding = VelocityForwarding::new("203.0.113.5", Uuid::from_u128(1), "bot");
+
+        let other_query = ClientboundCustomQueryPacket {
+            transaction_id: 1,
+            identifier: ResourceLocation::new("example:hello").unwrap(),
+            data: UnsizedByteArray::from(vec![]),
+        };
+        assert!(!client
+            .respond_velocity_forwarding(&other_query, &forwarding, b"secret")
+            .await
+            .unwrap());
+
+        server
+            .write(VelocityForwarding::query_packet(2).get())
+            .await
+            .unwrap();
+        let query = match client.read().await.unwrap() {
+            ClientboundLoginPacket::CustomQuery(p) => p,
+            _ => panic!("Wrong packet type"),
+        };
+        assert!(client
+            .respond_velocity_forwarding(&query, &forwarding, b"secret")
+            .await
+            .unwrap());
+
+        match server.read().await.unwrap() {
+            ServerboundLoginPacket::CustomQuery(p) => {
+                assert_eq!(p.transaction_id, 2);
+                let data = p.data.unwrap();
+                assert_eq!(
+                    VelocityForwarding::verify(&data, b"secret").unwrap(),
+                    forwarding
+                );
+            }
+            _ => panic!("Wrong packet type"),
+        }
+    }
+
+    #[test]
+    fn test_proxy_protocol_header() {
+        let header = proxy_protocol_header(
+            "203.0.113.5:12345".parse().unwrap(),
+            "[2001:db8::1]:25565".parse().unwrap(),
+        );
+        assert_eq!(&header[..12], &PROXY_PROTOCOL_SIGNATURE);
+        assert_eq!(&header[12..16], &[0x21, 0x21, 0, 36]);
+        // the IPv4 address is mapped to IPv6
+        assert_eq!(
+            &header[16..32],
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 203, 0, 113, 5]
+        );
+        assert_eq!(&header[32..34], &[0x20, 0x01]);
+        assert_eq!(&header[48..], &[0x30, 0x39, 0x63, 0xdd]);
+    }
+
+    #[tokio::test]
+    async fn test_new_with_proxy_protocol() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut conn = Connection::new_with_options(
+            &address,
+            &ConnectionOptions {
+                proxy_protocol_source: Some("203.0.113.5:12345".parse().unwrap()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        conn.write(
+            ClientIntentionPacket {
+                protocol_version: 760,
+                hostname: "localhost".to_string(),
+                port: 25565,
+                intention: ConnectionProtocol::Login,
+            }
+            .get(),
+        )
+        .await
+        .unwrap();
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut header = [0; 28];
+        stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(&header[12..16], &[0x21, 0x11, 0, 12]);
+        assert_eq!(&header[16..20], &[203, 0, 113, 5]);
+        assert_eq!(&header[20..24], &[127, 0, 0, 1]);
+        assert_eq!(&header[24..26], &[0x30, 0x39]);
+        assert_eq!(&header[26..28], &address.port().to_be_bytes());
+        // the handshake comes right after the header
+        let mut server =
+            Connection::<ServerboundHandshakePacket, ClientboundHandshakePacket>::wrap(stream)
+                .unwrap();
+        assert!(matches!(
+            server.read().await.unwrap(),
+            ServerboundHandshakePacket::ClientIntention(p) if p.hostname == "localhost"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let path = std::env::temp_dir().join(format!("azalea-test-{}.azrp", std::process::id()));
+        let (mut client, server) =
+            connection_pair::<ClientboundHandshakePacket, ServerboundHandshakePacket>();
+        client.record(Recorder::create(&path, ConnectionProtocol::Handshake).unwrap());
+
+        client
+            .write(
+                ClientIntentionPacket {
+                    protocol_version: 760,
+                    hostname: "localhost".to_string(),
+                    port: 25565,
+                    intention: ConnectionProtocol::Login,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        let mut client = client.login();
+        let mut server = server.login();
+        server.set_compression_threshold(0);
+        client.set_compression_threshold(0);
+        server
+            .writer
+            .write_raw(&RawPacket {
+                id: 0x7f,
+                data: vec![1, 2, 3],
+            })
+            .await
+            .unwrap();
+        server
+            .write(
+                ClientboundLoginCompressionPacket {
+                    compression_threshold: 256,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        // packets that can't be decoded are recorded too
+        assert!(client.read().await.is_err());
+        client.read().await.unwrap();
+        drop(client);
+
+        let packets = crate::recording::RecordingReader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(packets.len(), 3);
+
+        assert_eq!(packets[0].direction, Direction::Outbound);
+        assert_eq!(packets[0].state, ConnectionProtocol::Handshake);
+        assert!(matches!(
+            packets[0].packet::<ServerboundHandshakePacket>(ProtocolVersion::LATEST).unwrap(),
+            ServerboundHandshakePacket::ClientIntention(p) if p.hostname == "localhost"
+        ));
+
+        assert_eq!(packets[1].direction, Direction::Inbound);
+        assert_eq!(packets[1].state, ConnectionProtocol::Login);
+        assert_eq!(packets[1].data, [0x7f, 1, 2, 3]);
+
+        assert_eq!(packets[2].direction, Direction::Inbound);
+        assert!(packets[2].time >= packets[1].time);
+        assert!(matches!(
+            packets[2].packet::<ClientboundLoginPacket>(ProtocolVersion::LATEST).unwrap(),
+            ClientboundLoginPacket::LoginCompression(p) if p.compression_threshold == 256
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_into_channels() {
+        use crate::packets::login::serverbound_hello_packet::ServerboundHelloPacket;
+
+        let (client, mut server) =
+            connection_pair::<ClientboundLoginPacket, ServerboundLoginPacket>();
+        let (mut packets, sender) = client.into_channels();
+
+        server
+            .writer
+            .write_raw(&RawPacket {
+                id: 0x7f,
+                data: vec![],
+            })
+            .await
+            .unwrap();
+        server
+            .write(
+                ClientboundLoginCompressionPacket {
+                    compression_threshold: 256,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        // the unknown packet is skipped
+        assert!(matches!(
+            packets.recv().await.unwrap(),
+            ClientboundLoginPacket::LoginCompression(p) if p.compression_threshold == 256
+        ));
+
+        for username in ["a", "b"] {
+            sender
+                .send(
+                    ServerboundHelloPacket {
+                        username: username.to_string(),
+                        public_key: None,
+                        profile_id: None,
+                    }
+                    .get(),
+                )
+                .unwrap();
+        }
+        for username in ["a", "b"] {
+            assert!(matches!(
+                server.read().await.unwrap(),
+                ServerboundLoginPacket::Hello(p) if p.username == username
+            ));
+        }
+
+        // dropping the sender closes the connection
+        drop(sender);
+        assert!(matches!(
+            server.read().await,
+            Err(ReadPacketError::ConnectionClosed)
+        ));
+        server.writer.shutdown().await.unwrap();
+        assert!(packets.recv().await.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_new_unix() {
+        let path = std::env::temp_dir().join(format!("azalea-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        let mut conn = Connection::new_unix(&path).await.unwrap();
+        conn.write(
+            ClientIntentionPacket {
+                protocol_version: 760,
+                hostname: "localhost".to_string(),
+                port: 25565,
+                intention: ConnectionProtocol::Login,
+            }
+            .get(),
+        )
+        .await
+        .unwrap();
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read_stream, write_stream) = stream.into_split();
+        let mut server =
+            Connection::<ServerboundHandshakePacket, ClientboundHandshakePacket, _, _>::from_split(
+                read_stream,
+                write_stream,
+            );
+        assert!(matches!(
+            server.read().await.unwrap(),
+            ServerboundHandshakePacket::ClientIntention(p) if p.hostname == "localhost"
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reunite() {
+        let new_connection =
+            || connection_pair::<ClientboundLoginPacket, ServerboundLoginPacket>().0;
+        let (read_a, write_a) = new_connection().into_split();
+        let (read_b, write_b) = new_connection().into_split();
+
+        let (read_a, write_b) = match read_a.reunite(write_b) {
+            Err(ReuniteError(read_a, write_b)) => (read_a, write_b),
+            Ok(_) => panic!("Halves of different connections were reunited"),
+        };
+        let conn = read_a.reunite(write_a).unwrap();
+        assert_eq!(conn.reader.id(), conn.writer.id());
+        // the halves can switch state after they're reunited
+        let conn = conn.game();
+        assert_ne!(conn.writer.id(), write_b.id());
+        assert!(read_b.reunite(write_b).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_and_keep_alive() {
+        use crate::packets::game::clientbound_keep_alive_packet::ClientboundKeepAlivePacket;
+
+        let (mut client, mut server) =
+            connection_pair::<ClientboundGamePacket, ServerboundGamePacket>();
+        // the answer is flushed even if the writer is buffering
+        client.writer.set_auto_flush_threshold(None);
+
+        server
+            .write(ClientboundKeepAlivePacket { id: 1234 }.get())
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.read_and_keep_alive().await.unwrap(),
+            ClientboundGamePacket::KeepAlive(p) if p.id == 1234
+        ));
+        assert!(matches!(
+            server.read().await.unwrap(),
+            ServerboundGamePacket::KeepAlive(p) if p.id == 1234
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_handover() {
+        use crate::packets::game::clientbound_keep_alive_packet::ClientboundKeepAlivePacket;
+
+        let (mut client, mut server) =
+            connection_pair::<ClientboundGamePacket, ServerboundGamePacket>();
+        client.set_compression_threshold(0);
+        server.set_compression_threshold(0);
+        client.set_encryption_key([7; 16]);
+        server.set_encryption_key([7; 16]);
+
+        // both of these are probably read from the stream at once, so the
+        // second one is still in the buffer when the connection is handed over
+        server
+            .write_all([
+                ClientboundKeepAlivePacket { id: 1 }.get(),
+                ClientboundKeepAlivePacket { id: 2 }.get(),
+            ])
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundGamePacket::KeepAlive(p) if p.id == 1
+        ));
+
+        let (read_stream, write_stream, state) = client.into_handover().await.unwrap();
+        assert_eq!(state.compression_threshold, Some(0));
+        assert_eq!(state.encryption.unwrap().key, [7; 16]);
+        let mut client =
+            Connection::<ClientboundGamePacket, ServerboundGamePacket, _, _>::from_handover(
+                read_stream,
+                write_stream,
+                state,
+            );
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundGamePacket::KeepAlive(p) if p.id == 2
+        ));
+        server
+            .write(ClientboundKeepAlivePacket { id: 3 }.get())
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.read().await.unwrap(),
+            ClientboundGamePacket::KeepAlive(p) if p.id == 3
+        ));
+        client
+            .write(ServerboundKeepAlivePacket { id: 3 }.get())
+            .await
+            .unwrap();
+        assert!(matches!(
+            server.read().await.unwrap(),
+            ServerboundGamePacket::KeepAlive(p) if p.id == 3
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let mut conn = Connection::<ClientboundGamePacket, ServerboundGamePacket, _, _>::from_split(
+            tokio::io::empty(),
+            tokio::io::sink(),
+        );
+        conn.writer.set_rate_limit(Some(RateLimit {
+            packets_per_second: Some(10),
+            bytes_per_second: None,
+        }));
+        let start = Instant::now();
+        // the first 10 are a burst, and the 2 after that have to wait
+        for id in 0..12 {
+            conn.write(ServerboundKeepAlivePacket { id }.get())
+                .await
+                .unwrap();
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(150), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+
+        conn.writer.set_rate_limit(Some(RateLimit {
+            packets_per_second: None,
+            bytes_per_second: Some(1000),
+        }));
+        let start = Instant::now();
+        // 1200 bytes with a burst of 1000, so the second packet waits
+        let packet = RawPacket {
+            id: 0,
+            data: vec![0; 599],
+        };
+        conn.writer.write_raw(&packet).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(100));
+        conn.writer.write_raw(&packet).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(150), "{elapsed:?}");
     }
 }
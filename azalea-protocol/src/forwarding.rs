@@ -0,0 +1,261 @@
+//! Helpers for the player info forwarding done by BungeeCord and Velocity
+//! proxies.
+//!
+//! When a proxy connects to a backend server on behalf of a player, it has to
+//! tell the backend the real address and UUID of the player somehow.
+//! BungeeCord does this by stuffing the data into the hostname of the
+//! handshake, and Velocity's "modern" forwarding does it with a signed login
+//! plugin (custom query) response.
+
+use crate::packets::login::{
+    clientbound_custom_query_packet::ClientboundCustomQueryPacket,
+    serverbound_custom_query_packet::ServerboundCustomQueryPacket,
+};
+use azalea_buf::{
+    BufReadError, McBuf, McBufReadable, McBufVarReadable, McBufWritable, UnsizedByteArray,
+};
+use azalea_core::ResourceLocation;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// The channel that Velocity uses for modern forwarding.
+pub const VELOCITY_CHANNEL: &str = "velocity:player_info";
+/// The only modern forwarding version that we know how to read and write.
+pub const VELOCITY_MODERN_FORWARDING_VERSION: i32 = 1;
+
+#[derive(Error, Debug)]
+pub enum ForwardingError {
+    #[error("The hostname doesn't contain BungeeCord forwarding data")]
+    NotForwarded,
+    #[error("Invalid forwarded UUID: {0}")]
+    InvalidUuid(#[from] uuid::Error),
+    #[error("Invalid forwarded properties: {0}")]
+    InvalidProperties(#[from] serde_json::Error),
+    #[error("Forwarding data is too short to contain a signature")]
+    MissingSignature,
+    #[error("Forwarding data has an invalid signature")]
+    InvalidSignature,
+    #[error("Unsupported modern forwarding version {0}")]
+    UnsupportedVersion(i32),
+    #[error("{0}")]
+    Read(#[from] BufReadError),
+}
+
+/// A property of the player's game profile, like their skin.
+#[derive(Clone, Debug, PartialEq, Eq, McBuf, Serialize, Deserialize)]
+pub struct ForwardedProperty {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// The data that BungeeCord appends to the hostname of the handshake packet,
+/// separated by null bytes: `host\0ip\0uuid\0properties`.
+///
+/// # Examples
+///
+/// ```
+/// use azalea_protocol::forwarding::BungeecordForwarding;
+/// use uuid::Uuid;
+///
+/// let forwarding = BungeecordForwarding {
+///     host: "localhost".to_string(),
+///     ip: "127.0.0.1".to_string(),
+///     uuid: Uuid::nil(),
+///     properties: vec![],
+/// };
+/// let hostname = forwarding.to_hostname();
+/// assert_eq!(BungeecordForwarding::from_hostname(&hostname).unwrap(), forwarding);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BungeecordForwarding {
+    /// The hostname that the player originally connected with.
+    pub host: String,
+    /// The real IP address of the player.
+    pub ip: String,
+    pub uuid: Uuid,
+    pub properties: Vec<ForwardedProperty>,
+}
+
+impl BungeecordForwarding {
+    /// Parse the forwarding data from the hostname field of a
+    /// [`ClientIntentionPacket`].
+    ///
+    /// [`ClientIntentionPacket`]: crate::packets::handshake::client_intention_packet::ClientIntentionPacket
+    pub fn from_hostname(hostname: &str) -> Result<Self, ForwardingError> {
+        let mut parts = hostname.split('\0');
+        let host = parts.next().ok_or(ForwardingError::NotForwarded)?;
+        let ip = parts.next().ok_or(ForwardingError::NotForwarded)?;
+        let uuid = parts.next().ok_or(ForwardingError::NotForwarded)?;
+        // the properties are optional
+        let properties = match parts.next() {
+            Some(properties) if !properties.is_empty() => serde_json::from_str(properties)?,
+            _ => vec![],
+        };
+
+        Ok(BungeecordForwarding {
+            host: host.to_string(),
+            ip: ip.to_string(),
+            uuid: Uuid::parse_str(uuid)?,
+            properties,
+        })
+    }
+
+    /// Create the hostname that should be sent in the handshake.
+    pub fn to_hostname(&self) -> String {
+        let mut hostname = format!("{}\0{}\0{}", self.host, self.ip, self.uuid.as_simple());
+        if !self.properties.is_empty() {
+            hostname.push('\0');
+            hostname.push_str(
+                &serde_json::to_string(&self.properties)
+                    .expect("Properties should always be serializable"),
+            );
+        }
+        hostname
+    }
+}
+
+/// The player info that Velocity sends in response to a
+/// [`VELOCITY_CHANNEL`] custom query.
+#[derive(Clone, Debug, PartialEq, Eq, McBuf)]
+pub struct VelocityForwarding {
+    #[var]
+    pub version: i32,
+    /// The real IP address of the player.
+    pub address: String,
+    pub uuid: Uuid,
+    pub username: String,
+    pub properties: Vec<ForwardedProperty>,
+}
+
+impl VelocityForwarding {
+    /// Create the player info for the version of modern forwarding that we
+    /// support, without any properties.
+    pub fn new(address: impl Into<String>, uuid: Uuid, username: impl Into<String>) -> Self {
+        VelocityForwarding {
+            version: VELOCITY_MODERN_FORWARDING_VERSION,
+            address: address.into(),
+            uuid,
+            username: username.into(),
+            properties: vec![],
+        }
+    }
+
+    /// Serialize the player info and prefix it with its HMAC-SHA256
+    /// signature, which is the format Velocity uses.
+    pub fn sign(&self, secret: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.write_into(&mut data)
+            .expect("Writing to a Vec should never fail");
+        let mut signed = azalea_crypto::hmac_sha256(secret, &data).to_vec();
+        signed.extend(data);
+        signed
+    }
+
+    /// Check the signature of the data Velocity sent us and read the player
+    /// info from it.
+    pub fn verify(signed: &[u8], secret: &[u8]) -> Result<Self, ForwardingError> {
+        if signed.len() < 32 {
+            return Err(ForwardingError::MissingSignature);
+        }
+        let (signature, data) = signed.split_at(32);
+        let expected_signature = azalea_crypto::hmac_sha256(secret, data);
+        // compare without exiting early so we don't leak timing information
+        let difference = signature
+            .iter()
+            .zip(expected_signature.iter())
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        if difference != 0 {
+            return Err(ForwardingError::InvalidSignature);
+        }
+
+        let mut buf = Cursor::new(data);
+        let version = i32::var_read_from(&mut buf)?;
+        if version != VELOCITY_MODERN_FORWARDING_VERSION {
+            return Err(ForwardingError::UnsupportedVersion(version));
+        }
+        buf.set_position(0);
+        Ok(VelocityForwarding::read_from(&mut buf)?)
+    }
+
+    /// Create the custom query packet that a backend server sends to ask
+    /// Velocity for the player's info.
+    pub fn query_packet(transaction_id: u32) -> ClientboundCustomQueryPacket {
+        ClientboundCustomQueryPacket {
+            transaction_id,
+            identifier: ResourceLocation::new(VELOCITY_CHANNEL).unwrap(),
+            data: UnsizedByteArray::from(vec![VELOCITY_MODERN_FORWARDING_VERSION as u8]),
+        }
+    }
+
+    /// Create the signed response to Velocity's custom query.
+    pub fn response_packet(
+        &self,
+        transaction_id: u32,
+        secret: &[u8],
+    ) -> ServerboundCustomQueryPacket {
+        ServerboundCustomQueryPacket {
+            transaction_id,
+            data: Some(UnsizedByteArray::from(self.sign(secret))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bungeecord_roundtrip_with_properties() {
+        let forwarding = BungeecordForwarding {
+            host: "play.example.com".to_string(),
+            ip: "203.0.113.5".to_string(),
+            uuid: Uuid::from_u128(0x1234_5678_9abc_def0_1234_5678_9abc_def0),
+            properties: vec![ForwardedProperty {
+                name: "textures".to_string(),
+                value: "abc".to_string(),
+                signature: Some("def".to_string()),
+            }],
+        };
+        let hostname = forwarding.to_hostname();
+        assert_eq!(
+            hostname,
+            "play.example.com\u{0}203.0.113.5\u{0}123456789abcdef0123456789abcdef0\u{0}[{\"name\":\"textures\",\"value\":\"abc\",\"signature\":\"def\"}]"
+        );
+        assert_eq!(
+            BungeecordForwarding::from_hostname(&hostname).unwrap(),
+            forwarding
+        );
+    }
+
+    #[test]
+    fn test_bungeecord_not_forwarded() {
+        assert!(matches!(
+            BungeecordForwarding::from_hostname("localhost"),
+            Err(ForwardingError::NotForwarded)
+        ));
+    }
+
+    #[test]
+    fn test_velocity_sign_and_verify() {
+        let forwarding = VelocityForwarding {
+            version: VELOCITY_MODERN_FORWARDING_VERSION,
+            address: "203.0.113.5".to_string(),
+            uuid: Uuid::from_u128(1),
+            username: "bot".to_string(),
+            properties: vec![],
+        };
+        let signed = forwarding.sign(b"secret");
+        assert_eq!(
+            VelocityForwarding::verify(&signed, b"secret").unwrap(),
+            forwarding
+        );
+        assert!(matches!(
+            VelocityForwarding::verify(&signed, b"wrong secret"),
+            Err(ForwardingError::InvalidSignature)
+        ));
+    }
+}
@@ -13,9 +13,15 @@ use std::str::FromStr;
 #[cfg(feature = "connecting")]
 pub mod connect;
 #[cfg(feature = "packets")]
+pub mod forwarding;
+#[cfg(feature = "packets")]
 pub mod packets;
 pub mod read;
+#[cfg(feature = "connecting")]
+pub mod recording;
 pub mod resolver;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 pub mod write;
 
 /// A host and port. It's possible that the port doesn't resolve to anything.
@@ -56,7 +62,7 @@ impl<'a> TryFrom<&'a str> for ServerAddress {
 
 #[cfg(feature = "connecting")]
 pub async fn connect(address: ServerAddress) -> Result<(), Box<dyn std::error::Error>> {
-    use log::debug;
+    use tracing::debug;
 
     let resolved_address = resolver::resolve_address(&address).await;
     debug!("Resolved address: {:?}", resolved_address);
@@ -68,12 +74,13 @@ mod tests {
     use std::io::Cursor;
 
     use crate::{
+        connect::Connection,
         packets::login::{
             serverbound_hello_packet::{ProfilePublicKeyData, ServerboundHelloPacket},
             ServerboundLoginPacket,
         },
-        read::read_packet,
-        write::write_packet,
+        read::{read_packet, Decompressor, ReadPacketError},
+        write::{write_packet, Compressor},
     };
     use bytes::BytesMut;
     use uuid::Uuid;
@@ -91,9 +98,15 @@ mod tests {
         }
         .get();
         let mut stream = Vec::new();
-        write_packet(&packet, &mut stream, None, &mut None)
-            .await
-            .unwrap();
+        write_packet(
+            &packet,
+            &mut stream,
+            None,
+            &mut None,
+            &mut Compressor::default(),
+        )
+        .await
+        .unwrap();
 
         let mut stream = Cursor::new(stream);
 
@@ -102,6 +115,7 @@ mod tests {
             &mut BytesMut::new(),
             None,
             &mut None,
+            &mut Decompressor::default(),
         )
         .await
         .unwrap();
@@ -120,21 +134,215 @@ mod tests {
         }
         .get();
         let mut stream = Vec::new();
-        write_packet(&packet, &mut stream, None, &mut None)
+        let mut compressor = Compressor::default();
+        write_packet(&packet, &mut stream, None, &mut None, &mut compressor)
             .await
             .unwrap();
-        write_packet(&packet, &mut stream, None, &mut None)
+        write_packet(&packet, &mut stream, None, &mut None, &mut compressor)
             .await
             .unwrap();
         let mut stream = Cursor::new(stream);
 
         let mut buffer = BytesMut::new();
+        let mut decompressor = Decompressor::default();
+
+        let _ = read_packet::<ServerboundLoginPacket, _>(
+            &mut stream,
+            &mut buffer,
+            None,
+            &mut None,
+            &mut decompressor,
+        )
+        .await
+        .unwrap();
+        let _ = read_packet::<ServerboundLoginPacket, _>(
+            &mut stream,
+            &mut buffer,
+            None,
+            &mut None,
+            &mut decompressor,
+        )
+        .await
+        .unwrap();
+    }
 
-        let _ = read_packet::<ServerboundLoginPacket, _>(&mut stream, &mut buffer, None, &mut None)
+    #[tokio::test]
+    async fn test_compressed_hello_packets() {
+        let packet = ServerboundHelloPacket {
+            username: "test".to_string(),
+            public_key: Some(ProfilePublicKeyData {
+                expires_at: 0,
+                key: b"idontthinkthisreallymattersijustwantittobelongforthetest".to_vec(),
+                key_signature: b"idontthinkthisreallymattersijustwantittobelongforthetest".to_vec(),
+            }),
+            profile_id: Some(Uuid::from_u128(0)),
+        }
+        .get();
+        let mut stream = Vec::new();
+        let mut compressor = Compressor::default();
+        // one packet that's compressed and one that isn't
+        write_packet(&packet, &mut stream, Some(16), &mut None, &mut compressor)
             .await
             .unwrap();
-        let _ = read_packet::<ServerboundLoginPacket, _>(&mut stream, &mut buffer, None, &mut None)
+        write_packet(&packet, &mut stream, Some(1024), &mut None, &mut compressor)
             .await
             .unwrap();
+        let mut stream = Cursor::new(stream);
+
+        let mut buffer = BytesMut::new();
+        let mut decompressor = Decompressor::default();
+
+        for _ in 0..2 {
+            match read_packet::<ServerboundLoginPacket, _>(
+                &mut stream,
+                &mut buffer,
+                Some(16),
+                &mut None,
+                &mut decompressor,
+            )
+            .await
+            .unwrap()
+            {
+                ServerboundLoginPacket::Hello(p) => assert_eq!(p.username, "test"),
+                _ => panic!("Wrong packet type"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buffered_writes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut conn = Connection::new(&address).await.unwrap().login();
+        let (mut server_stream, _) = listener.accept().await.unwrap();
+
+        conn.writer.set_auto_flush_threshold(None);
+        for username in ["first", "second"] {
+            conn.write(
+                ServerboundHelloPacket {
+                    username: username.to_string(),
+                    public_key: None,
+                    profile_id: None,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        }
+        conn.flush().await.unwrap();
+
+        let mut buffer = BytesMut::new();
+        let mut decompressor = Decompressor::default();
+        for username in ["first", "second"] {
+            match read_packet::<ServerboundLoginPacket, _>(
+                &mut server_stream,
+                &mut buffer,
+                None,
+                &mut None,
+                &mut decompressor,
+            )
+            .await
+            .unwrap()
+            {
+                ServerboundLoginPacket::Hello(p) => assert_eq!(p.username, username),
+                _ => panic!("Wrong packet type"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_flushes_buffered_writes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (_, mut writer) = Connection::new(&address)
+            .await
+            .unwrap()
+            .login()
+            .into_split();
+        let (mut server_stream, _) = listener.accept().await.unwrap();
+
+        writer.set_auto_flush_threshold(None);
+        writer
+            .write(
+                ServerboundHelloPacket {
+                    username: "bot".to_string(),
+                    public_key: None,
+                    profile_id: None,
+                }
+                .get(),
+            )
+            .await
+            .unwrap();
+        writer.shutdown().await.unwrap();
+
+        let mut buffer = BytesMut::new();
+        let mut decompressor = Decompressor::default();
+        match read_packet::<ServerboundLoginPacket, _>(
+            &mut server_stream,
+            &mut buffer,
+            None,
+            &mut None,
+            &mut decompressor,
+        )
+        .await
+        .unwrap()
+        {
+            ServerboundLoginPacket::Hello(p) => assert_eq!(p.username, "bot"),
+            _ => panic!("Wrong packet type"),
+        }
+        // and then the connection is closed
+        assert!(matches!(
+            read_packet::<ServerboundLoginPacket, _>(
+                &mut server_stream,
+                &mut buffer,
+                None,
+                &mut None,
+                &mut decompressor,
+            )
+            .await,
+            Err(ReadPacketError::ConnectionClosed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_last_read_and_written_at() {
+        use crate::packets::login::clientbound_login_compression_packet::ClientboundLoginCompressionPacket;
+        use std::time::Duration;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut conn = Connection::new(&address).await.unwrap().login();
+        let (mut server_stream, _) = listener.accept().await.unwrap();
+        let created_at = conn.reader.last_read_at();
+        assert_eq!(conn.writer.last_written_at(), created_at);
+
+        std::thread::sleep(Duration::from_millis(10));
+        conn.write(
+            ServerboundHelloPacket {
+                username: "bot".to_string(),
+                public_key: None,
+                profile_id: None,
+            }
+            .get(),
+        )
+        .await
+        .unwrap();
+        assert!(conn.writer.last_written_at() > created_at);
+        assert_eq!(conn.reader.last_read_at(), created_at);
+
+        write_packet(
+            &ClientboundLoginCompressionPacket {
+                compression_threshold: 256,
+            }
+            .get(),
+            &mut server_stream,
+            None,
+            &mut None,
+            &mut Compressor::default(),
+        )
+        .await
+        .unwrap();
+        conn.read().await.unwrap();
+        assert!(conn.reader.last_read_at() > created_at);
     }
 }
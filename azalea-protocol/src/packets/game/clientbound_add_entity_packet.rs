@@ -5,6 +5,7 @@ use azalea_world::entity::EntityData;
 use uuid::Uuid;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundAddEntityPacket {
     /// The id of the entity.
     #[var]
@@ -2,6 +2,7 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundAddExperienceOrbPacket {
     #[var]
     pub id: u32,
@@ -2,15 +2,16 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundAnimatePacket {
     #[var]
     pub id: u32,
     pub action: AnimationAction,
 }
 
-// minecraft actually uses a u8 for this, but a varint still works and makes it
-// so i don't have to add a special handler
 #[derive(Clone, Debug, Copy, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[mcbuf(tag = "u8")]
 pub enum AnimationAction {
     SwingMainHand = 0,
     Hurt = 1,
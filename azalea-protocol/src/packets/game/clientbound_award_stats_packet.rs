@@ -3,12 +3,14 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::collections::HashMap;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundAwardStatsPacket {
     #[var]
     pub stats: HashMap<Stat, i32>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Stat {
     Mined(azalea_registry::Block),
     Crafted(azalea_registry::Item),
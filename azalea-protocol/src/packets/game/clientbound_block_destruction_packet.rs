@@ -3,6 +3,7 @@ use azalea_core::BlockPos;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundBlockDestructionPacket {
     /// The ID of the entity breaking the block.
     #[var]
@@ -3,6 +3,7 @@ use azalea_core::BlockPos;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundBlockEntityDataPacket {
     pub pos: BlockPos,
     pub block_entity_type: azalea_registry::BlockEntityType,
@@ -4,6 +4,7 @@ use azalea_core::BlockPos;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundBlockEventPacket {
     pub pos: BlockPos,
     pub b0: u8,
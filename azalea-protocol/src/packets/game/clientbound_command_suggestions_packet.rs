@@ -1,32 +1,102 @@
-// use azalea_brigadier::context::StringRange;
-use azalea_buf::{
-    // BufReadError, McBuf, McBufReadable, McBufVarReadable, McBufVarWritable, McBufWritable,
-    BufReadError,
-    McBufReadable,
-    McBufWritable,
+use azalea_brigadier::{
+    context::StringRange,
+    suggestion::{Suggestion, Suggestions},
 };
+use azalea_buf::{BufReadError, McBufReadable, McBufVarReadable, McBufVarWritable, McBufWritable};
+use azalea_chat::component::Component;
 use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
+/// The server's response to a `ServerboundCommandSuggestionPacket` with the
+/// same id.
 #[derive(Clone, Debug, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundCommandSuggestionsPacket {
     #[var]
     pub id: u32,
-    // pub suggestions: Suggestions,
+    pub suggestions: Suggestions,
 }
 
 impl McBufReadable for ClientboundCommandSuggestionsPacket {
-    fn read_from(_buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        // let id = u32::var_read_from(buf)?;
-        // let start = u32::var_read_from(buf)? as usize;
-        // let length = u32::var_read_from(buf)? as usize;
-        // let stringrange = StringRange::between(start, start + length);
-        todo!("Suggestions aren't implemented in azalea-brigadier yet")
+    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        let id = u32::var_read_from(buf)?;
+        let start = u32::var_read_from(buf)? as usize;
+        let length = u32::var_read_from(buf)? as usize;
+        let range = StringRange::between(start, start + length);
+
+        let count = u32::var_read_from(buf)?;
+        let mut suggestions = Vec::new();
+        for _ in 0..count {
+            let text = String::read_from(buf)?;
+            // brigadier messages are just strings, so the tooltip loses its
+            // formatting
+            let tooltip =
+                Option::<Component>::read_from(buf)?.map(|tooltip| tooltip.to_string().into());
+            suggestions.push(Suggestion {
+                range: range.clone(),
+                text,
+                tooltip,
+            });
+        }
+
+        Ok(Self {
+            id,
+            suggestions: Suggestions { range, suggestions },
+        })
     }
 }
 
 impl McBufWritable for ClientboundCommandSuggestionsPacket {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        todo!()
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        self.id.var_write_into(buf)?;
+        let range = &self.suggestions.range;
+        (range.start() as u32).var_write_into(buf)?;
+        (range.length() as u32).var_write_into(buf)?;
+
+        (self.suggestions.suggestions.len() as u32).var_write_into(buf)?;
+        for suggestion in &self.suggestions.suggestions {
+            suggestion.text.write_into(buf)?;
+            suggestion
+                .tooltip
+                .as_ref()
+                .map(|tooltip| serde_json::json!({ "text": tooltip.string() }).to_string())
+                .write_into(buf)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_suggestions_round_trip() {
+        let range = StringRange::between(1, 4);
+        let packet = ClientboundCommandSuggestionsPacket {
+            id: 5,
+            suggestions: Suggestions {
+                range: range.clone(),
+                suggestions: vec![
+                    Suggestion {
+                        range: range.clone(),
+                        text: "help".to_string(),
+                        tooltip: None,
+                    },
+                    Suggestion {
+                        range,
+                        text: "home".to_string(),
+                        tooltip: Some("Go home".to_string().into()),
+                    },
+                ],
+            },
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+
+        let read =
+            ClientboundCommandSuggestionsPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(read.id, 5);
+        assert_eq!(read.suggestions, packet.suggestions);
     }
 }
@@ -1,14 +1,15 @@
 use azalea_buf::BufReadError;
 use azalea_buf::McBuf;
-use azalea_buf::McBufVarReadable;
 use azalea_buf::{McBufReadable, McBufWritable};
+use azalea_buf::{McBufVarReadable, McBufVarWritable};
 use azalea_core::ResourceLocation;
 use azalea_protocol_macros::ClientboundGamePacket;
-use log::warn;
 use std::io::Cursor;
 use std::io::Write;
+use tracing::warn;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundCommandsPacket {
     pub entries: Vec<BrigadierNodeStub>,
     #[var]
@@ -16,6 +17,7 @@ pub struct ClientboundCommandsPacket {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BrigadierNodeStub {
     pub is_executable: bool,
     pub children: Vec<u32>,
@@ -24,6 +26,7 @@ pub struct BrigadierNodeStub {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BrigadierNumber<T> {
     min: Option<T>,
     max: Option<T>,
@@ -65,6 +68,7 @@ impl<T: McBufWritable> McBufWritable for BrigadierNumber<T> {
 }
 
 #[derive(Debug, Clone, Copy, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BrigadierString {
     /// Reads a single word
     SingleWord = 0,
@@ -75,6 +79,7 @@ pub enum BrigadierString {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BrigadierParser {
     Bool,
     Double(BrigadierNumber<f64>),
@@ -125,6 +130,9 @@ pub enum BrigadierParser {
     Resource { registry_key: ResourceLocation },
     TemplateMirror,
     TemplateRotation,
+    // a parser that we don't know about, we assume it doesn't have any
+    // properties since we can't know how long they'd be
+    Unknown(u32),
 }
 
 impl McBufReadable for BrigadierParser {
@@ -195,9 +203,95 @@ impl McBufReadable for BrigadierParser {
             45 => Ok(BrigadierParser::TemplateMirror),
             46 => Ok(BrigadierParser::TemplateRotation),
             47 => Ok(BrigadierParser::Uuid),
-            _ => Err(BufReadError::UnexpectedEnumVariant {
-                id: parser_type as i32,
-            }),
+            _ => {
+                warn!("Unknown Brigadier parser id {parser_type}, assuming it has no properties");
+                Ok(BrigadierParser::Unknown(parser_type))
+            }
+        }
+    }
+}
+
+impl McBufWritable for BrigadierParser {
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        let parser_type: u32 = match self {
+            BrigadierParser::Bool => 0,
+            BrigadierParser::Float(_) => 1,
+            BrigadierParser::Double(_) => 2,
+            BrigadierParser::Integer(_) => 3,
+            BrigadierParser::Long(_) => 4,
+            BrigadierParser::String(_) => 5,
+            BrigadierParser::Entity { .. } => 6,
+            BrigadierParser::GameProfile => 7,
+            BrigadierParser::BlockPos => 8,
+            BrigadierParser::ColumnPos => 9,
+            BrigadierParser::Vec3 => 10,
+            BrigadierParser::Vec2 => 11,
+            BrigadierParser::BlockState => 12,
+            BrigadierParser::BlockPredicate => 13,
+            BrigadierParser::ItemStack => 14,
+            BrigadierParser::ItemPredicate => 15,
+            BrigadierParser::Color => 16,
+            BrigadierParser::Component => 17,
+            BrigadierParser::Message => 18,
+            BrigadierParser::NbtCompoundTag => 19,
+            // Nbt is never read, but it means the same thing as NbtTag
+            BrigadierParser::Nbt | BrigadierParser::NbtTag => 20,
+            BrigadierParser::NbtPath => 21,
+            BrigadierParser::Objective => 22,
+            BrigadierParser::ObjectiveCriteira => 23,
+            BrigadierParser::Operation => 24,
+            BrigadierParser::Particle => 25,
+            BrigadierParser::Angle => 26,
+            BrigadierParser::Rotation => 27,
+            BrigadierParser::ScoreboardSlot => 28,
+            BrigadierParser::ScoreHolder { .. } => 29,
+            BrigadierParser::Swizzle => 30,
+            BrigadierParser::Team => 31,
+            BrigadierParser::ItemSlot => 32,
+            BrigadierParser::ResourceLocation => 33,
+            BrigadierParser::MobEffect => 34,
+            BrigadierParser::Function => 35,
+            BrigadierParser::EntityAnchor => 36,
+            BrigadierParser::IntRange => 37,
+            BrigadierParser::FloatRange => 38,
+            BrigadierParser::ItemEnchantment => 39,
+            BrigadierParser::EntitySummon => 40,
+            BrigadierParser::Dimension => 41,
+            BrigadierParser::Time => 42,
+            BrigadierParser::ResourceOrTag { .. } => 43,
+            BrigadierParser::Resource { .. } => 44,
+            BrigadierParser::TemplateMirror => 45,
+            BrigadierParser::TemplateRotation => 46,
+            BrigadierParser::Uuid => 47,
+            BrigadierParser::Unknown(parser_type) => *parser_type,
+        };
+        parser_type.var_write_into(buf)?;
+
+        match self {
+            BrigadierParser::Float(number) => number.write_into(buf),
+            BrigadierParser::Double(number) => number.write_into(buf),
+            BrigadierParser::Integer(number) => number.write_into(buf),
+            BrigadierParser::Long(number) => number.write_into(buf),
+            BrigadierParser::String(string) => string.write_into(buf),
+            BrigadierParser::Entity {
+                single,
+                players_only,
+            } => {
+                let mut flags: u8 = 0;
+                if *single {
+                    flags |= 0x01;
+                }
+                if *players_only {
+                    flags |= 0x02;
+                }
+                flags.write_into(buf)
+            }
+            BrigadierParser::ScoreHolder { allows_multiple } => {
+                u8::from(*allows_multiple).write_into(buf)
+            }
+            BrigadierParser::ResourceOrTag { registry_key }
+            | BrigadierParser::Resource { registry_key } => registry_key.write_into(buf),
+            _ => Ok(()),
         }
     }
 }
@@ -264,12 +358,53 @@ impl McBufReadable for BrigadierNodeStub {
 }
 
 impl McBufWritable for BrigadierNodeStub {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        todo!()
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        let mut flags: u8 = match self.node_type {
+            NodeType::Root => 0,
+            NodeType::Literal { .. } => 1,
+            NodeType::Argument { .. } => 2,
+        };
+        if self.is_executable {
+            flags |= 0x04;
+        }
+        if self.redirect_node.is_some() {
+            flags |= 0x08;
+        }
+        if let NodeType::Argument {
+            suggestions_type: Some(_),
+            ..
+        } = self.node_type
+        {
+            flags |= 0x10;
+        }
+        flags.write_into(buf)?;
+
+        self.children.var_write_into(buf)?;
+        if let Some(redirect_node) = self.redirect_node {
+            redirect_node.var_write_into(buf)?;
+        }
+
+        match &self.node_type {
+            NodeType::Root => {}
+            NodeType::Literal { name } => name.write_into(buf)?,
+            NodeType::Argument {
+                name,
+                parser,
+                suggestions_type,
+            } => {
+                name.write_into(buf)?;
+                parser.write_into(buf)?;
+                if let Some(suggestions_type) = suggestions_type {
+                    suggestions_type.write_into(buf)?;
+                }
+            }
+        }
+        Ok(())
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum NodeType {
     Root,
     Literal {
@@ -291,3 +426,146 @@ impl BrigadierNodeStub {
         }
     }
 }
+
+/// The command graph that the server sent us in a
+/// [`ClientboundCommandsPacket`]. Nodes refer to each other by their index in
+/// [`Self::nodes`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CommandTree {
+    pub nodes: Vec<BrigadierNodeStub>,
+    pub root_index: u32,
+}
+
+impl From<ClientboundCommandsPacket> for CommandTree {
+    fn from(packet: ClientboundCommandsPacket) -> Self {
+        CommandTree {
+            nodes: packet.entries,
+            root_index: packet.root_index,
+        }
+    }
+}
+
+impl CommandTree {
+    /// Get the node at the given index, or `None` if it doesn't exist.
+    pub fn node(&self, index: u32) -> Option<&BrigadierNodeStub> {
+        self.nodes.get(index as usize)
+    }
+
+    pub fn root(&self) -> Option<&BrigadierNodeStub> {
+        self.node(self.root_index)
+    }
+
+    /// Get the children of a node. Children with invalid indexes are
+    /// skipped.
+    pub fn children<'a>(
+        &'a self,
+        node: &'a BrigadierNodeStub,
+    ) -> impl Iterator<Item = &'a BrigadierNodeStub> {
+        node.children.iter().filter_map(|&index| self.node(index))
+    }
+
+    /// Get the node that a node redirects to, like how `/execute run`
+    /// redirects back to the root.
+    pub fn redirect(&self, node: &BrigadierNodeStub) -> Option<&BrigadierNodeStub> {
+        node.redirect_node.and_then(|index| self.node(index))
+    }
+
+    /// Get the child of a node that's a literal with the given name.
+    pub fn literal_child<'a>(
+        &'a self,
+        node: &'a BrigadierNodeStub,
+        name: &str,
+    ) -> Option<&'a BrigadierNodeStub> {
+        self.children(node).find(
+            |child| matches!(&child.node_type, NodeType::Literal { name: child_name } if child_name == name),
+        )
+    }
+
+    /// The names of all the commands that we can run, like `tp` or `give`.
+    pub fn root_commands(&self) -> Vec<&str> {
+        match self.root() {
+            Some(root) => self
+                .children(root)
+                .filter(|child| matches!(child.node_type, NodeType::Literal { .. }))
+                .filter_map(|child| child.name())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(
+        node_type: NodeType,
+        children: Vec<u32>,
+        redirect_node: Option<u32>,
+    ) -> BrigadierNodeStub {
+        BrigadierNodeStub {
+            is_executable: redirect_node.is_none() && !children.is_empty(),
+            children,
+            redirect_node,
+            node_type,
+        }
+    }
+
+    #[test]
+    fn test_command_tree() {
+        let literal = |name: &str| NodeType::Literal {
+            name: name.to_string(),
+        };
+        let tree = CommandTree::from(ClientboundCommandsPacket {
+            entries: vec![
+                node(literal("tp"), vec![1], None),
+                node(
+                    NodeType::Argument {
+                        name: "targets".to_string(),
+                        parser: BrigadierParser::Entity {
+                            single: false,
+                            players_only: false,
+                        },
+                        suggestions_type: None,
+                    },
+                    vec![],
+                    None,
+                ),
+                node(literal("teleport"), vec![], Some(0)),
+                node(NodeType::Root, vec![0, 2, 99], None),
+            ],
+            root_index: 3,
+        });
+
+        assert_eq!(tree.root_commands(), vec!["tp", "teleport"]);
+        let root = tree.root().unwrap();
+        let teleport = tree.literal_child(root, "teleport").unwrap();
+        let tp = tree.redirect(teleport).unwrap();
+        assert_eq!(tp.name(), Some("tp"));
+        let targets = tree.children(tp).next().unwrap();
+        assert!(matches!(
+            targets.node_type,
+            NodeType::Argument {
+                parser: BrigadierParser::Entity { single: false, .. },
+                ..
+            }
+        ));
+        assert!(tree.literal_child(root, "give").is_none());
+    }
+
+    #[test]
+    fn test_read_unknown_parser() {
+        // argument node named "a" with parser id 200, then a bool parser
+        let mut buf = Cursor::new(&[2, 0, 1, b'a', 200, 1][..]);
+        let node = BrigadierNodeStub::read_from(&mut buf).unwrap();
+        assert!(matches!(
+            node.node_type,
+            NodeType::Argument {
+                parser: BrigadierParser::Unknown(200),
+                ..
+            }
+        ));
+        assert_eq!(buf.position(), 6);
+    }
+}
@@ -3,6 +3,7 @@ use azalea_core::Slot;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundContainerSetSlotPacket {
     pub container_id: u8,
     #[var]
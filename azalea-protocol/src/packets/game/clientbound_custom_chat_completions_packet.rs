@@ -2,12 +2,14 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundCustomChatCompletionsPacket {
     pub action: Action,
     pub entries: Vec<String>,
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Action {
     Add = 0,
     Remove = 1,
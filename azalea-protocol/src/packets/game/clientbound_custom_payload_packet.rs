@@ -4,6 +4,7 @@ use azalea_core::ResourceLocation;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundCustomPayloadPacket {
     pub identifier: ResourceLocation,
     pub data: UnsizedByteArray,
@@ -5,6 +5,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use super::clientbound_sound_packet::SoundSource;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundCustomSoundPacket {
     pub name: ResourceLocation,
     pub source: SoundSource,
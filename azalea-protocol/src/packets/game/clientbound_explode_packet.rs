@@ -4,12 +4,16 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, PartialEq, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundExplodePacket {
     pub x: f32,
     pub y: f32,
     pub z: f32,
     pub power: f32,
+    /// The blocks that the explosion destroyed. They're sent as offsets from
+    /// the center of the explosion.
     pub to_blow: Vec<BlockPos>,
+    /// The velocity that's added to the player that this was sent to.
     pub knockback_x: f32,
     pub knockback_y: f32,
     pub knockback_z: f32,
@@ -27,12 +31,13 @@ impl McBufReadable for ClientboundExplodePacket {
         let z_floor = z.floor() as i32;
 
         let to_blow_len = u32::var_read_from(buf)?;
-        let mut to_blow = Vec::with_capacity(to_blow_len as usize);
+        let mut to_blow = Vec::new();
         for _ in 0..to_blow_len {
-            // the bytes are offsets from the main x y z
-            let x = x_floor + i8::read_from(buf)? as i32;
-            let y = y_floor + i8::read_from(buf)? as i32;
-            let z = z_floor + i8::read_from(buf)? as i32;
+            // the bytes are offsets from the main x y z, which wrap around
+            // like java ints
+            let x = x_floor.wrapping_add(i8::read_from(buf)? as i32);
+            let y = y_floor.wrapping_add(i8::read_from(buf)? as i32);
+            let z = z_floor.wrapping_add(i8::read_from(buf)? as i32);
             to_blow.push(BlockPos { x, y, z });
         }
 
@@ -68,9 +73,9 @@ impl McBufWritable for ClientboundExplodePacket {
         let z_floor = self.z.floor() as i32;
 
         for pos in &self.to_blow {
-            let x = (pos.x - x_floor) as i8;
-            let y = (pos.y - y_floor) as i8;
-            let z = (pos.z - z_floor) as i8;
+            let x = pos.x.wrapping_sub(x_floor) as i8;
+            let y = pos.y.wrapping_sub(y_floor) as i8;
+            let z = pos.z.wrapping_sub(z_floor) as i8;
             x.write_into(buf)?;
             y.write_into(buf)?;
             z.write_into(buf)?;
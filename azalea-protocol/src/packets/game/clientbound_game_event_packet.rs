@@ -2,12 +2,15 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundGameEventPacket {
     pub event: EventType,
     pub param: f32,
 }
 
 #[derive(Clone, Debug, Copy, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[mcbuf(tag = "u8")]
 pub enum EventType {
     NoRespawnBlockAvailable = 0,
     StartRaining = 1,
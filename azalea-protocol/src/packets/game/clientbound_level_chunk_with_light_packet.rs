@@ -1,9 +1,11 @@
 use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundGamePacket;
+use bytes::Bytes;
 
 use super::clientbound_light_update_packet::ClientboundLightUpdatePacketData;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLevelChunkWithLightPacket {
     pub x: i32,
     pub z: i32,
@@ -12,14 +14,18 @@ pub struct ClientboundLevelChunkWithLightPacket {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLevelChunkPacketData {
     pub heightmaps: azalea_nbt::Tag,
     // we can't parse the data in azalea-protocol because it dependso on context from other packets
-    pub data: Vec<u8>,
+    /// The chunk sections. This is [`Bytes`] so cloning the packet (like
+    /// when it's sent as an event) doesn't copy all of it.
+    pub data: Bytes,
     pub block_entities: Vec<BlockEntity>,
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BlockEntity {
     pub packed_xz: u8,
     pub y: u16,
@@ -3,6 +3,7 @@ use azalea_core::BlockPos;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLevelEventPacket {
     pub event_type: u32,
     pub pos: BlockPos,
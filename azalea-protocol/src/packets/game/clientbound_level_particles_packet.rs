@@ -1,12 +1,11 @@
-use azalea_buf::{BufReadError, McBufReadable, McBufVarReadable, McBufWritable};
-use azalea_core::ParticleData;
+use azalea_buf::{BufReadError, McBufReadable, McBufVarReadable, McBufVarWritable, McBufWritable};
+use azalea_core::Particle;
 use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLevelParticlesPacket {
-    #[var]
-    pub particle_id: u32,
     pub override_limiter: bool,
     pub x: f64,
     pub y: f64,
@@ -16,7 +15,9 @@ pub struct ClientboundLevelParticlesPacket {
     pub z_dist: f32,
     pub max_speed: f32,
     pub count: u32,
-    pub data: ParticleData,
+    /// The particle type and its options. The type is sent at the start of
+    /// the packet, but the options are at the end.
+    pub particle: Particle,
 }
 
 impl McBufReadable for ClientboundLevelParticlesPacket {
@@ -32,10 +33,9 @@ impl McBufReadable for ClientboundLevelParticlesPacket {
         let max_speed = f32::read_from(buf)?;
         let count = u32::read_from(buf)?;
 
-        let data = ParticleData::read_from_particle_id(buf, particle_id)?;
+        let particle = Particle::read_from_particle_id(buf, particle_id)?;
 
         Ok(Self {
-            particle_id,
             override_limiter,
             x,
             y,
@@ -45,13 +45,55 @@ impl McBufReadable for ClientboundLevelParticlesPacket {
             z_dist,
             max_speed,
             count,
-            data,
+            particle,
         })
     }
 }
 
 impl McBufWritable for ClientboundLevelParticlesPacket {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        todo!();
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        self.particle.id().var_write_into(buf)?;
+        self.override_limiter.write_into(buf)?;
+        self.x.write_into(buf)?;
+        self.y.write_into(buf)?;
+        self.z.write_into(buf)?;
+        self.x_dist.write_into(buf)?;
+        self.y_dist.write_into(buf)?;
+        self.z_dist.write_into(buf)?;
+        self.max_speed.write_into(buf)?;
+        self.count.write_into(buf)?;
+        self.particle.write_without_id(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_core::BlockParticle;
+
+    #[test]
+    fn test_read_write_level_particles() {
+        let packet = ClientboundLevelParticlesPacket {
+            override_limiter: false,
+            x: 1.,
+            y: 64.,
+            z: -3.,
+            x_dist: 0.5,
+            y_dist: 0.5,
+            z_dist: 0.5,
+            max_speed: 0.,
+            count: 10,
+            particle: Particle::FallingDust(BlockParticle { block_state: 1 }),
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+        // the particle id comes first
+        assert_eq!(buf[0], 25);
+        let read = ClientboundLevelParticlesPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(read.count, 10);
+        assert!(matches!(
+            read.particle,
+            Particle::FallingDust(BlockParticle { block_state: 1 })
+        ));
     }
 }
@@ -1,8 +1,10 @@
 use azalea_buf::McBuf;
 use azalea_core::BitSet;
 use azalea_protocol_macros::ClientboundGamePacket;
+use azalea_world::LightSection;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLightUpdatePacket {
     #[var]
     pub x: i32,
@@ -12,12 +14,13 @@ pub struct ClientboundLightUpdatePacket {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLightUpdatePacketData {
-    trust_edges: bool,
-    sky_y_mask: BitSet,
-    block_y_mask: BitSet,
-    empty_sky_y_mask: BitSet,
-    empty_block_y_mask: BitSet,
-    sky_updates: Vec<Vec<u8>>,
-    block_updates: Vec<Vec<u8>>,
+    pub trust_edges: bool,
+    pub sky_y_mask: BitSet,
+    pub block_y_mask: BitSet,
+    pub empty_sky_y_mask: BitSet,
+    pub empty_block_y_mask: BitSet,
+    pub sky_updates: Vec<LightSection>,
+    pub block_updates: Vec<LightSection>,
 }
@@ -3,6 +3,7 @@ use azalea_core::{GameType, GlobalPos, OptionalGameType, ResourceLocation};
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLoginPacket {
     pub player_id: u32,
     pub hardcore: bool,
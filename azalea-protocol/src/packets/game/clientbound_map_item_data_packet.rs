@@ -5,12 +5,16 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundMapItemDataPacket {
     // #[var]
     pub map_id: u32,
     pub scale: u8,
     pub locked: bool,
-    pub decorations: Vec<MapDecoration>,
+    /// The icons on the map, or `None` if they didn't change. `Some` with an
+    /// empty list means the map has no icons anymore.
+    pub decorations: Option<Vec<MapDecoration>>,
+    /// The part of the map's colors that changed, if any did.
     pub color_patch: Option<MapPatch>,
 }
 
@@ -19,7 +23,7 @@ impl McBufReadable for ClientboundMapItemDataPacket {
         let map_id = u32::var_read_from(buf)?;
         let scale = u8::read_from(buf)?;
         let locked = bool::read_from(buf)?;
-        let decorations = Option::<Vec<MapDecoration>>::read_from(buf)?.unwrap_or_default();
+        let decorations = Option::<Vec<MapDecoration>>::read_from(buf)?;
 
         let width = u8::read_from(buf)?;
         let color_patch = if width == 0 {
@@ -53,7 +57,6 @@ impl McBufWritable for ClientboundMapItemDataPacket {
         self.map_id.var_write_into(buf)?;
         self.scale.write_into(buf)?;
         self.locked.write_into(buf)?;
-        (!self.decorations.is_empty()).write_into(buf)?;
         self.decorations.write_into(buf)?;
         if let Some(color_patch) = &self.color_patch {
             color_patch.width.write_into(buf)?;
@@ -69,6 +72,7 @@ impl McBufWritable for ClientboundMapItemDataPacket {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MapDecoration {
     pub decoration_type: DecorationType,
     pub x: i8,
@@ -78,16 +82,61 @@ pub struct MapDecoration {
     pub name: Option<Component>,
 }
 
+/// A rectangle of colors on a map, which is 128x128 pixels.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MapPatch {
     pub start_x: u8,
     pub start_y: u8,
     pub width: u8,
     pub height: u8,
+    /// The packed color of each pixel, row by row. Use
+    /// [`MapPatch::to_rgba`] to get the actual colors.
     pub map_colors: Vec<u8>,
 }
 
+impl MapPatch {
+    /// The RGBA color of each pixel in the patch, row by row, like
+    /// [`map_color_to_rgba`].
+    pub fn to_rgba(&self) -> Vec<[u8; 4]> {
+        self.map_colors
+            .iter()
+            .map(|&color| map_color_to_rgba(color))
+            .collect()
+    }
+}
+
+/// The RGB of each base color that can be on a map, by id. These are
+/// vanilla's `MaterialColor`s.
+const MAP_BASE_COLORS: [u32; 62] = [
+    0x000000, 0x7fb238, 0xf7e9a3, 0xc7c7c7, 0xff0000, 0xa0a0ff, 0xa7a7a7, 0x007c00, 0xffffff,
+    0xa4a8b8, 0x976d4d, 0x707070, 0x4040ff, 0x8f7748, 0xfffcf5, 0xd87f33, 0xb24cd8, 0x6699d8,
+    0xe5e533, 0x7fcc19, 0xf27fa5, 0x4c4c4c, 0x999999, 0x4c7f99, 0x7f3fb2, 0x334cb2, 0x664c33,
+    0x667f33, 0x993333, 0x191919, 0xfaee4d, 0x5cdbd5, 0x4a80ff, 0x00d93a, 0x815631, 0x700200,
+    0xd1b1a1, 0x9f5224, 0x95576c, 0x706c8a, 0xba8524, 0x677535, 0xa04d4e, 0x392923, 0x876b62,
+    0x575c5c, 0x7a4958, 0x4c3e5c, 0x4c3223, 0x4c522a, 0x8e3c2e, 0x251610, 0xbd3031, 0x943f61,
+    0x5c191d, 0x167e86, 0x3a8e8c, 0x562c3e, 0x14b485, 0x646464, 0xd8af93, 0x7fa796,
+];
+
+/// How much each base color is darkened by, out of 255, for the brightness
+/// in the bottom two bits of a packed map color.
+const MAP_BRIGHTNESS: [u32; 4] = [180, 220, 255, 135];
+
+/// Convert a packed map color to RGBA with the vanilla palette. The top six
+/// bits are the base color and the bottom two are the brightness. Base color
+/// 0, and ids that don't exist, are transparent.
+pub fn map_color_to_rgba(color: u8) -> [u8; 4] {
+    let base = match MAP_BASE_COLORS.get(usize::from(color >> 2)) {
+        Some(&base) if color >> 2 != 0 => base,
+        _ => return [0, 0, 0, 0],
+    };
+    let brightness = MAP_BRIGHTNESS[usize::from(color & 3)];
+    let channel = |shift: u32| ((base >> shift & 0xff) * brightness / 255) as u8;
+    [channel(16), channel(8), channel(0), 255]
+}
+
 #[derive(Clone, Copy, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum DecorationType {
     Player,
     Frame,
@@ -117,3 +166,59 @@ pub enum DecorationType {
     BannerBlack,
     RedX,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_color_to_rgba() {
+        assert_eq!(map_color_to_rgba(0), [0, 0, 0, 0]);
+        assert_eq!(map_color_to_rgba(3), [0, 0, 0, 0]);
+        // white wool at full brightness
+        assert_eq!(map_color_to_rgba(8 * 4 + 2), [255, 255, 255, 255]);
+        // grass at the darkest brightness
+        assert_eq!(map_color_to_rgba(4 + 3), [67, 94, 29, 255]);
+        assert_eq!(map_color_to_rgba(255), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_read_write_map_item_data() {
+        let packet = ClientboundMapItemDataPacket {
+            map_id: 300,
+            scale: 0,
+            locked: true,
+            decorations: Some(vec![]),
+            color_patch: Some(MapPatch {
+                start_x: 1,
+                start_y: 2,
+                width: 2,
+                height: 1,
+                map_colors: vec![34, 7],
+            }),
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+        let read = ClientboundMapItemDataPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(read.map_id, 300);
+        assert!(read.locked);
+        assert!(matches!(read.decorations, Some(decorations) if decorations.is_empty()));
+        let color_patch = read.color_patch.unwrap();
+        assert_eq!((color_patch.start_x, color_patch.start_y), (1, 2));
+        assert_eq!(
+            color_patch.to_rgba(),
+            [[255, 255, 255, 255], [67, 94, 29, 255]]
+        );
+
+        let packet = ClientboundMapItemDataPacket {
+            decorations: None,
+            color_patch: None,
+            ..packet
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+        let read = ClientboundMapItemDataPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert!(read.decorations.is_none());
+        assert!(read.color_patch.is_none());
+    }
+}
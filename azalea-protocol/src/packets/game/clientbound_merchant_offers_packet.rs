@@ -3,6 +3,7 @@ use azalea_core::Slot;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundMerchantOffersPacket {
     #[var]
     pub container_id: u32,
@@ -15,16 +16,126 @@ pub struct ClientboundMerchantOffersPacket {
     pub can_restock: bool,
 }
 
+/// A trade that a villager or wandering trader offers. Select it with
+/// [`ServerboundSelectTradePacket`](super::serverbound_select_trade_packet::ServerboundSelectTradePacket)
+/// by its index in the list of offers.
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MerchantOffer {
+    /// The first item that the trade costs, before demand and discounts are
+    /// applied. See [`MerchantOffer::cost_a`].
     pub base_cost_a: Slot,
     pub result: Slot,
+    /// The second item that the trade costs, or [`Slot::Empty`] if it only
+    /// costs one item.
     pub cost_b: Slot,
     pub out_of_stock: bool,
+    /// How many times the trade was used since the merchant last restocked.
     pub uses: u32,
     pub max_uses: u32,
+    /// The experience the merchant gets from the trade.
     pub xp: u32,
+    /// Added to the count of the first cost. This is negative when the
+    /// player has a discount, like from curing a zombie villager or having
+    /// Hero of the Village.
     pub special_price_diff: i32,
     pub price_multiplier: f32,
-    pub demand: u32,
+    /// How in demand the trade is. High demand makes the first cost more
+    /// expensive, and it can be negative.
+    pub demand: i32,
+}
+
+impl MerchantOffer {
+    /// The first item that the trade actually costs, with demand and
+    /// discounts applied like vanilla does.
+    ///
+    /// The count is clamped between 1 and 64, since we don't know every
+    /// item's max stack size. This is only wrong for trades that cost items
+    /// that stack to 16 or don't stack.
+    pub fn cost_a(&self) -> Slot {
+        let mut cost = self.base_cost_a.clone();
+        if let Slot::Present(item) = &mut cost {
+            let count = i32::from(item.count);
+            let demand_diff = ((count * self.demand) as f32 * self.price_multiplier).floor() as i32;
+            item.count = (count + demand_diff.max(0) + self.special_price_diff).clamp(1, 64) as u8;
+        }
+        cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_buf::{McBufReadable, McBufWritable};
+    use azalea_core::SlotData;
+    use std::io::Cursor;
+
+    fn emeralds(count: u8) -> Slot {
+        Slot::Present(SlotData {
+            id: 1,
+            count,
+            nbt: azalea_nbt::Tag::End,
+        })
+    }
+
+    fn count(slot: &Slot) -> u8 {
+        match slot {
+            Slot::Present(item) => item.count,
+            Slot::Empty => 0,
+        }
+    }
+
+    #[test]
+    fn test_read_write_merchant_offers() {
+        let packet = ClientboundMerchantOffersPacket {
+            container_id: 2,
+            offers: vec![MerchantOffer {
+                base_cost_a: emeralds(10),
+                result: emeralds(1),
+                cost_b: Slot::Empty,
+                out_of_stock: false,
+                uses: 3,
+                max_uses: 12,
+                xp: 2,
+                special_price_diff: -2,
+                price_multiplier: 0.05,
+                demand: -4,
+            }],
+            villager_level: 1,
+            villager_xp: 0,
+            show_progress: true,
+            can_restock: true,
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+        let read = ClientboundMerchantOffersPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(read.offers.len(), 1);
+        let offer = &read.offers[0];
+        assert_eq!((offer.uses, offer.max_uses, offer.demand), (3, 12, -4));
+        assert!(matches!(offer.cost_b, Slot::Empty));
+        // negative demand doesn't make it cheaper, but the discount does
+        assert_eq!(count(&offer.cost_a()), 8);
+    }
+
+    #[test]
+    fn test_cost_a_demand() {
+        let offer = MerchantOffer {
+            base_cost_a: emeralds(20),
+            result: emeralds(1),
+            cost_b: Slot::Empty,
+            out_of_stock: false,
+            uses: 0,
+            max_uses: 12,
+            xp: 2,
+            special_price_diff: 0,
+            price_multiplier: 0.05,
+            demand: 10,
+        };
+        assert_eq!(count(&offer.cost_a()), 30);
+        let offer = MerchantOffer {
+            special_price_diff: -40,
+            ..offer
+        };
+        assert_eq!(count(&offer.cost_a()), 1);
+    }
 }
@@ -4,6 +4,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 
 /// This packet is sent by the server when an entity moves less then 8 blocks.
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundMoveEntityPosRotPacket {
     #[var]
     pub entity_id: u32,
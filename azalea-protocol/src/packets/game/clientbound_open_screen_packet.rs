@@ -3,6 +3,7 @@ use azalea_chat::component::Component;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundOpenScreenPacket {
     #[var]
     pub container_id: u32,
@@ -2,7 +2,10 @@ use azalea_buf::McBuf;
 use azalea_core::BlockPos;
 use azalea_protocol_macros::ClientboundGamePacket;
 
+/// Sent after placing a sign, so the player can write on it. Answer it with
+/// [`ServerboundSignUpdatePacket`](super::serverbound_sign_update_packet::ServerboundSignUpdatePacket).
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundOpenSignEditorPacket {
     pub pos: BlockPos,
 }
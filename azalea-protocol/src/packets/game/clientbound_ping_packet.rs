@@ -1,7 +1,13 @@
 use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundGamePacket;
 
+/// The server wants us to send back a [`ServerboundPongPacket`] with the same
+/// id. Vanilla servers don't send these, but anticheats use them to tell
+/// which packets we've received.
+///
+/// [`ServerboundPongPacket`]: super::serverbound_pong_packet::ServerboundPongPacket
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundPingPacket {
     pub id: u32,
 }
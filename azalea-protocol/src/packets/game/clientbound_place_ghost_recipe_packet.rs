@@ -3,6 +3,7 @@ use azalea_core::ResourceLocation;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundPlaceGhostRecipePacket {
     pub container_id: u8,
     pub recipe: ResourceLocation,
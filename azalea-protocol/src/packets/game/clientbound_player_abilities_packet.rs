@@ -4,6 +4,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundPlayerAbilitiesPacket {
     pub flags: PlayerAbilitiesFlags,
     pub flying_speed: f32,
@@ -12,6 +13,7 @@ pub struct ClientboundPlayerAbilitiesPacket {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PlayerAbilitiesFlags {
     pub invulnerable: bool,
     pub flying: bool,
@@ -3,6 +3,7 @@ use azalea_crypto::{MessageSignature, SignedMessageHeader};
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundPlayerChatHeaderPacket {
     pub header: SignedMessageHeader,
     pub header_signature: MessageSignature,
@@ -1,4 +1,4 @@
-use azalea_buf::McBuf;
+use azalea_buf::{read_collection_with_max, BufReadError, McBuf};
 use azalea_chat::{
     component::Component,
     translatable_component::{StringOrComponent, TranslatableComponent},
@@ -6,15 +6,18 @@ use azalea_chat::{
 use azalea_core::BitSet;
 use azalea_crypto::{MessageSignature, SignedMessageHeader};
 use azalea_protocol_macros::ClientboundGamePacket;
+use std::io::Cursor;
 use uuid::Uuid;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundPlayerChatPacket {
     pub message: PlayerChatMessage,
     pub chat_type: ChatTypeBound,
 }
 
 #[derive(Copy, Clone, Debug, McBuf, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ChatType {
     Chat = 0,
     SayCommand = 1,
@@ -26,6 +29,7 @@ pub enum ChatType {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChatTypeBound {
     pub chat_type: ChatType,
     pub name: Component,
@@ -33,6 +37,7 @@ pub struct ChatTypeBound {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PlayerChatMessage {
     pub signed_header: SignedMessageHeader,
     pub header_signature: MessageSignature,
@@ -42,10 +47,12 @@ pub struct PlayerChatMessage {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SignedMessageBody {
     pub content: ChatMessageContent,
     pub timestamp: u64,
     pub salt: u64,
+    #[mcbuf(read_with = "read_last_seen")]
     pub last_seen: Vec<LastSeenMessagesEntry>,
 }
 
@@ -117,19 +124,30 @@ impl ChatType {
     }
 }
 
+/// The most messages that the client remembers having seen.
+pub const MAX_LAST_SEEN_MESSAGES: usize = 5;
+
+fn read_last_seen(buf: &mut Cursor<&[u8]>) -> Result<Vec<LastSeenMessagesEntry>, BufReadError> {
+    read_collection_with_max(buf, MAX_LAST_SEEN_MESSAGES)
+}
+
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LastSeenMessagesEntry {
     pub profile_id: Uuid,
     pub last_signature: MessageSignature,
 }
 
 #[derive(Clone, Debug, McBuf, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LastSeenMessagesUpdate {
+    #[mcbuf(read_with = "read_last_seen")]
     pub last_seen: Vec<LastSeenMessagesEntry>,
     pub last_received: Option<LastSeenMessagesEntry>,
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChatMessageContent {
     pub plain: String,
     /// Only sent if the decorated message is different than the plain.
@@ -137,6 +155,7 @@ pub struct ChatMessageContent {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FilterMask {
     PassThrough,
     FullyFiltered,
@@ -7,11 +7,13 @@ use std::io::{Cursor, Write};
 use uuid::Uuid;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundPlayerInfoPacket {
     pub action: Action,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Action {
     AddPlayer(Vec<AddPlayer>),
     UpdateGameMode(Vec<UpdateGameMode>),
@@ -21,6 +23,7 @@ pub enum Action {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PlayerProperty {
     pub name: String,
     pub value: String,
@@ -28,6 +31,7 @@ pub struct PlayerProperty {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AddPlayer {
     pub uuid: Uuid,
     pub name: String,
@@ -41,6 +45,7 @@ pub struct AddPlayer {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct UpdateGameMode {
     pub uuid: Uuid,
     #[var]
@@ -48,6 +53,7 @@ pub struct UpdateGameMode {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct UpdateLatency {
     pub uuid: Uuid,
     #[var]
@@ -55,11 +61,13 @@ pub struct UpdateLatency {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct UpdateDisplayName {
     pub uuid: Uuid,
     pub display_name: Option<Component>,
 }
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RemovePlayer {
     pub uuid: Uuid,
 }
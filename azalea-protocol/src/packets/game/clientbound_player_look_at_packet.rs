@@ -2,6 +2,7 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundPlayerLookAtPacket {
     pub from_anchor: Anchor,
     pub x: f64,
@@ -11,12 +12,14 @@ pub struct ClientboundPlayerLookAtPacket {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Anchor {
     Feet = 0,
     Eyes = 1,
 }
 
 #[derive(McBuf, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AtEntity {
     #[var]
     pub entity: u32,
@@ -6,6 +6,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundRecipePacket {
     pub action: State,
     pub settings: RecipeBookSettings,
@@ -55,6 +56,7 @@ impl McBufReadable for ClientboundRecipePacket {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RecipeBookSettings {
     pub gui_open: bool,
     pub filtering_craftable: bool,
@@ -70,6 +72,7 @@ pub struct RecipeBookSettings {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum State {
     Init { to_highlight: Vec<ResourceLocation> },
     Add,
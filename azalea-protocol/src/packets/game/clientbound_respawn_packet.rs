@@ -3,6 +3,7 @@ use azalea_core::{GameType, GlobalPos, OptionalGameType, ResourceLocation};
 use azalea_protocol_macros::ClientboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundRespawnPacket {
     pub dimension_type: ResourceLocation,
     pub dimension: ResourceLocation,
@@ -7,6 +7,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSectionBlocksUpdatePacket {
     pub section_pos: ChunkSectionPos,
     pub suppress_light_updates: bool,
@@ -14,6 +15,7 @@ pub struct ClientboundSectionBlocksUpdatePacket {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BlockStateWithPosition {
     pub pos: ChunkSectionBlockPos,
     pub state: BlockState,
@@ -3,6 +3,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 
 /// Sent to change the player's slot selection.
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSetCarriedItemPacket {
     pub slot: u8,
 }
@@ -3,6 +3,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use azalea_world::entity::EntityMetadata;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSetEntityDataPacket {
     #[var]
     pub id: u32,
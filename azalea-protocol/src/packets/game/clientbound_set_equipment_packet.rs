@@ -5,6 +5,7 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::Cursor;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSetEquipmentPacket {
     #[var]
     pub entity: i32,
@@ -12,6 +13,7 @@ pub struct ClientboundSetEquipmentPacket {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct EquipmentSlots {
     pub slots: Vec<(EquipmentSlot, Slot)>,
 }
@@ -55,6 +57,7 @@ impl McBufWritable for EquipmentSlots {
 }
 
 #[derive(Clone, Debug, Copy, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum EquipmentSlot {
     MainHand = 0,
     OffHand = 1,
@@ -4,12 +4,14 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSetObjectivePacket {
     pub objective_name: String,
     pub method: Method,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Method {
     Add(DisplayInfo),
     Remove,
@@ -47,12 +49,14 @@ impl McBufWritable for Method {
 }
 
 #[derive(McBuf, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DisplayInfo {
     pub display_name: Component,
     pub render_type: RenderType,
 }
 
 #[derive(McBuf, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RenderType {
     Integer,
     Hearts,
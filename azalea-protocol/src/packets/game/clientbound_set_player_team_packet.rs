@@ -4,12 +4,14 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSetPlayerTeamPacket {
     pub name: String,
     pub method: Method,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Method {
     Add((Parameters, PlayerList)),
     Remove,
@@ -60,6 +62,7 @@ impl McBufWritable for Method {
 }
 
 #[derive(McBuf, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Parameters {
     pub display_name: Component,
     pub options: u8,
@@ -6,6 +6,7 @@ use std::{
 };
 
 #[derive(Clone, Debug, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSetScorePacket {
     pub owner: String,
     pub method: Method,
@@ -55,6 +56,7 @@ impl McBufWritable for ClientboundSetScorePacket {
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Method {
     Change { score: u32 },
     Remove,
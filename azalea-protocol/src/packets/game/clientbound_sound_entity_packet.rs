@@ -1,8 +1,12 @@
-use super::clientbound_sound_packet::SoundSource;
+use super::clientbound_sound_packet::{sound_name, SoundSource};
 use azalea_buf::McBuf;
+use azalea_core::ResourceLocation;
 use azalea_protocol_macros::ClientboundGamePacket;
 
+/// A sound from the sound event registry that follows an entity.
+
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSoundEntityPacket {
     pub sound: azalea_registry::SoundEvent,
     pub source: SoundSource,
@@ -12,3 +16,10 @@ pub struct ClientboundSoundEntityPacket {
     pub pitch: f32,
     pub seed: u64,
 }
+
+impl ClientboundSoundEntityPacket {
+    /// The name of the sound, like `minecraft:entity.warden.heartbeat`.
+    pub fn name(&self) -> ResourceLocation {
+        sound_name(self.sound)
+    }
+}
@@ -1,19 +1,41 @@
 use azalea_buf::McBuf;
+use azalea_core::ResourceLocation;
 use azalea_protocol_macros::ClientboundGamePacket;
 
+/// A sound from the sound event registry played at a position. Sounds that
+/// aren't in the registry are sent with
+/// [`ClientboundCustomSoundPacket`](super::clientbound_custom_sound_packet::ClientboundCustomSoundPacket)
+/// instead.
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundSoundPacket {
     pub sound: azalea_registry::SoundEvent,
     pub source: SoundSource,
+    /// x position multiplied by 8
     pub x: i32,
+    /// y position multiplied by 8
     pub y: i32,
+    /// z position multiplied by 8
     pub z: i32,
     pub volume: f32,
     pub pitch: f32,
     pub seed: u64,
 }
 
+impl ClientboundSoundPacket {
+    /// The name of the sound, like `minecraft:entity.warden.heartbeat`.
+    pub fn name(&self) -> ResourceLocation {
+        sound_name(self.sound)
+    }
+}
+
+/// The resource location of a sound event from the registry.
+pub(crate) fn sound_name(sound: azalea_registry::SoundEvent) -> ResourceLocation {
+    ResourceLocation::new(&sound.to_string()).expect("Registry ids are valid resource locations")
+}
+
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SoundSource {
     Master = 0,
     Music = 1,
@@ -26,3 +48,31 @@ pub enum SoundSource {
     Ambient = 8,
     Voice = 9,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_buf::{McBufReadable, McBufWritable};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_sound_name() {
+        let packet = ClientboundSoundPacket {
+            sound: azalea_registry::SoundEvent::EventRaidHorn,
+            source: SoundSource::Hostile,
+            x: 8,
+            y: 512,
+            z: -8,
+            volume: 64.,
+            pitch: 1.,
+            seed: 0,
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+        let read = ClientboundSoundPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(
+            read.name(),
+            ResourceLocation::new("minecraft:event.raid.horn").unwrap()
+        );
+    }
+}
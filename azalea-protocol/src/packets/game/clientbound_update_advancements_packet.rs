@@ -6,24 +6,36 @@ use std::collections::HashMap;
 use std::io::Cursor;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundUpdateAdvancementsPacket {
+    /// Whether the client should forget every advancement it knew about
+    /// before adding these. This is true when the player joins or when the
+    /// advancements are reloaded.
     pub reset: bool,
     pub added: HashMap<ResourceLocation, Advancement>,
     pub removed: Vec<ResourceLocation>,
+    /// The progress of advancements that changed, by advancement id.
     pub progress: HashMap<ResourceLocation, AdvancementProgress>,
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Advancement {
-    parent_id: Option<ResourceLocation>,
-    display: Option<DisplayInfo>,
+    pub parent_id: Option<ResourceLocation>,
+    /// How the advancement is shown in the advancements screen. Recipe
+    /// advancements and other hidden ones don't have this.
+    pub display: Option<DisplayInfo>,
     // rewards: AdvancementRewards.EMPTY,
-    criteria: HashMap<ResourceLocation, Criterion>,
-    requirements: Vec<Vec<String>>,
+    /// The criteria by name, like `has_the_recipe`.
+    pub criteria: HashMap<String, Criterion>,
+    /// The names of the criteria that have to be done for the advancement
+    /// to be done. At least one criterion from every list has to be done.
+    pub requirements: Vec<Vec<String>>,
     // requirements_strategy: RequirementsStrategy.AND
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DisplayInfo {
     pub title: Component,
     pub description: Component,
@@ -97,6 +109,7 @@ impl azalea_buf::McBufReadable for DisplayInfo {
 }
 
 #[derive(Clone, Debug, Copy, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FrameType {
     Task = 0,
     Challenge = 1,
@@ -105,66 +118,129 @@ pub enum FrameType {
 
 // nothing is written here
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Criterion {}
 
-pub type AdvancementProgress = HashMap<ResourceLocation, CriterionProgress>;
+/// How far the player is in an advancement.
+#[derive(Clone, Debug, Default, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AdvancementProgress {
+    /// The progress of each criterion, by name.
+    pub criteria: HashMap<String, CriterionProgress>,
+}
+
+impl AdvancementProgress {
+    /// Whether the criterion with the given name was done.
+    pub fn is_criterion_done(&self, criterion: &str) -> bool {
+        self.criteria
+            .get(criterion)
+            .is_some_and(CriterionProgress::is_done)
+    }
+
+    /// Whether the advancement is done, which is when at least one criterion
+    /// from every requirement is done.
+    pub fn is_done(&self, advancement: &Advancement) -> bool {
+        !advancement.requirements.is_empty()
+            && advancement.requirements.iter().all(|requirement| {
+                requirement
+                    .iter()
+                    .any(|criterion| self.is_criterion_done(criterion))
+            })
+    }
+}
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CriterionProgress {
-    date: Option<u64>,
+    /// When the criterion was done, in milliseconds since the epoch, or
+    /// `None` if it isn't done.
+    pub date: Option<u64>,
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use azalea_buf::{McBufReadable, McBufWritable};
-//     use azalea_core::ResourceLocation;
-//     use azalea_protocol_macros::ClientboundGamePacket;
-//     use std::io::Cursor;
-
-//     #[test]
-//     fn test() {
-//         let mut buf = Cursor::new(Vec::new());
-//         let packet = ClientboundUpdateAdvancementsPacket {
-//             reset: true,
-//             added: [(
-//                 ResourceLocation::new("minecraft:test").unwrap(),
-//                 Advancement {
-//                     parent_id: None,
-//                     display: Some(DisplayInfo {
-//                         title: Component::from("title".to_string()),
-//                         description: Component::from("description".to_string()),
-//                         icon: Slot::Empty,
-//                         frame: FrameType::Task,
-//                         show_toast: true,
-//                         hidden: false,
-//                         background: None,
-//                         x: 0.0,
-//                         y: 0.0,
-//                     }),
-//                     criteria: HashMap::new(),
-//                     requirements: Vec::new(),
-//                 },
-//             )]
-//             .into_iter()
-//             .collect(),
-//             removed: vec![ResourceLocation::new("minecraft:test2").unwrap()],
-//             progress: [(
-//                 ResourceLocation::new("minecraft:test3").unwrap(),
-//                 [(
-//                     ResourceLocation::new("minecraft:test4").unwrap(),
-//                     CriterionProgress {
-//                         date: Some(123456789),
-//                     },
-//                 )]
-//                 .into_iter()
-//                 .collect(),
-//             )]
-//             .into_iter()
-//             .collect(),
-//         };
-//         packet.write_into(&mut buf).unwrap();
-//         let packet = ClientboundUpdateAdvancementsPacket::read_from(&mut buf).unwrap();
-//         assert_eq!(packet.reset, true);
-//     }
-// }
+impl CriterionProgress {
+    pub fn is_done(&self) -> bool {
+        self.date.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_buf::{McBufReadable, McBufVarWritable, McBufWritable};
+
+    fn progress(done: &[&str]) -> AdvancementProgress {
+        AdvancementProgress {
+            criteria: done
+                .iter()
+                .map(|criterion| (criterion.to_string(), CriterionProgress { date: Some(0) }))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_read_advancements() {
+        // components can't be written yet, so the packet is put together by
+        // hand
+        let id = ResourceLocation::new("minecraft:story/mine_stone").unwrap();
+        let mut buf = Vec::new();
+        true.write_into(&mut buf).unwrap();
+        1u32.var_write_into(&mut buf).unwrap();
+        id.write_into(&mut buf).unwrap();
+        Some(ResourceLocation::new("minecraft:story/root").unwrap())
+            .write_into(&mut buf)
+            .unwrap();
+        true.write_into(&mut buf).unwrap();
+        r#"{"text":"Stone Age"}"#.write_into(&mut buf).unwrap();
+        r#"{"text":"Mine Stone"}"#.write_into(&mut buf).unwrap();
+        Slot::Empty.write_into(&mut buf).unwrap();
+        FrameType::Goal.write_into(&mut buf).unwrap();
+        // show toast
+        0b10u32.write_into(&mut buf).unwrap();
+        1f32.write_into(&mut buf).unwrap();
+        0f32.write_into(&mut buf).unwrap();
+        HashMap::from([("get_stone".to_string(), Criterion {})])
+            .write_into(&mut buf)
+            .unwrap();
+        vec![vec!["get_stone".to_string()]]
+            .write_into(&mut buf)
+            .unwrap();
+        // removed
+        Vec::<ResourceLocation>::new().write_into(&mut buf).unwrap();
+        HashMap::from([(id.clone(), progress(&["get_stone"]))])
+            .write_into(&mut buf)
+            .unwrap();
+
+        let packet =
+            ClientboundUpdateAdvancementsPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert!(packet.reset);
+        let advancement = &packet.added[&id];
+        let display = advancement.display.as_ref().unwrap();
+        assert_eq!(display.title.to_string(), "Stone Age");
+        assert!(matches!(display.frame, FrameType::Goal));
+        assert!(display.show_toast && !display.hidden);
+        assert!(display.background.is_none());
+        assert!(advancement.criteria.contains_key("get_stone"));
+        assert!(packet.progress[&id].is_done(advancement));
+    }
+
+    #[test]
+    fn test_advancement_progress_is_done() {
+        let advancement = Advancement {
+            parent_id: None,
+            display: None,
+            criteria: HashMap::new(),
+            requirements: vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["c".to_string()],
+            ],
+        };
+        assert!(progress(&["b", "c"]).is_done(&advancement));
+        assert!(!progress(&["a", "b"]).is_done(&advancement));
+        let not_done = AdvancementProgress {
+            criteria: [("c".to_string(), CriterionProgress { date: None })]
+                .into_iter()
+                .collect(),
+        };
+        assert!(!not_done.is_criterion_done("c"));
+    }
+}
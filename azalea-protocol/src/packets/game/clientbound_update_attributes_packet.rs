@@ -1,11 +1,10 @@
-use azalea_buf::{BufReadError, McBuf};
-use azalea_buf::{McBufReadable, McBufWritable};
+use azalea_buf::McBuf;
 use azalea_core::ResourceLocation;
 use azalea_protocol_macros::ClientboundGamePacket;
-use std::io::{Cursor, Write};
 use uuid::Uuid;
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundUpdateAttributesPacket {
     #[var]
     pub entity_id: u32,
@@ -13,6 +12,7 @@ pub struct ClientboundUpdateAttributesPacket {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AttributeSnapshot {
     pub attribute: ResourceLocation,
     pub base: f64,
@@ -20,33 +20,18 @@ pub struct AttributeSnapshot {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Modifier {
     pub uuid: Uuid,
     pub amount: f64,
-    pub operation: u8,
+    pub operation: Operation,
 }
 
-#[derive(Clone, Debug, Copy)]
-enum Operation {
+#[derive(Clone, Debug, Copy, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[mcbuf(tag = "u8")]
+pub enum Operation {
     Addition = 0,
     MultiplyBase = 1,
     MultiplyTotal = 2,
 }
-
-impl McBufReadable for Operation {
-    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        match u8::read_from(buf)? {
-            0 => Ok(Operation::Addition),
-            1 => Ok(Operation::MultiplyBase),
-            2 => Ok(Operation::MultiplyTotal),
-            id => Err(BufReadError::UnexpectedEnumVariant { id: id.into() }),
-        }
-    }
-}
-
-impl McBufWritable for Operation {
-    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
-        (*self as u8).write_into(buf)?;
-        Ok(())
-    }
-}
@@ -6,31 +6,48 @@ use azalea_protocol_macros::ClientboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundUpdateRecipesPacket {
     pub recipes: Vec<Recipe>,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Recipe {
     pub identifier: ResourceLocation,
     pub data: RecipeData,
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ShapelessRecipe {
     /// Used to group similar recipes together in the recipe book.
     /// Tag is present in recipe JSON
-    group: String,
-    ingredients: Vec<Ingredient>,
-    result: Slot,
+    pub group: String,
+    pub ingredients: Vec<Ingredient>,
+    pub result: Slot,
 }
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ShapedRecipe {
-    width: usize,
-    height: usize,
-    group: String,
-    ingredients: Vec<Ingredient>,
-    result: Slot,
+    pub width: usize,
+    pub height: usize,
+    pub group: String,
+    /// The ingredients row by row, so there's `width * height` of them.
+    /// Empty spaces in the pattern are ingredients with nothing allowed.
+    pub ingredients: Vec<Ingredient>,
+    pub result: Slot,
+}
+
+impl ShapedRecipe {
+    /// The ingredient at the position in the pattern, where 0, 0 is the top
+    /// left.
+    pub fn ingredient_at(&self, x: usize, y: usize) -> Option<&Ingredient> {
+        if x >= self.width {
+            return None;
+        }
+        self.ingredients.get(y * self.width + x)
+    }
 }
 
 impl McBufWritable for ShapedRecipe {
@@ -48,11 +65,11 @@ impl McBufWritable for ShapedRecipe {
 }
 impl McBufReadable for ShapedRecipe {
     fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        let width = u32::var_read_from(buf)?.try_into().unwrap();
-        let height = u32::var_read_from(buf)?.try_into().unwrap();
+        let width: usize = u32::var_read_from(buf)?.try_into().unwrap();
+        let height: usize = u32::var_read_from(buf)?.try_into().unwrap();
         let group = String::read_from(buf)?;
-        let mut ingredients = Vec::with_capacity(width * height);
-        for _ in 0..width * height {
+        let mut ingredients = Vec::new();
+        for _ in 0..width.saturating_mul(height) {
             ingredients.push(Ingredient::read_from(buf)?);
         }
         let result = Slot::read_from(buf)?;
@@ -68,28 +85,33 @@ impl McBufReadable for ShapedRecipe {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CookingRecipe {
-    group: String,
-    ingredient: Ingredient,
-    result: Slot,
-    experience: f32,
+    pub group: String,
+    pub ingredient: Ingredient,
+    pub result: Slot,
+    pub experience: f32,
+    /// How long it takes to cook, in ticks.
     #[var]
-    cooking_time: u32,
+    pub cooking_time: u32,
 }
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StoneCuttingRecipe {
-    group: String,
-    ingredient: Ingredient,
-    result: Slot,
+    pub group: String,
+    pub ingredient: Ingredient,
+    pub result: Slot,
 }
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SmithingRecipe {
-    base: Ingredient,
-    addition: Ingredient,
-    result: Slot,
+    pub base: Ingredient,
+    pub addition: Ingredient,
+    pub result: Slot,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RecipeData {
     CraftingShapeless(ShapelessRecipe),
     CraftingShaped(ShapedRecipe),
@@ -115,14 +137,82 @@ pub enum RecipeData {
     Smithing(SmithingRecipe),
 }
 
+/// One slot of a recipe, which any of the allowed items can go in.
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Ingredient {
     pub allowed: Vec<Slot>,
 }
 
+impl Ingredient {
+    /// Whether nothing goes in this slot, like the gaps in a shaped recipe.
+    pub fn is_empty(&self) -> bool {
+        self.allowed.iter().all(|slot| matches!(slot, Slot::Empty))
+    }
+}
+
+impl RecipeData {
+    /// The id of the recipe serializer, which is sent before the recipe.
+    pub fn serializer(&self) -> &'static str {
+        match self {
+            RecipeData::CraftingShapeless(_) => "minecraft:crafting_shapeless",
+            RecipeData::CraftingShaped(_) => "minecraft:crafting_shaped",
+            RecipeData::CraftingSpecialArmorDye => "minecraft:crafting_special_armordye",
+            RecipeData::CraftingSpecialBookCloning => "minecraft:crafting_special_bookcloning",
+            RecipeData::CraftingSpecialMapCloning => "minecraft:crafting_special_mapcloning",
+            RecipeData::CraftingSpecialMapExtending => "minecraft:crafting_special_mapextending",
+            RecipeData::CraftingSpecialFireworkRocket => {
+                "minecraft:crafting_special_firework_rocket"
+            }
+            RecipeData::CraftingSpecialFireworkStar => "minecraft:crafting_special_firework_star",
+            RecipeData::CraftingSpecialFireworkStarFade => {
+                "minecraft:crafting_special_firework_star_fade"
+            }
+            RecipeData::CraftingSpecialRepairItem => "minecraft:crafting_special_repairitem",
+            RecipeData::CraftingSpecialTippedArrow => "minecraft:crafting_special_tippedarrow",
+            RecipeData::CraftingSpecialBannerDuplicate => {
+                "minecraft:crafting_special_bannerduplicate"
+            }
+            RecipeData::CraftingSpecialBannerAddPattern => {
+                "minecraft:crafting_special_banneraddpattern"
+            }
+            RecipeData::CraftingSpecialShieldDecoration => {
+                "minecraft:crafting_special_shielddecoration"
+            }
+            RecipeData::CraftingSpecialShulkerBoxColoring => {
+                "minecraft:crafting_special_shulkerboxcoloring"
+            }
+            RecipeData::CraftingSpecialSuspiciousStew => {
+                "minecraft:crafting_special_suspiciousstew"
+            }
+            RecipeData::Smelting(_) => "minecraft:smelting",
+            RecipeData::Blasting(_) => "minecraft:blasting",
+            RecipeData::Smoking(_) => "minecraft:smoking",
+            RecipeData::CampfireCooking(_) => "minecraft:campfire_cooking",
+            RecipeData::Stonecutting(_) => "minecraft:stonecutting",
+            RecipeData::Smithing(_) => "minecraft:smithing",
+        }
+    }
+}
+
 impl McBufWritable for Recipe {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        todo!()
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        ResourceLocation::new(self.data.serializer())
+            .unwrap()
+            .write_into(buf)?;
+        self.identifier.write_into(buf)?;
+        match &self.data {
+            RecipeData::CraftingShapeless(recipe) => recipe.write_into(buf),
+            RecipeData::CraftingShaped(recipe) => recipe.write_into(buf),
+            RecipeData::Smelting(recipe)
+            | RecipeData::Blasting(recipe)
+            | RecipeData::Smoking(recipe)
+            | RecipeData::CampfireCooking(recipe) => recipe.write_into(buf),
+            RecipeData::Stonecutting(recipe) => recipe.write_into(buf),
+            RecipeData::Smithing(recipe) => recipe.write_into(buf),
+            // special recipes don't have any data
+            _ => Ok(()),
+        }
     }
 }
 
@@ -131,85 +221,56 @@ impl McBufReadable for Recipe {
         let recipe_type = ResourceLocation::read_from(buf)?;
         let identifier = ResourceLocation::read_from(buf)?;
 
-        // rust doesn't let us match ResourceLocation so we have to do a big
-        // if-else chain :(
-        let data = if recipe_type == ResourceLocation::new("minecraft:crafting_shapeless").unwrap()
-        {
-            RecipeData::CraftingShapeless(ShapelessRecipe::read_from(buf)?)
-        } else if recipe_type == ResourceLocation::new("minecraft:crafting_shaped").unwrap() {
-            RecipeData::CraftingShaped(ShapedRecipe::read_from(buf)?)
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_armordye").unwrap()
-        {
-            RecipeData::CraftingSpecialArmorDye
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_bookcloning").unwrap()
-        {
-            RecipeData::CraftingSpecialBookCloning
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_mapcloning").unwrap()
-        {
-            RecipeData::CraftingSpecialMapCloning
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_mapextending").unwrap()
-        {
-            RecipeData::CraftingSpecialMapExtending
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_firework_rocket").unwrap()
-        {
-            RecipeData::CraftingSpecialFireworkRocket
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_firework_star").unwrap()
-        {
-            RecipeData::CraftingSpecialFireworkStar
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_firework_star_fade").unwrap()
-        {
-            RecipeData::CraftingSpecialFireworkStarFade
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_repairitem").unwrap()
-        {
-            RecipeData::CraftingSpecialRepairItem
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_tippedarrow").unwrap()
-        {
-            RecipeData::CraftingSpecialTippedArrow
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_bannerduplicate").unwrap()
-        {
-            RecipeData::CraftingSpecialBannerDuplicate
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_banneraddpattern").unwrap()
-        {
-            RecipeData::CraftingSpecialBannerAddPattern
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_shielddecoration").unwrap()
-        {
-            RecipeData::CraftingSpecialShieldDecoration
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_shulkerboxcoloring").unwrap()
-        {
-            RecipeData::CraftingSpecialShulkerBoxColoring
-        } else if recipe_type
-            == ResourceLocation::new("minecraft:crafting_special_suspiciousstew").unwrap()
-        {
-            RecipeData::CraftingSpecialSuspiciousStew
-        } else if recipe_type == ResourceLocation::new("minecraft:smelting").unwrap() {
-            RecipeData::Smelting(CookingRecipe::read_from(buf)?)
-        } else if recipe_type == ResourceLocation::new("minecraft:blasting").unwrap() {
-            RecipeData::Blasting(CookingRecipe::read_from(buf)?)
-        } else if recipe_type == ResourceLocation::new("minecraft:smoking").unwrap() {
-            RecipeData::Smoking(CookingRecipe::read_from(buf)?)
-        } else if recipe_type == ResourceLocation::new("minecraft:campfire_cooking").unwrap() {
-            RecipeData::CampfireCooking(CookingRecipe::read_from(buf)?)
-        } else if recipe_type == ResourceLocation::new("minecraft:stonecutting").unwrap() {
-            RecipeData::Stonecutting(StoneCuttingRecipe::read_from(buf)?)
-        } else if recipe_type == ResourceLocation::new("minecraft:smithing").unwrap() {
-            RecipeData::Smithing(SmithingRecipe::read_from(buf)?)
-        } else {
-            return Err(BufReadError::UnexpectedStringEnumVariant {
-                id: recipe_type.to_string(),
-            });
+        let data = match recipe_type.to_string().as_str() {
+            "minecraft:crafting_shapeless" => {
+                RecipeData::CraftingShapeless(ShapelessRecipe::read_from(buf)?)
+            }
+            "minecraft:crafting_shaped" => {
+                RecipeData::CraftingShaped(ShapedRecipe::read_from(buf)?)
+            }
+            "minecraft:crafting_special_armordye" => RecipeData::CraftingSpecialArmorDye,
+            "minecraft:crafting_special_bookcloning" => RecipeData::CraftingSpecialBookCloning,
+            "minecraft:crafting_special_mapcloning" => RecipeData::CraftingSpecialMapCloning,
+            "minecraft:crafting_special_mapextending" => RecipeData::CraftingSpecialMapExtending,
+            "minecraft:crafting_special_firework_rocket" => {
+                RecipeData::CraftingSpecialFireworkRocket
+            }
+            "minecraft:crafting_special_firework_star" => RecipeData::CraftingSpecialFireworkStar,
+            "minecraft:crafting_special_firework_star_fade" => {
+                RecipeData::CraftingSpecialFireworkStarFade
+            }
+            "minecraft:crafting_special_repairitem" => RecipeData::CraftingSpecialRepairItem,
+            "minecraft:crafting_special_tippedarrow" => RecipeData::CraftingSpecialTippedArrow,
+            "minecraft:crafting_special_bannerduplicate" => {
+                RecipeData::CraftingSpecialBannerDuplicate
+            }
+            "minecraft:crafting_special_banneraddpattern" => {
+                RecipeData::CraftingSpecialBannerAddPattern
+            }
+            "minecraft:crafting_special_shielddecoration" => {
+                RecipeData::CraftingSpecialShieldDecoration
+            }
+            "minecraft:crafting_special_shulkerboxcoloring" => {
+                RecipeData::CraftingSpecialShulkerBoxColoring
+            }
+            "minecraft:crafting_special_suspiciousstew" => {
+                RecipeData::CraftingSpecialSuspiciousStew
+            }
+            "minecraft:smelting" => RecipeData::Smelting(CookingRecipe::read_from(buf)?),
+            "minecraft:blasting" => RecipeData::Blasting(CookingRecipe::read_from(buf)?),
+            "minecraft:smoking" => RecipeData::Smoking(CookingRecipe::read_from(buf)?),
+            "minecraft:campfire_cooking" => {
+                RecipeData::CampfireCooking(CookingRecipe::read_from(buf)?)
+            }
+            "minecraft:stonecutting" => {
+                RecipeData::Stonecutting(StoneCuttingRecipe::read_from(buf)?)
+            }
+            "minecraft:smithing" => RecipeData::Smithing(SmithingRecipe::read_from(buf)?),
+            _ => {
+                return Err(BufReadError::UnexpectedStringEnumVariant {
+                    id: recipe_type.to_string(),
+                });
+            }
         };
 
         let recipe = Recipe { identifier, data };
@@ -217,3 +278,86 @@ impl McBufReadable for Recipe {
         Ok(recipe)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_core::SlotData;
+
+    fn item(id: i32) -> Slot {
+        Slot::Present(SlotData {
+            id,
+            count: 1,
+            nbt: azalea_nbt::Tag::End,
+        })
+    }
+
+    fn ingredient(allowed: &[Slot]) -> Ingredient {
+        Ingredient {
+            allowed: allowed.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_read_write_recipes() {
+        let packet = ClientboundUpdateRecipesPacket {
+            recipes: vec![
+                Recipe {
+                    identifier: ResourceLocation::new("minecraft:stick").unwrap(),
+                    data: RecipeData::CraftingShaped(ShapedRecipe {
+                        width: 2,
+                        height: 2,
+                        group: "sticks".to_string(),
+                        ingredients: vec![
+                            ingredient(&[item(23)]),
+                            ingredient(&[]),
+                            ingredient(&[item(23)]),
+                            ingredient(&[]),
+                        ],
+                        result: item(800),
+                    }),
+                },
+                Recipe {
+                    identifier: ResourceLocation::new("minecraft:armor_dye").unwrap(),
+                    data: RecipeData::CraftingSpecialArmorDye,
+                },
+                Recipe {
+                    identifier: ResourceLocation::new("minecraft:glass").unwrap(),
+                    data: RecipeData::Smelting(CookingRecipe {
+                        group: String::new(),
+                        ingredient: ingredient(&[item(30), item(31)]),
+                        result: item(200),
+                        experience: 0.1,
+                        cooking_time: 200,
+                    }),
+                },
+            ],
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+        let mut cursor = Cursor::new(&buf[..]);
+        let read = ClientboundUpdateRecipesPacket::read_from(&mut cursor).unwrap();
+        assert_eq!(cursor.position() as usize, buf.len());
+        assert_eq!(read.recipes.len(), 3);
+
+        match &read.recipes[0].data {
+            RecipeData::CraftingShaped(recipe) => {
+                assert!(!recipe.ingredient_at(0, 1).unwrap().is_empty());
+                assert!(recipe.ingredient_at(1, 1).unwrap().is_empty());
+                assert!(recipe.ingredient_at(2, 0).is_none());
+            }
+            data => panic!("expected a shaped recipe, got {data:?}"),
+        }
+        assert!(matches!(
+            read.recipes[1].data,
+            RecipeData::CraftingSpecialArmorDye
+        ));
+        match &read.recipes[2].data {
+            RecipeData::Smelting(recipe) => {
+                assert_eq!(recipe.cooking_time, 200);
+                assert_eq!(recipe.ingredient.allowed.len(), 2);
+            }
+            data => panic!("expected a smelting recipe, got {data:?}"),
+        }
+    }
+}
@@ -7,31 +7,31 @@ use std::ops::Deref;
 use std::{collections::HashMap, io::Write};
 
 #[derive(Clone, Debug, McBuf, ClientboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundUpdateTagsPacket {
     pub tags: TagMap,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Tags {
     pub name: ResourceLocation,
     pub elements: Vec<i32>,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TagMap(HashMap<ResourceLocation, Vec<Tags>>);
 
 impl McBufReadable for TagMap {
     fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        let length = u32::var_read_from(buf)? as usize;
-        let mut data = HashMap::with_capacity(length);
+        let length = u32::var_read_from(buf)?;
+        // the lengths aren't used as the capacity so we can't get exploited
+        // into allocating a bunch
+        let mut data = HashMap::new();
         for _ in 0..length {
             let tag_type = ResourceLocation::read_from(buf)?;
-            let tags_count = i32::var_read_from(buf)? as usize;
-            let mut tags_vec = Vec::with_capacity(tags_count);
-            for _ in 0..tags_count {
-                let tags = Tags::read_from(buf)?;
-                tags_vec.push(tags);
-            }
+            let tags_vec = Vec::<Tags>::read_from(buf)?;
             data.insert(tag_type, tags_vec);
         }
         Ok(TagMap(data))
@@ -40,7 +40,7 @@ impl McBufReadable for TagMap {
 
 impl McBufWritable for TagMap {
     fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
-        (self.len() as u32).write_into(buf)?;
+        (self.len() as u32).var_write_into(buf)?;
         for (k, v) in &self.0 {
             k.write_into(buf)?;
             v.write_into(buf)?;
@@ -3,8 +3,9 @@ use azalea_core::BlockPos;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundBlockEntityTagQuery {
     #[var]
-    pub transaction_id: i32,
+    pub transaction_id: u32,
     pub pos: BlockPos,
 }
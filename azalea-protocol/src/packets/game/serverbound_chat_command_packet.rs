@@ -1,22 +1,52 @@
-use azalea_buf::McBuf;
+use azalea_buf::{read_collection_with_max, read_string_with_max, BufReadError, McBuf};
 use azalea_crypto::MessageSignature;
 use azalea_protocol_macros::ServerboundGamePacket;
+use std::io::Cursor;
 
-use super::clientbound_player_chat_packet::LastSeenMessagesUpdate;
+use super::{
+    clientbound_player_chat_packet::LastSeenMessagesUpdate,
+    serverbound_chat_packet::MAX_MESSAGE_LENGTH,
+};
+
+/// The most arguments that can be signed in a command.
+pub const MAX_ARGUMENT_SIGNATURES: usize = 8;
+/// The longest the name of a signed argument can be.
+pub const MAX_ARGUMENT_NAME_LENGTH: u32 = 16;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundChatCommandPacket {
+    /// The command without the slash. It can't be longer than
+    /// [`MAX_MESSAGE_LENGTH`].
+    #[mcbuf(read_with = "read_command")]
     pub command: String,
     // TODO: Choose a real timestamp type
     pub timestamp: u64,
     pub salt: u64,
+    #[mcbuf(read_with = "read_argument_signatures")]
     pub argument_signatures: Vec<ArgumentSignature>,
     pub signed_preview: bool,
     pub last_seen_messages: LastSeenMessagesUpdate,
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ArgumentSignature {
+    #[mcbuf(read_with = "read_argument_name")]
     pub name: String,
     pub signature: MessageSignature,
 }
+
+fn read_command(buf: &mut Cursor<&[u8]>) -> Result<String, BufReadError> {
+    read_string_with_max(buf, MAX_MESSAGE_LENGTH)
+}
+
+fn read_argument_signatures(
+    buf: &mut Cursor<&[u8]>,
+) -> Result<Vec<ArgumentSignature>, BufReadError> {
+    read_collection_with_max(buf, MAX_ARGUMENT_SIGNATURES)
+}
+
+fn read_argument_name(buf: &mut Cursor<&[u8]>) -> Result<String, BufReadError> {
+    read_string_with_max(buf, MAX_ARGUMENT_NAME_LENGTH)
+}
@@ -1,10 +1,16 @@
 use crate::packets::game::clientbound_player_chat_packet::LastSeenMessagesUpdate;
-use azalea_buf::McBuf;
+use azalea_buf::{read_string_with_max, BufReadError, McBuf};
 use azalea_crypto::MessageSignature;
 use azalea_protocol_macros::ServerboundGamePacket;
+use std::io::Cursor;
+
+/// The longest a chat message can be.
+pub const MAX_MESSAGE_LENGTH: u32 = 256;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundChatPacket {
+    #[mcbuf(read_with = "read_message")]
     pub message: String,
     pub timestamp: u64,
     pub salt: u64,
@@ -12,3 +18,7 @@ pub struct ServerboundChatPacket {
     pub signed_preview: bool,
     pub last_seen_messages: LastSeenMessagesUpdate,
 }
+
+fn read_message(buf: &mut Cursor<&[u8]>) -> Result<String, BufReadError> {
+    read_string_with_max(buf, MAX_MESSAGE_LENGTH)
+}
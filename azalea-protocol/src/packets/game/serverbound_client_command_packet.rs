@@ -2,11 +2,13 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundClientCommandPacket {
     pub action: Action,
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Action {
     PerformRespawn = 0,
     RequestStats = 1,
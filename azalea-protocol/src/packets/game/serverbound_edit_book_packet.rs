@@ -1,10 +1,38 @@
-use azalea_buf::McBuf;
+use azalea_buf::{
+    read_collection_with_max_by, read_string_with_max, BufReadError, McBuf, McBufReadable,
+};
 use azalea_protocol_macros::ServerboundGamePacket;
+use std::io::Cursor;
+
+/// The most pages that a book can have.
+pub const MAX_PAGES: usize = 200;
+/// The longest the text on a page can be.
+pub const MAX_PAGE_LENGTH: u32 = 8192;
+/// The longest the title of a signed book can be.
+pub const MAX_TITLE_LENGTH: u32 = 128;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundEditBookPacket {
     #[var]
     pub slot: u32,
+    #[mcbuf(read_with = "read_pages")]
     pub pages: Vec<String>,
+    /// The title of the book if it's being signed.
+    #[mcbuf(read_with = "read_title")]
     pub title: Option<String>,
 }
+
+fn read_pages(buf: &mut Cursor<&[u8]>) -> Result<Vec<String>, BufReadError> {
+    read_collection_with_max_by(buf, MAX_PAGES, |buf| {
+        read_string_with_max(buf, MAX_PAGE_LENGTH)
+    })
+}
+
+fn read_title(buf: &mut Cursor<&[u8]>) -> Result<Option<String>, BufReadError> {
+    if bool::read_from(buf)? {
+        Ok(Some(read_string_with_max(buf, MAX_TITLE_LENGTH)?))
+    } else {
+        Ok(None)
+    }
+}
@@ -5,6 +5,7 @@ use azalea_protocol_macros::ServerboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundInteractPacket {
     #[var]
     pub entity_id: u32,
@@ -14,12 +15,16 @@ pub struct ServerboundInteractPacket {
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ActionType {
-    Interact {
-        hand: InteractionHand,
-    },
+    /// Right clicking the entity.
+    Interact { hand: InteractionHand },
+    /// Left clicking the entity.
     Attack,
+    /// Right clicking a specific part of the entity, which is sent before
+    /// [`ActionType::Interact`].
     InteractAt {
+        /// Where the entity was clicked, relative to its position.
         location: Vec3,
         hand: InteractionHand,
     },
@@ -78,6 +83,7 @@ impl McBufReadable for ActionType {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum InteractionHand {
     MainHand = 0,
     OffHand = 1,
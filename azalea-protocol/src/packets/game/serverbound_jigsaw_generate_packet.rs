@@ -3,6 +3,7 @@ use azalea_core::BlockPos;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundJigsawGeneratePacket {
     pub pos: BlockPos,
     #[var]
@@ -3,6 +3,7 @@ use azalea_core::ResourceLocation;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundPlaceRecipePacket {
     pub container_id: u8,
     pub recipe: ResourceLocation,
@@ -1,29 +1,25 @@
 use crate::packets::BufReadError;
-use azalea_buf::{McBufReadable, McBufWritable};
+use azalea_buf::{McBuf, McBufReadable, McBufWritable};
 use azalea_protocol_macros::ServerboundGamePacket;
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 
-#[derive(Clone, Debug, ServerboundGamePacket)]
+#[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundPlayerAbilitiesPacket {
+    /// This is sent as a byte of flags, but the other flags are ignored.
+    #[mcbuf(read_with = "read_is_flying", write_with = "write_is_flying")]
     is_flying: bool,
 }
 
-impl McBufReadable for ServerboundPlayerAbilitiesPacket {
-    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
-        let byte = u8::read_from(buf)?;
-        Ok(Self {
-            is_flying: byte & 2 != 0,
-        })
-    }
+fn read_is_flying(buf: &mut Cursor<&[u8]>) -> Result<bool, BufReadError> {
+    let byte = u8::read_from(buf)?;
+    Ok(byte & 2 != 0)
 }
 
-impl McBufWritable for ServerboundPlayerAbilitiesPacket {
-    fn write_into(&self, buf: &mut impl std::io::Write) -> Result<(), std::io::Error> {
-        let mut byte = 0;
-        if self.is_flying {
-            byte |= 2;
-        }
-        byte.write_into(buf)?;
-        Ok(())
+fn write_is_flying(is_flying: &bool, buf: &mut impl Write) -> Result<(), std::io::Error> {
+    let mut byte = 0;
+    if *is_flying {
+        byte |= 2;
     }
+    u8::write_into(&byte, buf)
 }
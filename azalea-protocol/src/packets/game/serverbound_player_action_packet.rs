@@ -4,6 +4,7 @@ use azalea_core::Direction;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundPlayerActionPacket {
     pub action: Action,
     pub pos: BlockPos,
@@ -13,12 +14,16 @@ pub struct ServerboundPlayerActionPacket {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Action {
     StartDestroyBlock = 0,
     AbortDestroyBlock = 1,
     StopDestroyBlock = 2,
+    /// Drop the whole stack that we're holding. The position and direction
+    /// are ignored for this and every action after it.
     DropAllItems = 3,
     DropItem = 4,
+    /// Stop using the item that we're holding, like shooting a bow.
     ReleaseUseItem = 5,
     SwapItemWithOffhand = 6,
 }
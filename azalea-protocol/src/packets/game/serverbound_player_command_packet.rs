@@ -2,6 +2,7 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundPlayerCommandPacket {
     #[var]
     pub id: u32,
@@ -11,6 +12,7 @@ pub struct ServerboundPlayerCommandPacket {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Action {
     PressShiftKey = 0,
     ReleaseShiftKey = 1,
@@ -5,6 +5,7 @@ use azalea_buf::{McBufReadable, McBufWritable};
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundPlayerInputPacket {
     pub xxa: f32,
     pub zza: f32,
@@ -39,7 +40,7 @@ impl McBufWritable for ServerboundPlayerInputPacket {
         if self.is_shift_key_down {
             byte |= 2;
         }
-        byte.write_into(buf)?;
+        u8::write_into(&byte, buf)?;
         Ok(())
     }
 }
@@ -2,6 +2,7 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundRecipeBookChangeSettingsPacket {
     pub book_type: RecipeBookType,
     pub is_open: bool,
@@ -9,6 +10,7 @@ pub struct ServerboundRecipeBookChangeSettingsPacket {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RecipeBookType {
     Crafting = 0,
     Furnace = 1,
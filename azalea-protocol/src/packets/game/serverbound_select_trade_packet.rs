@@ -2,7 +2,10 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundSelectTradePacket {
+    /// The index of the trade in
+    /// [`ClientboundMerchantOffersPacket::offers`](super::clientbound_merchant_offers_packet::ClientboundMerchantOffersPacket::offers).
     #[var]
     pub item: u32,
 }
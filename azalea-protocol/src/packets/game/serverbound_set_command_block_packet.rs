@@ -5,20 +5,30 @@ use azalea_protocol_macros::ServerboundGamePacket;
 use std::io::Cursor;
 
 #[derive(Clone, Debug, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundSetCommandBlockPacket {
     pub pos: BlockPos,
     pub command: String,
     pub mode: Mode,
 
+    /// Whether the command block remembers the output of the last command
+    /// that it ran.
     pub track_output: bool,
+    /// Only run if the command block behind this one succeeded.
     pub conditional: bool,
+    /// Run without needing a redstone signal.
     pub automatic: bool,
 }
 
-#[derive(McBuf, Clone, Copy, Debug)]
+/// The kind of command block, which is shown by its color.
+#[derive(McBuf, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Mode {
+    /// A chain command block, which runs after the one behind it.
     Sequence = 0,
+    /// A repeating command block, which runs every tick.
     Auto = 1,
+    /// An impulse command block, which runs once when it's powered.
     Redstone = 2,
 }
 
@@ -63,3 +73,28 @@ impl McBufWritable for ServerboundSetCommandBlockPacket {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_write_flags() {
+        let packet = ServerboundSetCommandBlockPacket {
+            pos: BlockPos::new(1, 2, 3),
+            command: "say hi".to_string(),
+            mode: Mode::Auto,
+            track_output: true,
+            conditional: false,
+            automatic: true,
+        };
+        let mut buf = Vec::new();
+        packet.write_into(&mut buf).unwrap();
+        assert_eq!(&buf[buf.len() - 2..], [1, 0b101]);
+
+        let read = ServerboundSetCommandBlockPacket::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(read.command, "say hi");
+        assert_eq!(read.mode, Mode::Auto);
+        assert!(read.track_output && !read.conditional && read.automatic);
+    }
+}
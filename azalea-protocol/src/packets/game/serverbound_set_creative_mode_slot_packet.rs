@@ -3,6 +3,7 @@ use azalea_core::Slot;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundSetCreativeModeSlotPacket {
     pub slot_num: u16,
     pub item_stack: Slot,
@@ -9,15 +9,19 @@ use std::io::Cursor;
 use std::io::Write;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundSetJigsawBlockPacket {
     pub pos: BlockPos,
     pub name: ResourceLocation,
     pub target: ResourceLocation,
     pub pool: ResourceLocation,
     pub final_state: String,
-    pub joint: String, // TODO: Does JigsawBlockEntity$JointType::getSerializedName, may not be implemented
+    pub joint: JointType,
 }
 
+/// Whether the piece attached to a vertical jigsaw can be rotated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum JointType {
     Rollable,
     Aligned,
@@ -43,3 +47,22 @@ impl McBufWritable for JointType {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_write_joint_type() {
+        let mut buf = Vec::new();
+        JointType::Aligned.write_into(&mut buf).unwrap();
+        assert_eq!(buf, b"\x07aligned");
+        assert_eq!(
+            JointType::read_from(&mut Cursor::new(&buf[..])).unwrap(),
+            JointType::Aligned
+        );
+        let mut buf = Vec::new();
+        "sideways".to_string().write_into(&mut buf).unwrap();
+        assert!(JointType::read_from(&mut Cursor::new(&buf[..])).is_err());
+    }
+}
@@ -6,12 +6,16 @@ use azalea_protocol_macros::ServerboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundSetStructureBlockPacket {
     pub pos: BlockPos,
     pub update_type: UpdateType,
     pub mode: StructureMode,
     pub name: String,
+    /// Where the structure starts relative to the structure block, between
+    /// -48 and 48.
     pub offset: BytePosition,
+    /// The size of the structure, up to 48 on each axis.
     pub size: BytePosition,
     pub mirror: Mirror,
     pub rotation: Rotation,
@@ -23,13 +27,15 @@ pub struct ServerboundSetStructureBlockPacket {
 }
 
 #[derive(Clone, Debug, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BytePosition {
-    pub x: u8,
-    pub y: u8,
-    pub z: u8,
+    pub x: i8,
+    pub y: i8,
+    pub z: i8,
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum UpdateType {
     UpdateData = 0,
     SaveArea = 1,
@@ -38,6 +44,7 @@ pub enum UpdateType {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum StructureMode {
     Save = 0,
     Load = 1,
@@ -46,6 +53,7 @@ pub enum StructureMode {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Mirror {
     None = 0,
     LeftRight = 1,
@@ -53,6 +61,7 @@ pub enum Mirror {
 }
 
 #[derive(McBuf, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Rotation {
     None = 0,
     Clockwise90 = 1,
@@ -61,6 +70,7 @@ pub enum Rotation {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Flags {
     pub ignore_entities: bool,
     pub show_air: bool,
@@ -94,3 +104,18 @@ impl McBufWritable for Flags {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_offset() {
+        let offset = BytePosition { x: -48, y: 1, z: 0 };
+        let mut buf = Vec::new();
+        offset.write_into(&mut buf).unwrap();
+        assert_eq!(buf, [0xd0, 1, 0]);
+        let read = BytePosition::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!((read.x, read.y, read.z), (-48, 1, 0));
+    }
+}
@@ -3,7 +3,9 @@ use azalea_core::BlockPos;
 use azalea_protocol_macros::ServerboundGamePacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundSignUpdatePacket {
     pub pos: BlockPos,
+    /// The text on each line. Formatting codes are removed by the server.
     pub lines: [String; 4],
 }
@@ -3,6 +3,7 @@ use azalea_protocol_macros::ServerboundGamePacket;
 use uuid::Uuid;
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundTeleportToEntityPacket {
     pub uuid: Uuid,
 }
@@ -5,6 +5,7 @@ use azalea_protocol_macros::ServerboundGamePacket;
 use std::io::{Cursor, Write};
 
 #[derive(Clone, Debug, McBuf, ServerboundGamePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundUseItemOnPacket {
     pub hand: InteractionHand,
     pub block_hit: BlockHitResult,
@@ -13,6 +14,7 @@ pub struct ServerboundUseItemOnPacket {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BlockHitResult {
     pub block_pos: BlockPos,
     pub direction: Direction,
@@ -4,9 +4,13 @@ use azalea_protocol_macros::ServerboundHandshakePacket;
 use std::hash::Hash;
 
 #[derive(Hash, Clone, Debug, McBuf, ServerboundHandshakePacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientIntentionPacket {
+    /// The protocol version of the client. This is usually
+    /// [`PROTOCOL_VERSION`](crate::packets::PROTOCOL_VERSION), but pingers
+    /// sometimes send -1 to ask for the server's version.
     #[var]
-    pub protocol_version: u32,
+    pub protocol_version: i32,
     pub hostname: String,
     pub port: u16,
     pub intention: ConnectionProtocol,
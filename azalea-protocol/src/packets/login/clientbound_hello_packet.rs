@@ -2,6 +2,7 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ClientboundLoginPacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundLoginPacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundHelloPacket {
     // TODO: make this len thing work
     // #[len(20)]
@@ -3,6 +3,7 @@ use azalea_chat::component::Component;
 use azalea_protocol_macros::ClientboundLoginPacket;
 
 #[derive(Clone, Debug, McBuf, ClientboundLoginPacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ClientboundLoginDisconnectPacket {
     pub reason: Component,
 }
@@ -2,6 +2,7 @@ use azalea_buf::{McBuf, UnsizedByteArray};
 use azalea_protocol_macros::ServerboundLoginPacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundLoginPacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundCustomQueryPacket {
     #[var]
     pub transaction_id: u32,
@@ -1,27 +1,60 @@
-use azalea_buf::McBuf;
+use crate::packets::{McBufVersioned, ProtocolVersion};
+use azalea_buf::{BufReadError, McBuf, McBufReadable, McBufWritable};
 use azalea_protocol_macros::ServerboundLoginPacket;
+use std::io::{Cursor, Write};
 use uuid::Uuid;
 
-#[derive(Clone, Debug, ServerboundLoginPacket, McBuf, PartialEq, Eq)]
+#[derive(Clone, Debug, ServerboundLoginPacket, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundHelloPacket {
     pub username: String,
     pub public_key: Option<ProfilePublicKeyData>,
+    /// The UUID of the player. This was added in 1.19.1, so it's always
+    /// `None` when it's read from older versions and isn't written to them.
     pub profile_id: Option<Uuid>,
 }
 
 #[derive(Clone, Debug, McBuf, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ProfilePublicKeyData {
     pub expires_at: u64,
     pub key: Vec<u8>,
     pub key_signature: Vec<u8>,
 }
 
+impl McBufVersioned for ServerboundHelloPacket {
+    fn read_versioned(
+        buf: &mut Cursor<&[u8]>,
+        version: ProtocolVersion,
+    ) -> Result<Self, BufReadError> {
+        Ok(ServerboundHelloPacket {
+            username: String::read_from(buf)?,
+            public_key: Option::read_from(buf)?,
+            profile_id: if version >= ProtocolVersion::V1_19_1 {
+                Option::read_from(buf)?
+            } else {
+                None
+            },
+        })
+    }
+
+    fn write_versioned(
+        &self,
+        buf: &mut impl Write,
+        version: ProtocolVersion,
+    ) -> Result<(), std::io::Error> {
+        self.username.write_into(buf)?;
+        self.public_key.write_into(buf)?;
+        if version >= ProtocolVersion::V1_19_1 {
+            self.profile_id.write_into(buf)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
-
     use super::*;
-    use azalea_buf::{McBufReadable, McBufWritable};
 
     #[test]
     fn test_read_write() {
@@ -31,8 +64,34 @@ mod tests {
             profile_id: Some(Uuid::from_u128(0)),
         };
         let mut buf: Vec<u8> = Vec::new();
-        packet.write_into(&mut buf).unwrap();
-        let packet2 = ServerboundHelloPacket::read_from(&mut Cursor::new(&buf)).unwrap();
+        packet
+            .write_versioned(&mut buf, ProtocolVersion::V1_19_1)
+            .unwrap();
+        let packet2 = ServerboundHelloPacket::read_versioned(
+            &mut Cursor::new(&buf),
+            ProtocolVersion::V1_19_1,
+        )
+        .unwrap();
         assert_eq!(packet, packet2);
     }
+
+    #[test]
+    fn test_read_write_1_19() {
+        let packet = ServerboundHelloPacket {
+            username: "test".to_string(),
+            public_key: None,
+            profile_id: Some(Uuid::from_u128(0)),
+        };
+        let mut buf: Vec<u8> = Vec::new();
+        packet
+            .write_versioned(&mut buf, ProtocolVersion::V1_19)
+            .unwrap();
+        // the length of the username, the username, and no public key
+        assert_eq!(buf, [4, b't', b'e', b's', b't', 0]);
+        let packet2 =
+            ServerboundHelloPacket::read_versioned(&mut Cursor::new(&buf), ProtocolVersion::V1_19)
+                .unwrap();
+        assert_eq!(packet2.username, "test");
+        assert_eq!(packet2.profile_id, None);
+    }
 }
@@ -6,12 +6,14 @@ use std::io::{Cursor, Write};
 use azalea_buf::{McBufReadable, McBufWritable};
 
 #[derive(Clone, Debug, McBuf, ServerboundLoginPacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundKeyPacket {
     pub key_bytes: Vec<u8>,
     pub nonce_or_salt_signature: NonceOrSaltSignature,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum NonceOrSaltSignature {
     Nonce(Vec<u8>),
     SaltSignature(SaltSignaturePair),
@@ -4,14 +4,70 @@ pub mod login;
 pub mod status;
 
 use crate::read::ReadPacketError;
-use azalea_buf::{BufReadError, McBufVarReadable, McBufVarWritable, McBufWritable};
+use azalea_buf::{BufReadError, McBufReadable, McBufVarReadable, McBufVarWritable, McBufWritable};
 use std::io::{Cursor, Write};
 
 // TODO: rename the packet files to just like clientbound_add_entity instead of clientbound_add_entity_packet
 
-pub const PROTOCOL_VERSION: u32 = 760;
+/// The protocol version that the packet definitions in this crate match,
+/// which is Minecraft 1.19.1 and 1.19.2. Servers running other versions may
+/// still accept it (for example with ViaVersion), but packets that changed
+/// between versions won't be read correctly.
+///
+/// This is what's sent in the handshake by default, but since it's just a
+/// field on [`ClientIntentionPacket`] you can send any version you want.
+///
+/// [`ClientIntentionPacket`]: handshake::client_intention_packet::ClientIntentionPacket
+pub const PROTOCOL_VERSION: i32 = ProtocolVersion::LATEST as i32;
+
+/// A version of the protocol that a connection can use. See
+/// [`Connection::set_protocol_version`].
+///
+/// The packets are read and written for the connection's version. Only the
+/// packets that are different in the handshake, status, and login states are
+/// handled for older versions though, so game packets are always the ones
+/// from [`ProtocolVersion::LATEST`].
+///
+/// [`Connection::set_protocol_version`]: crate::connect::Connection::set_protocol_version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum ProtocolVersion {
+    /// Minecraft 1.19.
+    V1_19 = 759,
+    /// Minecraft 1.19.1 and 1.19.2.
+    V1_19_1 = 760,
+}
+
+impl ProtocolVersion {
+    /// The newest version that we support, which is what connections use by
+    /// default.
+    pub const LATEST: ProtocolVersion = ProtocolVersion::V1_19_1;
+
+    /// Get the version from the number that's sent in the handshake, or
+    /// `None` if we don't support it.
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            759 => Some(ProtocolVersion::V1_19),
+            760 => Some(ProtocolVersion::V1_19_1),
+            _ => None,
+        }
+    }
+
+    /// The number that's sent in the handshake for this version.
+    pub fn id(&self) -> i32 {
+        *self as i32
+    }
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        ProtocolVersion::LATEST
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ConnectionProtocol {
     Handshake = -1,
     Game = 0,
@@ -39,9 +95,48 @@ where
     fn id(&self) -> u32;
 
     /// Read a packet by its id, ConnectionProtocol, and flow
-    fn read(id: u32, buf: &mut Cursor<&[u8]>) -> Result<Self, ReadPacketError>;
+    fn read(
+        id: u32,
+        buf: &mut Cursor<&[u8]>,
+        version: ProtocolVersion,
+    ) -> Result<Self, ReadPacketError>;
+
+    fn write(&self, buf: &mut impl Write, version: ProtocolVersion) -> Result<(), std::io::Error>;
+}
+
+/// Reading and writing the fields of a packet for a version of the protocol.
+///
+/// This is implemented for everything that implements `McBuf`, ignoring the
+/// version. Packets that changed between versions implement it themselves
+/// instead of deriving `McBuf`.
+pub trait McBufVersioned: Sized {
+    fn read_versioned(
+        buf: &mut Cursor<&[u8]>,
+        version: ProtocolVersion,
+    ) -> Result<Self, BufReadError>;
+
+    fn write_versioned(
+        &self,
+        buf: &mut impl Write,
+        version: ProtocolVersion,
+    ) -> Result<(), std::io::Error>;
+}
 
-    fn write(&self, buf: &mut impl Write) -> Result<(), std::io::Error>;
+impl<T: McBufReadable + McBufWritable> McBufVersioned for T {
+    fn read_versioned(
+        buf: &mut Cursor<&[u8]>,
+        _version: ProtocolVersion,
+    ) -> Result<Self, BufReadError> {
+        T::read_from(buf)
+    }
+
+    fn write_versioned(
+        &self,
+        buf: &mut impl Write,
+        _version: ProtocolVersion,
+    ) -> Result<(), std::io::Error> {
+        self.write_into(buf)
+    }
 }
 
 impl azalea_buf::McBufReadable for ConnectionProtocol {
@@ -56,3 +151,36 @@ impl McBufWritable for ConnectionProtocol {
         (*self as i32).var_write_into(buf)
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::game::{
+        clientbound_set_default_spawn_position_packet::ClientboundSetDefaultSpawnPositionPacket,
+        clientbound_system_chat_packet::ClientboundSystemChatPacket,
+    };
+    use azalea_core::BlockPos;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialize_packets() {
+        let packet = ClientboundSystemChatPacket {
+            content: "hello".to_string().into(),
+            overlay: false,
+        }
+        .get();
+        assert_eq!(
+            serde_json::to_value(&packet).unwrap(),
+            json!({ "SystemChat": { "content": { "text": "hello" }, "overlay": false } })
+        );
+
+        let packet = ClientboundSetDefaultSpawnPositionPacket {
+            pos: BlockPos::new(1, 64, -3),
+            angle: 0.,
+        }
+        .get();
+        assert_eq!(
+            serde_json::to_value(&packet).unwrap(),
+            json!({ "SetDefaultSpawnPosition": { "pos": { "x": 1, "y": 64, "z": -3 }, "angle": 0.0 } })
+        );
+    }
+}
@@ -1,23 +1,23 @@
 use azalea_buf::{BufReadError, McBufReadable, McBufWritable};
 use azalea_chat::component::Component;
 use azalea_protocol_macros::ClientboundStatusPacket;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::io::{Cursor, Write};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Version {
     pub name: Component,
     pub protocol: i32,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SamplePlayer {
     pub id: String,
     pub name: String,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Players {
     pub max: i32,
     pub online: i32,
@@ -25,10 +25,12 @@ pub struct Players {
     pub sample: Vec<SamplePlayer>,
 }
 
-// the entire packet is just json, which is why it has deserialize
-#[derive(Clone, Debug, Deserialize, ClientboundStatusPacket)]
+// the entire packet is just json, which is why it has serialize and
+// deserialize
+#[derive(Clone, Debug, Serialize, Deserialize, ClientboundStatusPacket)]
 pub struct ClientboundStatusResponsePacket {
     pub description: Component,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub favicon: Option<String>,
     pub players: Players,
     pub version: Version,
@@ -44,7 +46,7 @@ impl McBufReadable for ClientboundStatusResponsePacket {
 }
 
 impl McBufWritable for ClientboundStatusResponsePacket {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        todo!()
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        serde_json::to_string(self)?.write_into(buf)
     }
 }
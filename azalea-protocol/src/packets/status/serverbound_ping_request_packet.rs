@@ -2,6 +2,7 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ServerboundStatusPacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundStatusPacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundPingRequestPacket {
     pub time: u64,
 }
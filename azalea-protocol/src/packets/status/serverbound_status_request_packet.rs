@@ -2,4 +2,5 @@ use azalea_buf::McBuf;
 use azalea_protocol_macros::ServerboundStatusPacket;
 
 #[derive(Clone, Debug, McBuf, ServerboundStatusPacket)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ServerboundStatusRequestPacket {}
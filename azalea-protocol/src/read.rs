@@ -1,19 +1,14 @@
-use crate::packets::ProtocolPacket;
+use crate::packets::{ConnectionProtocol, ProtocolPacket, ProtocolVersion};
 use azalea_buf::BufReadError;
 use azalea_buf::McBufVarReadable;
 use azalea_crypto::Aes128CfbDec;
 use bytes::Buf;
-use bytes::BytesMut;
-use flate2::read::ZlibDecoder;
-use futures::StreamExt;
-use log::{log_enabled, trace};
-use std::{
-    fmt::Debug,
-    io::{Cursor, Read},
-};
+use bytes::{Bytes, BytesMut};
+use flate2::{Decompress, FlushDecompress, Status};
+use std::{fmt::Debug, io::Cursor};
 use thiserror::Error;
-use tokio::io::AsyncRead;
-use tokio_util::codec::{BytesCodec, FramedRead};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{enabled, trace, Level};
 
 #[derive(Error, Debug)]
 pub enum ReadPacketError {
@@ -22,9 +17,30 @@ pub enum ReadPacketError {
         packet_id: u32,
         packet_name: String,
         source: BufReadError,
+        /// The (decrypted and decompressed) bytes of the packet, not
+        /// including the packet id.
+        data: Vec<u8>,
     },
     #[error("Unknown packet id {id} in state {state_name}")]
-    UnknownPacketId { state_name: String, id: u32 },
+    UnknownPacketId {
+        state_name: String,
+        id: u32,
+        /// The (decrypted and decompressed) bytes of the packet, not
+        /// including the packet id.
+        data: Vec<u8>,
+    },
+    /// The packet id is past the end of the packet table for the state we're
+    /// in, which usually means the connection is in the wrong state (for
+    /// example, still reading login packets after the server switched to
+    /// game).
+    #[error("Unexpected packet id {id} in state {state:?}, is the connection in the wrong state?")]
+    UnexpectedPacket {
+        id: u32,
+        state: ConnectionProtocol,
+        /// The (decrypted and decompressed) bytes of the packet, not
+        /// including the packet id.
+        data: Vec<u8>,
+    },
     #[error("Couldn't read packet id")]
     ReadPacketId { source: BufReadError },
     #[error(transparent)]
@@ -49,6 +65,34 @@ pub enum ReadPacketError {
     },
     #[error("Connection closed")]
     ConnectionClosed,
+    /// We didn't get a packet within the connection's read timeout. See
+    /// [`crate::connect::ConnectionOptions::read_timeout`].
+    #[error("Timed out waiting for a packet")]
+    Timeout,
+}
+
+impl ReadPacketError {
+    /// Whether the connection can still be read from after this error.
+    ///
+    /// Packets that couldn't be decoded (like ones with an id we don't know,
+    /// which servers with custom packets send) were still read completely, so
+    /// the next read will start at the next packet. Errors like the
+    /// connection closing or a corrupted packet length can't be recovered
+    /// from.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            ReadPacketError::Parse { .. }
+            | ReadPacketError::UnknownPacketId { .. }
+            | ReadPacketError::UnexpectedPacket { .. }
+            | ReadPacketError::ReadPacketId { .. }
+            | ReadPacketError::Decompress { .. }
+            | ReadPacketError::LeftoverData { .. }
+            | ReadPacketError::Timeout => true,
+            ReadPacketError::FrameSplitter { .. }
+            | ReadPacketError::IoError { .. }
+            | ReadPacketError::ConnectionClosed => false,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -66,6 +110,9 @@ pub enum FrameSplitterError {
     },
     #[error("Packet is longer than {max} bytes (is {size})")]
     BadLength { max: usize, size: usize },
+    /// The packet's length is more than [`PacketLimits::max_frame_size`].
+    #[error("Packet is {size} bytes, which is more than the maximum of {max}")]
+    TooBig { max: u32, size: u32 },
     #[error("Connection reset by peer")]
     ConnectionReset,
     #[error("Connection closed")]
@@ -74,18 +121,27 @@ pub enum FrameSplitterError {
 
 /// Read a length, then read that amount of bytes from BytesMut. If there's not
 /// enough data, return None
-fn parse_frame(buffer: &mut BytesMut) -> Result<BytesMut, FrameSplitterError> {
+fn parse_frame(buffer: &mut BytesMut, max_frame_size: u32) -> Result<BytesMut, FrameSplitterError> {
     // copy the buffer first and read from the copy, then once we make sure
     // the packet is all good we read it fully
     let mut buffer_copy = Cursor::new(&buffer[..]);
     // Packet Length
     let length = match u32::var_read_from(&mut buffer_copy) {
-        Ok(length) => length as usize,
+        Ok(length) => length,
         Err(err) => match err {
             BufReadError::Io(io_err) => return Err(FrameSplitterError::Io { source: io_err }),
             _ => return Err(err.into()),
         },
     };
+    // check this before waiting for the rest of the packet, so a huge length
+    // can't make us buffer forever
+    if length > max_frame_size {
+        return Err(FrameSplitterError::TooBig {
+            max: max_frame_size,
+            size: length,
+        });
+    }
+    let length = length as usize;
 
     if length > buffer_copy.remaining() {
         return Err(FrameSplitterError::BadLength {
@@ -106,13 +162,21 @@ fn parse_frame(buffer: &mut BytesMut) -> Result<BytesMut, FrameSplitterError> {
     Ok(data)
 }
 
-fn frame_splitter(buffer: &mut BytesMut) -> Result<Option<Vec<u8>>, FrameSplitterError> {
+fn frame_splitter(
+    buffer: &mut BytesMut,
+    max_frame_size: u32,
+) -> Result<Option<BytesMut>, FrameSplitterError> {
     // https://tokio.rs/tokio/tutorial/framing
-    let read_frame = parse_frame(buffer);
+    let read_frame = parse_frame(buffer, max_frame_size);
     match read_frame {
-        Ok(frame) => return Ok(Some(frame.to_vec())),
+        Ok(frame) => return Ok(Some(frame)),
         Err(err) => match err {
-            FrameSplitterError::BadLength { .. } | FrameSplitterError::Io { .. } => {
+            FrameSplitterError::BadLength { max, size } => {
+                // we know how long the packet is, so make room for the rest
+                // of it now instead of growing the buffer a bit at a time
+                buffer.reserve(size - max);
+            }
+            FrameSplitterError::Io { .. } => {
                 // we probably just haven't read enough yet
             }
             _ => return Err(err),
@@ -122,13 +186,14 @@ fn frame_splitter(buffer: &mut BytesMut) -> Result<Option<Vec<u8>>, FrameSplitte
     Ok(None)
 }
 
-fn packet_decoder<P: ProtocolPacket + Debug>(
+pub(crate) fn packet_decoder<P: ProtocolPacket + Debug>(
     stream: &mut Cursor<&[u8]>,
+    version: ProtocolVersion,
 ) -> Result<P, ReadPacketError> {
     // Packet ID
     let packet_id =
         u32::var_read_from(stream).map_err(|e| ReadPacketError::ReadPacketId { source: e })?;
-    P::read(packet_id, stream)
+    P::read(packet_id, stream, version)
 }
 
 // this is always true in multiplayer, false in singleplayer
@@ -136,6 +201,33 @@ static VALIDATE_DECOMPRESSED: bool = true;
 
 pub static MAXIMUM_UNCOMPRESSED_LENGTH: u32 = 2097152;
 
+/// Vanilla doesn't allow packet lengths that take more than 3 bytes as a
+/// VarInt.
+pub static MAXIMUM_FRAME_LENGTH: u32 = (1 << 21) - 1;
+
+/// How big packets are allowed to be, so a malicious server can't make us run
+/// out of memory with a huge packet or a zlib bomb.
+#[derive(Clone, Copy, Debug)]
+pub struct PacketLimits {
+    /// The maximum length of a packet as it's sent over the network (after
+    /// compression). Packets that say they're longer than this fail with
+    /// [`FrameSplitterError::TooBig`] before we read them.
+    pub max_frame_size: u32,
+    /// The maximum length of a packet after it's decompressed. Packets that
+    /// decompress to more than this fail with
+    /// [`DecompressionError::AboveCompressionThreshold`].
+    pub max_decompressed_size: u32,
+}
+
+impl Default for PacketLimits {
+    fn default() -> Self {
+        Self {
+            max_frame_size: MAXIMUM_FRAME_LENGTH,
+            max_decompressed_size: MAXIMUM_UNCOMPRESSED_LENGTH,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum DecompressionError {
     #[error("Couldn't read VarInt length for data")]
@@ -157,78 +249,261 @@ pub enum DecompressionError {
     AboveCompressionThreshold { size: u32, maximum: u32 },
 }
 
-fn compression_decoder(
-    stream: &mut Cursor<&[u8]>,
+/// The zlib state and output buffer used for decompressing packets. These
+/// are kept around between packets so we don't have to allocate new ones
+/// every time we read a compressed packet.
+pub struct Decompressor {
+    zlib: Decompress,
+    buffer: Vec<u8>,
+}
+
+impl Default for Decompressor {
+    fn default() -> Self {
+        Self {
+            zlib: Decompress::new(true),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+fn compression_decoder<'a>(
+    data: &'a [u8],
     compression_threshold: u32,
-) -> Result<Vec<u8>, DecompressionError> {
+    decompressor: &'a mut Decompressor,
+    max_decompressed_size: u32,
+) -> Result<&'a [u8], DecompressionError> {
+    let mut stream = Cursor::new(data);
     // Data Length
-    let n = u32::var_read_from(stream)?;
+    let n = u32::var_read_from(&mut stream)?;
+    let compressed = &data[stream.position() as usize..];
     if n == 0 {
         // no data size, no compression
-        let mut buf = vec![];
-        std::io::Read::read_to_end(stream, &mut buf)?;
-        return Ok(buf);
+        return Ok(compressed);
     }
 
-    if VALIDATE_DECOMPRESSED {
-        if n < compression_threshold {
-            return Err(DecompressionError::BelowCompressionThreshold {
-                size: n,
-                threshold: compression_threshold,
-            });
-        }
-        if n > MAXIMUM_UNCOMPRESSED_LENGTH {
+    if VALIDATE_DECOMPRESSED && n < compression_threshold {
+        return Err(DecompressionError::BelowCompressionThreshold {
+            size: n,
+            threshold: compression_threshold,
+        });
+    }
+    if n > max_decompressed_size {
+        return Err(DecompressionError::AboveCompressionThreshold {
+            size: n,
+            maximum: max_decompressed_size,
+        });
+    }
+
+    let Decompressor { zlib, buffer } = decompressor;
+    zlib.reset(true);
+    buffer.clear();
+    buffer.reserve(n as usize);
+    loop {
+        let input = &compressed[zlib.total_in() as usize..];
+        let status = zlib
+            // Finish makes miniz expect the whole output to fit at once, so
+            // it can't be used if we might have to grow the buffer
+            .decompress_vec(input, buffer, FlushDecompress::None)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        // zlib bombs could use all of our memory, so stop once we're over the
+        // limit
+        if buffer.len() > max_decompressed_size as usize {
             return Err(DecompressionError::AboveCompressionThreshold {
-                size: n,
-                maximum: MAXIMUM_UNCOMPRESSED_LENGTH,
+                size: buffer.len() as u32,
+                maximum: max_decompressed_size,
             });
         }
+        if status == Status::StreamEnd {
+            break;
+        }
+        if buffer.len() < buffer.capacity() {
+            // zlib stopped even though it had room to write more, so the
+            // compressed data must've been cut off
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        // the packet was bigger than it said it was, keep going anyways (but
+        // only up to one byte past the limit, so we can tell if it's too big)
+        let max_room = max_decompressed_size as usize + 1 - buffer.len();
+        buffer.reserve_exact(buffer.capacity().min(max_room));
     }
 
-    let mut decoded_buf = vec![];
-    let mut decoder = ZlibDecoder::new(stream);
-    decoder.read_to_end(&mut decoded_buf)?;
-
-    Ok(decoded_buf)
+    Ok(buffer)
 }
 
+/// Read and decode the next packet from the stream. Bytes that were read but
+/// aren't part of this packet are left in `buffer` for the next call.
+///
+/// This is cancel safe as long as the same buffer and cipher are passed to
+/// the next call, since everything that was read is already in the buffer.
+///
+/// Packets are read as they are in [`ProtocolVersion::LATEST`]. Use a
+/// [`Connection`] to read packets from other versions.
+///
+/// [`Connection`]: crate::connect::Connection
 pub async fn read_packet<'a, P: ProtocolPacket + Debug, R>(
     stream: &'a mut R,
     buffer: &mut BytesMut,
     compression_threshold: Option<u32>,
     cipher: &mut Option<Aes128CfbDec>,
+    decompressor: &mut Decompressor,
+) -> Result<P, ReadPacketError>
+where
+    R: AsyncRead + std::marker::Unpin + std::marker::Send + std::marker::Sync,
+{
+    read_packet_with_limits(
+        stream,
+        buffer,
+        compression_threshold,
+        cipher,
+        decompressor,
+        &PacketLimits::default(),
+    )
+    .await
+}
+
+/// Like [`read_packet`], but with custom limits on how big packets can be.
+pub async fn read_packet_with_limits<P: ProtocolPacket + Debug, R>(
+    stream: &mut R,
+    buffer: &mut BytesMut,
+    compression_threshold: Option<u32>,
+    cipher: &mut Option<Aes128CfbDec>,
+    decompressor: &mut Decompressor,
+    limits: &PacketLimits,
 ) -> Result<P, ReadPacketError>
 where
     R: AsyncRead + std::marker::Unpin + std::marker::Send + std::marker::Sync,
 {
-    let mut framed = FramedRead::new(stream, BytesCodec::new());
-    let mut buf = loop {
-        if let Some(buf) = frame_splitter(buffer)? {
+    let frame = read_raw_frame(stream, buffer, cipher, limits.max_frame_size).await?;
+    decode_frame(
+        &frame,
+        compression_threshold,
+        decompressor,
+        limits.max_decompressed_size,
+        ProtocolVersion::LATEST,
+    )
+}
+
+/// How many bytes we make room for in the buffer before reading from the
+/// stream, if we don't know how long the next packet is yet.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Read the next packet without decoding it, returning its id and the bytes
+/// of its fields. The packet is still decrypted and decompressed, so this is
+/// useful for proxies and recorders that don't care what's in the packets.
+///
+/// Packets that weren't compressed aren't copied, the bytes that are
+/// returned point into the read buffer.
+pub async fn read_frame<R>(
+    stream: &mut R,
+    buffer: &mut BytesMut,
+    compression_threshold: Option<u32>,
+    cipher: &mut Option<Aes128CfbDec>,
+    decompressor: &mut Decompressor,
+    limits: &PacketLimits,
+) -> Result<(u32, Bytes), ReadPacketError>
+where
+    R: AsyncRead + std::marker::Unpin + std::marker::Send + std::marker::Sync,
+{
+    let frame = read_raw_frame(stream, buffer, cipher, limits.max_frame_size)
+        .await?
+        .freeze();
+    let buf = decompress_frame(
+        &frame,
+        compression_threshold,
+        decompressor,
+        limits.max_decompressed_size,
+    )?;
+    let mut stream = Cursor::new(buf);
+    let id =
+        u32::var_read_from(&mut stream).map_err(|e| ReadPacketError::ReadPacketId { source: e })?;
+    let data = &buf[stream.position() as usize..];
+
+    let frame_range = frame.as_ptr_range();
+    let data = if frame_range.start <= data.as_ptr() && data.as_ptr_range().end <= frame_range.end {
+        frame.slice_ref(data)
+    } else {
+        // it was decompressed into the decompressor's buffer, which gets
+        // reused for the next packet
+        Bytes::copy_from_slice(data)
+    };
+    Ok((id, data))
+}
+
+/// Read the (decrypted but still compressed) bytes of the next packet.
+///
+/// Bytes are read straight into `buffer`, so its allocation is reused for
+/// every packet once the frames that were split off of it are dropped.
+pub(crate) async fn read_raw_frame<R>(
+    stream: &mut R,
+    buffer: &mut BytesMut,
+    cipher: &mut Option<Aes128CfbDec>,
+    max_frame_size: u32,
+) -> Result<BytesMut, ReadPacketError>
+where
+    R: AsyncRead + std::marker::Unpin + std::marker::Send + std::marker::Sync,
+{
+    loop {
+        if let Some(buf) = frame_splitter(buffer, max_frame_size)? {
             // we got a full packet!!
-            break buf;
+            return Ok(buf);
         } else {
             // no full packet yet :( keep reading
         };
 
-        // if we were given a cipher, decrypt the packet
-        if let Some(message) = framed.next().await {
-            let mut bytes = message?;
-
-            if let Some(cipher) = cipher {
-                azalea_crypto::decrypt_packet(cipher, &mut bytes);
-            }
-
-            buffer.extend_from_slice(&bytes);
-        } else {
+        if buffer.capacity() - buffer.len() < READ_CHUNK_SIZE {
+            buffer.reserve(READ_CHUNK_SIZE);
+        }
+        let start = buffer.len();
+        // read_buf is cancel safe, so if this times out the bytes we already
+        // read are still in the buffer
+        if stream.read_buf(buffer).await? == 0 {
             return Err(ReadPacketError::ConnectionClosed);
-        };
-    };
+        }
 
-    if let Some(compression_threshold) = compression_threshold {
-        buf = compression_decoder(&mut Cursor::new(&buf[..]), compression_threshold)?;
+        // if we were given a cipher, decrypt the bytes we just read
+        if let Some(cipher) = cipher {
+            azalea_crypto::decrypt_packet(cipher, &mut buffer[start..]);
+        }
     }
+}
+
+/// Decompress a frame from [`read_raw_frame`] and decode the packet in it.
+pub(crate) fn decode_frame<P: ProtocolPacket + Debug>(
+    frame: &[u8],
+    compression_threshold: Option<u32>,
+    decompressor: &mut Decompressor,
+    max_decompressed_size: u32,
+    version: ProtocolVersion,
+) -> Result<P, ReadPacketError> {
+    let buf = decompress_frame(
+        frame,
+        compression_threshold,
+        decompressor,
+        max_decompressed_size,
+    )?;
+    packet_decoder(&mut Cursor::new(buf), version)
+}
+
+/// Decompress a frame from [`read_raw_frame`], returning the bytes of the packet
+/// id and its fields.
+pub(crate) fn decompress_frame<'a>(
+    frame: &'a [u8],
+    compression_threshold: Option<u32>,
+    decompressor: &'a mut Decompressor,
+    max_decompressed_size: u32,
+) -> Result<&'a [u8], ReadPacketError> {
+    let buf = if let Some(compression_threshold) = compression_threshold {
+        compression_decoder(
+            frame,
+            compression_threshold,
+            decompressor,
+            max_decompressed_size,
+        )?
+    } else {
+        frame
+    };
 
-    if log_enabled!(log::Level::Trace) {
+    if enabled!(Level::TRACE) {
         let buf_string: String = {
             if buf.len() > 500 {
                 let cut_off_buf = &buf[..500];
@@ -240,15 +515,14 @@ where
         trace!("Reading packet with bytes: {buf_string}");
     }
 
-    let packet = packet_decoder(&mut Cursor::new(&buf[..]))?;
-
-    Ok(packet)
+    Ok(buf)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::packets::game::{clientbound_player_chat_packet::ChatType, ClientboundGamePacket};
+    use crate::packets::login::ClientboundLoginPacket;
     use std::io::Cursor;
 
     #[tokio::test]
@@ -267,7 +541,8 @@ mod tests {
             77, 66, 69, 82, 93, 32, 112, 108, 97, 121, 101, 114, 49, 34, 125, 93, 44, 34, 116, 101,
             120, 116, 34, 58, 34, 34, 125, 0,
         ]);
-        let packet = packet_decoder::<ClientboundGamePacket>(&mut buf).unwrap();
+        let packet =
+            packet_decoder::<ClientboundGamePacket>(&mut buf, ProtocolVersion::LATEST).unwrap();
         match &packet {
             ClientboundGamePacket::PlayerChat(m) => {
                 assert_eq!(
@@ -279,4 +554,259 @@ mod tests {
             _ => panic!("Wrong packet type"),
         }
     }
+
+    #[test]
+    fn test_parse_error_has_packet_data() {
+        // a keep alive packet that's missing half of its id
+        let mut buf: Cursor<&[u8]> = Cursor::new(&[0x20, 1, 2, 3, 4]);
+        match packet_decoder::<ClientboundGamePacket>(&mut buf, ProtocolVersion::LATEST) {
+            Err(ReadPacketError::Parse {
+                packet_id, data, ..
+            }) => {
+                assert_eq!(packet_id, 0x20);
+                assert_eq!(data, vec![1, 2, 3, 4]);
+            }
+            r => panic!("Expected a parse error, got {r:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_packets_around_compression_threshold() {
+        use crate::{
+            packets::game::clientbound_custom_payload_packet::ClientboundCustomPayloadPacket,
+            write::{write_packet, Compressor},
+        };
+        use azalea_buf::UnsizedByteArray;
+        use azalea_core::ResourceLocation;
+
+        // the id and identifier take up 17 bytes, so these are one byte
+        // below and exactly at the threshold
+        let packets: Vec<ClientboundGamePacket> = [238, 239]
+            .into_iter()
+            .map(|data_len| {
+                ClientboundCustomPayloadPacket {
+                    identifier: ResourceLocation::new("minecraft:brand").unwrap(),
+                    data: UnsizedByteArray::from(vec![1; data_len]),
+                }
+                .get()
+            })
+            .collect();
+
+        let mut stream = Vec::new();
+        let mut compressor = Compressor::default();
+        for packet in &packets {
+            write_packet(packet, &mut stream, Some(256), &mut None, &mut compressor)
+                .await
+                .unwrap();
+        }
+        // the first packet is sent with a data length of 0, the second one
+        // has its real length
+        assert_eq!(&stream[2..3], &[0]);
+        let second_packet_start = 2 + 1 + 255;
+        assert_eq!(
+            &stream[second_packet_start + 1..second_packet_start + 3],
+            &[0x80, 0x02]
+        );
+
+        let mut reader = Cursor::new(stream);
+        let mut buffer = BytesMut::new();
+        let mut decompressor = Decompressor::default();
+        for data_len in [238, 239] {
+            let packet = read_packet::<ClientboundGamePacket, _>(
+                &mut reader,
+                &mut buffer,
+                Some(256),
+                &mut None,
+                &mut decompressor,
+            )
+            .await
+            .unwrap();
+            match packet {
+                ClientboundGamePacket::CustomPayload(p) => assert_eq!(p.data.len(), data_len),
+                _ => panic!("Wrong packet type"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_packet_in_pieces() {
+        use crate::{
+            packets::game::clientbound_custom_payload_packet::ClientboundCustomPayloadPacket,
+            write::{write_packet, Compressor},
+        };
+        use azalea_buf::UnsizedByteArray;
+        use azalea_core::ResourceLocation;
+        use tokio::io::AsyncWriteExt;
+
+        let packet: ClientboundGamePacket = ClientboundCustomPayloadPacket {
+            identifier: ResourceLocation::new("minecraft:brand").unwrap(),
+            data: UnsizedByteArray::from((0..100_000).map(|i| i as u8).collect::<Vec<_>>()),
+        }
+        .get();
+        let mut data = Vec::new();
+        write_packet(
+            &packet,
+            &mut data,
+            None,
+            &mut None,
+            &mut Compressor::default(),
+        )
+        .await
+        .unwrap();
+
+        // the other side can only hold a few bytes at a time, so the packet
+        // has to be read in a lot of small pieces
+        let (mut client, mut server) = tokio::io::duplex(100);
+        tokio::spawn(async move {
+            server.write_all(&data).await.unwrap();
+        });
+        let mut buffer = BytesMut::new();
+        let read = read_packet::<ClientboundGamePacket, _>(
+            &mut client,
+            &mut buffer,
+            None,
+            &mut None,
+            &mut Decompressor::default(),
+        )
+        .await
+        .unwrap();
+        match (read, packet) {
+            (
+                ClientboundGamePacket::CustomPayload(read),
+                ClientboundGamePacket::CustomPayload(p),
+            ) => {
+                assert_eq!(read.data, p.data)
+            }
+            _ => panic!("Wrong packet type"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        use crate::{
+            packets::game::clientbound_custom_payload_packet::ClientboundCustomPayloadPacket,
+            write::{write_packet, Compressor},
+        };
+        use azalea_buf::UnsizedByteArray;
+        use azalea_core::ResourceLocation;
+
+        // one packet that's too small to be compressed and one that isn't
+        let mut stream = Vec::new();
+        let mut compressor = Compressor::default();
+        for data_len in [10, 1000] {
+            let packet: ClientboundGamePacket = ClientboundCustomPayloadPacket {
+                identifier: ResourceLocation::new("minecraft:brand").unwrap(),
+                data: UnsizedByteArray::from(vec![1; data_len]),
+            }
+            .get();
+            write_packet(&packet, &mut stream, Some(256), &mut None, &mut compressor)
+                .await
+                .unwrap();
+        }
+
+        let mut reader = Cursor::new(stream);
+        let mut buffer = BytesMut::new();
+        let mut decompressor = Decompressor::default();
+        for data_len in [10, 1000] {
+            let (id, data) = read_frame(
+                &mut reader,
+                &mut buffer,
+                Some(256),
+                &mut None,
+                &mut decompressor,
+                &PacketLimits::default(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(id, 0x16);
+            // the identifier, then the data
+            assert_eq!(data[0] as usize, "minecraft:brand".len());
+            assert_eq!(data.len(), 1 + "minecraft:brand".len() + data_len);
+        }
+    }
+
+    #[test]
+    fn test_compressed_packet_below_threshold() {
+        // a compressed packet that claims to be smaller than the threshold
+        let data = [10, 0x78, 0x9c, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let mut decompressor = Decompressor::default();
+        assert!(matches!(
+            compression_decoder(&data, 256, &mut decompressor, MAXIMUM_UNCOMPRESSED_LENGTH),
+            Err(DecompressionError::BelowCompressionThreshold {
+                size: 10,
+                threshold: 256
+            })
+        ));
+    }
+
+    #[test]
+    fn test_frame_too_big() {
+        // a packet that says it's 300 bytes long
+        let mut buffer = BytesMut::from(&[0xac, 0x02, 0, 0][..]);
+        assert!(matches!(
+            frame_splitter(&mut buffer, 256),
+            Err(FrameSplitterError::TooBig {
+                max: 256,
+                size: 300
+            })
+        ));
+        assert!(matches!(frame_splitter(&mut buffer, 300), Ok(None)));
+    }
+
+    #[test]
+    fn test_decompressed_packet_too_big() {
+        use azalea_buf::McBufVarWritable;
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0; 10_000]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let packet = |size: u32| {
+            let mut data = Vec::new();
+            size.var_write_into(&mut data).unwrap();
+            data.extend_from_slice(&compressed);
+            data
+        };
+        let mut decompressor = Decompressor::default();
+
+        // says it's too big
+        assert!(matches!(
+            compression_decoder(&packet(10_000), 256, &mut decompressor, 1000),
+            Err(DecompressionError::AboveCompressionThreshold {
+                size: 10_000,
+                maximum: 1000
+            })
+        ));
+        // lies about its size to get past the check
+        assert!(matches!(
+            compression_decoder(&packet(500), 256, &mut decompressor, 1000),
+            Err(DecompressionError::AboveCompressionThreshold { maximum: 1000, .. })
+        ));
+        assert_eq!(
+            compression_decoder(&packet(500), 256, &mut decompressor, 10_000)
+                .unwrap()
+                .len(),
+            10_000
+        );
+    }
+
+    #[test]
+    fn test_read_packet_from_wrong_state() {
+        // a game packet id is past the end of the login packet table
+        let data = [1, 2, 3];
+        let result = ClientboundLoginPacket::read(
+            0x30,
+            &mut Cursor::new(&data[..]),
+            ProtocolVersion::LATEST,
+        );
+        assert!(matches!(
+            result,
+            Err(ReadPacketError::UnexpectedPacket {
+                id: 0x30,
+                state: ConnectionProtocol::Login,
+                data,
+            }) if data == [1, 2, 3]
+        ));
+    }
 }
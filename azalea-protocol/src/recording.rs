@@ -0,0 +1,476 @@
+//! Recording the packets that go through a connection to a file, so they can
+//! be looked at later or used as test cases for the packet decoders. See
+//! [`Connection::record`](crate::connect::Connection::record).
+//!
+//! # Format
+//!
+//! All numbers are big-endian. A recording starts with the 4 bytes `AZRP`
+//! and a version byte, which is currently `1`. After that there's a record
+//! for every packet:
+//!
+//! | Field     | Type  | Description                                                 |
+//! |-----------|-------|-------------------------------------------------------------|
+//! | Time      | `u64` | Microseconds since the recording was started                |
+//! | Direction | `u8`  | `0` if the packet was read, `1` if it was written           |
+//! | State     | `i8`  | The [`ConnectionProtocol`] the connection was in            |
+//! | Length    | `u32` | The length of the packet                                    |
+//! | Packet    | bytes | The packet id and fields, after decryption and decompression |
+//!
+//! Packets that couldn't be decoded are recorded too.
+//!
+//! Recordings can be played back with [`ReplayConnection`].
+
+use crate::packets::game::{ClientboundGamePacket, ServerboundGamePacket};
+use crate::packets::handshake::{ClientboundHandshakePacket, ServerboundHandshakePacket};
+use crate::packets::login::{ClientboundLoginPacket, ServerboundLoginPacket};
+use crate::packets::status::{ClientboundStatusPacket, ServerboundStatusPacket};
+use crate::packets::{ConnectionProtocol, ProtocolPacket, ProtocolVersion};
+use crate::read::{packet_decoder, ReadPacketError};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, BufWriter, Cursor, Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{trace, warn};
+
+/// The bytes that every recording starts with.
+const MAGIC: [u8; 4] = *b"AZRP";
+/// The version of the format that we write.
+const VERSION: u8 = 1;
+
+/// Whether a packet was read from or written to the connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// Writes packets to a recording. It can be cloned, and all the clones write
+/// to the same recording.
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<Mutex<RecorderInner>>,
+}
+
+struct RecorderInner {
+    writer: Option<Box<dyn Write + Send>>,
+    started_at: Instant,
+    state: ConnectionProtocol,
+}
+
+impl Recorder {
+    /// Start a recording that's written to `writer`. `state` is the state that
+    /// the connection is currently in, which is updated by the connection
+    /// when it switches states.
+    pub fn new(
+        mut writer: impl Write + Send + 'static,
+        state: ConnectionProtocol,
+    ) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Recorder {
+            inner: Arc::new(Mutex::new(RecorderInner {
+                writer: Some(Box::new(writer)),
+                started_at: Instant::now(),
+                state,
+            })),
+        })
+    }
+
+    /// Start a recording in a new file at `path`, replacing it if it
+    /// already exists.
+    pub fn create(path: impl AsRef<Path>, state: ConnectionProtocol) -> io::Result<Self> {
+        Recorder::new(BufWriter::new(File::create(path)?), state)
+    }
+
+    /// Add a packet to the recording. If writing fails, a warning is logged
+    /// and nothing else is recorded, so a broken recording never breaks the
+    /// connection.
+    pub fn record(&self, direction: Direction, packet: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        let time = inner.started_at.elapsed().as_micros() as u64;
+        let state = inner.state;
+        let writer = match &mut inner.writer {
+            Some(writer) => writer,
+            None => return,
+        };
+        let result = (|| {
+            writer.write_all(&time.to_be_bytes())?;
+            writer.write_all(&[direction as u8, state as i8 as u8])?;
+            writer.write_all(&(packet.len() as u32).to_be_bytes())?;
+            writer.write_all(packet)
+        })();
+        if let Err(e) = result {
+            warn!("Couldn't write packet to recording, stopping the recording: {e}");
+            inner.writer = None;
+        }
+    }
+
+    /// Set the state that the next packets are recorded with.
+    pub fn set_state(&self, state: ConnectionProtocol) {
+        self.inner.lock().unwrap().state = state;
+    }
+
+    /// Flush the recording. This also happens when every clone of the
+    /// recorder is dropped.
+    pub fn flush(&self) -> io::Result<()> {
+        match &mut self.inner.lock().unwrap().writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A packet from a recording.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedPacket {
+    /// How long after the recording started the packet went through.
+    pub time: Duration,
+    pub direction: Direction,
+    pub state: ConnectionProtocol,
+    /// The packet id followed by its fields.
+    pub data: Vec<u8>,
+}
+
+impl RecordedPacket {
+    /// Decode the packet. `P` has to be the packet type for the packet's
+    /// direction and state, like [`ClientboundGamePacket`] for inbound game
+    /// packets on a client.
+    ///
+    /// [`ClientboundGamePacket`]: crate::packets::game::ClientboundGamePacket
+    pub fn packet<P: ProtocolPacket + Debug>(
+        &self,
+        version: ProtocolVersion,
+    ) -> Result<P, ReadPacketError> {
+        packet_decoder(&mut Cursor::new(&self.data), version)
+    }
+}
+
+/// Reads the packets from a recording. This is an iterator over the
+/// packets in it.
+pub struct RecordingReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> RecordingReader<R> {
+    /// Start reading a recording, checking that it's in a format we
+    /// understand.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a packet recording",
+            ));
+        }
+        if header[4] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported recording version {}", header[4]),
+            ));
+        }
+        Ok(RecordingReader { reader })
+    }
+
+    fn read_packet(&mut self) -> io::Result<Option<RecordedPacket>> {
+        let mut header = [0; 14];
+        // a recording can end after any packet
+        match self.reader.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => self.reader.read_exact(&mut header[1..])?,
+        }
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let time = u64::from_be_bytes(header[..8].try_into().unwrap());
+        let direction = match header[8] {
+            0 => Direction::Inbound,
+            1 => Direction::Outbound,
+            _ => return Err(invalid("Invalid packet direction")),
+        };
+        let state = ConnectionProtocol::from_i32(header[9] as i8 as i32)
+            .ok_or_else(|| invalid("Invalid connection state"))?;
+        let length = u32::from_be_bytes(header[10..].try_into().unwrap());
+        let mut data = vec![0; length as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(RecordedPacket {
+            time: Duration::from_micros(time),
+            direction,
+            state,
+            data,
+        }))
+    }
+}
+
+impl RecordingReader<io::BufReader<File>> {
+    /// Open a recording file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        RecordingReader::new(io::BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = io::Result<RecordedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_packet().transpose()
+    }
+}
+
+/// Plays back the inbound packets from a recording, with the same timing
+/// that they were recorded with. It has the same methods for reading and
+/// writing packets as [`Connection`](crate::connect::Connection), so code
+/// that handles packets can be run against a recorded session.
+///
+/// Packets that are written aren't sent anywhere, and the outbound packets
+/// in the recording are ignored.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use azalea_protocol::recording::ReplayConnection;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let conn = ReplayConnection::open("session.azrp")?;
+/// let mut conn = conn.login();
+/// while let Ok(packet) = conn.read().await {
+///     println!("{packet:?}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReplayConnection<R: ProtocolPacket, W: ProtocolPacket> {
+    packets: VecDeque<RecordedPacket>,
+    /// When the replay started, which is when the recording started in the
+    /// recording's timeline.
+    started_at: tokio::time::Instant,
+    real_time: bool,
+    protocol_version: ProtocolVersion,
+    _reading: PhantomData<R>,
+    _writing: PhantomData<W>,
+}
+
+impl ReplayConnection<ClientboundHandshakePacket, ServerboundHandshakePacket> {
+    /// Load a recording to play back. Like a new
+    /// [`Connection`](crate::connect::Connection), the replay starts in the
+    /// handshake state.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        ReplayConnection::new(RecordingReader::open(path)?)
+    }
+
+    /// Load a recording from a reader. The whole recording is read before
+    /// this returns.
+    pub fn new(reader: RecordingReader<impl Read>) -> io::Result<Self> {
+        let packets = reader
+            .filter(|packet| match packet {
+                Ok(packet) => packet.direction == Direction::Inbound,
+                Err(_) => true,
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(ReplayConnection {
+            packets,
+            started_at: tokio::time::Instant::now(),
+            real_time: true,
+            protocol_version: ProtocolVersion::LATEST,
+            _reading: PhantomData,
+            _writing: PhantomData,
+        })
+    }
+
+    /// Change our state from handshake to login.
+    pub fn login(self) -> ReplayConnection<ClientboundLoginPacket, ServerboundLoginPacket> {
+        self.switch_state()
+    }
+
+    /// Change our state from handshake to status.
+    pub fn status(self) -> ReplayConnection<ClientboundStatusPacket, ServerboundStatusPacket> {
+        self.switch_state()
+    }
+}
+
+impl ReplayConnection<ClientboundLoginPacket, ServerboundLoginPacket> {
+    /// Change our state from login to game.
+    pub fn game(self) -> ReplayConnection<ClientboundGamePacket, ServerboundGamePacket> {
+        self.switch_state()
+    }
+}
+
+impl<R, W> ReplayConnection<R, W>
+where
+    R: ProtocolPacket + Debug,
+    W: ProtocolPacket + Debug,
+{
+    /// Read the next inbound packet from the recording, waiting until it's
+    /// as far into the replay as the packet was into the recording.
+    ///
+    /// When the recording runs out of packets this returns
+    /// [`ReadPacketError::ConnectionClosed`], like a connection that the
+    /// server closed.
+    pub async fn read(&mut self) -> Result<R, ReadPacketError> {
+        let packet = self
+            .packets
+            .pop_front()
+            .ok_or(ReadPacketError::ConnectionClosed)?;
+        if self.real_time {
+            tokio::time::sleep_until(self.started_at + packet.time).await;
+        }
+        let result = packet.packet(self.protocol_version);
+        trace!("Replayed packet {result:?}");
+        result
+    }
+
+    /// Pretend to write a packet. It isn't sent anywhere.
+    pub async fn write(&mut self, packet: W) -> io::Result<()> {
+        trace!("Replay ignoring written packet {packet:?}");
+        Ok(())
+    }
+
+    /// Pretend to write a lot of packets at once.
+    pub async fn write_all(&mut self, packets: impl IntoIterator<Item = W>) -> io::Result<()> {
+        for packet in packets {
+            self.write(packet).await?;
+        }
+        Ok(())
+    }
+
+    /// Does nothing, since written packets aren't sent anywhere.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Whether packets are replayed with the timing they were recorded
+    /// with, which is true by default. If this is false, packets are read as
+    /// fast as possible.
+    pub fn set_real_time(&mut self, real_time: bool) {
+        self.real_time = real_time;
+    }
+
+    /// Set the version of the protocol that the packets were recorded with,
+    /// which is [`ProtocolVersion::LATEST`] by default.
+    pub fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+        self.protocol_version = protocol_version;
+    }
+
+    /// The number of inbound packets that haven't been read yet.
+    pub fn remaining(&self) -> usize {
+        self.packets.len()
+    }
+
+    fn switch_state<R2, W2>(self) -> ReplayConnection<R2, W2>
+    where
+        R2: ProtocolPacket,
+        W2: ProtocolPacket,
+    {
+        ReplayConnection {
+            packets: self.packets,
+            started_at: self.started_at,
+            real_time: self.real_time,
+            protocol_version: self.protocol_version,
+            _reading: PhantomData,
+            _writing: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::login::{
+        clientbound_hello_packet::ClientboundHelloPacket,
+        clientbound_login_compression_packet::ClientboundLoginCompressionPacket,
+    };
+
+    /// A writer that keeps what's written to it so the test can read it
+    /// after the recorder is dropped.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn encode(packet: impl ProtocolPacket) -> Vec<u8> {
+        let mut data = Vec::new();
+        azalea_buf::McBufVarWritable::var_write_into(&packet.id(), &mut data).unwrap();
+        packet.write(&mut data, ProtocolVersion::LATEST).unwrap();
+        data
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let buffer = SharedBuffer::default();
+        let recorder = Recorder::new(buffer.clone(), ConnectionProtocol::Login).unwrap();
+        recorder.record(
+            Direction::Inbound,
+            &encode(
+                ClientboundLoginCompressionPacket {
+                    compression_threshold: 256,
+                }
+                .get(),
+            ),
+        );
+        // outbound packets are skipped
+        recorder.record(Direction::Outbound, &[0x00, 0x03, b'b', b'o', b't', 0]);
+        std::thread::sleep(Duration::from_millis(50));
+        recorder.record(
+            Direction::Inbound,
+            &encode(
+                ClientboundHelloPacket {
+                    server_id: String::new(),
+                    public_key: vec![1, 2, 3],
+                    nonce: vec![4, 5],
+                }
+                .get(),
+            ),
+        );
+        drop(recorder);
+
+        let data = buffer.0.lock().unwrap().clone();
+        let mut conn = ReplayConnection::new(RecordingReader::new(&data[..]).unwrap())
+            .unwrap()
+            .login();
+        assert_eq!(conn.remaining(), 2);
+        let started_at = Instant::now();
+        assert!(matches!(
+            conn.read().await.unwrap(),
+            ClientboundLoginPacket::LoginCompression(p) if p.compression_threshold == 256
+        ));
+        conn.write(
+            crate::packets::login::serverbound_hello_packet::ServerboundHelloPacket {
+                username: "bot".to_string(),
+                public_key: None,
+                profile_id: None,
+            }
+            .get(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            conn.read().await.unwrap(),
+            ClientboundLoginPacket::Hello(p) if p.nonce == [4, 5]
+        ));
+        // the second packet was replayed with the same delay it was recorded with
+        assert!(started_at.elapsed() >= Duration::from_millis(40));
+        assert!(matches!(
+            conn.read().await,
+            Err(ReadPacketError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_recording() {
+        assert!(RecordingReader::new(&b"AZRQ\x01"[..]).is_err());
+        assert!(RecordingReader::new(&b"AZRP\x02"[..]).is_err());
+        // a recording that ends in the middle of a packet
+        let mut reader = RecordingReader::new(&b"AZRP\x01\0\0\0"[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+}
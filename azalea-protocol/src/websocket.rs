@@ -0,0 +1,254 @@
+//! Sending packets over a WebSocket, for going through a WebSocket to TCP
+//! bridge like websockify.
+//!
+//! The packets are sent as the same byte stream as over TCP, split into
+//! binary messages. Since the connection types are generic over their
+//! streams, [`WebSocketReader`] and [`WebSocketWriter`] can be used with
+//! [`Connection::from_split`] for any WebSocket implementation.
+//!
+//! This doesn't work in browsers (wasm32). The connection still uses tokio's
+//! networking to resolve and connect, so this is only for native clients
+//! that have to go through a WebSocket bridge.
+
+use crate::connect::{connection_span, Connection, ConnectionError, ConnectionOptions};
+use crate::packets::handshake::{ClientboundHandshakePacket, ServerboundHandshakePacket};
+use futures::{ready, stream::SplitSink, stream::SplitStream, Sink, Stream, StreamExt};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::debug;
+
+/// The WebSocket stream that [`Connection::new_websocket`] uses.
+pub type DefaultWebSocketStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The read half of a WebSocket, which reads the data from binary messages.
+/// Text messages are ignored, and a close message is the end of the stream.
+pub struct WebSocketReader<S = SplitStream<DefaultWebSocketStream>> {
+    stream: S,
+    /// The message that we're in the middle of reading.
+    message: Vec<u8>,
+    position: usize,
+}
+
+/// The write half of a WebSocket, which sends every write as a binary
+/// message.
+pub struct WebSocketWriter<S = SplitSink<DefaultWebSocketStream, Message>> {
+    sink: S,
+}
+
+impl<S> WebSocketReader<S> {
+    pub fn new(stream: S) -> Self {
+        WebSocketReader {
+            stream,
+            message: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<S> WebSocketWriter<S> {
+    pub fn new(sink: S) -> Self {
+        WebSocketWriter { sink }
+    }
+}
+
+/// The read half of a [`WebSocketStream`] that was split with [`split`].
+pub type SplitWebSocketReader<T> = WebSocketReader<SplitStream<WebSocketStream<T>>>;
+/// The write half of a [`WebSocketStream`] that was split with [`split`].
+pub type SplitWebSocketWriter<T> = WebSocketWriter<SplitSink<WebSocketStream<T>, Message>>;
+
+/// Split a WebSocket into halves that can be used as the streams of a
+/// [`Connection`].
+pub fn split<T>(websocket: WebSocketStream<T>) -> (SplitWebSocketReader<T>, SplitWebSocketWriter<T>)
+where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (sink, stream) = websocket.split();
+    (WebSocketReader::new(stream), WebSocketWriter::new(sink))
+}
+
+fn to_io_error(error: tungstenite::Error) -> io::Error {
+    match error {
+        tungstenite::Error::Io(error) => error,
+        tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => {
+            io::Error::new(io::ErrorKind::BrokenPipe, error)
+        }
+        // everything else is the other side not following the protocol
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+impl<S> AsyncRead for WebSocketReader<S>
+where
+    S: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if self.position < self.message.len() {
+                let end = (self.position + buf.remaining()).min(self.message.len());
+                buf.put_slice(&self.message[self.position..end]);
+                self.position = end;
+                return Poll::Ready(Ok(()));
+            }
+            match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                Some(Ok(Message::Binary(data))) => {
+                    self.message = data;
+                    self.position = 0;
+                }
+                // reading nothing means the stream ended
+                Some(Ok(Message::Close(_))) | None => return Poll::Ready(Ok(())),
+                // pings are answered by tungstenite
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Poll::Ready(Err(to_io_error(e))),
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for WebSocketWriter<S>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(Pin::new(&mut self.sink).poll_ready(cx)).map_err(to_io_error)?;
+        Pin::new(&mut self.sink)
+            .start_send(Message::Binary(buf.to_vec()))
+            .map_err(to_io_error)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.sink).poll_flush(cx).map_err(to_io_error)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.sink).poll_close(cx).map_err(to_io_error)
+    }
+}
+
+impl
+    Connection<
+        ClientboundHandshakePacket,
+        ServerboundHandshakePacket,
+        WebSocketReader,
+        WebSocketWriter,
+    >
+{
+    /// Create a new connection through a WebSocket at the given `ws://`
+    /// URL.
+    ///
+    /// ```rust,no_run
+    /// # use azalea_protocol::connect::Connection;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let conn = Connection::new_websocket("ws://localhost:8080").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn new_websocket(url: &str) -> Result<Self, ConnectionError> {
+        Self::new_websocket_with_options(url, &ConnectionOptions::default()).await
+    }
+
+    /// Create a new connection through a WebSocket with the given options.
+    /// The PROXY protocol header is never sent, since the bridge is what
+    /// connects to the server.
+    pub async fn new_websocket_with_options(
+        url: &str,
+        options: &ConnectionOptions,
+    ) -> Result<Self, ConnectionError> {
+        let connect = async {
+            let (websocket, _) = tokio_tungstenite::connect_async(url).await?;
+            Ok(websocket)
+        };
+        let websocket = match options.connect_timeout {
+            Some(connect_timeout) => tokio::time::timeout(connect_timeout, connect)
+                .await
+                .unwrap_or(Err(ConnectionError::Timeout))?,
+            None => connect.await?,
+        };
+        let (read_stream, write_stream) = split(websocket);
+        let mut connection = Connection::from_split(read_stream, write_stream);
+        connection_span(connection.id()).in_scope(|| debug!("is with {url}"));
+        connection.set_options(options);
+        Ok(connection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::handshake::client_intention_packet::ClientIntentionPacket;
+    use crate::packets::login::{
+        clientbound_login_compression_packet::ClientboundLoginCompressionPacket,
+        ClientboundLoginPacket, ServerboundLoginPacket,
+    };
+    use crate::packets::{ConnectionProtocol, PROTOCOL_VERSION};
+
+    #[tokio::test]
+    async fn test_websocket() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let (read_stream, write_stream) = split(websocket);
+            let mut server = Connection::<
+                ServerboundHandshakePacket,
+                ClientboundHandshakePacket,
+                _,
+                _,
+            >::from_split(read_stream, write_stream);
+            assert!(matches!(
+                server.read().await.unwrap(),
+                ServerboundHandshakePacket::ClientIntention(p) if p.hostname == "localhost"
+            ));
+            let mut server = server.login();
+            server
+                .write(
+                    ClientboundLoginCompressionPacket {
+                        compression_threshold: 256,
+                    }
+                    .get(),
+                )
+                .await
+                .unwrap();
+            // make sure the client can read everything before we close
+            assert!(server.read().await.is_err());
+        });
+
+        let mut conn = Connection::new_websocket(&format!("ws://{address}"))
+            .await
+            .unwrap();
+        conn.write(
+            ClientIntentionPacket {
+                protocol_version: PROTOCOL_VERSION,
+                hostname: "localhost".to_string(),
+                port: 25565,
+                intention: ConnectionProtocol::Login,
+            }
+            .get(),
+        )
+        .await
+        .unwrap();
+        let mut conn: Connection<ClientboundLoginPacket, ServerboundLoginPacket, _, _> =
+            conn.login();
+        assert!(matches!(
+            conn.read().await.unwrap(),
+            ClientboundLoginPacket::LoginCompression(p) if p.compression_threshold == 256
+        ));
+        conn.writer.shutdown().await.unwrap();
+        server.await.unwrap();
+    }
+}
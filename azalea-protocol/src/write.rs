@@ -1,16 +1,31 @@
-use crate::{packets::ProtocolPacket, read::MAXIMUM_UNCOMPRESSED_LENGTH};
-use async_compression::tokio::bufread::ZlibEncoder;
+use crate::{
+    packets::{ProtocolPacket, ProtocolVersion},
+    read::MAXIMUM_UNCOMPRESSED_LENGTH,
+};
 use azalea_buf::McBufVarWritable;
 use azalea_crypto::Aes128CfbEnc;
+use bytes::{BufMut, BytesMut};
+use flate2::{Compress, Compression, FlushCompress, Status};
 use std::fmt::Debug;
 use thiserror::Error;
-use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-fn frame_prepender(data: &mut Vec<u8>) -> Result<Vec<u8>, std::io::Error> {
-    let mut buf = Vec::new();
-    (data.len() as u32).var_write_into(&mut buf)?;
-    buf.append(data);
-    Ok(buf)
+/// How many bytes are kept free at the start of the packet buffers, so the
+/// frame length and data length can be written in front of the packet
+/// without moving it. This is enough for two varints.
+const HEADROOM: usize = 10;
+
+/// Write a varint so that it ends right before `end`, and return where it
+/// starts. There has to be enough room in front of `end` for it.
+fn prepend_varint(buf: &mut [u8], end: usize, value: u32) -> usize {
+    let mut varint = [0; 5];
+    let mut remaining = &mut varint[..];
+    // writing to a slice that's big enough can't fail
+    value.var_write_into(&mut remaining).unwrap();
+    let length = 5 - remaining.len();
+    let start = end - length;
+    buf[start..end].copy_from_slice(&varint[..length]);
+    start
 }
 
 #[derive(Error, Debug)]
@@ -27,18 +42,23 @@ pub enum PacketEncodeError {
 
 fn packet_encoder<P: ProtocolPacket + std::fmt::Debug>(
     packet: &P,
-) -> Result<Vec<u8>, PacketEncodeError> {
-    let mut buf = Vec::new();
-    packet.id().var_write_into(&mut buf)?;
-    packet.write(&mut buf)?;
-    if buf.len() > MAXIMUM_UNCOMPRESSED_LENGTH as usize {
+    buf: &mut BytesMut,
+    version: ProtocolVersion,
+) -> Result<(), PacketEncodeError> {
+    buf.clear();
+    buf.put_bytes(0, HEADROOM);
+    let mut writer = (&mut *buf).writer();
+    packet.id().var_write_into(&mut writer)?;
+    packet.write(&mut writer, version)?;
+    let length = buf.len() - HEADROOM;
+    if length > MAXIMUM_UNCOMPRESSED_LENGTH as usize {
         return Err(PacketEncodeError::TooBig {
-            actual: buf.len(),
+            actual: length,
             maximum: MAXIMUM_UNCOMPRESSED_LENGTH as usize,
             packet_string: format!("{packet:?}"),
         });
     }
-    Ok(buf)
+    Ok(())
 }
 
 #[derive(Error, Debug)]
@@ -47,45 +67,246 @@ pub enum PacketCompressError {
     Io(#[from] std::io::Error),
 }
 
-async fn compression_encoder(
+/// The zlib state and buffers used for encoding packets. These are kept
+/// around between packets so we don't have to allocate new ones every time we
+/// write a packet.
+pub struct Compressor {
+    zlib: Compress,
+    /// The id and fields of the packet, after [`HEADROOM`] bytes. The frame
+    /// is made in place in here when the packet isn't compressed.
+    packet_buffer: BytesMut,
+    /// The compressed packet, after [`HEADROOM`] bytes. It's a `Vec` because
+    /// that's what zlib writes into.
+    compression_buffer: Vec<u8>,
+    /// A copy of frames that are in the packet buffer, since encrypting them
+    /// in place would change the packet.
+    encryption_buffer: Vec<u8>,
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Self {
+            zlib: Compress::new(Compression::default(), true),
+            packet_buffer: BytesMut::from(&[0; HEADROOM][..]),
+            compression_buffer: Vec::new(),
+            encryption_buffer: Vec::new(),
+        }
+    }
+}
+
+impl Compressor {
+    /// Set the zlib compression level, from 0 (no compression) to 9 (best
+    /// compression). Higher levels make packets smaller but take more CPU
+    /// time. Levels higher than 9 are treated as 9.
+    pub fn set_level(&mut self, level: u32) {
+        self.zlib = Compress::new(Compression::new(level.min(9)), true);
+    }
+
+    /// The bytes of the packet id and fields of the last packet that was
+    /// written with this, before compression and encryption.
+    pub(crate) fn last_packet(&self) -> &[u8] {
+        &self.packet_buffer[HEADROOM..]
+    }
+}
+
+/// Compress the data into the buffer after [`HEADROOM`] bytes, and put the
+/// uncompressed length in front of it. Returns where the data length starts.
+fn compression_encoder(
     data: &[u8],
-    compression_threshold: u32,
-) -> Result<Vec<u8>, PacketCompressError> {
+    zlib: &mut Compress,
+    buf: &mut Vec<u8>,
+) -> Result<usize, PacketCompressError> {
     let n = data.len();
-    // if it's less than the compression threshold, don't compress
-    if n < compression_threshold as usize {
-        let mut buf = Vec::new();
-        0.var_write_into(&mut buf)?;
-        buf.write_all(data).await?;
-        Ok(buf)
-    } else {
-        // otherwise, compress
-        let mut deflater = ZlibEncoder::new(data);
-        // write deflated data to buf
-        let mut buf = Vec::new();
-        deflater.read_to_end(&mut buf).await?;
-        Ok(buf)
+    buf.clear();
+    buf.resize(HEADROOM, 0);
+    zlib.reset();
+    buf.reserve(n);
+    loop {
+        let input = &data[zlib.total_in() as usize..];
+        let status = zlib
+            .compress_vec(input, buf, FlushCompress::Finish)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        if status == Status::StreamEnd {
+            break;
+        }
+        // we ran out of room, so make some more
+        buf.reserve(buf.capacity());
     }
+    Ok(prepend_varint(buf, HEADROOM, n as u32))
 }
 
+/// Encode and write a packet to the stream. The packet is written as it is in
+/// [`ProtocolVersion::LATEST`].
 pub async fn write_packet<P, W>(
     packet: &P,
     stream: &mut W,
     compression_threshold: Option<u32>,
     cipher: &mut Option<Aes128CfbEnc>,
+    compressor: &mut Compressor,
 ) -> std::io::Result<()>
 where
     P: ProtocolPacket + Debug,
     W: AsyncWrite + Unpin + Send,
 {
-    let mut buf = packet_encoder(packet).unwrap();
-    if let Some(threshold) = compression_threshold {
-        buf = compression_encoder(&buf, threshold).await.unwrap();
-    }
-    buf = frame_prepender(&mut buf).unwrap();
+    encode_packet(packet, compressor, ProtocolVersion::LATEST);
+    write_encoded_packet(stream, compression_threshold, cipher, compressor).await
+}
+
+/// Encode the id and fields of a packet into the compressor's packet buffer.
+pub(crate) fn encode_packet<P: ProtocolPacket + Debug>(
+    packet: &P,
+    compressor: &mut Compressor,
+    version: ProtocolVersion,
+) {
+    packet_encoder(packet, &mut compressor.packet_buffer, version).unwrap();
+}
+
+/// Put the id and fields of a packet that was already encoded into the
+/// compressor's packet buffer.
+pub(crate) fn encode_raw_packet(id: u32, data: &[u8], compressor: &mut Compressor) {
+    let buf = &mut compressor.packet_buffer;
+    buf.clear();
+    buf.put_bytes(0, HEADROOM);
+    // writing to a BytesMut can't fail
+    id.var_write_into(&mut (&mut *buf).writer()).unwrap();
+    buf.extend_from_slice(data);
+}
+
+/// Compress, frame, and encrypt the packet in the compressor's packet buffer,
+/// and write it to the stream.
+///
+/// The frame is made by writing the lengths into the space in front of the
+/// packet (or the compressed packet), so the packet is only copied if it has
+/// to be encrypted without being compressed.
+pub(crate) async fn write_encoded_packet<W>(
+    stream: &mut W,
+    compression_threshold: Option<u32>,
+    cipher: &mut Option<Aes128CfbEnc>,
+    compressor: &mut Compressor,
+) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    let Compressor {
+        zlib,
+        packet_buffer,
+        compression_buffer,
+        encryption_buffer,
+    } = compressor;
+    let packet_length = packet_buffer.len() - HEADROOM;
+
+    let (frame_buffer, start, in_packet_buffer): (&mut [u8], usize, bool) =
+        match compression_threshold {
+            // packets that are exactly the threshold are compressed, like
+            // vanilla
+            Some(threshold) if packet_length >= threshold as usize => {
+                let start =
+                    compression_encoder(&packet_buffer[HEADROOM..], zlib, compression_buffer)
+                        .unwrap();
+                (compression_buffer, start, false)
+            }
+            // if it's less than the compression threshold, don't compress
+            Some(_) => {
+                let start = prepend_varint(packet_buffer, HEADROOM, 0);
+                (packet_buffer, start, true)
+            }
+            None => (packet_buffer, HEADROOM, true),
+        };
+    let frame_length = (frame_buffer.len() - start) as u32;
+    let start = prepend_varint(frame_buffer, start, frame_length);
+    let frame = &mut frame_buffer[start..];
+
     // if we were given a cipher, encrypt the packet
-    if let Some(cipher) = cipher {
-        azalea_crypto::encrypt_packet(cipher, &mut buf);
+    match cipher {
+        Some(cipher) if in_packet_buffer => {
+            encryption_buffer.clear();
+            encryption_buffer.extend_from_slice(frame);
+            azalea_crypto::encrypt_packet(cipher, encryption_buffer);
+            stream.write_all(encryption_buffer).await
+        }
+        Some(cipher) => {
+            azalea_crypto::encrypt_packet(cipher, frame);
+            stream.write_all(frame).await
+        }
+        None => stream.write_all(frame).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a raw packet with the given id and data, and return the frame
+    /// that's written for it.
+    async fn write_raw(
+        id: u32,
+        data: &[u8],
+        compression_threshold: Option<u32>,
+        cipher: &mut Option<Aes128CfbEnc>,
+        compressor: &mut Compressor,
+    ) -> Vec<u8> {
+        let mut stream = Vec::new();
+        encode_raw_packet(id, data, compressor);
+        write_encoded_packet(&mut stream, compression_threshold, cipher, compressor)
+            .await
+            .unwrap();
+        stream
+    }
+
+    #[tokio::test]
+    async fn test_compression_threshold() {
+        let mut compressor = Compressor::default();
+
+        // packets smaller than the threshold get a data length of 0 and
+        // aren't compressed
+        let data = vec![0; 254];
+        let frame = write_raw(0, &data, Some(256), &mut None, &mut compressor).await;
+        // the length is 256 (0x80 0x02), then the data length and packet id
+        assert_eq!(&frame[..4], &[0x80, 0x02, 0, 0]);
+        assert_eq!(&frame[4..], &data[..]);
+
+        // packets that are exactly the threshold are compressed, like vanilla
+        let data = vec![0; 255];
+        let frame = write_raw(0, &data, Some(256), &mut None, &mut compressor).await;
+        assert_eq!(frame[0] as usize, frame.len() - 1);
+        assert_eq!(&frame[1..3], &[0x80, 0x02]);
+        assert!(frame.len() < data.len());
+    }
+
+    #[tokio::test]
+    async fn test_no_compression() {
+        let mut compressor = Compressor::default();
+        let frame = write_raw(0x12, &[1, 2, 3], None, &mut None, &mut compressor).await;
+        assert_eq!(frame, [4, 0x12, 1, 2, 3]);
+        // the buffers are reused for the next packet
+        let frame = write_raw(0x34, &[5], None, &mut None, &mut compressor).await;
+        assert_eq!(frame, [2, 0x34, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_encryption_keeps_packet() {
+        let key = [7; 16];
+        let mut compressor = Compressor::default();
+        let (enc, mut dec) = azalea_crypto::create_cipher(&key);
+        let mut frame =
+            write_raw(0x12, &[1, 2, 3], Some(256), &mut Some(enc), &mut compressor).await;
+        // the packet is still there for interceptors and recordings
+        assert_eq!(compressor.last_packet(), [0x12, 1, 2, 3]);
+        azalea_crypto::decrypt_packet(&mut dec, &mut frame);
+        assert_eq!(frame, [5, 0, 0x12, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_compression_level() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let compressed_size = |level| {
+            let mut compressor = Compressor::default();
+            compressor.set_level(level);
+            let mut buf = Vec::new();
+            let start = compression_encoder(&data, &mut compressor.zlib, &mut buf).unwrap();
+            buf.len() - start
+        };
+        assert!(compressed_size(0) > data.len());
+        assert!(compressed_size(9) < compressed_size(1));
     }
-    stream.write_all(&buf).await
 }
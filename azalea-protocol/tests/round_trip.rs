@@ -0,0 +1,187 @@
+//! Make sure that packets read back the same as they were written, by
+//! reading packets from random bytes and writing them again.
+//!
+//! Every packet id in a state has to be read at least once, except for the
+//! ones that each test skips because random bytes are almost never a valid
+//! packet for them, like packets with JSON chat components.
+
+use azalea_protocol::packets::{
+    game::{ClientboundGamePacket, ServerboundGamePacket},
+    handshake::{ClientboundHandshakePacket, ServerboundHandshakePacket},
+    login::{ClientboundLoginPacket, ServerboundLoginPacket},
+    status::{ClientboundStatusPacket, ServerboundStatusPacket},
+    ProtocolPacket, ProtocolVersion,
+};
+use azalea_protocol::read::ReadPacketError;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{fmt::Debug, io::Cursor};
+
+/// How many random buffers are tried for every packet id.
+const ATTEMPTS: usize = 200;
+/// How many random buffers are tried for a packet id before giving up, if
+/// none of the first [`ATTEMPTS`] could be read as a packet.
+const MAX_ATTEMPTS: usize = 2000;
+/// The longest buffer that packets are read from.
+const MAX_LENGTH: usize = 96;
+/// The highest packet id that's tried, which is higher than any state has.
+const MAX_ID: u32 = 0x80;
+
+/// A byte that's more likely to be a small number or a letter, so lengths
+/// stay short and strings are usually valid.
+fn random_byte(rng: &mut impl Rng) -> u8 {
+    match rng.gen_range(0..4) {
+        0 => 0,
+        1 => rng.gen_range(1..4),
+        2 => rng.gen_range(b'a'..=b'z'),
+        _ => rng.gen(),
+    }
+}
+
+/// Write the packet, then check that reading and writing it again gives the
+/// same bytes. If `unordered` is true, the packet has a map (or an nbt
+/// compound) in it that might be written in a different order, so only the
+/// order of the bytes is allowed to change.
+fn assert_round_trips<P: ProtocolPacket + Debug>(id: u32, packet: &P, unordered: bool) {
+    let mut written = Vec::new();
+    packet.write(&mut written, ProtocolVersion::LATEST).unwrap();
+    let read =
+        P::read(id, &mut Cursor::new(&written), ProtocolVersion::LATEST).unwrap_or_else(|e| {
+            panic!("Couldn't read {packet:?} after writing it as {written:?}: {e}")
+        });
+    let mut rewritten = Vec::new();
+    read.write(&mut rewritten, ProtocolVersion::LATEST).unwrap();
+
+    let (mut expected, mut actual) = (written.clone(), rewritten.clone());
+    if unordered {
+        expected.sort_unstable();
+        actual.sort_unstable();
+    }
+    assert_eq!(
+        expected, actual,
+        "{packet:?} was written as {written:?}, but read back as {read:?} which was written as {rewritten:?}"
+    );
+}
+
+/// The ids that are in the state's packet table.
+fn known_ids<P: ProtocolPacket + Debug>() -> Vec<u32> {
+    (0..MAX_ID)
+        .filter(|&id| {
+            !matches!(
+                P::read(id, &mut Cursor::new(&[]), ProtocolVersion::LATEST),
+                Err(ReadPacketError::UnknownPacketId { .. }
+                    | ReadPacketError::UnexpectedPacket { .. })
+            )
+        })
+        .collect()
+}
+
+/// Read every packet id from prefixes of random buffers, and check that the
+/// packets that were read round trip. Every id in the state has to be read
+/// at least once, except for the `skipped` ones. The packets with the ids in
+/// `unordered` have maps in them, so the order of their bytes can change.
+fn round_trip_state<P: ProtocolPacket + Debug>(seed: u64, unordered: &[u32], skipped: &[u32]) {
+    let known_ids = known_ids::<P>();
+    for id in unordered.iter().chain(skipped) {
+        assert!(known_ids.contains(id), "{id:#04x} isn't a packet id");
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut read_ids = Vec::new();
+    for &id in &known_ids {
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt >= ATTEMPTS && (read_ids.contains(&id) || skipped.contains(&id)) {
+                break;
+            }
+            let buf: Vec<u8> = (0..MAX_LENGTH).map(|_| random_byte(&mut rng)).collect();
+            // packets fail to read if there's data left over, so try every
+            // length
+            for len in 0..=MAX_LENGTH {
+                if let Ok(packet) =
+                    P::read(id, &mut Cursor::new(&buf[..len]), ProtocolVersion::LATEST)
+                {
+                    assert_round_trips(id, &packet, unordered.contains(&id));
+                    if !read_ids.contains(&id) {
+                        read_ids.push(id);
+                    }
+                }
+            }
+        }
+    }
+
+    let missing: Vec<u32> = known_ids
+        .into_iter()
+        .filter(|id| !read_ids.contains(id) && !skipped.contains(id))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "No packets were read for the ids {missing:#04x?}"
+    );
+}
+
+#[test]
+fn test_round_trip_handshake() {
+    round_trip_state::<ClientboundHandshakePacket>(0, &[], &[]);
+    round_trip_state::<ServerboundHandshakePacket>(1, &[], &[]);
+}
+
+#[test]
+fn test_round_trip_status() {
+    // the status response is json
+    round_trip_state::<ClientboundStatusPacket>(2, &[], &[0x00]);
+    round_trip_state::<ServerboundStatusPacket>(3, &[], &[]);
+}
+
+#[test]
+fn test_round_trip_login() {
+    round_trip_state::<ClientboundLoginPacket>(4, &[], &[]);
+    round_trip_state::<ServerboundLoginPacket>(5, &[], &[]);
+}
+
+#[test]
+fn test_round_trip_game() {
+    round_trip_state::<ClientboundGamePacket>(
+        8,
+        &[
+            0x04, // award stats
+            0x07, // block entity data
+            0x11, // container set content
+            0x13, // container set slot
+            0x21, // level chunk with light
+            0x23, // level particles
+            0x25, // login
+            0x27, // merchant offers
+            0x50, // set entity data
+            0x53, // set equipment
+            0x64, // tag query
+            0x67, // update advancements
+            0x69, // update mob effect
+            0x6a, // update recipes
+            0x6b, // update tags
+        ],
+        &[
+            // chat components
+            0x19, // disconnect
+            0x2d, // open screen
+            0x36, // player combat kill
+            0x43, // set action bar text
+            0x5b, // set subtitle text
+            0x5d, // set title text
+            0x62, // system chat
+            0x63, // tab list
+            // too long or too complicated
+            0x21, // level chunk with light
+            0x24, // light update
+            0x33, // player chat
+        ],
+    );
+    round_trip_state::<ServerboundGamePacket>(
+        9,
+        &[
+            0x0b, // container click
+            0x2b, // set creative mode slot
+        ],
+        &[
+            0x2c, // set jigsaw block, which has a lot of resource locations
+        ],
+    );
+}
@@ -133,5 +133,15 @@ pub fn registry(input: TokenStream) -> TokenStream {
         }
     });
 
+    // serialized as the registry id, like minecraft:stone
+    generated.extend(quote! {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for #name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+    });
+
     generated.into()
 }
@@ -0,0 +1,234 @@
+//! Biomes, and finding which biome is at a position.
+
+use crate::block_entity::get_nbt_field;
+use azalea_core::{BlockPos, ResourceLocation};
+use azalea_nbt::Tag;
+use std::collections::HashMap;
+
+/// What falls from the sky when it's raining in a biome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precipitation {
+    None,
+    Rain,
+    Snow,
+}
+
+/// A biome from the biome registry that the server sends when we log in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Biome {
+    /// The id that the biome is referred to by in chunk data.
+    pub id: u32,
+    pub name: ResourceLocation,
+    pub temperature: f32,
+    pub downfall: f32,
+    pub precipitation: Precipitation,
+}
+
+/// The biomes that the server told us about, by their ids.
+#[derive(Clone, Debug, Default)]
+pub struct BiomeRegistry {
+    biomes: HashMap<u32, Biome>,
+}
+
+impl BiomeRegistry {
+    /// Read the `minecraft:worldgen/biome` registry from the registry holder
+    /// in the login packet. Returns `None` if it's missing. Biomes that are
+    /// missing fields are skipped.
+    pub fn from_registry_holder(registry_holder: &Tag) -> Option<Self> {
+        let entries = get_nbt_field(registry_holder, "minecraft:worldgen/biome")?;
+        let entries = get_nbt_field(entries, "value")?.as_list()?;
+        let biomes = entries
+            .iter()
+            .filter_map(Self::biome_from_nbt)
+            .map(|biome| (biome.id, biome))
+            .collect();
+        Some(BiomeRegistry { biomes })
+    }
+
+    fn biome_from_nbt(entry: &Tag) -> Option<Biome> {
+        let element = get_nbt_field(entry, "element")?;
+        let precipitation = match get_nbt_field(element, "precipitation")?.as_string()? {
+            "rain" => Precipitation::Rain,
+            "snow" => Precipitation::Snow,
+            _ => Precipitation::None,
+        };
+        Some(Biome {
+            id: *get_nbt_field(entry, "id")?.as_int()? as u32,
+            name: ResourceLocation::new(get_nbt_field(entry, "name")?.as_string()?).ok()?,
+            temperature: *get_nbt_field(element, "temperature")?.as_float()?,
+            downfall: *get_nbt_field(element, "downfall")?.as_float()?,
+            precipitation,
+        })
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Biome> {
+        self.biomes.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.biomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.biomes.is_empty()
+    }
+}
+
+/// Biomes are stored in 4x4x4 cells, so to make the edges between them less
+/// blocky, vanilla picks the cell for a block from the 8 cells around it
+/// using randomness from the hashed seed. This returns the x, y, and z of
+/// that cell, in quarter blocks.
+pub fn biome_cell(pos: &BlockPos, hashed_seed: i64) -> (i32, i32, i32) {
+    let (x, y, z) = (pos.x - 2, pos.y - 2, pos.z - 2);
+    let (quart_x, quart_y, quart_z) = (x >> 2, y >> 2, z >> 2);
+    let fraction_x = (x & 3) as f64 / 4.;
+    let fraction_y = (y & 3) as f64 / 4.;
+    let fraction_z = (z & 3) as f64 / 4.;
+
+    let mut closest = 0;
+    let mut closest_distance = f64::INFINITY;
+    for corner in 0..8 {
+        let (offset_x, offset_y, offset_z) = corner_offset(corner);
+        let distance = fiddled_distance(
+            hashed_seed,
+            quart_x + offset_x,
+            quart_y + offset_y,
+            quart_z + offset_z,
+            fraction_x - offset_x as f64,
+            fraction_y - offset_y as f64,
+            fraction_z - offset_z as f64,
+        );
+        if closest_distance > distance {
+            closest = corner;
+            closest_distance = distance;
+        }
+    }
+
+    let (offset_x, offset_y, offset_z) = corner_offset(closest);
+    (quart_x + offset_x, quart_y + offset_y, quart_z + offset_z)
+}
+
+fn corner_offset(corner: u8) -> (i32, i32, i32) {
+    (
+        (corner >> 2 & 1) as i32,
+        (corner >> 1 & 1) as i32,
+        (corner & 1) as i32,
+    )
+}
+
+fn fiddled_distance(
+    seed: i64,
+    quart_x: i32,
+    quart_y: i32,
+    quart_z: i32,
+    fraction_x: f64,
+    fraction_y: f64,
+    fraction_z: f64,
+) -> f64 {
+    let mut random = next_random(seed, quart_x as i64);
+    random = next_random(random, quart_y as i64);
+    random = next_random(random, quart_z as i64);
+    random = next_random(random, quart_x as i64);
+    random = next_random(random, quart_y as i64);
+    random = next_random(random, quart_z as i64);
+    let fiddle_x = fiddle(random);
+    random = next_random(random, seed);
+    let fiddle_y = fiddle(random);
+    random = next_random(random, seed);
+    let fiddle_z = fiddle(random);
+    (fraction_z + fiddle_z).powi(2)
+        + (fraction_y + fiddle_y).powi(2)
+        + (fraction_x + fiddle_x).powi(2)
+}
+
+fn fiddle(random: i64) -> f64 {
+    let fraction = (random >> 24).rem_euclid(1024) as f64 / 1024.;
+    (fraction - 0.5) * 0.9
+}
+
+/// Vanilla's `LinearCongruentialGenerator.next`.
+fn next_random(random: i64, add: i64) -> i64 {
+    random
+        .wrapping_mul(
+            random
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407),
+        )
+        .wrapping_add(add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_biome_registry() {
+        let plains = Tag::from([
+            ("name", Tag::String("minecraft:plains".to_string())),
+            ("id", Tag::Int(1)),
+            (
+                "element",
+                Tag::from([
+                    ("precipitation", Tag::String("rain".to_string())),
+                    ("temperature", Tag::Float(0.8)),
+                    ("downfall", Tag::Float(0.4)),
+                ]),
+            ),
+        ]);
+        let registry_holder = Tag::from([(
+            "",
+            Tag::from([(
+                "minecraft:worldgen/biome",
+                Tag::from([
+                    ("type", Tag::String("minecraft:worldgen/biome".to_string())),
+                    ("value", Tag::List(vec![plains, Tag::from([])])),
+                ]),
+            )]),
+        )]);
+
+        let registry = BiomeRegistry::from_registry_holder(&registry_holder).unwrap();
+        assert_eq!(registry.len(), 1);
+        let plains = registry.get(1).unwrap();
+        assert_eq!(
+            plains.name,
+            ResourceLocation::new("minecraft:plains").unwrap()
+        );
+        assert_eq!(plains.precipitation, Precipitation::Rain);
+        assert_eq!(plains.temperature, 0.8);
+        assert!(registry.get(0).is_none());
+    }
+
+    #[test]
+    fn test_biome_cell() {
+        for seed in [0, 1234, -5678] {
+            for x in -20..20 {
+                for y in [-64, 0, 70] {
+                    let pos = BlockPos::new(x, y, x * 3);
+                    let (quart_x, quart_y, quart_z) = biome_cell(&pos, seed);
+                    // the cell is always one of the ones next to the block
+                    assert!((quart_x - (pos.x >> 2)).abs() <= 1);
+                    assert!((quart_y - (pos.y >> 2)).abs() <= 1);
+                    assert!((quart_z - (pos.z >> 2)).abs() <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_biome_cell_vanilla() {
+        // the hashed seed for the world seed 0, and cells that were worked out
+        // with a copy of vanilla's BiomeManager.getBiome in java
+        let hashed_seed = 8794265229978523055;
+        for (pos, cell) in [
+            (BlockPos::new(0, 0, 0), (-1, 0, -1)),
+            (BlockPos::new(1, 64, 1), (0, 15, 0)),
+            (BlockPos::new(-5, -60, 13), (-2, -16, 3)),
+            (BlockPos::new(123, 70, -456), (31, 17, -115)),
+            (BlockPos::new(-1000, -64, 1000), (-250, -17, 249)),
+            (BlockPos::new(7, 319, -7), (1, 79, -2)),
+            (BlockPos::new(30000, 12, -29999), (7500, 3, -7500)),
+        ] {
+            assert_eq!(biome_cell(&pos, hashed_seed), cell, "{pos:?}");
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, ops::Range};
 
 // this is from minecraft's code
 // yeah idk either
@@ -85,6 +85,7 @@ pub struct BitStorage {
 #[derive(Debug)]
 pub enum BitStorageError {
     InvalidLength { got: usize, expected: usize },
+    TooManyBits(usize),
 }
 impl fmt::Display for BitStorageError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -94,6 +95,12 @@ impl fmt::Display for BitStorageError {
                 "Invalid length given for storage, got: {}, but expected: {}",
                 got, expected
             ),
+            BitStorageError::TooManyBits(bits) => {
+                write!(
+                    f,
+                    "Storage can't have {bits} bits per entry, the maximum is 32"
+                )
+            }
         }
     }
 }
@@ -103,20 +110,21 @@ impl BitStorage {
     /// Create a new BitStorage with the given number of bits per entry.
     /// `size` is the number of entries in the BitStorage.
     pub fn new(bits: usize, size: usize, data: Option<Vec<u64>>) -> Result<Self, BitStorageError> {
-        if let Some(data) = &data {
-            // 0 bit storage
-            if data.is_empty() {
-                return Ok(BitStorage {
-                    data: Vec::with_capacity(0),
-                    bits,
-                    size,
-                    ..Default::default()
-                });
-            }
+        // 0 bit storage
+        if bits == 0 || matches!(&data, Some(data) if data.is_empty()) {
+            return Ok(BitStorage {
+                data: Vec::with_capacity(0),
+                bits,
+                size,
+                ..Default::default()
+            });
         }
 
-        // vanilla has this assert but it's not always true for some reason??
-        // assert!(bits >= 1 && bits <= 32);
+        // vanilla also asserts that bits is at least 1, but that's not always
+        // true for some reason??
+        if bits > 32 {
+            return Err(BitStorageError::TooManyBits(bits));
+        }
 
         let values_per_long = 64 / bits;
         let magic_index = values_per_long - 1;
@@ -166,12 +174,7 @@ impl BitStorage {
         // int var5 = (var1 - var2 * this.valuesPerLong) * this.bits;
         // return (int)(var3 >> var5 & this.mask);
 
-        assert!(
-            index < self.size,
-            "Index {} out of bounds (must be less than {})",
-            index,
-            self.size
-        );
+        self.check_index(index);
 
         // 0 bit storage
         if self.data.is_empty() {
@@ -185,13 +188,14 @@ impl BitStorage {
     }
 
     pub fn get_and_set(&mut self, index: usize, value: u64) -> u64 {
+        self.check_index(index);
+        self.check_value(value);
+
         // 0 bit storage
         if self.data.is_empty() {
             return 0;
         }
 
-        assert!(index < self.size);
-        assert!(value <= self.mask);
         let cell_index = self.cell_index(index as u64);
         let cell = &mut self.data[cell_index];
         let bit_index = (index - cell_index * self.values_per_long as usize) * self.bits;
@@ -201,24 +205,98 @@ impl BitStorage {
     }
 
     pub fn set(&mut self, index: usize, value: u64) {
+        self.check_index(index);
+        self.check_value(value);
+
         // 0 bit storage
         if self.data.is_empty() {
             return;
         }
 
-        assert!(index < self.size);
-        assert!(value <= self.mask);
         let cell_index = self.cell_index(index as u64);
         let cell = &mut self.data[cell_index];
         let bit_index = (index - cell_index * self.values_per_long as usize) * self.bits;
         *cell = *cell & !(self.mask << bit_index) | (value & self.mask) << bit_index;
     }
 
+    /// Set every entry in the range to the value. This sets whole words at a
+    /// time, so it's much faster than calling [`BitStorage::set`] for every
+    /// index.
+    pub fn set_range(&mut self, range: Range<usize>, value: u64) {
+        if range.is_empty() {
+            return;
+        }
+        self.check_index(range.end - 1);
+        self.check_value(value);
+
+        // 0 bit storage
+        if self.data.is_empty() {
+            return;
+        }
+
+        let values_per_long = self.values_per_long as usize;
+        let repeated =
+            (0..values_per_long).fold(0, |word, i| word | (value & self.mask) << (i * self.bits));
+        let mut index = range.start;
+        while index < range.end {
+            let cell_index = index / values_per_long;
+            let first = index - cell_index * values_per_long;
+            let count = (values_per_long - first).min(range.end - index);
+            let bit_count = count * self.bits;
+            let mask = if bit_count == 64 {
+                u64::MAX
+            } else {
+                ((1 << bit_count) - 1) << (first * self.bits)
+            };
+            let cell = &mut self.data[cell_index];
+            *cell = *cell & !mask | repeated & mask;
+            index += count;
+        }
+    }
+
+    /// Set every entry to the value.
+    pub fn fill(&mut self, value: u64) {
+        self.set_range(0..self.size, value);
+    }
+
+    /// Iterate over every entry in order, reading each word only once.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        let values_per_long = self.values_per_long as usize;
+        self.data
+            .iter()
+            .flat_map(move |&cell| {
+                (0..values_per_long).map(move |i| cell >> (i * self.bits) & self.mask)
+            })
+            // 0 bit storage doesn't have any data
+            .chain(std::iter::repeat(0))
+            .take(self.size)
+    }
+
     /// The number of entries.
     #[inline]
     pub fn size(&self) -> usize {
         self.size
     }
+
+    #[inline]
+    fn check_index(&self, index: usize) {
+        assert!(
+            index < self.size,
+            "Index {} out of bounds (must be less than {})",
+            index,
+            self.size
+        );
+    }
+
+    #[inline]
+    fn check_value(&self, value: u64) {
+        assert!(
+            value <= self.mask,
+            "Value {} doesn't fit in {} bits",
+            value,
+            self.bits
+        );
+    }
 }
 
 #[cfg(test)]
@@ -237,4 +315,115 @@ mod tests {
             assert_eq!(storage.get(i), *expected);
         }
     }
+
+    #[test]
+    fn test_too_many_bits() {
+        // this would make the mask overflow
+        assert!(matches!(
+            BitStorage::new(200, 4096, Some(vec![0; 4096])),
+            Err(BitStorageError::TooManyBits(200))
+        ));
+    }
+
+    /// A pattern that doesn't repeat every word, so values that cross into
+    /// the wrong word or get shifted incorrectly will be caught.
+    fn pattern(index: usize, bits: usize) -> u64 {
+        (index as u64).wrapping_mul(2654435761) & ((1 << bits) - 1)
+    }
+
+    #[test]
+    fn test_set_and_get() {
+        for bits in [1, 2, 4, 5, 8, 15] {
+            let size = 4096;
+            let mut storage = BitStorage::new(bits, size, None).unwrap();
+
+            for i in 0..size {
+                storage.set(i, pattern(i, bits));
+            }
+            for i in 0..size {
+                assert_eq!(storage.get(i), pattern(i, bits), "bits {bits}, index {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_and_set_returns_old_value() {
+        for bits in [1, 2, 4, 5, 8, 15] {
+            let size = 4096;
+            let mut storage = BitStorage::new(bits, size, None).unwrap();
+            for i in 0..size {
+                storage.set(i, pattern(i, bits));
+            }
+            for i in 0..size {
+                let new_value = pattern(i + 1, bits);
+                assert_eq!(storage.get_and_set(i, new_value), pattern(i, bits));
+                assert_eq!(storage.get(i), new_value);
+            }
+            // make sure the new values didn't clobber their neighbors
+            for i in 0..size {
+                assert_eq!(
+                    storage.get(i),
+                    pattern(i + 1, bits),
+                    "bits {bits}, index {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_range() {
+        for bits in [1, 2, 4, 5, 8, 15] {
+            let size = 4096;
+            let mut storage = BitStorage::new(bits, size, None).unwrap();
+            for i in 0..size {
+                storage.set(i, pattern(i, bits));
+            }
+            let value = (1 << bits) - 1;
+            storage.set_range(3..1000, value);
+            for i in 0..size {
+                let expected = if (3..1000).contains(&i) {
+                    value
+                } else {
+                    pattern(i, bits)
+                };
+                assert_eq!(storage.get(i), expected, "bits {bits}, index {i}");
+            }
+            assert!(storage.iter().eq((0..size).map(|i| storage.get(i))));
+
+            storage.fill(1);
+            assert!(storage.iter().all(|v| v == 1));
+            assert_eq!(storage.iter().count(), size);
+        }
+    }
+
+    #[test]
+    fn test_zero_bit_storage() {
+        let mut storage = BitStorage::new(0, 4096, None).unwrap();
+        storage.set(4095, 0);
+        assert_eq!(storage.get_and_set(0, 0), 0);
+        assert_eq!(storage.get(100), 0);
+        storage.fill(0);
+        assert_eq!(storage.iter().count(), 4096);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 4096 out of bounds")]
+    fn test_get_out_of_bounds() {
+        let storage = BitStorage::new(4, 4096, None).unwrap();
+        storage.get(4096);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 4096 out of bounds")]
+    fn test_set_out_of_bounds_in_zero_bit_storage() {
+        let mut storage = BitStorage::new(0, 4096, None).unwrap();
+        storage.set(4096, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit in 4 bits")]
+    fn test_set_value_too_big() {
+        let mut storage = BitStorage::new(4, 4096, None).unwrap();
+        storage.set(0, 16);
+    }
 }
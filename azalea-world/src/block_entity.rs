@@ -0,0 +1,262 @@
+//! Block entities, which are the extra data that some blocks like chests and
+//! signs have.
+
+use azalea_chat::component::Component;
+use azalea_core::ResourceLocation;
+use azalea_nbt::Tag;
+use azalea_registry::BlockEntityType;
+
+/// A block entity and the NBT data that the server sent for it.
+#[derive(Clone, Debug)]
+pub struct BlockEntity {
+    pub kind: BlockEntityType,
+    pub data: Tag,
+}
+
+/// The data of a chest or trapped chest.
+#[derive(Clone, Debug)]
+pub struct Chest {
+    pub custom_name: Option<Component>,
+    /// The items in the chest. Servers don't usually send these, so this is
+    /// only filled in for chests that we read from somewhere else.
+    pub items: Vec<ChestItem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChestItem {
+    pub slot: u8,
+    pub id: ResourceLocation,
+    pub count: u8,
+}
+
+/// The data of a sign.
+#[derive(Clone, Debug)]
+pub struct Sign {
+    pub lines: [Component; 4],
+    /// The name of the dye color of the text, like `black`.
+    pub color: String,
+    pub glowing: bool,
+}
+
+/// The data of a mob spawner.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Spawner {
+    /// The id of the entity that the spawner spawns, or `None` if it's empty.
+    pub entity: Option<ResourceLocation>,
+    /// How many ticks are left until the next spawn.
+    pub delay: i16,
+}
+
+/// The data of a banner. The base color of the banner is in its block state.
+#[derive(Clone, Debug)]
+pub struct Banner {
+    pub custom_name: Option<Component>,
+    pub patterns: Vec<BannerPattern>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BannerPattern {
+    /// The short code of the pattern, like `bts` for bottom stripe.
+    pub pattern: String,
+    /// The id of the dye color of the pattern.
+    pub color: i32,
+}
+
+impl BlockEntity {
+    pub fn new(kind: BlockEntityType, data: Tag) -> Self {
+        Self { kind, data }
+    }
+
+    /// Get a field from the block entity's NBT.
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        get_nbt_field(&self.data, key)
+    }
+
+    fn custom_name(&self) -> Option<Component> {
+        Component::from_json(self.get("CustomName")?.as_string()?).ok()
+    }
+
+    /// The chest data, if this is a chest or trapped chest.
+    pub fn as_chest(&self) -> Option<Chest> {
+        if !matches!(
+            self.kind,
+            BlockEntityType::Chest | BlockEntityType::TrappedChest
+        ) {
+            return None;
+        }
+        let items = match self.get("Items").and_then(|items| items.as_list()) {
+            Some(items) => items
+                .iter()
+                .filter_map(|item| {
+                    let item = item.as_compound()?;
+                    Some(ChestItem {
+                        slot: *item.get("Slot")?.as_byte()? as u8,
+                        id: ResourceLocation::new(item.get("id")?.as_string()?).ok()?,
+                        count: *item.get("Count")?.as_byte()? as u8,
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Some(Chest {
+            custom_name: self.custom_name(),
+            items,
+        })
+    }
+
+    /// The sign data, if this is a sign.
+    pub fn as_sign(&self) -> Option<Sign> {
+        if self.kind != BlockEntityType::Sign {
+            return None;
+        }
+        let line = |key| {
+            self.get(key)
+                .and_then(|line| line.as_string())
+                .and_then(|line| Component::from_json(line).ok())
+                .unwrap_or_else(|| Component::from(String::new()))
+        };
+        Some(Sign {
+            lines: [line("Text1"), line("Text2"), line("Text3"), line("Text4")],
+            color: self
+                .get("Color")
+                .and_then(|color| color.as_string())
+                .unwrap_or("black")
+                .to_string(),
+            glowing: self
+                .get("GlowingText")
+                .and_then(|glowing| glowing.as_byte())
+                .is_some_and(|&glowing| glowing != 0),
+        })
+    }
+
+    /// The spawner data, if this is a mob spawner.
+    pub fn as_spawner(&self) -> Option<Spawner> {
+        if self.kind != BlockEntityType::MobSpawner {
+            return None;
+        }
+        let entity = self
+            .get("SpawnData")
+            .and_then(|spawn_data| get_nbt_field(spawn_data, "entity"))
+            .and_then(|entity| get_nbt_field(entity, "id"))
+            .and_then(|id| id.as_string())
+            .and_then(|id| ResourceLocation::new(id).ok());
+        Some(Spawner {
+            entity,
+            delay: self
+                .get("Delay")
+                .and_then(|delay| delay.as_short())
+                .copied()
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The banner data, if this is a banner.
+    pub fn as_banner(&self) -> Option<Banner> {
+        if self.kind != BlockEntityType::Banner {
+            return None;
+        }
+        let patterns = match self.get("Patterns").and_then(|patterns| patterns.as_list()) {
+            Some(patterns) => patterns
+                .iter()
+                .filter_map(|pattern| {
+                    Some(BannerPattern {
+                        pattern: get_nbt_field(pattern, "Pattern")?.as_string()?.to_string(),
+                        color: *get_nbt_field(pattern, "Color")?.as_int()?,
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Some(Banner {
+            custom_name: self.custom_name(),
+            patterns,
+        })
+    }
+}
+
+/// Get a field from a compound. NBT that was read with a name (like in
+/// packets) is wrapped in a root compound with an empty name, so the field is
+/// looked for in there too.
+pub(crate) fn get_nbt_field<'a>(tag: &'a Tag, key: &str) -> Option<&'a Tag> {
+    let compound = tag.as_compound()?;
+    match compound.get(key) {
+        Some(value) => Some(value),
+        None => compound.get("")?.as_compound()?.get(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        let data = Tag::from([(
+            "",
+            Tag::from([
+                ("Text1", Tag::String(r#"{"text":"hello"}"#.to_string())),
+                ("Text2", Tag::String(r#""world""#.to_string())),
+                ("Color", Tag::String("red".to_string())),
+                ("GlowingText", Tag::Byte(1)),
+            ]),
+        )]);
+        let sign = BlockEntity::new(BlockEntityType::Sign, data)
+            .as_sign()
+            .unwrap();
+        assert_eq!(sign.lines[0].to_string(), "hello");
+        assert_eq!(sign.lines[1].to_string(), "world");
+        assert_eq!(sign.lines[3].to_string(), "");
+        assert_eq!(sign.color, "red");
+        assert!(sign.glowing);
+    }
+
+    #[test]
+    fn test_wrong_kind() {
+        let block_entity = BlockEntity::new(BlockEntityType::Sign, Tag::from([]));
+        assert!(block_entity.as_chest().is_none());
+        assert!(block_entity.as_spawner().is_none());
+        assert!(block_entity.as_banner().is_none());
+    }
+
+    #[test]
+    fn test_spawner_and_banner() {
+        let spawner = BlockEntity::new(
+            BlockEntityType::MobSpawner,
+            Tag::from([
+                (
+                    "SpawnData",
+                    Tag::from([(
+                        "entity",
+                        Tag::from([("id", Tag::String("minecraft:zombie".to_string()))]),
+                    )]),
+                ),
+                ("Delay", Tag::Short(20)),
+            ]),
+        );
+        assert_eq!(
+            spawner.as_spawner().unwrap(),
+            Spawner {
+                entity: Some(ResourceLocation::new("minecraft:zombie").unwrap()),
+                delay: 20,
+            }
+        );
+
+        let banner = BlockEntity::new(
+            BlockEntityType::Banner,
+            Tag::from([(
+                "Patterns",
+                Tag::List(vec![Tag::from([
+                    ("Pattern", Tag::String("bts".to_string())),
+                    ("Color", Tag::Int(14)),
+                ])]),
+            )]),
+        );
+        assert_eq!(
+            banner.as_banner().unwrap().patterns,
+            [BannerPattern {
+                pattern: "bts".to_string(),
+                color: 14,
+            }]
+        );
+    }
+}
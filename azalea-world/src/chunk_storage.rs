@@ -1,18 +1,26 @@
+use crate::block_entity::BlockEntity;
+use crate::heightmap::{Heightmap, HeightmapKind};
+use crate::light::{ChunkLight, LightLayer};
 use crate::palette::PalettedContainer;
 use crate::palette::PalettedContainerType;
 use crate::Dimension;
-use azalea_block::BlockState;
+use azalea_block::{Block, BlockState};
 use azalea_buf::BufReadError;
 use azalea_buf::{McBufReadable, McBufWritable};
 use azalea_core::floor_mod;
 use azalea_core::{BlockPos, ChunkBlockPos, ChunkPos, ChunkSectionBlockPos};
+use azalea_nbt::Tag;
 use std::fmt::Debug;
 use std::io::Cursor;
 use std::{
+    collections::HashMap,
     io::Write,
+    mem,
     ops::{Index, IndexMut},
     sync::{Arc, Mutex},
 };
+use thiserror::Error;
+use tracing::{trace, trace_span};
 
 const SECTION_HEIGHT: u32 = 16;
 
@@ -26,13 +34,22 @@ pub struct ChunkStorage {
     chunks: Vec<Option<Arc<Mutex<Chunk>>>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Chunk {
     pub sections: Vec<Section>,
+    pub light: ChunkLight,
+    /// The heightmaps that are kept up to date when blocks are set. Chunks
+    /// from the server have all of the [`HeightmapKind`]s.
+    pub heightmaps: HashMap<HeightmapKind, Heightmap>,
+    /// The block entities in the chunk, like chests and signs. Block
+    /// entities are removed when their block is replaced by a different one.
+    pub block_entities: HashMap<ChunkBlockPos, BlockEntity>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Section {
+    /// The number of blocks in the section that aren't air. This is kept up
+    /// to date when blocks are set through the chunk.
     pub block_count: u16,
     pub states: PalettedContainer,
     pub biomes: PalettedContainer,
@@ -42,8 +59,8 @@ impl Default for Section {
     fn default() -> Self {
         Section {
             block_count: 0,
-            states: PalettedContainer::new(&PalettedContainerType::BlockStates).unwrap(),
-            biomes: PalettedContainer::new(&PalettedContainerType::Biomes).unwrap(),
+            states: PalettedContainer::new(&PalettedContainerType::BlockStates),
+            biomes: PalettedContainer::new(&PalettedContainerType::Biomes),
         }
     }
 }
@@ -52,6 +69,9 @@ impl Default for Chunk {
     fn default() -> Self {
         Chunk {
             sections: vec![Section::default(); (384 / 16) as usize],
+            light: ChunkLight::default(),
+            heightmaps: HashMap::new(),
+            block_entities: HashMap::new(),
         }
     }
 }
@@ -79,6 +99,17 @@ impl ChunkStorage {
             && (chunk_pos.z - self.view_center.z).unsigned_abs() <= self.chunk_radius
     }
 
+    /// Iterate over the chunks that are loaded and in the view range.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = (ChunkPos, &Arc<Mutex<Chunk>>)> {
+        let radius = self.chunk_radius as i32;
+        let center = self.view_center;
+        (center.x - radius..=center.x + radius)
+            .flat_map(move |x| {
+                (center.z - radius..=center.z + radius).map(move |z| ChunkPos::new(x, z))
+            })
+            .filter_map(|pos| self[&pos].as_ref().map(|chunk| (pos, chunk)))
+    }
+
     pub fn get_block_state(&self, pos: &BlockPos) -> Option<BlockState> {
         let chunk_pos = ChunkPos::from(pos);
         let chunk = self[&chunk_pos].as_ref()?;
@@ -96,13 +127,67 @@ impl ChunkStorage {
         Some(chunk.get_and_set(&ChunkBlockPos::from(pos), state, self.min_y))
     }
 
+    /// The y position right above the highest block of the kind in the
+    /// column, or `None` if the chunk isn't loaded.
+    pub fn get_height(&self, kind: HeightmapKind, x: i32, z: i32) -> Option<i32> {
+        let chunk = self[&ChunkPos::new(x.div_floor(16), z.div_floor(16))].as_ref()?;
+        let chunk = chunk.lock().unwrap();
+        chunk.get_height(kind, x.rem_euclid(16) as u8, z.rem_euclid(16) as u8)
+    }
+
+    /// Get the id of the biome in the 4x4x4 cell, which is in quarter
+    /// blocks. Returns `None` if the chunk isn't loaded.
+    pub fn get_biome_id(&self, quart_x: i32, quart_y: i32, quart_z: i32) -> Option<u32> {
+        let chunk = self[&ChunkPos::new(quart_x >> 2, quart_z >> 2)].as_ref()?;
+        let chunk = chunk.lock().unwrap();
+        chunk.get_biome_id(
+            (quart_x & 3) as u8,
+            quart_y,
+            (quart_z & 3) as u8,
+            self.min_y,
+        )
+    }
+
+    /// Get a copy of the block entity at the position, if there's one there
+    /// and the chunk is loaded.
+    pub fn get_block_entity(&self, pos: &BlockPos) -> Option<BlockEntity> {
+        let chunk = self[&ChunkPos::from(pos)].as_ref()?;
+        let chunk = chunk.lock().unwrap();
+        chunk.block_entities.get(&ChunkBlockPos::from(pos)).cloned()
+    }
+
+    /// Set or remove the block entity at the position, and return the one
+    /// that was there before. Nothing happens if the chunk isn't loaded.
+    pub fn set_block_entity(
+        &self,
+        pos: &BlockPos,
+        block_entity: Option<BlockEntity>,
+    ) -> Option<BlockEntity> {
+        let chunk = self[&ChunkPos::from(pos)].as_ref()?;
+        let mut chunk = chunk.lock().unwrap();
+        let pos = ChunkBlockPos::from(pos);
+        match block_entity {
+            Some(block_entity) => chunk.block_entities.insert(pos, block_entity),
+            None => chunk.block_entities.remove(&pos),
+        }
+    }
+
+    pub fn get_light(&self, layer: LightLayer, pos: &BlockPos) -> Option<u8> {
+        let chunk_pos = ChunkPos::from(pos);
+        let chunk = self[&chunk_pos].as_ref()?;
+        let chunk = chunk.lock().unwrap();
+        chunk.get_light(layer, &ChunkBlockPos::from(pos), self.min_y)
+    }
+
     pub fn replace_with_packet_data(
         &mut self,
         pos: &ChunkPos,
         data: &mut Cursor<&[u8]>,
+        heightmaps: &Tag,
     ) -> Result<(), BufReadError> {
+        let _span = trace_span!("replace_with_packet_data", x = pos.x, z = pos.z).entered();
         if !self.in_range(pos) {
-            log::trace!(
+            trace!(
                 "Ignoring chunk since it's not in the view range: {}, {}",
                 pos.x,
                 pos.z
@@ -110,12 +195,11 @@ impl ChunkStorage {
             return Ok(());
         }
 
-        let chunk = Arc::new(Mutex::new(Chunk::read_with_dimension_height(
-            data,
-            self.height,
-        )?));
+        let mut chunk = Chunk::read_with_dimension_height(data, self.height)?;
+        chunk.set_heightmaps(heightmaps, self.min_y);
+        let chunk = Arc::new(Mutex::new(chunk));
 
-        log::trace!("Loaded chunk {:?}", pos);
+        trace!("Loaded chunk {:?}", pos);
         self[pos] = Some(chunk);
 
         Ok(())
@@ -149,12 +233,19 @@ impl Chunk {
         dimension_height: u32,
     ) -> Result<Self, BufReadError> {
         let section_count = dimension_height / SECTION_HEIGHT;
+        let _span = trace_span!("read_chunk", section_count).entered();
         let mut sections = Vec::with_capacity(section_count as usize);
-        for _ in 0..section_count {
-            let section = Section::read_from(buf)?;
+        for i in 0..section_count {
+            let _span = trace_span!("read_section", index = i).entered();
+            let section = Section::read_from(buf).map_err(|e| e.at_index(i as usize))?;
             sections.push(section);
         }
-        Ok(Chunk { sections })
+        Ok(Chunk {
+            sections,
+            light: ChunkLight::default(),
+            heightmaps: HashMap::new(),
+            block_entities: HashMap::new(),
+        })
     }
 
     pub fn section_index(&self, y: i32, min_y: i32) -> u32 {
@@ -175,6 +266,26 @@ impl Chunk {
         Some(section.get(chunk_section_pos))
     }
 
+    /// The number of light sections in the chunk, which is one more above and
+    /// below than the number of block sections.
+    pub fn light_section_count(&self) -> usize {
+        self.sections.len() + 2
+    }
+
+    /// Get the light level at a position, or `None` if it's outside of the
+    /// light sections.
+    pub fn get_light(&self, layer: LightLayer, pos: &ChunkBlockPos, min_y: i32) -> Option<u8> {
+        let light_section_index = pos.y.div_floor(16) - min_y.div_floor(16) + 1;
+        if light_section_index < 0 || light_section_index as usize >= self.light_section_count() {
+            return None;
+        }
+        Some(self.light.get(
+            layer,
+            light_section_index as usize,
+            ChunkSectionBlockPos::from(pos),
+        ))
+    }
+
     pub fn get_and_set(
         &mut self,
         pos: &ChunkBlockPos,
@@ -185,15 +296,106 @@ impl Chunk {
         // TODO: make sure the section exists
         let section = &mut self.sections[section_index as usize];
         let chunk_section_pos = ChunkSectionBlockPos::from(pos);
-        section.get_and_set(chunk_section_pos, state)
+        let previous_state = section.get_and_set(chunk_section_pos, state);
+
+        // like vanilla, the block entity stays if only the block's
+        // properties changed
+        if self.block_entities.contains_key(pos)
+            && Box::<dyn Block>::from(previous_state).id() != Box::<dyn Block>::from(state).id()
+        {
+            self.block_entities.remove(pos);
+        }
+
+        // the heightmaps are taken out so they can look at the other blocks
+        // in the column
+        let mut heightmaps = mem::take(&mut self.heightmaps);
+        for heightmap in heightmaps.values_mut() {
+            heightmap.update(pos.x, pos.y, pos.z, state, |y| {
+                self.get(&ChunkBlockPos::new(pos.x, y, pos.z), min_y)
+                    .unwrap_or(BlockState::Air)
+            });
+        }
+        self.heightmaps = heightmaps;
+
+        previous_state
     }
 
     pub fn set(&mut self, pos: &ChunkBlockPos, state: BlockState, min_y: i32) {
-        let section_index = self.section_index(pos.y, min_y);
-        // TODO: make sure the section exists
-        let section = &mut self.sections[section_index as usize];
-        let chunk_section_pos = ChunkSectionBlockPos::from(pos);
-        section.set(chunk_section_pos, state)
+        self.get_and_set(pos, state, min_y);
+    }
+
+    /// Get the id of the biome in the 4x4x4 cell. The x and z are from 0 to
+    /// 3, and the y is in quarter blocks and is clamped to the height of the
+    /// chunk like in vanilla.
+    pub fn get_biome_id(&self, quart_x: u8, quart_y: i32, quart_z: u8, min_y: i32) -> Option<u32> {
+        if self.sections.is_empty() {
+            return None;
+        }
+        let min_quart_y = min_y >> 2;
+        let max_quart_y = min_quart_y + self.sections.len() as i32 * 4 - 1;
+        let quart_y = quart_y.clamp(min_quart_y, max_quart_y);
+        let section = &self.sections[self.section_index(quart_y << 2, min_y) as usize];
+        Some(
+            section
+                .biomes
+                .get(quart_x as usize, (quart_y & 3) as usize, quart_z as usize),
+        )
+    }
+
+    /// The y position right above the highest block of the kind in the
+    /// column, or `None` if the chunk doesn't have that heightmap.
+    pub fn get_height(&self, kind: HeightmapKind, x: u8, z: u8) -> Option<i32> {
+        Some(self.heightmaps.get(&kind)?.first_available(x, z))
+    }
+
+    /// Set the heightmaps from the NBT that's sent with the chunk. Heightmaps
+    /// that are missing or invalid are calculated from the blocks instead.
+    pub fn set_heightmaps(&mut self, heightmaps: &Tag, min_y: i32) {
+        let height = self.sections.len() as u32 * SECTION_HEIGHT;
+        for kind in HeightmapKind::ALL {
+            let heightmap = Heightmap::from_nbt(kind, heightmaps, height, min_y)
+                .unwrap_or_else(|| self.calculate_heightmap(kind, min_y));
+            self.heightmaps.insert(kind, heightmap);
+        }
+    }
+
+    /// Calculate every kind of heightmap from the blocks in the chunk.
+    pub fn recalculate_heightmaps(&mut self, min_y: i32) {
+        for kind in HeightmapKind::ALL {
+            let heightmap = self.calculate_heightmap(kind, min_y);
+            self.heightmaps.insert(kind, heightmap);
+        }
+    }
+
+    fn calculate_heightmap(&self, kind: HeightmapKind, min_y: i32) -> Heightmap {
+        let height = self.sections.len() as u32 * SECTION_HEIGHT;
+        let mut heightmap = Heightmap::new(kind, height, min_y);
+        for x in 0..16 {
+            for z in 0..16 {
+                'column: for (section_index, section) in self.sections.iter().enumerate().rev() {
+                    if section.is_empty() {
+                        continue;
+                    }
+                    for y in (0..16).rev() {
+                        if kind.is_opaque(section.get(ChunkSectionBlockPos::new(x, y, z))) {
+                            let y = min_y + section_index as i32 * 16 + y as i32;
+                            heightmap.set_first_available(x, z, y + 1);
+                            break 'column;
+                        }
+                    }
+                }
+            }
+        }
+        heightmap
+    }
+
+    /// Shrink the palettes of every section to fit the blocks and biomes
+    /// that are in them now. See `PalettedContainer::optimize`.
+    pub fn optimize(&mut self) {
+        for section in &mut self.sections {
+            section.states.optimize();
+            section.biomes.optimize();
+        }
     }
 }
 
@@ -220,6 +422,19 @@ impl Debug for ChunkStorage {
     }
 }
 
+/// An error from reading a chunk section that the server sent.
+#[derive(Error, Debug)]
+pub enum SectionReadError {
+    #[error("Invalid block state {state} (index {index}) found in section")]
+    InvalidBlockState { state: u32, index: usize },
+}
+
+impl From<SectionReadError> for BufReadError {
+    fn from(e: SectionReadError) -> Self {
+        BufReadError::Other(Box::new(e))
+    }
+}
+
 impl McBufReadable for Section {
     fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
         let block_count = u16::read_from(buf)?;
@@ -230,19 +445,22 @@ impl McBufReadable for Section {
         //     "A section has more blocks than what should be possible. This is a bug!"
         // );
 
-        let states = PalettedContainer::read_with_type(buf, &PalettedContainerType::BlockStates)?;
+        let states = PalettedContainer::read_with_type(buf, &PalettedContainerType::BlockStates)
+            .map_err(|e| e.in_field("states"))?;
 
         for i in 0..states.storage.size() {
-            if !BlockState::is_valid_state(states.storage.get(i) as u32) {
-                return Err(BufReadError::Custom(format!(
-                    "Invalid block state {} (index {}) found in section.",
-                    states.storage.get(i),
-                    i
-                )));
+            let state = states.storage.get(i) as u32;
+            if !BlockState::is_valid_state(state) {
+                return Err(BufReadError::from(SectionReadError::InvalidBlockState {
+                    state,
+                    index: i,
+                })
+                .in_field("states"));
             }
         }
 
-        let biomes = PalettedContainer::read_with_type(buf, &PalettedContainerType::Biomes)?;
+        let biomes = PalettedContainer::read_with_type(buf, &PalettedContainerType::Biomes)
+            .map_err(|e| e.in_field("biomes"))?;
         Ok(Section {
             block_count,
             states,
@@ -261,7 +479,23 @@ impl McBufWritable for Section {
 }
 
 impl Section {
-    fn get(&self, pos: ChunkSectionBlockPos) -> BlockState {
+    /// Whether the section only has air in it, so it can be skipped when
+    /// looking for blocks.
+    pub fn is_empty(&self) -> bool {
+        self.block_count == 0
+    }
+
+    /// Count the blocks that aren't air again, for when the states were
+    /// changed directly instead of through [`Section::set`].
+    pub fn recalculate_block_count(&mut self) {
+        self.block_count = self
+            .states
+            .iter()
+            .filter(|&(_, _, _, state)| !is_air(state))
+            .count() as u16;
+    }
+
+    pub(crate) fn get(&self, pos: ChunkSectionBlockPos) -> BlockState {
         // TODO: use the unsafe method and do the check earlier
         let state = self
             .states
@@ -274,16 +508,29 @@ impl Section {
         let previous_state =
             self.states
                 .get_and_set(pos.x as usize, pos.y as usize, pos.z as usize, state as u32);
+        // saturating since the count that the server sends isn't always right
+        match (is_air(previous_state), is_air(state as u32)) {
+            (true, false) => self.block_count = self.block_count.saturating_add(1),
+            (false, true) => self.block_count = self.block_count.saturating_sub(1),
+            _ => {}
+        }
         // if there's an unknown block assume it's air
         BlockState::try_from(previous_state).unwrap_or(BlockState::Air)
     }
 
-    fn set(&mut self, pos: ChunkSectionBlockPos, state: BlockState) {
-        self.states
-            .set(pos.x as usize, pos.y as usize, pos.z as usize, state as u32);
+    pub fn set(&mut self, pos: ChunkSectionBlockPos, state: BlockState) {
+        self.get_and_set(pos, state);
     }
 }
 
+/// Whether the state is one of the air blocks, which aren't counted in
+/// [`Section::block_count`].
+pub(crate) fn is_air(state_id: u32) -> bool {
+    state_id == BlockState::Air as u32
+        || state_id == BlockState::CaveAir as u32
+        || state_id == BlockState::VoidAir as u32
+}
+
 impl Default for ChunkStorage {
     fn default() -> Self {
         Self::new(8, 384, -64)
@@ -327,4 +574,86 @@ mod tests {
             .get_block_state(&BlockPos { x: 0, y: -65, z: 0 })
             .is_none());
     }
+
+    #[test]
+    fn test_heightmaps() {
+        let mut chunk = Chunk::default();
+        chunk.set(&ChunkBlockPos::new(2, 70, 3), BlockState::Stone, -64);
+        chunk.set(&ChunkBlockPos::new(2, 30, 3), BlockState::Stone, -64);
+        // the chunk didn't have heightmaps, so they're calculated
+        chunk.set_heightmaps(&Tag::Compound(Default::default()), -64);
+        assert_eq!(
+            chunk.get_height(HeightmapKind::WorldSurface, 2, 3),
+            Some(71)
+        );
+        assert_eq!(
+            chunk.get_height(HeightmapKind::MotionBlocking, 0, 0),
+            Some(-64)
+        );
+
+        chunk.set(&ChunkBlockPos::new(2, 70, 3), BlockState::Air, -64);
+        assert_eq!(
+            chunk.get_height(HeightmapKind::WorldSurface, 2, 3),
+            Some(31)
+        );
+        chunk.set(&ChunkBlockPos::new(0, 319, 0), BlockState::Stone, -64);
+        assert_eq!(
+            chunk.get_height(HeightmapKind::MotionBlocking, 0, 0),
+            Some(320)
+        );
+    }
+
+    #[test]
+    fn test_block_entity_removed() {
+        let mut chunk = Chunk::default();
+        let pos = ChunkBlockPos::new(1, 64, 1);
+        chunk.set(&pos, BlockState::Lantern_TrueTrue, -64);
+        chunk.block_entities.insert(
+            pos,
+            BlockEntity::new(
+                azalea_registry::BlockEntityType::Chest,
+                Tag::Compound(Default::default()),
+            ),
+        );
+        // changing the block's properties keeps the block entity
+        chunk.set(&pos, BlockState::Lantern_FalseTrue, -64);
+        assert!(chunk.block_entities.contains_key(&pos));
+        chunk.set(&pos, BlockState::Air, -64);
+        assert!(chunk.block_entities.is_empty());
+    }
+
+    #[test]
+    fn test_get_biome_id() {
+        let mut chunk = Chunk::default();
+        // the biomes in the bottom section
+        chunk.sections[0].biomes.set(1, 2, 3, 5);
+        assert_eq!(chunk.get_biome_id(1, -16 + 2, 3, -64), Some(5));
+        assert_eq!(chunk.get_biome_id(1, -16 + 3, 3, -64), Some(0));
+        // positions below the chunk use the lowest biomes
+        chunk.sections[0].biomes.set(0, 0, 0, 7);
+        assert_eq!(chunk.get_biome_id(0, -100, 0, -64), Some(7));
+        chunk.sections[23].biomes.set(0, 3, 0, 8);
+        assert_eq!(chunk.get_biome_id(0, 1000, 0, -64), Some(8));
+    }
+
+    #[test]
+    fn test_block_count() {
+        let mut chunk = Chunk::default();
+        assert!(chunk.sections.iter().all(Section::is_empty));
+
+        let pos = ChunkBlockPos::new(1, -62, 3);
+        chunk.set(&pos, BlockState::Stone, -64);
+        chunk.set(&pos, BlockState::Dirt, -64);
+        chunk.set(&ChunkBlockPos::new(4, -59, 6), BlockState::CaveAir, -64);
+        assert_eq!(chunk.sections[0].block_count, 1);
+        assert!(chunk.sections[1..].iter().all(Section::is_empty));
+
+        chunk.set(&pos, BlockState::Air, -64);
+        assert!(chunk.sections[0].is_empty());
+
+        let section = &mut chunk.sections[0];
+        section.states.fill(BlockState::Stone as u32);
+        section.recalculate_block_count();
+        assert_eq!(section.block_count, 4096);
+    }
 }
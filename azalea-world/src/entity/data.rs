@@ -1,14 +1,50 @@
-use azalea_buf::{BufReadError, McBufVarReadable};
+use azalea_buf::{BufReadError, McBufVarReadable, McBufVarWritable};
 use azalea_buf::{McBuf, McBufReadable, McBufWritable};
 use azalea_chat::component::Component;
 use azalea_core::{BlockPos, Direction, GlobalPos, Particle, Slot};
 use std::io::{Cursor, Write};
 use uuid::Uuid;
 
-#[derive(Clone, Debug)]
-pub struct EntityMetadata(Vec<EntityDataItem>);
+/// The synced data of an entity, like its pose, custom name or whether it's
+/// a baby. Which index means what depends on the entity type, but indexes 0
+/// to 7 are the same for every entity.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EntityMetadata(pub Vec<EntityDataItem>);
+
+impl EntityMetadata {
+    /// Get the value at the given index, if it was sent.
+    pub fn get(&self, index: u8) -> Option<&EntityDataValue> {
+        self.0
+            .iter()
+            .find(|item| item.index == index)
+            .map(|item| &item.value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EntityDataItem> {
+        self.0.iter()
+    }
+
+    /// The entity's custom name, like a name tag or the text on an armor
+    /// stand. This is `None` if the name wasn't sent or was removed.
+    pub fn custom_name(&self) -> Option<&Component> {
+        match self.get(2) {
+            Some(EntityDataValue::OptionalComponent(name)) => name.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The entity's pose, if it was sent.
+    pub fn pose(&self) -> Option<Pose> {
+        match self.get(6) {
+            Some(EntityDataValue::Pose(pose)) => Some(*pose),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct EntityDataItem {
     // we can't identify what the index is for here because we don't know the
     // entity type
@@ -43,6 +79,7 @@ impl McBufWritable for EntityMetadata {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum EntityDataValue {
     Byte(u8),
     // varint
@@ -69,7 +106,7 @@ pub enum EntityDataValue {
     Pose(Pose),
     CatVariant(azalea_registry::CatVariant),
     FrogVariant(azalea_registry::FrogVariant),
-    GlobalPos(GlobalPos),
+    OptionalGlobalPos(Option<GlobalPos>),
     PaintingVariant(azalea_registry::PaintingVariant),
 }
 
@@ -116,7 +153,7 @@ impl McBufReadable for EntityDataValue {
             18 => EntityDataValue::Pose(Pose::read_from(buf)?),
             19 => EntityDataValue::CatVariant(azalea_registry::CatVariant::read_from(buf)?),
             20 => EntityDataValue::FrogVariant(azalea_registry::FrogVariant::read_from(buf)?),
-            21 => EntityDataValue::GlobalPos(GlobalPos::read_from(buf)?),
+            21 => EntityDataValue::OptionalGlobalPos(Option::<GlobalPos>::read_from(buf)?),
             22 => {
                 EntityDataValue::PaintingVariant(azalea_registry::PaintingVariant::read_from(buf)?)
             }
@@ -129,13 +166,77 @@ impl McBufReadable for EntityDataValue {
     }
 }
 
+impl EntityDataValue {
+    /// The id of the serializer for this type of value, which is sent before
+    /// the value.
+    pub fn data_type(&self) -> u32 {
+        match self {
+            EntityDataValue::Byte(_) => 0,
+            EntityDataValue::Int(_) => 1,
+            EntityDataValue::Float(_) => 2,
+            EntityDataValue::String(_) => 3,
+            EntityDataValue::Component(_) => 4,
+            EntityDataValue::OptionalComponent(_) => 5,
+            EntityDataValue::ItemStack(_) => 6,
+            EntityDataValue::Boolean(_) => 7,
+            EntityDataValue::Rotations { .. } => 8,
+            EntityDataValue::BlockPos(_) => 9,
+            EntityDataValue::OptionalBlockPos(_) => 10,
+            EntityDataValue::Direction(_) => 11,
+            EntityDataValue::OptionalUuid(_) => 12,
+            EntityDataValue::OptionalBlockState(_) => 13,
+            EntityDataValue::CompoundTag(_) => 14,
+            EntityDataValue::Particle(_) => 15,
+            EntityDataValue::VillagerData(_) => 16,
+            EntityDataValue::OptionalUnsignedInt(_) => 17,
+            EntityDataValue::Pose(_) => 18,
+            EntityDataValue::CatVariant(_) => 19,
+            EntityDataValue::FrogVariant(_) => 20,
+            EntityDataValue::OptionalGlobalPos(_) => 21,
+            EntityDataValue::PaintingVariant(_) => 22,
+        }
+    }
+}
+
 impl McBufWritable for EntityDataValue {
-    fn write_into(&self, _buf: &mut impl Write) -> Result<(), std::io::Error> {
-        todo!();
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        self.data_type().var_write_into(buf)?;
+        match self {
+            EntityDataValue::Byte(value) => value.write_into(buf),
+            EntityDataValue::Int(value) => value.var_write_into(buf),
+            EntityDataValue::Float(value) => value.write_into(buf),
+            EntityDataValue::String(value) => value.write_into(buf),
+            EntityDataValue::Component(value) => value.write_into(buf),
+            EntityDataValue::OptionalComponent(value) => value.write_into(buf),
+            EntityDataValue::ItemStack(value) => value.write_into(buf),
+            EntityDataValue::Boolean(value) => value.write_into(buf),
+            EntityDataValue::Rotations { x, y, z } => {
+                x.write_into(buf)?;
+                y.write_into(buf)?;
+                z.write_into(buf)
+            }
+            EntityDataValue::BlockPos(value) => value.write_into(buf),
+            EntityDataValue::OptionalBlockPos(value) => value.write_into(buf),
+            EntityDataValue::Direction(value) => value.write_into(buf),
+            EntityDataValue::OptionalUuid(value) => value.write_into(buf),
+            EntityDataValue::OptionalBlockState(value) => value.unwrap_or(0).var_write_into(buf),
+            EntityDataValue::CompoundTag(value) => value.write_into(buf),
+            EntityDataValue::Particle(value) => value.write_into(buf),
+            EntityDataValue::VillagerData(value) => value.write_into(buf),
+            EntityDataValue::OptionalUnsignedInt(value) => {
+                value.map_or(0, |value| value + 1).var_write_into(buf)
+            }
+            EntityDataValue::Pose(value) => value.write_into(buf),
+            EntityDataValue::CatVariant(value) => value.write_into(buf),
+            EntityDataValue::FrogVariant(value) => value.write_into(buf),
+            EntityDataValue::OptionalGlobalPos(value) => value.write_into(buf),
+            EntityDataValue::PaintingVariant(value) => value.write_into(buf),
+        }
     }
 }
 
-#[derive(Clone, Debug, Copy, McBuf)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Pose {
     Standing = 0,
     FallFlying = 1,
@@ -145,14 +246,125 @@ pub enum Pose {
     Sneaking = 5,
     LongJumping = 6,
     Dying = 7,
+    Croaking = 8,
+    UsingTongue = 9,
+    Roaring = 10,
+    Sniffing = 11,
+    Emerging = 12,
+    Digging = 13,
 }
 
 #[derive(Debug, Clone, McBuf)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct VillagerData {
+    pub kind: azalea_registry::VillagerType,
+    pub profession: azalea_registry::VillagerProfession,
     #[var]
-    type_: u32,
-    #[var]
-    profession: u32,
-    #[var]
-    level: u32,
+    pub level: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_entity_metadata() {
+        let mut buf = Vec::new();
+        // shared flags
+        buf.extend([0, 0, 0x20]);
+        // custom name
+        buf.extend([2, 5, 1]);
+        r#"{"text":"Hello"}"#.to_string().write_into(&mut buf).unwrap();
+        // pose
+        buf.extend([6, 18, 5]);
+        // baby
+        buf.extend([16, 7, 1]);
+        buf.push(0xff);
+
+        let metadata = EntityMetadata::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert!(matches!(metadata.get(0), Some(EntityDataValue::Byte(0x20))));
+        assert_eq!(metadata.custom_name().unwrap().to_string(), "Hello");
+        assert_eq!(metadata.pose(), Some(Pose::Sneaking));
+        assert!(matches!(
+            metadata.get(16),
+            Some(EntityDataValue::Boolean(true))
+        ));
+        assert!(metadata.get(17).is_none());
+    }
+
+    #[test]
+    fn test_write_entity_metadata() {
+        let metadata = EntityMetadata(vec![
+            EntityDataItem {
+                index: 1,
+                value: EntityDataValue::Int(300),
+            },
+            EntityDataItem {
+                index: 8,
+                value: EntityDataValue::Rotations {
+                    x: 1.,
+                    y: 2.,
+                    z: 3.,
+                },
+            },
+            EntityDataItem {
+                index: 9,
+                value: EntityDataValue::OptionalBlockState(None),
+            },
+            EntityDataItem {
+                index: 10,
+                value: EntityDataValue::OptionalUnsignedInt(Some(0)),
+            },
+            EntityDataItem {
+                index: 11,
+                value: EntityDataValue::ItemStack(Slot::Empty),
+            },
+            EntityDataItem {
+                index: 18,
+                value: EntityDataValue::VillagerData(VillagerData {
+                    kind: azalea_registry::VillagerType::Plains,
+                    profession: azalea_registry::VillagerProfession::Farmer,
+                    level: 2,
+                }),
+            },
+            EntityDataItem {
+                index: 19,
+                value: EntityDataValue::Pose(Pose::Digging),
+            },
+        ]);
+        let mut buf = Vec::new();
+        metadata.write_into(&mut buf).unwrap();
+
+        let read = EntityMetadata::read_from(&mut Cursor::new(&buf[..])).unwrap();
+        assert!(matches!(read.get(1), Some(EntityDataValue::Int(300))));
+        assert!(matches!(
+            read.get(9),
+            Some(EntityDataValue::OptionalBlockState(None))
+        ));
+        assert!(matches!(
+            read.get(10),
+            Some(EntityDataValue::OptionalUnsignedInt(Some(0)))
+        ));
+        assert!(matches!(
+            read.get(11),
+            Some(EntityDataValue::ItemStack(Slot::Empty))
+        ));
+        assert!(matches!(
+            read.get(18),
+            Some(EntityDataValue::VillagerData(VillagerData {
+                profession: azalea_registry::VillagerProfession::Farmer,
+                level: 2,
+                ..
+            }))
+        ));
+        assert_eq!(read.pose(), None);
+        assert!(matches!(
+            read.get(19),
+            Some(EntityDataValue::Pose(Pose::Digging))
+        ));
+
+        let mut rewritten = Vec::new();
+        read.write_into(&mut rewritten).unwrap();
+        assert_eq!(buf, rewritten);
+    }
 }
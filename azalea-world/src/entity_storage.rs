@@ -1,8 +1,8 @@
 use crate::entity::EntityData;
 use azalea_core::ChunkPos;
-use log::warn;
 use nohash_hasher::{IntMap, IntSet};
 use std::collections::HashMap;
+use tracing::warn;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -0,0 +1,200 @@
+//! Heightmaps, which keep track of the highest block in every column of a
+//! chunk.
+
+use crate::{
+    block_entity::get_nbt_field, chunk_storage::is_air, palette::ceil_log2, BitStorage,
+    BitStorageError,
+};
+use azalea_block::{Block, BlockState};
+use azalea_nbt::Tag;
+
+/// The heightmaps that the server sends to clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HeightmapKind {
+    /// The highest block that isn't air.
+    WorldSurface,
+    /// The highest block that blocks motion or has a fluid in it.
+    MotionBlocking,
+}
+
+impl HeightmapKind {
+    pub const ALL: [HeightmapKind; 2] =
+        [HeightmapKind::WorldSurface, HeightmapKind::MotionBlocking];
+
+    /// The name of the heightmap in chunk NBT.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeightmapKind::WorldSurface => "WORLD_SURFACE",
+            HeightmapKind::MotionBlocking => "MOTION_BLOCKING",
+        }
+    }
+
+    /// Whether the heightmap stops at this block.
+    pub fn is_opaque(&self, state: BlockState) -> bool {
+        match self {
+            HeightmapKind::WorldSurface => !is_air(state as u32),
+            // fluids have collision here too, which is what we want since
+            // they count as motion blocking
+            HeightmapKind::MotionBlocking => {
+                !is_air(state as u32) && Box::<dyn Block>::from(state).behavior().has_collision
+            }
+        }
+    }
+}
+
+/// The y position above the highest block of a kind in every column of a
+/// chunk.
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    pub kind: HeightmapKind,
+    data: BitStorage,
+    min_y: i32,
+}
+
+impl Heightmap {
+    /// Create a heightmap where every column is empty.
+    pub fn new(kind: HeightmapKind, dimension_height: u32, min_y: i32) -> Self {
+        Self::from_data(kind, dimension_height, min_y, None).unwrap()
+    }
+
+    /// Create a heightmap from the longs it's stored as in chunk packets and
+    /// region files.
+    pub fn from_data(
+        kind: HeightmapKind,
+        dimension_height: u32,
+        min_y: i32,
+        data: Option<Vec<u64>>,
+    ) -> Result<Self, BitStorageError> {
+        let bits = ceil_log2(dimension_height as usize + 1);
+        Ok(Heightmap {
+            kind,
+            data: BitStorage::new(bits as usize, 256, data)?,
+            min_y,
+        })
+    }
+
+    /// Read the heightmap of this kind from a compound like
+    /// `{MOTION_BLOCKING: [L; ...], WORLD_SURFACE: [L; ...]}`. Returns
+    /// `None` if it's missing or has the wrong length.
+    pub fn from_nbt(
+        kind: HeightmapKind,
+        tag: &Tag,
+        dimension_height: u32,
+        min_y: i32,
+    ) -> Option<Self> {
+        let data = get_nbt_field(tag, kind.name())?.as_longarray()?;
+        if data.is_empty() {
+            return None;
+        }
+        let data = data.iter().map(|&long| long as u64).collect();
+        Self::from_data(kind, dimension_height, min_y, Some(data)).ok()
+    }
+
+    fn index(x: u8, z: u8) -> usize {
+        x as usize + z as usize * 16
+    }
+
+    /// The y position right above the highest block in the column, or the
+    /// minimum y if it doesn't have any blocks.
+    pub fn first_available(&self, x: u8, z: u8) -> i32 {
+        self.data.get(Self::index(x, z)) as i32 + self.min_y
+    }
+
+    pub fn set_first_available(&mut self, x: u8, z: u8, y: i32) {
+        self.data.set(Self::index(x, z), (y - self.min_y) as u64);
+    }
+
+    /// The longs that the heightmap is stored as.
+    pub fn data(&self) -> &[u64] {
+        &self.data.data
+    }
+
+    /// Update the heightmap after the block at the position was set to the
+    /// state. `get_state` gets the state at a y position in the same column,
+    /// and is used to find the next highest block if the highest one was
+    /// removed. Returns whether the heightmap changed.
+    pub fn update(
+        &mut self,
+        x: u8,
+        y: i32,
+        z: u8,
+        state: BlockState,
+        get_state: impl Fn(i32) -> BlockState,
+    ) -> bool {
+        let first_available = self.first_available(x, z);
+        if y <= first_available - 2 {
+            return false;
+        }
+        if self.kind.is_opaque(state) {
+            if y >= first_available {
+                self.set_first_available(x, z, y + 1);
+                return true;
+            }
+        } else if first_available - 1 == y {
+            let new_first_available = (self.min_y..y)
+                .rev()
+                .find(|&y| self.kind.is_opaque(get_state(y)))
+                .map_or(self.min_y, |y| y + 1);
+            self.set_first_available(x, z, new_first_available);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let mut column = vec![BlockState::Air; 384];
+        let mut heightmap = Heightmap::new(HeightmapKind::WorldSurface, 384, -64);
+        assert_eq!(heightmap.first_available(3, 4), -64);
+
+        let mut set = |heightmap: &mut Heightmap, y: i32, state: BlockState| {
+            column[(y + 64) as usize] = state;
+            heightmap.update(3, y, 4, state, |y| column[(y + 64) as usize])
+        };
+        assert!(set(&mut heightmap, 10, BlockState::Stone));
+        assert!(set(&mut heightmap, 100, BlockState::Stone));
+        assert_eq!(heightmap.first_available(3, 4), 101);
+        // blocks below the top don't change anything
+        assert!(!set(&mut heightmap, 50, BlockState::Stone));
+        assert_eq!(heightmap.first_available(3, 4), 101);
+
+        assert!(set(&mut heightmap, 100, BlockState::Air));
+        assert_eq!(heightmap.first_available(3, 4), 51);
+        assert!(set(&mut heightmap, 50, BlockState::Air));
+        assert!(set(&mut heightmap, 10, BlockState::Air));
+        assert_eq!(heightmap.first_available(3, 4), -64);
+        // other columns weren't changed
+        assert_eq!(heightmap.first_available(4, 3), -64);
+    }
+
+    #[test]
+    fn test_from_nbt() {
+        let mut heightmap = Heightmap::new(HeightmapKind::MotionBlocking, 384, -64);
+        heightmap.set_first_available(15, 15, 319);
+        let data = heightmap.data().iter().map(|&long| long as i64).collect();
+        let tag = Tag::Compound(
+            [(
+                "".to_string(),
+                Tag::Compound(
+                    [("MOTION_BLOCKING".to_string(), Tag::LongArray(data))]
+                        .into_iter()
+                        .collect(),
+                ),
+            )]
+            .into_iter()
+            .collect(),
+        );
+
+        let read = Heightmap::from_nbt(HeightmapKind::MotionBlocking, &tag, 384, -64).unwrap();
+        assert_eq!(read.first_available(15, 15), 319);
+        assert_eq!(read.first_available(0, 0), -64);
+        assert!(Heightmap::from_nbt(HeightmapKind::WorldSurface, &tag, 384, -64).is_none());
+        // the wrong height means the data has the wrong length
+        assert!(Heightmap::from_nbt(HeightmapKind::MotionBlocking, &tag, 4064, -64).is_none());
+    }
+}
@@ -1,18 +1,31 @@
 #![feature(int_roundings)]
 
+mod biome;
 mod bit_storage;
+mod block_entity;
 mod chunk_storage;
 pub mod entity;
 mod entity_storage;
+mod heightmap;
+mod light;
 mod palette;
+mod region;
+mod snapshot;
 
 use azalea_block::BlockState;
 use azalea_buf::BufReadError;
-use azalea_core::{BlockPos, ChunkPos, PositionDelta8, Vec3};
-pub use bit_storage::BitStorage;
-pub use chunk_storage::{Chunk, ChunkStorage};
+use azalea_core::{BitSet, BlockPos, ChunkPos, PositionDelta8, Vec3};
+use azalea_nbt::Tag;
+pub use biome::{biome_cell, Biome, BiomeRegistry, Precipitation};
+pub use bit_storage::{BitStorage, BitStorageError};
+pub use block_entity::{Banner, BannerPattern, BlockEntity, Chest, ChestItem, Sign, Spawner};
+pub use chunk_storage::{Chunk, ChunkStorage, SectionReadError};
 use entity::{EntityData, EntityMut, EntityRef};
 pub use entity_storage::EntityStorage;
+pub use heightmap::{Heightmap, HeightmapKind};
+pub use light::{ChunkLight, LightLayer, LightSection, LIGHT_SECTION_SIZE};
+pub use region::{RegionError, RegionFile};
+pub use snapshot::{BlockChange, SnapshotDiffError, WorldSnapshot};
 use std::{
     io::Cursor,
     ops::{Index, IndexMut},
@@ -28,6 +41,10 @@ use uuid::Uuid;
 pub struct Dimension {
     chunk_storage: ChunkStorage,
     entity_storage: EntityStorage,
+    biome_registry: BiomeRegistry,
+    /// The hashed seed from the login packet, which is used to blend the
+    /// edges of biomes.
+    hashed_seed: i64,
 }
 
 #[derive(Error, Debug)]
@@ -41,15 +58,21 @@ impl Dimension {
         Dimension {
             chunk_storage: ChunkStorage::new(chunk_radius, height, min_y),
             entity_storage: EntityStorage::new(),
+            biome_registry: BiomeRegistry::default(),
+            hashed_seed: 0,
         }
     }
 
+    /// Read a chunk from the data and heightmaps in a chunk packet, and
+    /// replace the chunk at the position with it.
     pub fn replace_with_packet_data(
         &mut self,
         pos: &ChunkPos,
         data: &mut Cursor<&[u8]>,
+        heightmaps: &Tag,
     ) -> Result<(), BufReadError> {
-        self.chunk_storage.replace_with_packet_data(pos, data)
+        self.chunk_storage
+            .replace_with_packet_data(pos, data, heightmaps)
     }
 
     pub fn set_chunk(&mut self, pos: &ChunkPos, chunk: Option<Chunk>) -> Result<(), BufReadError> {
@@ -69,6 +92,92 @@ impl Dimension {
         self.chunk_storage.set_block_state(pos, state)
     }
 
+    /// The y position right above the highest block of the kind at the x and
+    /// z, or `None` if the chunk isn't loaded.
+    pub fn get_height(&self, kind: HeightmapKind, x: i32, z: i32) -> Option<i32> {
+        self.chunk_storage.get_height(kind, x, z)
+    }
+
+    /// The highest block that isn't air at the x and z. This is `None` if the
+    /// chunk isn't loaded or there's only air in the column.
+    pub fn get_top_block(&self, x: i32, z: i32) -> Option<BlockPos> {
+        let height = self.get_height(HeightmapKind::WorldSurface, x, z)?;
+        if height <= self.min_y() {
+            return None;
+        }
+        Some(BlockPos::new(x, height - 1, z))
+    }
+
+    /// Set the biomes and hashed seed that the server sent in the login
+    /// packet, which are needed for [`Dimension::get_biome`].
+    pub fn set_biomes(&mut self, biome_registry: BiomeRegistry, hashed_seed: i64) {
+        self.biome_registry = biome_registry;
+        self.hashed_seed = hashed_seed;
+    }
+
+    pub fn biome_registry(&self) -> &BiomeRegistry {
+        &self.biome_registry
+    }
+
+    /// Get the biome at the position, the same way that the vanilla client
+    /// does. Returns `None` if the chunk isn't loaded or the server didn't
+    /// send the biome.
+    pub fn get_biome(&self, pos: &BlockPos) -> Option<Biome> {
+        let (quart_x, quart_y, quart_z) = biome_cell(pos, self.hashed_seed);
+        let id = self.chunk_storage.get_biome_id(quart_x, quart_y, quart_z)?;
+        self.biome_registry.get(id).cloned()
+    }
+
+    /// Get a copy of the block entity at the position, like a chest or a
+    /// sign. Use the methods on [`BlockEntity`] to read its data.
+    pub fn get_block_entity(&self, pos: &BlockPos) -> Option<BlockEntity> {
+        self.chunk_storage.get_block_entity(pos)
+    }
+
+    /// Set or remove the block entity at the position, and return the one
+    /// that was there before. Nothing happens if the chunk isn't loaded.
+    pub fn set_block_entity(
+        &mut self,
+        pos: &BlockPos,
+        block_entity: Option<BlockEntity>,
+    ) -> Option<BlockEntity> {
+        self.chunk_storage.set_block_entity(pos, block_entity)
+    }
+
+    /// Get the block light level (0-15) at a position, or `None` if the chunk
+    /// isn't loaded.
+    pub fn block_light(&self, pos: &BlockPos) -> Option<u8> {
+        self.chunk_storage.get_light(LightLayer::Block, pos)
+    }
+
+    /// Get the sky light level (0-15) at a position, or `None` if the chunk
+    /// isn't loaded.
+    pub fn sky_light(&self, pos: &BlockPos) -> Option<u8> {
+        self.chunk_storage.get_light(LightLayer::Sky, pos)
+    }
+
+    /// Apply light data from a chunk or light update packet to a loaded
+    /// chunk. Nothing happens if the chunk isn't loaded.
+    pub fn update_light(
+        &mut self,
+        pos: &ChunkPos,
+        layer: LightLayer,
+        mask: &BitSet,
+        empty_mask: &BitSet,
+        updates: &[LightSection],
+    ) {
+        if !self.chunk_storage.in_range(pos) {
+            return;
+        }
+        if let Some(chunk) = &self[pos] {
+            let mut chunk = chunk.lock().unwrap();
+            let section_count = chunk.light_section_count();
+            chunk
+                .light
+                .update(layer, section_count, mask, empty_mask, updates);
+        }
+    }
+
     pub fn set_entity_pos(&mut self, entity_id: u32, new_pos: Vec3) -> Result<(), MoveEntityError> {
         let mut entity = self
             .entity_mut(entity_id)
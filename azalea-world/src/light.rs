@@ -0,0 +1,202 @@
+use azalea_buf::{
+    read_prefixed_array, BufReadError, McBufReadable, McBufVarWritable, McBufWritable,
+};
+use azalea_core::{BitSet, ChunkSectionBlockPos};
+use std::io::{Cursor, Write};
+
+/// The number of bytes in a light section, since there's 4096 blocks in a
+/// section and each one takes up half a byte.
+pub const LIGHT_SECTION_SIZE: usize = 2048;
+
+/// The light levels (0-15) of the blocks in a section, with two blocks in
+/// every byte. It's sent with its length first, even though it's always the
+/// same length.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LightSection(pub Box<[u8; LIGHT_SECTION_SIZE]>);
+
+impl Default for LightSection {
+    fn default() -> Self {
+        LightSection(Box::new([0; LIGHT_SECTION_SIZE]))
+    }
+}
+
+impl McBufReadable for LightSection {
+    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        Ok(LightSection(Box::new(read_prefixed_array(buf)?)))
+    }
+}
+
+impl McBufWritable for LightSection {
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        (LIGHT_SECTION_SIZE as u32).var_write_into(buf)?;
+        self.0.write_into(buf)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LightSection {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0[..])
+    }
+}
+
+/// The two kinds of light that Minecraft keeps track of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LightLayer {
+    Sky,
+    Block,
+}
+
+impl LightLayer {
+    /// The light level used for sections that the server never sent us.
+    pub fn default_level(&self) -> u8 {
+        match self {
+            LightLayer::Sky => 15,
+            LightLayer::Block => 0,
+        }
+    }
+}
+
+/// The sky and block light in a chunk. There's one more light section above
+/// and below the chunk than there are block sections, so index 0 is the
+/// section right below the bottom of the world.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkLight {
+    sky: Vec<Option<LightSection>>,
+    block: Vec<Option<LightSection>>,
+}
+
+impl ChunkLight {
+    fn layer(&self, layer: LightLayer) -> &Vec<Option<LightSection>> {
+        match layer {
+            LightLayer::Sky => &self.sky,
+            LightLayer::Block => &self.block,
+        }
+    }
+
+    fn layer_mut(&mut self, layer: LightLayer) -> &mut Vec<Option<LightSection>> {
+        match layer {
+            LightLayer::Sky => &mut self.sky,
+            LightLayer::Block => &mut self.block,
+        }
+    }
+
+    /// Get the light level (0-15) at a position in a light section. Sections
+    /// we don't have data for use [`LightLayer::default_level`].
+    pub fn get(&self, layer: LightLayer, section_index: usize, pos: ChunkSectionBlockPos) -> u8 {
+        let index = ((pos.y as usize) << 8) | ((pos.z as usize) << 4) | pos.x as usize;
+        self.layer(layer)
+            .get(section_index)
+            .and_then(|section| section.as_ref())
+            .map(|section| (section.0[index >> 1] >> ((index & 1) * 4)) & 0xf)
+            .unwrap_or_else(|| layer.default_level())
+    }
+
+    /// Apply the light data for one layer from a chunk or light update
+    /// packet. `section_count` is the number of light sections in the chunk.
+    ///
+    /// Every section that's set in `mask` has an array in `updates` (in
+    /// order), sections in `empty_mask` are completely dark, and sections in
+    /// neither are left alone.
+    pub fn update(
+        &mut self,
+        layer: LightLayer,
+        section_count: usize,
+        mask: &BitSet,
+        empty_mask: &BitSet,
+        updates: &[LightSection],
+    ) {
+        let sections = self.layer_mut(layer);
+        sections.resize(section_count, None);
+        let mut updates = updates.iter();
+        for (i, section) in sections.iter_mut().enumerate() {
+            if mask.index(i) {
+                match updates.next() {
+                    Some(data) => {
+                        *section = Some(data.clone());
+                    }
+                    None => {
+                        tracing::warn!("Light mask has more sections than were sent");
+                        break;
+                    }
+                }
+            } else if empty_mask.index(i) {
+                *section = Some(LightSection::default());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chunk, Dimension};
+    use azalea_core::{BlockPos, ChunkPos};
+
+    #[test]
+    fn test_light_update() {
+        let mut light = ChunkLight::default();
+        let mut mask = BitSet::new(26);
+        mask.set(1);
+        mask.set(3);
+        let mut empty_mask = BitSet::new(26);
+        empty_mask.set(2);
+
+        let mut first = LightSection::default();
+        // x=1 is the high nibble of the first byte
+        first.0[0] = 0x7a;
+        let mut second = LightSection(Box::new([0xff; LIGHT_SECTION_SIZE]));
+        // y=15, z=15, x=14 and x=15
+        second.0[2047] = 0x35;
+        light.update(LightLayer::Block, 26, &mask, &empty_mask, &[first, second]);
+
+        let pos = |x, y, z| ChunkSectionBlockPos { x, y, z };
+        assert_eq!(light.get(LightLayer::Block, 1, pos(0, 0, 0)), 0xa);
+        assert_eq!(light.get(LightLayer::Block, 1, pos(1, 0, 0)), 0x7);
+        assert_eq!(light.get(LightLayer::Block, 2, pos(1, 0, 0)), 0);
+        assert_eq!(light.get(LightLayer::Block, 3, pos(14, 15, 15)), 0x5);
+        assert_eq!(light.get(LightLayer::Block, 3, pos(15, 15, 15)), 0x3);
+        assert_eq!(light.get(LightLayer::Block, 3, pos(3, 3, 3)), 15);
+        // sections we don't know about use the default level
+        assert_eq!(light.get(LightLayer::Block, 0, pos(0, 0, 0)), 0);
+        assert_eq!(light.get(LightLayer::Sky, 1, pos(0, 0, 0)), 15);
+
+        // an update that only marks section 1 as empty leaves section 3 alone
+        let mut empty_mask = BitSet::new(26);
+        empty_mask.set(1);
+        light.update(LightLayer::Block, 26, &BitSet::new(26), &empty_mask, &[]);
+        assert_eq!(light.get(LightLayer::Block, 1, pos(0, 0, 0)), 0);
+        assert_eq!(light.get(LightLayer::Block, 3, pos(15, 15, 15)), 0x3);
+    }
+
+    #[test]
+    fn test_dimension_light() {
+        let mut dimension = Dimension::default();
+        let chunk_pos = ChunkPos::new(-1, 0);
+        assert_eq!(dimension.sky_light(&BlockPos::new(-1, -64, 0)), None);
+        dimension
+            .set_chunk(&chunk_pos, Some(Chunk::default()))
+            .unwrap();
+
+        // light section 1 is the bottom section of the world (y -64 to -49)
+        let mut mask = BitSet::new(26);
+        mask.set(1);
+        let mut data = LightSection::default();
+        // x=15, y=0, z=0 in the section
+        data.0[7] = 0xc0;
+        dimension.update_light(
+            &chunk_pos,
+            LightLayer::Block,
+            &mask,
+            &BitSet::new(26),
+            &[data],
+        );
+
+        assert_eq!(dimension.block_light(&BlockPos::new(-1, -64, 0)), Some(12));
+        assert_eq!(dimension.block_light(&BlockPos::new(-2, -64, 0)), Some(0));
+        assert_eq!(dimension.block_light(&BlockPos::new(-1, 100, 0)), Some(0));
+        assert_eq!(dimension.sky_light(&BlockPos::new(-1, 100, 0)), Some(15));
+        // outside of the light sections
+        assert_eq!(dimension.block_light(&BlockPos::new(-1, -81, 0)), None);
+    }
+}
@@ -1,5 +1,11 @@
-use azalea_buf::{BufReadError, McBufReadable, McBufVarReadable, McBufWritable};
-use std::io::{Cursor, Write};
+use azalea_block::BlockState;
+use azalea_buf::{BufReadError, McBufReadable, McBufVarReadable, McBufVarWritable, McBufWritable};
+use std::{
+    collections::HashMap,
+    io::{Cursor, Write},
+    ops::Range,
+};
+use tracing::{trace, trace_span};
 
 use crate::BitStorage;
 
@@ -19,17 +25,17 @@ pub struct PalettedContainer {
 }
 
 impl PalettedContainer {
-    pub fn new(container_type: &'static PalettedContainerType) -> Result<Self, String> {
+    pub fn new(container_type: &'static PalettedContainerType) -> Self {
         let palette = Palette::SingleValue(0);
         let size = container_type.size();
         let storage = BitStorage::new(0, size, Some(vec![])).unwrap();
 
-        Ok(PalettedContainer {
+        PalettedContainer {
             bits_per_entry: 0,
             palette,
             storage,
             container_type: *container_type,
-        })
+        }
     }
 
     pub fn read_with_type(
@@ -46,7 +52,8 @@ impl PalettedContainer {
             bits_per_entry != 0 || data.is_empty(),
             "Bits per entry is 0 but data is not empty."
         );
-        let storage = BitStorage::new(bits_per_entry.into(), size, Some(data)).unwrap();
+        let storage = BitStorage::new(bits_per_entry.into(), size, Some(data))
+            .map_err(|e| BufReadError::Other(Box::new(e)))?;
 
         Ok(PalettedContainer {
             bits_per_entry,
@@ -56,6 +63,145 @@ impl PalettedContainer {
         })
     }
 
+    /// Create a container from a flat list of ids, in the same order as
+    /// [`Self::get_index`]. This is much faster than calling `set` for every
+    /// index since the palette is only allocated once.
+    ///
+    /// `biome_registry_size` is the number of biomes in the biome registry,
+    /// which decides the bits per entry of the global palette for biomes like
+    /// in vanilla. It's ignored for block states, which use
+    /// [`BlockState::max_state`].
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the number of ids isn't the size of the container
+    /// (4096 for block states and 64 for biomes).
+    pub fn from_ids(
+        container_type: &'static PalettedContainerType,
+        ids: &[u32],
+        biome_registry_size: usize,
+    ) -> Self {
+        let size = container_type.size();
+        assert_eq!(ids.len(), size, "Expected {size} ids but got {}", ids.len());
+
+        let mut palette = Vec::new();
+        let mut palette_indices = HashMap::new();
+        let indices = ids
+            .iter()
+            .map(|&id| {
+                *palette_indices.entry(id).or_insert_with(|| {
+                    palette.push(id);
+                    palette.len() - 1
+                }) as u64
+            })
+            .collect::<Vec<_>>();
+
+        if palette.len() == 1 {
+            return PalettedContainer {
+                bits_per_entry: 0,
+                palette: Palette::SingleValue(palette[0]),
+                storage: BitStorage::new(0, size, Some(vec![])).unwrap(),
+                container_type: *container_type,
+            };
+        }
+
+        let bits_per_entry = match container_type {
+            // block states always use at least 4 bits, like in vanilla
+            PalettedContainerType::BlockStates => ceil_log2(palette.len()).max(4),
+            PalettedContainerType::Biomes => ceil_log2(palette.len()),
+        };
+        let (bits_per_entry, palette) =
+            match PaletteType::from_bits_and_type(bits_per_entry, container_type) {
+                PaletteType::Linear => (bits_per_entry, Palette::Linear(palette)),
+                PaletteType::Hashmap => (bits_per_entry, Palette::Hashmap(palette.into())),
+                _ => {
+                    let global_size = match container_type {
+                        PalettedContainerType::BlockStates => BlockState::max_state() as usize + 1,
+                        PalettedContainerType::Biomes => biome_registry_size,
+                    };
+                    (ceil_log2(global_size).max(bits_per_entry), Palette::Global)
+                }
+            };
+
+        let mut storage = BitStorage::new(bits_per_entry as usize, size, None).unwrap();
+        for (index, (&id, &paletted_value)) in ids.iter().zip(&indices).enumerate() {
+            let value = match palette {
+                Palette::Global => id as u64,
+                _ => paletted_value,
+            };
+            storage.set(index, value);
+        }
+
+        PalettedContainer {
+            bits_per_entry,
+            palette,
+            storage,
+            container_type: *container_type,
+        }
+    }
+
+    /// Repack the container into the smallest palette and bits per entry that
+    /// can hold the values that are in it right now. Palettes only ever grow
+    /// while setting values, so call this before writing a container that's
+    /// been edited a lot.
+    pub fn optimize(&mut self) {
+        let _span = trace_span!("optimize", bits_per_entry = self.bits_per_entry).entered();
+        let ids = self
+            .iter()
+            .map(|(_, _, _, value)| value)
+            .collect::<Vec<_>>();
+        // we don't know the size of the biome registry here, but a container
+        // can only use the global palette after optimizing if it already did,
+        // so keep the bits it has
+        let biome_registry_size = 1 << self.bits_per_entry;
+        *self =
+            PalettedContainer::from_ids(self.container_type.as_static(), &ids, biome_registry_size);
+    }
+
+    /// Set every value in the container, which also shrinks the palette to a
+    /// single value.
+    pub fn fill(&mut self, value: u32) {
+        self.bits_per_entry = 0;
+        self.palette = Palette::SingleValue(value);
+        self.storage = BitStorage::new(0, self.container_type.size(), Some(vec![])).unwrap();
+    }
+
+    /// Set every index in the range to the value. Indices are in the same
+    /// order as [`Self::get_index`], so a range of whole y levels is
+    /// `get_index(0, min_y, 0)..get_index(0, max_y + 1, 0)`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the range goes past the end of the container.
+    pub fn set_region(&mut self, range: Range<usize>, value: u32) {
+        if range.start == 0 && range.end == self.container_type.size() {
+            return self.fill(value);
+        }
+        if range.is_empty() {
+            return;
+        }
+        let paletted_value = self.id_for(value);
+        self.storage.set_range(range, paletted_value as u64);
+    }
+
+    /// Iterate over the coordinates and values of everything in the
+    /// container, in the same order as [`Self::get_index`].
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, usize, u32)> + '_ {
+        let size_bits = self.container_type.size_bits();
+        let mask = (1 << size_bits) - 1;
+        self.storage
+            .iter()
+            .enumerate()
+            .map(move |(index, paletted_value)| {
+                (
+                    index & mask,
+                    index >> (size_bits * 2),
+                    (index >> size_bits) & mask,
+                    self.palette.value_for(paletted_value as usize),
+                )
+            })
+    }
+
     /// Calculates the index of the given coordinates.
     pub fn get_index(&self, x: usize, y: usize, z: usize) -> usize {
         let size_bits = self.container_type.size_bits();
@@ -79,8 +225,10 @@ impl PalettedContainer {
     /// Sets the id at the given coordinates and return the previous id
     pub fn get_and_set(&mut self, x: usize, y: usize, z: usize, value: u32) -> u32 {
         let paletted_value = self.id_for(value);
-        self.storage
-            .get_and_set(self.get_index(x, y, z), paletted_value as u64) as u32
+        let previous_paletted_value = self
+            .storage
+            .get_and_set(self.get_index(x, y, z), paletted_value as u64);
+        self.palette.value_for(previous_paletted_value as usize)
     }
 
     /// Sets the id at the given index and return the previous id. You probably want `.set` instead.
@@ -121,6 +269,11 @@ impl PalettedContainer {
     }
 
     fn on_resize(&mut self, bits_per_entry: u8, value: u32) -> usize {
+        trace!(
+            from = self.bits_per_entry,
+            to = bits_per_entry,
+            "Resizing paletted container"
+        );
         // in vanilla this is always true, but it's sometimes false in purpur servers
         // assert!(bits_per_entry <= 5, "bits_per_entry must be <= 5");
         let mut new_data = self.create_or_reuse_data(bits_per_entry);
@@ -159,14 +312,12 @@ impl PalettedContainer {
                 }
             }
             Palette::Hashmap(palette) => {
-                // TODO? vanilla keeps this in memory as a hashmap, but also i don't care
-                if let Some(index) = palette.iter().position(|v| *v == value) {
+                if let Some(index) = palette.index_of(value) {
                     return index;
                 }
                 let capacity = 2usize.pow(self.bits_per_entry.into());
                 if capacity > palette.len() {
-                    palette.push(value);
-                    palette.len() - 1
+                    palette.push(value)
                 } else {
                     self.on_resize(self.bits_per_entry + 1, value)
                 }
@@ -199,22 +350,70 @@ pub enum Palette {
     SingleValue(u32),
     // in vanilla this keeps a `size` field that might be less than the length, but i'm not sure it's actually needed?
     Linear(Vec<u32>),
-    Hashmap(Vec<u32>),
+    Hashmap(HashmapPalette),
     Global,
 }
 
+/// A palette that also keeps a map from values to their index, so looking up
+/// the index of a value doesn't have to go through the whole list. This is
+/// used for sections with lots of different block states.
+#[derive(Clone, Debug, Default)]
+pub struct HashmapPalette {
+    values: Vec<u32>,
+    indices: HashMap<u32, usize>,
+}
+
+impl HashmapPalette {
+    /// The index of the value in the palette, if it's in it.
+    pub fn index_of(&self, value: u32) -> Option<usize> {
+        self.indices.get(&value).copied()
+    }
+
+    /// Add the value to the end of the palette and return its index. The
+    /// value shouldn't already be in the palette.
+    pub fn push(&mut self, value: u32) -> usize {
+        debug_assert!(!self.indices.contains_key(&value));
+        let index = self.values.len();
+        self.values.push(value);
+        self.indices.insert(value, index);
+        index
+    }
+
+    pub fn get(&self, index: usize) -> Option<u32> {
+        self.values.get(index).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The values in the palette, in the order of their indices.
+    pub fn values(&self) -> &[u32] {
+        &self.values
+    }
+}
+
+impl From<Vec<u32>> for HashmapPalette {
+    fn from(values: Vec<u32>) -> Self {
+        let mut indices = HashMap::with_capacity(values.len());
+        for (index, &value) in values.iter().enumerate() {
+            // if a value is in the palette twice, the first index is used
+            indices.entry(value).or_insert(index);
+        }
+        Self { values, indices }
+    }
+}
+
 impl Palette {
     pub fn value_for(&self, id: usize) -> u32 {
         match self {
             Palette::SingleValue(v) => *v,
             Palette::Linear(v) => v[id],
-            Palette::Hashmap(v) => {
-                if id >= v.len() {
-                    0
-                } else {
-                    v[id]
-                }
-            }
+            Palette::Hashmap(v) => v.get(id).unwrap_or(0),
             Palette::Global => id as u32,
         }
     }
@@ -224,13 +423,13 @@ impl McBufWritable for Palette {
     fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
         match self {
             Palette::SingleValue(value) => {
-                value.write_into(buf)?;
+                value.var_write_into(buf)?;
             }
             Palette::Linear(values) => {
-                values.write_into(buf)?;
+                values.var_write_into(buf)?;
             }
-            Palette::Hashmap(values) => {
-                values.write_into(buf)?;
+            Palette::Hashmap(palette) => {
+                palette.values.var_write_into(buf)?;
             }
             Palette::Global => {}
         }
@@ -259,7 +458,7 @@ impl PaletteType {
         Ok(match self {
             PaletteType::SingleValue => Palette::SingleValue(u32::var_read_from(buf)?),
             PaletteType::Linear => Palette::Linear(Vec::<u32>::var_read_from(buf)?),
-            PaletteType::Hashmap => Palette::Hashmap(Vec::<u32>::var_read_from(buf)?),
+            PaletteType::Hashmap => Palette::Hashmap(Vec::<u32>::var_read_from(buf)?.into()),
             PaletteType::Global => Palette::Global,
         })
     }
@@ -268,7 +467,7 @@ impl PaletteType {
         match self {
             PaletteType::SingleValue => Palette::SingleValue(0),
             PaletteType::Linear => Palette::Linear(Vec::new()),
-            PaletteType::Hashmap => Palette::Hashmap(Vec::new()),
+            PaletteType::Hashmap => Palette::Hashmap(HashmapPalette::default()),
             PaletteType::Global => Palette::Global,
         }
     }
@@ -285,6 +484,11 @@ impl From<&Palette> for PaletteType {
     }
 }
 
+/// The number of bits needed to store `n` different values.
+pub(crate) fn ceil_log2(n: usize) -> u8 {
+    (usize::BITS - (n - 1).leading_zeros()) as u8
+}
+
 impl PalettedContainerType {
     fn size_bits(&self) -> usize {
         match self {
@@ -296,6 +500,13 @@ impl PalettedContainerType {
     fn size(&self) -> usize {
         1 << (self.size_bits() * 3)
     }
+
+    fn as_static(&self) -> &'static PalettedContainerType {
+        match self {
+            PalettedContainerType::BlockStates => &PalettedContainerType::BlockStates,
+            PalettedContainerType::Biomes => &PalettedContainerType::Biomes,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -304,8 +515,7 @@ mod tests {
 
     #[test]
     fn test_resize_0_bits_to_1() {
-        let mut palette_container =
-            PalettedContainer::new(&PalettedContainerType::BlockStates).unwrap();
+        let mut palette_container = PalettedContainer::new(&PalettedContainerType::BlockStates);
 
         assert_eq!(palette_container.bits_per_entry, 0);
         assert_eq!(palette_container.get_at_index(0), 0);
@@ -323,8 +533,7 @@ mod tests {
 
     #[test]
     fn test_resize_0_bits_to_5() {
-        let mut palette_container =
-            PalettedContainer::new(&PalettedContainerType::BlockStates).unwrap();
+        let mut palette_container = PalettedContainer::new(&PalettedContainerType::BlockStates);
 
         palette_container.set_at_index(0, 0); // 0 bits
         assert_eq!(palette_container.bits_per_entry, 0);
@@ -356,4 +565,160 @@ mod tests {
         palette_container.set_at_index(16, 16); // 5 bits
         assert_eq!(palette_container.bits_per_entry, 5);
     }
+
+    #[test]
+    fn test_from_ids() {
+        let container_type = &PalettedContainerType::BlockStates;
+        for (distinct, bits_per_entry, palette_type) in [
+            (1, 0, PaletteType::SingleValue),
+            (2, 4, PaletteType::Linear),
+            (16, 4, PaletteType::Linear),
+            (17, 5, PaletteType::Hashmap),
+            (
+                300,
+                ceil_log2(BlockState::max_state() as usize + 1),
+                PaletteType::Global,
+            ),
+        ] {
+            let ids = (0..4096u32)
+                .map(|i| (i * 7 + 1) % distinct)
+                .collect::<Vec<_>>();
+            let container = PalettedContainer::from_ids(container_type, &ids, 0);
+            assert_eq!(container.bits_per_entry, bits_per_entry);
+            assert_eq!(PaletteType::from(&container.palette), palette_type);
+            for (index, &id) in ids.iter().enumerate() {
+                assert_eq!(container.get_at_index(index), id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_ids_global_biomes() {
+        // 9 biomes don't fit in the 3 bits that linear biome palettes can
+        // have, so the global palette is used, sized by the registry and not
+        // by the biggest id in the section
+        let ids = (0..64u32).map(|i| i % 9).collect::<Vec<_>>();
+        for (biome_registry_size, bits_per_entry) in [(63, 6), (64, 6), (65, 7), (200, 8)] {
+            let container = PalettedContainer::from_ids(
+                &PalettedContainerType::Biomes,
+                &ids,
+                biome_registry_size,
+            );
+            assert_eq!(container.bits_per_entry, bits_per_entry);
+            assert_eq!(PaletteType::from(&container.palette), PaletteType::Global);
+            for (index, &id) in ids.iter().enumerate() {
+                assert_eq!(container.get_at_index(index), id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let mut palette_container = PalettedContainer::new(&PalettedContainerType::BlockStates);
+        palette_container.set(0, 0, 0, 5);
+        palette_container.set(1, 0, 0, 6);
+        // the previous value is returned, not its index in the palette
+        assert_eq!(palette_container.get_and_set(0, 0, 0, 7), 5);
+        assert_eq!(palette_container.get_and_set(0, 0, 0, 6), 7);
+        assert_eq!(palette_container.get_and_set(2, 0, 0, 1), 0);
+    }
+
+    #[test]
+    fn test_optimize() {
+        let mut palette_container = PalettedContainer::new(&PalettedContainerType::BlockStates);
+        for i in 0..256 {
+            palette_container.set_at_index(i, i as u32);
+        }
+        assert_eq!(palette_container.bits_per_entry, 8);
+        for i in 2..256 {
+            palette_container.set_at_index(i, 1);
+        }
+        // setting values never shrinks the palette
+        assert_eq!(palette_container.bits_per_entry, 8);
+
+        palette_container.optimize();
+        assert_eq!(palette_container.bits_per_entry, 4);
+        assert_eq!(
+            PaletteType::from(&palette_container.palette),
+            PaletteType::Linear
+        );
+        assert_eq!(palette_container.get_at_index(0), 0);
+        for i in 1..4096 {
+            assert_eq!(
+                palette_container.get_at_index(i),
+                if i < 256 { 1 } else { 0 }
+            );
+        }
+
+        for i in 0..4096 {
+            palette_container.set_at_index(i, 5);
+        }
+        palette_container.optimize();
+        assert_eq!(palette_container.bits_per_entry, 0);
+        assert_eq!(palette_container.get_at_index(100), 5);
+    }
+
+    #[test]
+    fn test_bulk_set() {
+        let mut palette_container = PalettedContainer::new(&PalettedContainerType::BlockStates);
+        palette_container.set(1, 2, 3, 7);
+
+        // y levels 4 to 5
+        let range = palette_container.get_index(0, 4, 0)..palette_container.get_index(0, 6, 0);
+        palette_container.set_region(range.clone(), 9);
+        for (x, y, z, value) in palette_container.iter() {
+            let expected = if (x, y, z) == (1, 2, 3) {
+                7
+            } else if (4..6).contains(&y) {
+                9
+            } else {
+                0
+            };
+            assert_eq!(value, expected, "{x} {y} {z}");
+            assert_eq!(palette_container.get(x, y, z), value);
+        }
+        assert_eq!(palette_container.iter().count(), 4096);
+
+        palette_container.fill(3);
+        assert_eq!(palette_container.bits_per_entry, 0);
+        assert!(palette_container.iter().all(|(_, _, _, value)| value == 3));
+        palette_container.set_region(range, 4);
+        assert_eq!(palette_container.get(15, 5, 15), 4);
+        assert_eq!(palette_container.get(15, 6, 15), 3);
+    }
+
+    #[test]
+    fn test_hashmap_palette() {
+        let mut palette_container = PalettedContainer::new(&PalettedContainerType::BlockStates);
+        for i in 0..256 {
+            palette_container.set_at_index(i, i as u32 * 3);
+        }
+        assert_eq!(palette_container.bits_per_entry, 8);
+        let Palette::Hashmap(palette) = &palette_container.palette else {
+            panic!("Expected a hashmap palette");
+        };
+        assert_eq!(palette.len(), 256);
+        for i in 0..256 {
+            assert_eq!(palette.index_of(i as u32 * 3), Some(i));
+            assert_eq!(palette_container.get_at_index(i), i as u32 * 3);
+        }
+        assert_eq!(palette.index_of(1), None);
+
+        // values that are already in the palette don't get added again
+        palette_container.set_at_index(300, 9);
+        assert_eq!(palette_container.get_at_index(300), 9);
+        assert_eq!(palette_container.bits_per_entry, 8);
+
+        // and the palette is written the same as before
+        let mut buf = Vec::new();
+        palette_container.write_into(&mut buf).unwrap();
+        let read = PalettedContainer::read_with_type(
+            &mut Cursor::new(&buf[..]),
+            &PalettedContainerType::BlockStates,
+        )
+        .unwrap();
+        for i in 0..4096 {
+            assert_eq!(read.get_at_index(i), palette_container.get_at_index(i));
+        }
+    }
 }
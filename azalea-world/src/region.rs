@@ -0,0 +1,452 @@
+//! Reading chunks from the Anvil region files (`.mca`) that worlds are saved
+//! as.
+
+use crate::{
+    chunk_storage::Section,
+    light::ChunkLight,
+    palette::{ceil_log2, Palette, PaletteType, PalettedContainer, PalettedContainerType},
+    BitStorage, Chunk,
+};
+use azalea_block::BlockState;
+use azalea_core::ChunkPos;
+use azalea_nbt::Tag;
+use std::{collections::HashMap, io::Cursor, path::Path};
+use thiserror::Error;
+
+/// The size of a sector in a region file. Chunks are always stored at the
+/// start of a sector.
+const SECTOR_SIZE: usize = 4096;
+/// The size of the header, which contains the locations and timestamps of
+/// the chunks.
+const HEADER_SIZE: usize = SECTOR_SIZE * 2;
+/// The number of blocks in a chunk section.
+const SECTION_SIZE: usize = 16 * 16 * 16;
+
+#[derive(Error, Debug)]
+pub enum RegionError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("Region file is too short to contain a header")]
+    MissingHeader,
+    #[error("Chunk data is outside of the region file")]
+    ChunkOutOfBounds,
+    #[error("Unsupported chunk compression type {0}")]
+    UnsupportedCompression(u8),
+    #[error("Invalid chunk NBT: {0}")]
+    Nbt(String),
+    #[error("Missing or invalid NBT field {0:?}")]
+    InvalidField(&'static str),
+    #[error("Invalid block state data: {0}")]
+    InvalidBlockStates(String),
+}
+
+impl From<azalea_nbt::Error> for RegionError {
+    fn from(e: azalea_nbt::Error) -> Self {
+        RegionError::Nbt(e.to_string())
+    }
+}
+
+/// An Anvil region file, which stores 32x32 chunks.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use azalea_core::ChunkPos;
+/// use azalea_world::RegionFile;
+///
+/// let region = RegionFile::open("world/region/r.0.0.mca").unwrap();
+/// // overworld chunks in 1.18+ have 384 blocks, starting at y -64
+/// let chunk = region.read_chunk(&ChunkPos::new(3, 5), 384, -64).unwrap();
+/// ```
+pub struct RegionFile {
+    data: Vec<u8>,
+}
+
+impl RegionFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RegionError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, RegionError> {
+        if data.len() < HEADER_SIZE {
+            return Err(RegionError::MissingHeader);
+        }
+        Ok(RegionFile { data })
+    }
+
+    /// Get the offset and length (in bytes) of a chunk in the file. Chunk
+    /// positions are wrapped to be inside of the region.
+    fn chunk_location(&self, pos: &ChunkPos) -> Option<(usize, usize)> {
+        let index = 4 * (pos.x.rem_euclid(32) + pos.z.rem_euclid(32) * 32) as usize;
+        let location = &self.data[index..index + 4];
+        let sector_offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+        let sector_count = location[3] as usize;
+        if sector_offset == 0 || sector_count == 0 {
+            return None;
+        }
+        Some((sector_offset * SECTOR_SIZE, sector_count * SECTOR_SIZE))
+    }
+
+    /// Whether the chunk has been saved in this region file.
+    pub fn has_chunk(&self, pos: &ChunkPos) -> bool {
+        self.chunk_location(pos).is_some()
+    }
+
+    /// Read and decompress the NBT for a chunk. Returns `None` if the chunk
+    /// isn't in the region file.
+    pub fn read_chunk_nbt(&self, pos: &ChunkPos) -> Result<Option<Tag>, RegionError> {
+        let (offset, max_length) = match self.chunk_location(pos) {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+        let header = self
+            .data
+            .get(offset..offset + 5)
+            .ok_or(RegionError::ChunkOutOfBounds)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let compression_type = header[4];
+        if length == 0 || length + 4 > max_length {
+            return Err(RegionError::ChunkOutOfBounds);
+        }
+        // the length includes the compression type
+        let data = self
+            .data
+            .get(offset + 5..offset + 4 + length)
+            .ok_or(RegionError::ChunkOutOfBounds)?;
+
+        let tag = match compression_type {
+            1 => Tag::read_gzip(&mut Cursor::new(data.to_vec()))?,
+            2 => Tag::read_zlib(&mut &data[..])?,
+            3 => Tag::read(&mut Cursor::new(data))?,
+            // 4 is lz4 (added in 1.20.5) and 128 and above means the chunk is
+            // stored in a separate .mcc file
+            _ => return Err(RegionError::UnsupportedCompression(compression_type)),
+        };
+        Ok(Some(tag))
+    }
+
+    /// Read a chunk from the region file, in the same format as chunks
+    /// that we get from the server. Returns `None` if the chunk isn't in the
+    /// region file. See [`Chunk::from_nbt`].
+    pub fn read_chunk(
+        &self,
+        pos: &ChunkPos,
+        dimension_height: u32,
+        min_y: i32,
+    ) -> Result<Option<Chunk>, RegionError> {
+        match self.read_chunk_nbt(pos)? {
+            Some(tag) => Ok(Some(Chunk::from_nbt(&tag, dimension_height, min_y)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Chunk {
+    /// Create a chunk from the NBT that it's saved as in region files.
+    ///
+    /// Both the 1.18+ layout (`sections` with `block_states`) and the 1.16
+    /// and 1.17 layout (`Level.Sections` with `Palette` and `BlockStates`)
+    /// are supported. Sections outside of the dimension height are ignored.
+    ///
+    /// Heightmaps are calculated from the blocks instead of being read.
+    /// Biomes aren't read, since their ids depend on the biome registry the
+    /// server sends us.
+    pub fn from_nbt(tag: &Tag, dimension_height: u32, min_y: i32) -> Result<Chunk, RegionError> {
+        // the root compound has a single unnamed compound in it
+        let root = tag
+            .as_compound()
+            .and_then(|root| root.values().next())
+            .and_then(|root| root.as_compound())
+            .ok_or(RegionError::InvalidField(""))?;
+
+        let section_count = dimension_height as usize / 16;
+        let mut chunk = Chunk {
+            sections: vec![Section::default(); section_count],
+            light: ChunkLight::default(),
+            heightmaps: HashMap::new(),
+            block_entities: HashMap::new(),
+        };
+
+        let (sections, palette_field, data_field) = match root.get("sections") {
+            Some(sections) => (sections, "palette", "data"),
+            None => {
+                let level = root
+                    .get("Level")
+                    .and_then(|level| level.as_compound())
+                    .ok_or(RegionError::InvalidField("sections"))?;
+                let sections = level
+                    .get("Sections")
+                    .ok_or(RegionError::InvalidField("Sections"))?;
+                (sections, "Palette", "BlockStates")
+            }
+        };
+        let sections = sections
+            .as_list()
+            .ok_or(RegionError::InvalidField("sections"))?;
+
+        for section_tag in sections {
+            let section_tag = section_tag
+                .as_compound()
+                .ok_or(RegionError::InvalidField("sections"))?;
+            let section_y = *section_tag
+                .get("Y")
+                .and_then(|y| y.as_byte())
+                .ok_or(RegionError::InvalidField("Y"))? as i32;
+            let section_index = section_y - min_y.div_floor(16);
+            if section_index < 0 || section_index as usize >= section_count {
+                continue;
+            }
+
+            // in 1.18+ the palette and data are in a block_states compound,
+            // before that they're directly in the section
+            let block_states = match section_tag.get("block_states") {
+                Some(block_states) => block_states
+                    .as_compound()
+                    .ok_or(RegionError::InvalidField("block_states"))?,
+                None => section_tag,
+            };
+            // sections that only have light data don't have a palette
+            let palette = match block_states.get(palette_field) {
+                Some(palette) => palette
+                    .as_list()
+                    .ok_or(RegionError::InvalidField(palette_field))?,
+                None => continue,
+            };
+            let palette = palette
+                .iter()
+                .map(block_state_from_nbt)
+                .collect::<Result<Vec<_>, _>>()?;
+            let data = match block_states.get(data_field) {
+                Some(data) => data
+                    .as_longarray()
+                    .ok_or(RegionError::InvalidField(data_field))?
+                    .iter()
+                    .map(|&long| long as u64)
+                    .collect(),
+                None => Vec::new(),
+            };
+
+            let states = PalettedContainer::from_palette_and_data(
+                &PalettedContainerType::BlockStates,
+                palette,
+                data,
+            )?;
+            let section = &mut chunk.sections[section_index as usize];
+            *section = Section {
+                states,
+                ..Default::default()
+            };
+            section.recalculate_block_count();
+        }
+
+        // the saved heightmaps might be for a different height, so they're
+        // always calculated from the blocks
+        chunk.recalculate_heightmaps(min_y);
+
+        Ok(chunk)
+    }
+}
+
+impl PalettedContainer {
+    /// Create a block state container from the palette and packed data
+    /// that's in region files. Unlike the network format, the data always
+    /// refers to the palette, even when it has more than 256 entries.
+    fn from_palette_and_data(
+        container_type: &'static PalettedContainerType,
+        palette: Vec<u32>,
+        data: Vec<u64>,
+    ) -> Result<Self, RegionError> {
+        if palette.len() <= 1 {
+            let mut container = PalettedContainer::new(container_type);
+            if let Some(&value) = palette.first() {
+                container.palette = Palette::SingleValue(value);
+            }
+            return Ok(container);
+        }
+
+        // block states always use at least 4 bits
+        let bits_per_entry = ceil_log2(palette.len()).max(4);
+        let storage = BitStorage::new(bits_per_entry as usize, SECTION_SIZE, Some(data))
+            .map_err(|e| RegionError::InvalidBlockStates(e.to_string()))?;
+        if let Some(index) = (0..storage.size()).find(|&i| storage.get(i) as usize >= palette.len())
+        {
+            return Err(RegionError::InvalidBlockStates(format!(
+                "Palette index {} at {index} is out of bounds",
+                storage.get(index)
+            )));
+        }
+
+        match PaletteType::from_bits_and_type(bits_per_entry, container_type) {
+            PaletteType::Linear => Ok(PalettedContainer {
+                bits_per_entry,
+                palette: Palette::Linear(palette),
+                storage,
+                container_type: *container_type,
+            }),
+            PaletteType::Hashmap => Ok(PalettedContainer {
+                bits_per_entry,
+                palette: Palette::Hashmap(palette.into()),
+                storage,
+                container_type: *container_type,
+            }),
+            // the global palette uses state ids directly, so we have to
+            // look up every value in the palette
+            _ => {
+                let ids = (0..storage.size())
+                    .map(|i| palette[storage.get(i) as usize])
+                    .collect::<Vec<_>>();
+                // these are block states, so the biome registry size isn't used
+                Ok(PalettedContainer::from_ids(container_type, &ids, 0))
+            }
+        }
+    }
+}
+
+/// Get the block state from a palette entry like
+/// `{Name: "minecraft:oak_log", Properties: {axis: "x"}}`. Unknown blocks are
+/// turned into air, like vanilla does.
+fn block_state_from_nbt(tag: &Tag) -> Result<u32, RegionError> {
+    let compound = tag
+        .as_compound()
+        .ok_or(RegionError::InvalidField("palette"))?;
+    let name = compound
+        .get("Name")
+        .and_then(|name| name.as_string())
+        .ok_or(RegionError::InvalidField("Name"))?;
+    let id = name.strip_prefix("minecraft:").unwrap_or(name);
+    let properties = match compound.get("Properties").and_then(|p| p.as_compound()) {
+        Some(properties) => properties
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_string()?.to_string())))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let state = BlockState::from_id_and_properties(id, &properties).unwrap_or(BlockState::Air);
+    Ok(state as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_core::{BlockPos, ChunkBlockPos};
+
+    fn palette_entry(name: &str, axis: Option<&str>) -> Tag {
+        match axis {
+            Some(axis) => Tag::from([
+                ("Name", Tag::String(name.to_string())),
+                (
+                    "Properties",
+                    Tag::from([("axis", Tag::String(axis.to_string()))]),
+                ),
+            ]),
+            None => Tag::from([("Name", Tag::String(name.to_string()))]),
+        }
+    }
+
+    /// A region file with a single chunk at 1, 2 where the section at y -64
+    /// has a stone floor and an oak log at 3, -63, 4.
+    fn region_with_chunk(compression_type: u8) -> RegionFile {
+        // stone for the bottom layer, an oak log at 3, 1, 4, and air
+        // everywhere else
+        let mut indices = vec![2u64; SECTION_SIZE];
+        for index in indices.iter_mut().take(256) {
+            *index = 0;
+        }
+        indices[(1 << 8) | (4 << 4) | 3] = 1;
+        // 4 bits per entry, 16 entries per long
+        let data = indices
+            .chunks(16)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u64, |acc, (i, &index)| acc | (index << (i * 4))) as i64
+            })
+            .collect();
+
+        let section = Tag::from([
+            ("Y", Tag::Byte(-4)),
+            (
+                "block_states",
+                Tag::from([
+                    (
+                        "palette",
+                        Tag::List(vec![
+                            palette_entry("minecraft:stone", None),
+                            palette_entry("minecraft:oak_log", Some("x")),
+                            palette_entry("minecraft:air", None),
+                        ]),
+                    ),
+                    ("data", Tag::LongArray(data)),
+                ]),
+            ),
+        ]);
+        let chunk = Tag::from([(
+            "",
+            Tag::from([
+                ("xPos", Tag::Int(1)),
+                ("zPos", Tag::Int(2)),
+                ("sections", Tag::List(vec![section])),
+            ]),
+        )]);
+
+        let mut chunk_data = Vec::new();
+        match compression_type {
+            1 => chunk.write_gzip(&mut chunk_data).unwrap(),
+            2 => chunk.write_zlib(&mut chunk_data).unwrap(),
+            _ => chunk.write(&mut chunk_data).unwrap(),
+        }
+
+        let mut data = vec![0; HEADER_SIZE];
+        let index = 4 * (1 + 2 * 32);
+        // the chunk is in the third sector and takes up one sector
+        data[index..index + 4].copy_from_slice(&[0, 0, 2, 1]);
+        data.extend(((chunk_data.len() + 1) as u32).to_be_bytes());
+        data.push(compression_type);
+        data.extend(chunk_data);
+        data.resize(HEADER_SIZE + SECTOR_SIZE, 0);
+        RegionFile::from_bytes(data).unwrap()
+    }
+
+    #[test]
+    fn test_read_chunk() {
+        for compression_type in [1, 2, 3] {
+            let region = region_with_chunk(compression_type);
+            assert!(!region.has_chunk(&ChunkPos::new(0, 0)));
+            assert!(region
+                .read_chunk(&ChunkPos::new(0, 0), 384, -64)
+                .unwrap()
+                .is_none());
+
+            // positions are wrapped to be inside the region
+            let chunk = region
+                .read_chunk(&ChunkPos::new(-31, 34), 384, -64)
+                .unwrap()
+                .unwrap();
+            assert_eq!(chunk.sections[0].block_count, 257);
+            let get = |x, y, z| {
+                chunk
+                    .get(&ChunkBlockPos::from(&BlockPos::new(x, y, z)), -64)
+                    .unwrap()
+            };
+            assert!(get(0, -64, 0) == BlockState::Stone);
+            assert!(
+                get(3, -63, 4)
+                    == BlockState::from(azalea_block::OakLogBlock {
+                        axis: azalea_block::Axis::X
+                    })
+            );
+            assert!(get(4, -63, 4) == BlockState::Air);
+            assert!(get(0, 100, 0) == BlockState::Air);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_compression() {
+        let region = region_with_chunk(4);
+        assert!(matches!(
+            region.read_chunk(&ChunkPos::new(1, 2), 384, -64),
+            Err(RegionError::UnsupportedCompression(4))
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{Chunk, Dimension};
+use azalea_block::BlockState;
+use azalea_buf::{BufReadError, McBufReadable, McBufVarReadable, McBufVarWritable, McBufWritable};
+use azalea_core::{BlockPos, ChunkPos, ChunkSectionBlockPos};
+use std::{
+    collections::HashMap,
+    io::{Cursor, Write},
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+
+/// A copy of the chunks that were loaded in a [`Dimension`] at some point in
+/// time, which can be saved, loaded back into a dimension, or compared with
+/// another snapshot.
+#[derive(Clone, Debug)]
+pub struct WorldSnapshot {
+    pub height: u32,
+    pub min_y: i32,
+    pub chunks: HashMap<ChunkPos, Chunk>,
+}
+
+/// A block that's different between two [`WorldSnapshot`]s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockChange {
+    pub pos: BlockPos,
+    pub old: BlockState,
+    pub new: BlockState,
+}
+
+/// The error from [`WorldSnapshot::diff`] when the snapshots can't be
+/// compared.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SnapshotDiffError {
+    /// The snapshots are for dimensions with different heights, like if
+    /// they were taken in different dimensions, so their sections aren't at
+    /// the same y.
+    #[error(
+        "Snapshots have different heights ({min_y} to {max_y} and {other_min_y} to {other_max_y})"
+    )]
+    DifferentHeights {
+        min_y: i32,
+        max_y: i32,
+        other_min_y: i32,
+        other_max_y: i32,
+    },
+}
+
+impl Dimension {
+    /// Copy the chunks that are currently loaded.
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            height: self.height(),
+            min_y: self.min_y(),
+            chunks: self
+                .chunk_storage
+                .loaded_chunks()
+                .map(|(pos, chunk)| (pos, chunk.lock().unwrap().clone()))
+                .collect(),
+        }
+    }
+
+    /// Replace chunks in the dimension with the ones from the snapshot.
+    /// Chunks that are outside of our view range are ignored.
+    pub fn load_snapshot(&mut self, snapshot: &WorldSnapshot) {
+        for (pos, chunk) in &snapshot.chunks {
+            if self.chunk_storage.in_range(pos) {
+                self[pos] = Some(Arc::new(Mutex::new(chunk.clone())));
+            }
+        }
+    }
+}
+
+impl WorldSnapshot {
+    /// Get every block that's different in `other`. Chunks that are only in
+    /// one of the snapshots are skipped, since we don't know what was there
+    /// in the other one.
+    ///
+    /// Returns an error if the snapshots have a different `min_y` or height.
+    pub fn diff(&self, other: &WorldSnapshot) -> Result<Vec<BlockChange>, SnapshotDiffError> {
+        if self.min_y != other.min_y || self.height != other.height {
+            return Err(SnapshotDiffError::DifferentHeights {
+                min_y: self.min_y,
+                max_y: self.min_y + self.height as i32 - 1,
+                other_min_y: other.min_y,
+                other_max_y: other.min_y + other.height as i32 - 1,
+            });
+        }
+        let mut changes = Vec::new();
+        for (chunk_pos, old_chunk) in &self.chunks {
+            let new_chunk = match other.chunks.get(chunk_pos) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            for (section_index, (old_section, new_section)) in old_chunk
+                .sections
+                .iter()
+                .zip(new_chunk.sections.iter())
+                .enumerate()
+            {
+                let section_y = self.min_y + section_index as i32 * 16;
+                for y in 0..16 {
+                    for z in 0..16 {
+                        for x in 0..16 {
+                            let pos = ChunkSectionBlockPos { x, y, z };
+                            let old = old_section.get(pos.clone());
+                            let new = new_section.get(pos);
+                            if old != new {
+                                changes.push(BlockChange {
+                                    pos: BlockPos {
+                                        x: chunk_pos.x * 16 + x as i32,
+                                        y: section_y + y as i32,
+                                        z: chunk_pos.z * 16 + z as i32,
+                                    },
+                                    old,
+                                    new,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(changes)
+    }
+}
+
+impl McBufWritable for WorldSnapshot {
+    fn write_into(&self, buf: &mut impl Write) -> Result<(), std::io::Error> {
+        self.height.var_write_into(buf)?;
+        self.min_y.write_into(buf)?;
+        (self.chunks.len() as u32).var_write_into(buf)?;
+        for (pos, chunk) in &self.chunks {
+            pos.x.write_into(buf)?;
+            pos.z.write_into(buf)?;
+            chunk.write_into(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl McBufReadable for WorldSnapshot {
+    fn read_from(buf: &mut Cursor<&[u8]>) -> Result<Self, BufReadError> {
+        let height = u32::var_read_from(buf)?;
+        let min_y = i32::read_from(buf)?;
+        let chunk_count = u32::var_read_from(buf)?;
+        let mut chunks = HashMap::new();
+        for _ in 0..chunk_count {
+            let pos = ChunkPos::new(i32::read_from(buf)?, i32::read_from(buf)?);
+            let mut chunk = Chunk::read_with_dimension_height(buf, height)?;
+            chunk.recalculate_heightmaps(min_y);
+            chunks.insert(pos, chunk);
+        }
+        Ok(WorldSnapshot {
+            height,
+            min_y,
+            chunks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_roundtrip_and_diff() {
+        let mut dimension = Dimension::default();
+        dimension
+            .set_chunk(&ChunkPos::new(0, 0), Some(Chunk::default()))
+            .unwrap();
+        dimension
+            .set_chunk(&ChunkPos::new(-1, 2), Some(Chunk::default()))
+            .unwrap();
+        dimension.set_block_state(&BlockPos::new(1, 2, 3), BlockState::Stone);
+        let before = dimension.snapshot();
+        assert_eq!(before.chunks.len(), 2);
+
+        let mut buf = Vec::new();
+        before.write_into(&mut buf).unwrap();
+        let before = WorldSnapshot::read_from(&mut Cursor::new(&buf)).unwrap();
+
+        let mut dimension = Dimension::default();
+        dimension.load_snapshot(&before);
+        assert_eq!(
+            dimension.get_block_state(&BlockPos::new(1, 2, 3)),
+            Some(BlockState::Stone)
+        );
+
+        dimension.set_block_state(&BlockPos::new(1, 2, 3), BlockState::Air);
+        dimension.set_block_state(&BlockPos::new(-5, -60, 40), BlockState::Dirt);
+        let after = dimension.snapshot();
+
+        let mut changes = before.diff(&after).unwrap();
+        changes.sort_by_key(|change| change.pos.x);
+        assert_eq!(
+            changes,
+            vec![
+                BlockChange {
+                    pos: BlockPos::new(-5, -60, 40),
+                    old: BlockState::Air,
+                    new: BlockState::Dirt,
+                },
+                BlockChange {
+                    pos: BlockPos::new(1, 2, 3),
+                    old: BlockState::Stone,
+                    new: BlockState::Air,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_different_heights() {
+        let mut overworld = Dimension::new(8, 384, -64);
+        overworld
+            .set_chunk(&ChunkPos::new(0, 0), Some(Chunk::default()))
+            .unwrap();
+        let mut nether = Dimension::new(8, 256, 0);
+        nether
+            .set_chunk(&ChunkPos::new(0, 0), Some(Chunk::default()))
+            .unwrap();
+
+        assert_eq!(
+            overworld.snapshot().diff(&nether.snapshot()),
+            Err(SnapshotDiffError::DifferentHeights {
+                min_y: -64,
+                max_y: 319,
+                other_min_y: 0,
+                other_max_y: 255,
+            })
+        );
+        assert_eq!(overworld.snapshot().diff(&overworld.snapshot()), Ok(vec![]));
+    }
+}